@@ -0,0 +1,130 @@
+//! Debug shapes, texts and log lines the server sends to clients which ask for them.
+
+use nalgebra::UnitQuaternion;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+use crate::Vec3;
+
+/// Helper struct, use one of the `dbg_*!()` macros.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DebugShape {
+    pub shape: Shape,
+    /// Time left (decreases every frame)
+    pub time: f32,
+    pub color: Rgba,
+    pub category: DebugCategory,
+    pub source: DebugSource,
+}
+
+/// Which part of the game drew a shape, see `dbg_category!()`.
+///
+/// Each category can be toggled by its own `d_draw_<category>` cvar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DebugCategory {
+    /// Anything that didn't specify a category.
+    Misc,
+    Camera,
+    Cycles,
+    Projectiles,
+}
+
+/// Which endpoint drew a shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DebugSource {
+    Client,
+    Server,
+    /// Anything else - e.g. before the endpoint is set.
+    Other,
+}
+
+impl DebugSource {
+    pub fn from_endpoint(name: &str) -> Self {
+        match name {
+            "sv" | "losv" => DebugSource::Server,
+            "cl" | "locl" => DebugSource::Client,
+            _ => DebugSource::Other,
+        }
+    }
+}
+
+/// Helper struct, use one of the `dbg_*!()` macros.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub enum Shape {
+    Line {
+        begin: Vec3,
+        end: Vec3,
+    },
+    Arrow {
+        begin: Vec3,
+        dir: Vec3,
+    },
+    Cross {
+        point: Vec3,
+    },
+    Rot {
+        point: Vec3,
+        rot: UnitQuaternion<f32>,
+        scale: f32,
+    },
+    Sphere {
+        center: Vec3,
+        radius: f32,
+    },
+    /// An AABB if `rot` is identity, otherwise an OBB.
+    Box {
+        center: Vec3,
+        half_extents: Vec3,
+        rot: UnitQuaternion<f32>,
+    },
+    /// `begin` and `end` are the centers of the hemispheres.
+    Capsule {
+        begin: Vec3,
+        end: Vec3,
+        radius: f32,
+    },
+}
+
+/// A color with the same fields (and wire format) as Fyrox's `Color`
+/// which this crate can't depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+/// Helper struct, use `dbg_worldtext!()`.
+///
+/// Not a `Shape` because it's drawn using the UI, not lines.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DebugWorldText {
+    pub pos: Vec3,
+    pub text: String,
+    /// Time left (decreases every frame)
+    pub time: f32,
+    pub color: Rgba,
+}
+
+/// How important a log line is.
+///
+/// Used to filter which lines the server forwards to admins.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Display, EnumString,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+/// Helper struct, use one of the `dbg_log*!()` macros.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LogLine {
+    pub level: LogLevel,
+    /// The whole line including the endpoint name.
+    pub text: String,
+}
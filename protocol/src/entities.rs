@@ -0,0 +1,198 @@
+//! Parts of the game's entities which are sent over the network.
+
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+use crate::Vec3;
+
+/// Names of cycle skins for `cl_skin`, the index is sent over the network.
+///
+/// LATER More models, currently the skin is only validated and replicated.
+pub const SKINS: &[&str] = &["default"];
+
+/// Cheats the player has turned on, only allowed with `sv_cheats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Cheats {
+    /// The cycle flies where the player is looking and nothing collides with it,
+    /// not even projectiles.
+    pub noclip: bool,
+    /// Projectiles hit the cycle but don't kill it.
+    pub god: bool,
+}
+
+/// How the player's cycle looks, chosen using `cl_color` and `cl_skin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Look {
+    /// RGB, white means the model's original colors.
+    pub color: [u8; 3],
+    /// Index into `SKINS`.
+    pub skin: u8,
+}
+
+impl Look {
+    /// Parse `rrggbb` or `#rrggbb`.
+    pub fn parse_color(s: &str) -> Option<[u8; 3]> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some([channel(0)?, channel(2)?, channel(4)?])
+    }
+
+    /// Replace anything the server doesn't allow with defaults.
+    pub fn validated(self) -> Self {
+        let default = Self::default();
+        // Too dark to see in the arena.
+        let color = if self.color.iter().all(|&c| c < 32) {
+            default.color
+        } else {
+            self.color
+        };
+        let skin = if usize::from(self.skin) < SKINS.len() {
+            self.skin
+        } else {
+            default.skin
+        };
+        Self { color, skin }
+    }
+}
+
+impl Default for Look {
+    fn default() -> Self {
+        Self {
+            color: [255, 255, 255],
+            skin: 0,
+        }
+    }
+}
+
+/// Where the match is, the game mode on the server decides when it changes.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum MatchPhase {
+    /// Players can drive around and shoot but kills don't count.
+    ///
+    /// Ends when enough players are ready (`g_warmup_ready_ratio`) or at the deadline.
+    Warmup {
+        /// Game time when the match starts anyway, `None` until somebody is playing.
+        deadline: Option<f32>,
+    },
+    /// Kills count.
+    Live {
+        /// Game time when the round ends, `None` without `g_time_limit`.
+        end: Option<f32>,
+    },
+    /// The time ran out while several players shared the lead.
+    Overtime {
+        rule: OvertimeRule,
+        /// Game time when the overtime period ends, only for `ExtraRounds`.
+        end: Option<f32>,
+    },
+    /// Waiting for players who lost connection, see `g_competitive`.
+    ///
+    /// Cycles stand still and nobody can shoot.
+    Paused {
+        /// Game time when the match continues even if they're not back.
+        deadline: f32,
+    },
+}
+
+impl MatchPhase {
+    /// The same phase with its deadlines moved `seconds` later, used after a pause.
+    pub fn delayed(self, seconds: f32) -> Self {
+        let delay = |time: Option<f32>| time.map(|time| time + seconds);
+        match self {
+            Self::Warmup { deadline } => Self::Warmup {
+                deadline: delay(deadline),
+            },
+            Self::Live { end } => Self::Live { end: delay(end) },
+            Self::Overtime { rule, end } => Self::Overtime {
+                rule,
+                end: delay(end),
+            },
+            Self::Paused { deadline } => Self::Paused {
+                deadline: deadline + seconds,
+            },
+        }
+    }
+}
+
+/// How a tie for the lead is broken when the time runs out, see `g_overtime`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum OvertimeRule {
+    /// The round ends in a draw.
+    Off,
+    /// Play periods of `g_overtime_time` seconds until somebody leads at the end of one.
+    ExtraRounds,
+    /// The first kill by one of the leaders wins.
+    GoldenFrag,
+    /// Leaders who get killed drop out, the last one left wins.
+    SuddenDeath,
+}
+
+/// Which kind of cycle the player drives, see `g_vehicles`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum VehicleKind {
+    Standard,
+    /// Accelerates faster, see `g_vehicle_fast_acceleration`.
+    Fast,
+    /// Slower but spawns with armor, see `g_vehicle_armored_armor`.
+    Armored,
+}
+
+/// Something visual happened in the world, replicated so clients can show it.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum Effect {
+    /// A cycle got hit.
+    Explosion {
+        pos: Vec3,
+        victim_index: Option<u32>,
+    },
+    Impact {
+        pos: Vec3,
+        normal: Vec3,
+    },
+    /// A cycle got launched by a jump pad.
+    JumpPad {
+        pos: Vec3,
+    },
+    /// A cycle went through a teleporter.
+    Teleport {
+        from: Vec3,
+        to: Vec3,
+    },
+}
+
+/// A gesture played on the player's cycle, sent using the `emote` console command.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Display, EnumIter, EnumString,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum Emote {
+    Wave,
+    Cheer,
+    Taunt,
+    Gg,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_look() {
+        assert_eq!(Look::parse_color("ff8000"), Some([255, 128, 0]));
+        assert_eq!(Look::parse_color("#00FF10"), Some([0, 255, 16]));
+        assert_eq!(Look::parse_color("ff80"), None);
+        assert_eq!(Look::parse_color("gg8000"), None);
+        assert_eq!(Look::parse_color("ff80€"), None);
+
+        let dark = Look {
+            color: [0, 10, 20],
+            skin: 200,
+        };
+        assert_eq!(dark.validated(), Look::default());
+    }
+}
@@ -0,0 +1,175 @@
+//! What players send every frame - `Input`.
+
+use std::fmt::{self, Debug, Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter, EnumString};
+
+/// What a player is doing - pressed `Action`s, `Axis` values and where they're aiming.
+///
+/// Devices are mapped to actions and axes on the client (see `client::bindings`)
+/// so new abilities and input devices don't change what's sent over the network.
+///
+/// LATER Would be nice to send as little as possible since this is networked.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Input {
+    /// LATER This should probably never be networked, since cl and sv have different time.
+    pub real_time: f32,
+
+    /// LATER specify whether this is the current or prev or next frame,
+    /// it might get messy depending on when input vs timekeeping is done
+    /// and when it's sent.
+    pub game_time: f32,
+
+    /// Counterclockwise: 0 is directly forward, negative is left, positive right.
+    ///
+    /// Nalgebra rotations follow the right hand rule,
+    /// thumb points in +Y (up), the curl of fingers shows direction.
+    ///
+    /// Some things like shooting need the angle at the exact time
+    /// so we send yaw and pitch with each input, not just once per frame.
+    pub yaw: Deg,
+    pub yaw_speed: Deg,
+    pub pitch: Deg,
+    pub pitch_speed: Deg,
+
+    /// Pressed `Action`s, one bit each.
+    actions: u32,
+    /// `Axis` values scaled to [-127, 127] so they take less space.
+    axes: [i8; MAX_AXES],
+}
+
+/// Space for axes in `Input`, new axes can be added without changing the wire format.
+const MAX_AXES: usize = 8;
+
+// LATER ClientInput? - zoom, map, chat, score, pause, screenshot, console, ...
+// These don't need to be networked
+
+impl Input {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.actions & (1 << action as u32) != 0
+    }
+
+    pub fn set_pressed(&mut self, action: Action, pressed: bool) {
+        if pressed {
+            self.actions |= 1 << action as u32;
+        } else {
+            self.actions &= !(1 << action as u32);
+        }
+    }
+
+    pub fn axis(&self, axis: Axis) -> f32 {
+        f32::from(self.axes[axis as usize]) / 127.0
+    }
+
+    /// The value is clamped to [-1, 1].
+    pub fn set_axis(&mut self, axis: Axis, value: f32) {
+        self.axes[axis as usize] = (value.clamp(-1.0, 1.0) * 127.0).round() as i8;
+    }
+
+    /// Same buttons, axes and aim, times are ignored since they change every frame.
+    pub fn same_controls(&self, other: &Input) -> bool {
+        self.actions == other.actions
+            && self.axes == other.axes
+            && self.yaw.0 == other.yaw.0
+            && self.pitch.0 == other.pitch.0
+    }
+
+    pub fn release_all_keys(&mut self) {
+        self.actions = 0;
+        self.axes = [0; MAX_AXES];
+    }
+}
+
+impl Debug for Input {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // Only output the pressed buttons so it's more readable.
+        write!(
+            f,
+            "Input {{ time {} yaw {} {}/s pitch {} {}/s ",
+            self.game_time, self.yaw, self.yaw_speed, self.pitch, self.pitch_speed,
+        )?;
+        for action in Action::iter().filter(|&action| self.pressed(action)) {
+            write!(f, "{} ", action)?;
+        }
+        for axis in Axis::iter() {
+            let value = self.axis(axis);
+            if value != 0.0 {
+                write!(f, "{} {} ", axis, value)?;
+            }
+        }
+        write!(f, "}}")?;
+        Ok(())
+    }
+}
+
+/// Digital inputs - either pressed or not.
+///
+/// Each is one bit in `Input`, only add new ones at the end so the existing bits don't change.
+/// LATER At most 32.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumIter, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum Action {
+    Fire1,
+    Fire2,
+    Marker1,
+    Marker2,
+    Zoom,
+    Up,
+    Down,
+    /// LATER There's no boost mechanic yet.
+    Boost,
+    PrevWeapon,
+    NextWeapon,
+    Reload,
+    Flag,
+    Grenade,
+    Map,
+    Score,
+    Chat,
+    Pause,
+    Screenshot,
+}
+
+/// Analog inputs in the range [-1, 1].
+///
+/// Keys bound to an axis set it to -1, 0 or 1, LATER gamepad sticks anything in between.
+/// Only add new ones at the end, at most `MAX_AXES`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumIter, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum Axis {
+    /// Forward is positive.
+    Move,
+    /// Right is positive.
+    Steer,
+    /// Turning at a constant speed, e.g. with a stick. Right is positive.
+    ///
+    /// Only used by the client to change `yaw`, the server gets the resulting angle.
+    AimYaw,
+    /// Down is positive like with the mouse. Client only like `AimYaw`.
+    AimPitch,
+}
+
+// Why not use an existing crate like https://crates.io/crates/angle?
+// - Not worth adding a dep for such a simple thing
+// - It shows signs of lack of attention to detail
+//   (bad readme, missing doc comments, inconsistent formatting)
+//   on the surface so it probably contains deeper issues as well.
+// - It tries to be smart and implements questionable ops such as comparisons.
+// This reasoning might change if this struct gets larger but it'll probably mean
+// only taking inspiration and bits of code from the angle crate, not adding it as a dep.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub struct Deg(pub f32);
+
+impl Deg {
+    pub fn to_radians(self) -> f32 {
+        self.0.to_radians()
+    }
+}
+
+impl Display for Deg {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}°", self.0)
+    }
+}
@@ -0,0 +1,221 @@
+//! The RustCycles network protocol without any game or engine code.
+//!
+//! External tools (server browsers, stat collectors, bots) can use this
+//! to talk to a server without depending on Fyrox.
+//!
+//! Each message is a frame header followed by the bincode-serialized message.
+//! The header is the length of the message and its CRC-32, both little endian.
+//! TCP already keeps messages in order and checks for errors
+//! but this catches bugs and peers which send garbage before we try to deserialize it.
+//!
+//! The messages themselves are in `messages`, the types they contain in the other modules.
+
+pub mod debug;
+pub mod entities;
+pub mod input;
+pub mod messages;
+
+use std::{collections::VecDeque, mem};
+
+use bincode::Options;
+use serde::{de::DeserializeOwned, Serialize};
+
+pub use nalgebra;
+
+/// Same as Fyrox's `Vec3`, Fyrox re-exports this version of nalgebra.
+pub type Vec3 = nalgebra::Vector3<f32>;
+
+pub type MsgLen = u32;
+pub const HEADER_LEN: usize = mem::size_of::<MsgLen>();
+
+pub type Checksum = u32;
+pub const CHECKSUM_LEN: usize = mem::size_of::<Checksum>();
+
+/// Length and checksum.
+pub const FRAME_HEADER_LEN: usize = HEADER_LEN + CHECKSUM_LEN;
+
+/// Anything longer is considered malformed.
+///
+/// This is much more than any legitimate message
+/// but protects against a hostile peer making us allocate gigabytes.
+pub const MAX_MSG_LEN: usize = 16 * 1024 * 1024;
+
+/// A serialized message ready to be sent.
+///
+/// Can be reused with `serialize_into` to avoid allocating for every message.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkMessage {
+    pub content_len: [u8; HEADER_LEN],
+    pub checksum: [u8; CHECKSUM_LEN],
+    pub buf: Vec<u8>,
+}
+
+impl NetworkMessage {
+    /// Append the frame header and the message to `out`.
+    pub fn write_frame(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.content_len);
+        out.extend_from_slice(&self.checksum);
+        out.extend_from_slice(&self.buf);
+    }
+}
+
+pub fn serialize<M>(msg: M) -> NetworkMessage
+where
+    M: Serialize,
+{
+    let mut network_msg = NetworkMessage::default();
+    serialize_into(&mut network_msg, msg);
+    network_msg
+}
+
+/// Same as `serialize` but reuses the buffer in `network_msg`.
+pub fn serialize_into<M>(network_msg: &mut NetworkMessage, msg: M)
+where
+    M: Serialize,
+{
+    let buf = &mut network_msg.buf;
+    buf.clear();
+    bincode::serialize_into(&mut *buf, &msg).expect("bincode failed to serialize message");
+    network_msg.content_len = MsgLen::try_from(buf.len())
+        .unwrap_or_else(|err| {
+            panic!("bincode message length ({} bytes) overflowed its type: {:?}", buf.len(), err)
+        })
+        .to_le_bytes();
+    network_msg.checksum = crc32(buf).to_le_bytes();
+}
+
+/// Deserialize a message serialized by `serialize`.
+///
+/// Never panics, no matter what the bytes are.
+pub fn deserialize<M>(bytes: &[u8]) -> Result<M, bincode::Error>
+where
+    M: DeserializeOwned,
+{
+    // Same encoding as `bincode::serialize` but with a limit.
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_MSG_LEN as u64)
+        .deserialize(bytes)
+}
+
+/// Parse a message from `buffer` or return None if there's not enough data.
+///
+/// Returns an error if the data is malformed, the connection should then be closed.
+pub fn parse_one<M>(buffer: &mut VecDeque<u8>) -> Result<Option<M>, String>
+where
+    M: DeserializeOwned,
+{
+    if buffer.len() < FRAME_HEADER_LEN {
+        return Ok(None);
+    }
+
+    // There's no convenient way to make this generic over msg len 2 and 4,
+    // just keep one version commented out.
+    //let len_bytes = [buffer[0], buffer[1]];
+    //let content_len = usize::from(MsgLen::from_le_bytes(len_bytes));
+    let len_bytes = [buffer[0], buffer[1], buffer[2], buffer[3]];
+    let content_len = usize::try_from(MsgLen::from_le_bytes(len_bytes)).unwrap();
+    if content_len > MAX_MSG_LEN {
+        return Err(format!("message too long ({} bytes)", content_len));
+    }
+
+    if buffer.len() < FRAME_HEADER_LEN + content_len {
+        // Not enough bytes in buffer for a full message.
+        return Ok(None);
+    }
+
+    let checksum_bytes = [buffer[4], buffer[5], buffer[6], buffer[7]];
+    let checksum = Checksum::from_le_bytes(checksum_bytes);
+    buffer.drain(0..FRAME_HEADER_LEN);
+    let bytes: Vec<_> = buffer.drain(0..content_len).collect();
+    if crc32(&bytes) != checksum {
+        return Err(format!("checksum mismatch ({} bytes)", content_len));
+    }
+    let msg = deserialize(&bytes).map_err(|err| err.to_string())?;
+
+    Ok(Some(msg))
+}
+
+/// CRC-32 as used by zlib and PNG.
+pub fn crc32(bytes: &[u8]) -> Checksum {
+    let mut crc = !0;
+    for &byte in bytes {
+        crc = CRC_TABLE[usize::from((crc as u8) ^ byte)] ^ (crc >> 8);
+    }
+    !crc
+}
+
+const CRC_TABLE: [Checksum; 256] = crc_table();
+
+const fn crc_table() -> [Checksum; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as Checksum;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(network_msg: &NetworkMessage) -> Vec<u8> {
+        let mut frame = Vec::new();
+        network_msg.write_frame(&mut frame);
+        frame
+    }
+
+    #[test]
+    fn test_parse_partial() {
+        let frame = frame(&serialize("hello".to_owned()));
+        let mut buffer = VecDeque::new();
+        buffer.extend(&frame[..FRAME_HEADER_LEN + 2]);
+        assert_eq!(parse_one::<String>(&mut buffer), Ok(None));
+
+        buffer.extend(&frame[FRAME_HEADER_LEN + 2..]);
+        assert_eq!(parse_one::<String>(&mut buffer), Ok(Some("hello".to_owned())));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_serialize_into_reuses() {
+        let mut network_msg = serialize("a longer message".to_owned());
+        let capacity = network_msg.buf.capacity();
+        serialize_into(&mut network_msg, "short".to_owned());
+        assert_eq!(network_msg.buf.capacity(), capacity);
+
+        let mut buffer: VecDeque<u8> = frame(&network_msg).into();
+        assert_eq!(parse_one::<String>(&mut buffer), Ok(Some("short".to_owned())));
+    }
+
+    #[test]
+    fn test_message_too_long() {
+        let mut buffer: VecDeque<u8> = MsgLen::MAX.to_le_bytes().into_iter().collect();
+        buffer.extend([0; CHECKSUM_LEN]);
+        assert!(parse_one::<String>(&mut buffer).is_err());
+    }
+
+    #[test]
+    fn test_corrupted() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let mut frame = frame(&serialize("hello".to_owned()));
+        let last = frame.len() - 1;
+        frame[last] ^= 1;
+        let mut buffer: VecDeque<u8> = frame.into();
+        assert!(parse_one::<String>(&mut buffer).is_err());
+    }
+}
@@ -0,0 +1,430 @@
+//! Messages sent between the client and server, usually over the network.
+//!
+//! LATER These will form the basis of demo recording and replay.
+
+use std::fmt::{self, Display, Formatter};
+
+use nalgebra::UnitQuaternion;
+use serde::{Deserialize, Serialize};
+use strum_macros::IntoStaticStr;
+
+use crate::{
+    debug::{DebugShape, DebugWorldText, LogLine},
+    entities::{Cheats, Effect, Emote, Look, MatchPhase, VehicleKind},
+    input::Input,
+    Vec3,
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum ClientMessage {
+    Input(Input),
+    /// A line of chat, lines starting with `/` are commands, see `server::chat`.
+    Chat(String),
+    /// Play an emote on the player's cycle, rate limited by `sv_emote_interval`.
+    Emote(Emote),
+    Join,
+    Observe,
+    /// Choose how the player's cycle looks, sent after connecting.
+    Look(Look),
+    /// The client's fingerprint from `cl_identity_file`, sent after connecting.
+    Fingerprint(u64),
+    /// The player is (or is no longer) ready to start the match, see `MatchPhase::Warmup`.
+    Ready(bool),
+    /// Switch to another team, the server checks it keeps teams even.
+    Team(u8),
+    /// Reply to `ServerMessage::Ping` with the same number.
+    Pong(u32),
+    /// The server refuses (and logs) cheats unless `sv_cheats` is enabled.
+    Cheat(Cheat),
+    /// Ask for admin privileges.
+    ///
+    /// Optionally also ask the server to forward its log lines to this client.
+    AdminLogin {
+        password: String,
+        remote_log: bool,
+    },
+    /// Hash of the client's state, see `d_desync_check`.
+    DesyncCheck {
+        /// The server tick of the last applied update, see `Init::frame_number`.
+        frame_number: u32,
+        hash: u64,
+    },
+    /// Ask for this many updates per second, see `cl_updaterate`.
+    UpdateRate(f32),
+    /// The token from the previous connection's `Init`, sent after reconnecting
+    /// to get back the score and team, see `sv_reconnect_time`.
+    Reconnect(u64),
+    /// Whether to send `ServerMessage::Debug`, see `d_remote_debug`.
+    RemoteDebug(bool),
+    /// What to spawn as next time, see `cl_vehicle`.
+    Vehicle(VehicleKind),
+}
+
+/// A handle to a player, cycle or projectile sent over the network.
+///
+/// Unlike a bare index, it doesn't match another entity after the pool slot is reused.
+/// Clients spawn entities at the same handles as the server
+/// so received handles can be checked against the client's pools.
+///
+/// Messages which are only shown to players (chat, kills, summaries) use plain player indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct NetHandle {
+    pub index: u32,
+    pub generation: u32,
+}
+
+// LATER Since messages get serialized immediately, consider using slices instead of Vecs to avoid allocations.
+
+/// Message sent from server to client
+#[derive(Debug, Deserialize, Serialize, IntoStaticStr)]
+pub enum ServerMessage {
+    /// Initial game state that is sent to a new player upon connecting.
+    ///
+    /// This is intentionally separate from messages such as AddPlayer or SpawnCycle
+    /// because eventually those might trigger additional effects
+    /// such as info messages, sounds, particles, etc.
+    Init(Init),
+    /// Add a new player to the game.
+    AddPlayer(AddPlayer),
+    /// Remove the player and all data associated with him, for example when he disconnects.
+    RemovePlayer { player_handle: NetHandle },
+    /// This player is now observing.
+    Observe { player_handle: NetHandle },
+    /// This player is now spectating.
+    Spectate {
+        player_handle: NetHandle,
+        spectatee_handle: NetHandle,
+    },
+    /// This player is now playing.
+    Join { player_handle: NetHandle },
+    /// Spawn a new cycle for an existing player.
+    SpawnCycle(PlayerCycle),
+    /// The player changed how their cycle looks.
+    PlayerLook(PlayerLook),
+    /// The player's client told us its fingerprint.
+    PlayerFingerprint(PlayerFingerprint),
+    /// The player is (or is no longer) ready to start the match.
+    Ready {
+        player_handle: NetHandle,
+        ready: bool,
+    },
+    /// The player joined a team, either by choice or by autobalance.
+    PlayerTeam(PlayerTeam),
+    /// The match moved to another phase, e.g. warmup ended.
+    MatchPhase(MatchPhase),
+    /// The player turned cheats on or off.
+    PlayerCheats(PlayerCheats),
+    /// `sv_cheats` changed, clients use it for client-side cheats such as `d_fly_camera`.
+    CheatsAllowed(bool),
+    /// Somebody said something or the server is replying to a chat command.
+    Chat(ChatLine),
+    /// The player played an emote.
+    Emote {
+        player_handle: NetHandle,
+        emote: Emote,
+    },
+    /// The player left the map's bounds and has this many seconds to come back
+    /// or `None` if they came back (or were killed).
+    OutOfBounds {
+        player_index: u32,
+        time_left: Option<f32>,
+    },
+    /// The player's cycle was destroyed, they get a new one in this many seconds.
+    Respawn { player_index: u32, time_left: f32 },
+    /// Message of the day, sent after `Init`, see `sv_motd`.
+    Motd(String),
+    /// Measure the round trip time, the client should reply with `ClientMessage::Pong` immediately.
+    Ping(u32),
+    /// Somebody died, for the kill feed.
+    Kill(PlayerKill),
+    /// The player took one of the map's pickups, same order as the map's pickups.
+    Pickup {
+        player_handle: NetHandle,
+        pickup_index: u32,
+    },
+    /// The round ended, how everyone did.
+    MatchSummary(MatchSummary),
+    /// Scores and pings of all players, sent every `sv_ping_interval`.
+    Scores(Vec<PlayerScore>),
+    /// Remove the cycle from game state, for example when the player switches to observer mode.
+    DespawnCycle { cycle_handle: NetHandle },
+    /// Update the translations, rotations, velocities, etc. of everything.
+    Update(Update),
+    /// What happened during updates this client skipped because of its `cl_updaterate`,
+    /// sent right before the next update it gets.
+    Skipped { ticks: u32, effects: Vec<Effect> },
+    /// Debug texts and shapes from the server, only sent to clients which asked for them.
+    Debug(DebugItems),
+    /// Log lines from the server, only sent to admins who asked for them.
+    Log(Vec<LogLine>),
+    /// Reply to `ClientMessage::DesyncCheck` with the server's hash.
+    ///
+    /// If the hashes differ, the whole state is sent so the client can find which part differs.
+    DesyncCheck {
+        frame_number: u32,
+        hash: u64,
+        state: Option<StateSummary>,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Init {
+    pub player_handles: Vec<NetHandle>,
+    pub local_player_handle: NetHandle,
+    pub player_cycles: Vec<PlayerCycle>,
+    pub player_projectiles: Vec<PlayerProjectile>,
+    pub player_looks: Vec<PlayerLook>,
+    pub player_fingerprints: Vec<PlayerFingerprint>,
+    pub ready_handles: Vec<NetHandle>,
+    pub player_teams: Vec<PlayerTeam>,
+    /// Only players with some cheats on.
+    pub player_cheats: Vec<PlayerCheats>,
+    pub cheats_allowed: bool,
+    pub phase: MatchPhase,
+    /// `sv_name`
+    pub server_name: String,
+    /// `sv_region`
+    pub region: String,
+    /// `g_map`, the name of the map's manifest.
+    pub map: String,
+    /// `sv_icon_file` and `sv_accent_color`, clients should validate it again.
+    pub theme: ServerTheme,
+    /// Send this back in `ClientMessage::Reconnect` after losing connection.
+    pub reconnect_token: u64,
+    /// The server tick of the last `Update` before this. Clients add the `ticks`
+    /// of `Update` and `Skipped` to know which tick they're at.
+    pub frame_number: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AddPlayer {
+    pub player_handle: NetHandle,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlayerCycle {
+    pub player_handle: NetHandle,
+    pub cycle_handle: NetHandle,
+    pub vehicle: VehicleKind,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlayerLook {
+    pub player_handle: NetHandle,
+    pub look: Look,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlayerFingerprint {
+    pub player_handle: NetHandle,
+    pub fingerprint: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlayerTeam {
+    pub player_handle: NetHandle,
+    /// `None` if the player left teams, e.g. after `loadstate` from free for all.
+    pub team: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlayerCheats {
+    pub player_handle: NetHandle,
+    pub cheats: Cheats,
+}
+
+/// Cheat console commands.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum Cheat {
+    /// Toggle `Cheats::noclip`.
+    Noclip,
+    /// Toggle `Cheats::god`.
+    God,
+    /// LATER There are no weapons yet so this is always refused.
+    Give(String),
+    /// Move the cycle to the position and stop it.
+    Teleport([f32; 3]),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChatLine {
+    /// `None` if it's from the server.
+    pub player_index: Option<u32>,
+    pub text: String,
+    /// Sent using `/me`.
+    pub me: bool,
+}
+
+impl Display for ChatLine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match (self.player_index, self.me) {
+            (Some(player_index), true) => write!(f, "* player {} {}", player_index, self.text),
+            (Some(player_index), false) => write!(f, "player {}: {}", player_index, self.text),
+            (None, _) => write!(f, "server: {}", self.text),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlayerKill {
+    /// Same as the victim when killed by the map.
+    pub killer_index: u32,
+    pub victim_index: u32,
+    /// Other players who recently hit the victim.
+    pub assist_indices: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MatchSummary {
+    pub winner_index: Option<u32>,
+    pub players: Vec<PlayerSummary>,
+    pub awards: Vec<Award>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PlayerSummary {
+    pub player_index: u32,
+    pub frags: u32,
+    pub deaths: u32,
+    pub shots: u32,
+    pub hits: u32,
+    /// Most frags without dying.
+    pub best_streak: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Award {
+    pub kind: AwardKind,
+    pub player_index: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum AwardKind {
+    MostFrags,
+    BestAccuracy,
+    LongestStreak,
+}
+
+impl AwardKind {
+    /// The key of its name, see `client::locale`.
+    pub fn key(self) -> &'static str {
+        match self {
+            AwardKind::MostFrags => "award.most_frags",
+            AwardKind::BestAccuracy => "award.best_accuracy",
+            AwardKind::LongestStreak => "award.longest_streak",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlayerScore {
+    pub player_handle: NetHandle,
+    pub score: u32,
+    pub assists: u32,
+    pub ping: Option<u16>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlayerProjectile {
+    pub player_handle: NetHandle,
+    pub projectile_handle: NetHandle,
+}
+
+/// LATER Cycles have no riders, turrets or skeletal animations yet. If they get them,
+/// add a compact animation state per cycle here (an enum plus blend parameters like speed
+/// and aim) and let clients pose the skeleton from it instead of sending bone transforms.
+/// Aim doesn't need anything new, it's in `player_inputs`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Update {
+    /// Server ticks since the previous update, more than 1 with a lower `sv_snapshot_rate`.
+    pub ticks: u32,
+    pub player_inputs: Vec<PlayerInput>,
+    pub cycle_physics: Vec<CyclePhysics>,
+    /// Same order as the map's platforms.
+    pub platform_poses: Vec<PlatformPose>,
+    /// Which of the map's pickups are available, same order as in the manifest.
+    pub pickups: Vec<bool>,
+    pub player_powerups: Vec<PlayerPowerups>,
+    pub effects: Vec<Effect>,
+}
+
+/// Everything drawn by debug macros on the server since the last update.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DebugItems {
+    pub texts: Vec<String>,
+    pub shapes: Vec<DebugShape>,
+    pub world_texts: Vec<DebugWorldText>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlayerInput {
+    pub player_handle: NetHandle,
+    pub input: Input,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlayerPowerups {
+    pub player_handle: NetHandle,
+    pub armor: u32,
+    pub cloaked: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CyclePhysics {
+    pub cycle_handle: NetHandle,
+    pub translation: Vec3,
+    pub rotation: UnitQuaternion<f32>,
+    pub velocity: Vec3,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlatformPose {
+    pub translation: Vec3,
+    pub rotation: UnitQuaternion<f32>,
+}
+
+/// The parts of `GameState` which should be the same on the client and server.
+///
+/// Floats are quantized by `d_desync_check_precision`
+/// so tiny differences don't count as a desync.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StateSummary {
+    pub fields: Vec<StateField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct StateField {
+    /// E.g. `cycle 1 pos`.
+    pub name: String,
+    pub values: Vec<i64>,
+}
+
+impl StateSummary {
+    /// Describe the first field which is different or missing in `other`.
+    pub fn first_difference(&self, other: &StateSummary) -> Option<String> {
+        for field in &self.fields {
+            match other.fields.iter().find(|f| f.name == field.name) {
+                Some(f) if f.values != field.values => {
+                    return Some(format!("{}: {:?} vs {:?}", field.name, field.values, f.values));
+                }
+                Some(_) => {}
+                None => return Some(format!("{}: missing on the other side", field.name)),
+            }
+        }
+        other
+            .fields
+            .iter()
+            .find(|f| !self.fields.iter().any(|field| field.name == f.name))
+            .map(|f| format!("{}: missing on this side", f.name))
+    }
+}
+
+/// The server's icon and accent color - `sv_icon_file` and `sv_accent_color`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ServerTheme {
+    /// PNG data, empty means no icon.
+    pub icon: Vec<u8>,
+    /// RGB, `None` means the default text color.
+    pub accent: Option<[u8; 3]>,
+}
@@ -1,4 +1,28 @@
 //! The client in a client-server multiplayer game architecture.
 
+pub(crate) mod bindings;
+pub(crate) mod botclient;
+pub(crate) mod caster;
+pub(crate) mod decals;
+pub(crate) mod effects;
+pub(crate) mod environment;
+pub(crate) mod feedback;
 pub(crate) mod game;
+pub(crate) mod hud;
+pub(crate) mod input_record;
+pub(crate) mod inspector;
+pub(crate) mod lights;
+pub(crate) mod locale;
+pub(crate) mod mutes;
+pub(crate) mod presence;
+pub(crate) mod probe;
 pub(crate) mod process;
+pub(crate) mod profile;
+pub(crate) mod reconnect;
+pub(crate) mod ring;
+pub(crate) mod servers;
+pub(crate) mod smoothing;
+pub(crate) mod trails;
+pub(crate) mod viewmodel;
+pub(crate) mod window;
+pub(crate) mod wrecks;
@@ -0,0 +1,448 @@
+//! Mapping physical buttons to `Action`s and `Axis`es.
+//!
+//! Gameplay only looks at actions and axes so it doesn't care which device the input came from.
+//! A button bound to an axis pushes it one way while held, e.g. W is `+move` and S is `-move`.
+//! Bindings can be changed with the `bind` and `unbind` console commands.
+//!
+//! LATER Gamepads (gilrs) - sticks would set axes directly, buttons would be bound like keys.
+//!     Also rumble, see `client::feedback`.
+//! LATER Save bindings to a config file.
+
+use std::str::FromStr;
+
+use fxhash::FxHashMap;
+use fyrox::event::{MouseButton, ScanCode};
+
+use crate::{
+    common::{Action, Axis, Input},
+    prelude::*,
+};
+
+/// A physical key or button which can be bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Button {
+    Key(ScanCode),
+    Mouse(MouseButton),
+}
+
+impl FromStr for Button {
+    type Err = String;
+
+    /// Keys are named after `scan_codes` in lowercase,
+    /// mouse buttons are `mouse_left`, `mouse_right`, `mouse_middle` and `mouse<number>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let button = match s {
+            "mouse_left" => Button::Mouse(MouseButton::Left),
+            "mouse_right" => Button::Mouse(MouseButton::Right),
+            "mouse_middle" => Button::Mouse(MouseButton::Middle),
+            _ => {
+                if let Some(num) = s.strip_prefix("mouse").and_then(|num| num.parse().ok()) {
+                    Button::Mouse(MouseButton::Other(num))
+                } else if let Some(&(_, code)) =
+                    scan_codes::NAMES.iter().find(|(name, _)| *name == s)
+                {
+                    Button::Key(code)
+                } else {
+                    return Err(format!("unknown button {}", s));
+                }
+            }
+        };
+        Ok(button)
+    }
+}
+
+/// What a button does while held.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Binding {
+    Action(Action),
+    /// Pushes the axis towards the value - `+move` is `Axis::Move` with 1, `-move` with -1.
+    Axis(Axis, f32),
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let axis = |name: &str| Axis::from_str(name).map_err(|_| format!("unknown axis {}", name));
+        if let Some(name) = s.strip_prefix('+') {
+            Ok(Binding::Axis(axis(name)?, 1.0))
+        } else if let Some(name) = s.strip_prefix('-') {
+            Ok(Binding::Axis(axis(name)?, -1.0))
+        } else {
+            Action::from_str(s)
+                .map(Binding::Action)
+                .map_err(|_| format!("unknown action {}", s))
+        }
+    }
+}
+
+pub(crate) struct Bindings {
+    bindings: FxHashMap<Button, Binding>,
+    /// Buttons bound to an axis which are currently held.
+    held: Vec<Button>,
+}
+
+impl Bindings {
+    pub(crate) fn new() -> Self {
+        use scan_codes::*;
+
+        let defaults = [
+            (Button::Key(W), Binding::Axis(Axis::Move, 1.0)),
+            (Button::Key(S), Binding::Axis(Axis::Move, -1.0)),
+            (Button::Key(A), Binding::Axis(Axis::Steer, -1.0)),
+            (Button::Key(D), Binding::Axis(Axis::Steer, 1.0)),
+            (Button::Key(SPACE), Binding::Action(Action::Up)),
+            (Button::Key(L_SHIFT), Binding::Action(Action::Down)),
+            (Button::Key(L_CTRL), Binding::Action(Action::Boost)),
+            (Button::Key(Q), Binding::Action(Action::PrevWeapon)),
+            (Button::Key(E), Binding::Action(Action::NextWeapon)),
+            (Button::Key(R), Binding::Action(Action::Reload)),
+            (Button::Key(F), Binding::Action(Action::Flag)),
+            (Button::Key(G), Binding::Action(Action::Grenade)),
+            (Button::Key(M), Binding::Action(Action::Map)),
+            (Button::Key(TAB), Binding::Action(Action::Score)),
+            (Button::Key(ENTER), Binding::Action(Action::Chat)),
+            (Button::Key(PAUSE), Binding::Action(Action::Pause)),
+            (Button::Key(F12), Binding::Action(Action::Screenshot)),
+            (Button::Mouse(MouseButton::Left), Binding::Action(Action::Fire1)),
+            (Button::Mouse(MouseButton::Right), Binding::Action(Action::Fire2)),
+            (Button::Mouse(MouseButton::Middle), Binding::Action(Action::Zoom)),
+            (Button::Mouse(MouseButton::Other(8)), Binding::Action(Action::Marker1)),
+            (Button::Mouse(MouseButton::Other(9)), Binding::Action(Action::Marker2)),
+        ];
+
+        Self {
+            bindings: defaults.into_iter().collect(),
+            held: Vec::new(),
+        }
+    }
+
+    /// Change what the button does, `None` unbinds it.
+    pub(crate) fn bind(&mut self, input: &mut Input, button: Button, binding: Option<Binding>) {
+        // Release it first so the old binding doesn't get stuck.
+        self.button(input, button, false);
+        match binding {
+            Some(binding) => self.bindings.insert(button, binding),
+            None => self.bindings.remove(&button),
+        };
+    }
+
+    /// Update the input after a button was pressed or released.
+    pub(crate) fn button(&mut self, input: &mut Input, button: Button, pressed: bool) {
+        match self.bindings.get(&button).copied() {
+            Some(Binding::Action(action)) => input.set_pressed(action, pressed),
+            Some(Binding::Axis(axis, _)) => {
+                self.held.retain(|&held| held != button);
+                if pressed {
+                    self.held.push(button);
+                }
+                // Opposite buttons cancel out.
+                let value = self
+                    .held
+                    .iter()
+                    .filter_map(|held| match self.bindings[held] {
+                        Binding::Axis(held_axis, value) if held_axis == axis => Some(value),
+                        _ => None,
+                    })
+                    .sum();
+                input.set_axis(axis, value);
+            }
+            None => {}
+        }
+    }
+
+    /// E.g. when opening the console, otherwise the keys would stay pressed.
+    pub(crate) fn release_all(&mut self, input: &mut Input) {
+        self.held.clear();
+        input.release_all_keys();
+    }
+}
+
+/// Layout independant scancodes.
+///
+/// This is a separate mod so you can glob-import it.
+#[rustfmt::skip]
+// ...and also so I can stop rustfmt from mangling it.
+// Seriously, remove #[rustfmt::skip] and see what it does, I dare you.
+// I've never seen anybody ever format comments like that
+// and rustfmt does it by default without a way to disable it.
+// I. Just. Hate. It.
+pub(crate) mod scan_codes {
+    #![allow(dead_code)]
+
+    use fyrox::event::ScanCode;
+
+    // Apparently there are different numbering schemes all called "scancodes".
+    // This image is the least inaccurate for the one in winit (on Kubuntu 22.04):
+    // https://forum.thegamecreators.com/thread/145420
+    // Note that many keys are different (e.g. R_ALT, KP_ENTER, arrows, ...).
+
+    pub(crate) const ESC: ScanCode = 1;
+    pub(crate) const NUM1: ScanCode = 2;
+    pub(crate) const NUM2: ScanCode = 3;
+    pub(crate) const NUM3: ScanCode = 4;
+    pub(crate) const NUM4: ScanCode = 5;
+    pub(crate) const NUM5: ScanCode = 6;
+    pub(crate) const NUM6: ScanCode = 7;
+    pub(crate) const NUM7: ScanCode = 8;
+    pub(crate) const NUM8: ScanCode = 9;
+    pub(crate) const NUM9: ScanCode = 10;
+    pub(crate) const NUM0: ScanCode = 11;
+    pub(crate) const MINUS: ScanCode = 12;
+    pub(crate) const EQUALS: ScanCode = 13;
+    pub(crate) const BACKSPACE: ScanCode = 14;
+    pub(crate) const TAB: ScanCode = 15;
+    pub(crate) const Q: ScanCode = 16;
+    pub(crate) const W: ScanCode = 17;
+    pub(crate) const E: ScanCode = 18;
+    pub(crate) const R: ScanCode = 19;
+    pub(crate) const T: ScanCode = 20;
+    pub(crate) const Y: ScanCode = 21;
+    pub(crate) const U: ScanCode = 22;
+    pub(crate) const I: ScanCode = 23;
+    pub(crate) const O: ScanCode = 24;
+    pub(crate) const P: ScanCode = 25;
+    pub(crate) const LBRACKET: ScanCode = 26;
+    pub(crate) const RBRACKET: ScanCode = 27;
+    pub(crate) const ENTER: ScanCode = 28;
+    pub(crate) const L_CTRL: ScanCode = 29;
+    pub(crate) const A: ScanCode = 30;
+    pub(crate) const S: ScanCode = 31;
+    pub(crate) const D: ScanCode = 32;
+    pub(crate) const F: ScanCode = 33;
+    pub(crate) const G: ScanCode = 34;
+    pub(crate) const H: ScanCode = 35;
+    pub(crate) const J: ScanCode = 36;
+    pub(crate) const K: ScanCode = 37;
+    pub(crate) const L: ScanCode = 38;
+    pub(crate) const SEMICOLON: ScanCode = 39;
+    pub(crate) const APOSTROPHE: ScanCode = 40;
+    pub(crate) const BACKTICK: ScanCode = 41;
+    pub(crate) const L_SHIFT: ScanCode = 42;
+    pub(crate) const BACKSLASH: ScanCode = 43;
+    pub(crate) const Z: ScanCode = 44;
+    pub(crate) const X: ScanCode = 45;
+    pub(crate) const C: ScanCode = 46;
+    pub(crate) const V: ScanCode = 47;
+    pub(crate) const B: ScanCode = 48;
+    pub(crate) const N: ScanCode = 49;
+    pub(crate) const M: ScanCode = 50;
+    pub(crate) const COMMA: ScanCode = 51;
+    pub(crate) const PERIOD: ScanCode = 52;
+    pub(crate) const SLASH: ScanCode = 53;
+    pub(crate) const R_SHIFT: ScanCode = 54;
+    pub(crate) const KP_MULTIPLY: ScanCode = 55;
+    pub(crate) const L_ALT: ScanCode = 56;
+    pub(crate) const SPACE: ScanCode = 57;
+    pub(crate) const CAPS_LOCK: ScanCode = 58;
+    pub(crate) const F1: ScanCode = 59;
+    pub(crate) const F2: ScanCode = 60;
+    pub(crate) const F3: ScanCode = 61;
+    pub(crate) const F4: ScanCode = 62;
+    pub(crate) const F5: ScanCode = 63;
+    pub(crate) const F6: ScanCode = 64;
+    pub(crate) const F7: ScanCode = 65;
+    pub(crate) const F8: ScanCode = 66;
+    pub(crate) const F9: ScanCode = 67;
+    pub(crate) const F10: ScanCode = 68;
+    pub(crate) const F11: ScanCode = 69;
+    pub(crate) const F12: ScanCode = 70;
+    pub(crate) const KP7: ScanCode = 71;
+    pub(crate) const KP8: ScanCode = 72;
+    pub(crate) const KP9: ScanCode = 73;
+    pub(crate) const KP_MINUS: ScanCode = 74;
+    pub(crate) const KP4: ScanCode = 75;
+    pub(crate) const KP5: ScanCode = 76;
+    pub(crate) const KP6: ScanCode = 77;
+    pub(crate) const KP_PLUS: ScanCode = 78;
+    pub(crate) const KP1: ScanCode = 79;
+    pub(crate) const KP2: ScanCode = 80;
+    pub(crate) const KP3: ScanCode = 81;
+    pub(crate) const KP0: ScanCode = 82;
+    pub(crate) const KP_PERIOD: ScanCode = 83;
+    // 84
+    // 85
+    pub(crate) const BACKSLASH2: ScanCode = 86; // Between LSHIFT and Z, not on all keyboards
+    // 87
+    // 88
+    // 89
+    // 90
+    // 91
+    // 92
+    // 93
+    // 94
+    // 95
+    pub(crate) const KP_ENTER: ScanCode = 96;
+    pub(crate) const R_CTRL: ScanCode = 97;
+    pub(crate) const KP_DIVIDE: ScanCode = 98;
+    pub(crate) const PRINT_SCREEN: ScanCode = 99;
+    pub(crate) const R_ALT: ScanCode = 100;
+    // 101
+    pub(crate) const HOME: ScanCode = 102;
+    pub(crate) const UP_ARROW: ScanCode = 103;
+    pub(crate) const PG_UP: ScanCode = 104;
+    pub(crate) const LEFT_ARROW: ScanCode = 105;
+    pub(crate) const RIGHT_ARROW: ScanCode = 106;
+    pub(crate) const END: ScanCode = 107;
+    pub(crate) const DOWN_ARROW: ScanCode = 108;
+    pub(crate) const PG_DOWN: ScanCode = 109;
+    pub(crate) const INSERT: ScanCode = 110;
+    pub(crate) const DELETE: ScanCode = 111;
+    // 112
+    // 113
+    // 114
+    // 115
+    // 116
+    // 117
+    // 118
+    pub(crate) const PAUSE: ScanCode = 119;
+    // 120
+    // 121
+    // 122
+    // 123
+    // 124
+    pub(crate) const L_SUPER: ScanCode = 125;
+    pub(crate) const R_SUPER: ScanCode = 126;
+    pub(crate) const MENU: ScanCode = 127;
+
+    /// Names for the `bind` command.
+    pub(crate) const NAMES: &[(&str, ScanCode)] = &[
+        ("esc", ESC),
+        ("num1", NUM1),
+        ("num2", NUM2),
+        ("num3", NUM3),
+        ("num4", NUM4),
+        ("num5", NUM5),
+        ("num6", NUM6),
+        ("num7", NUM7),
+        ("num8", NUM8),
+        ("num9", NUM9),
+        ("num0", NUM0),
+        ("minus", MINUS),
+        ("equals", EQUALS),
+        ("backspace", BACKSPACE),
+        ("tab", TAB),
+        ("q", Q),
+        ("w", W),
+        ("e", E),
+        ("r", R),
+        ("t", T),
+        ("y", Y),
+        ("u", U),
+        ("i", I),
+        ("o", O),
+        ("p", P),
+        ("lbracket", LBRACKET),
+        ("rbracket", RBRACKET),
+        ("enter", ENTER),
+        ("l_ctrl", L_CTRL),
+        ("a", A),
+        ("s", S),
+        ("d", D),
+        ("f", F),
+        ("g", G),
+        ("h", H),
+        ("j", J),
+        ("k", K),
+        ("l", L),
+        ("semicolon", SEMICOLON),
+        ("apostrophe", APOSTROPHE),
+        ("backtick", BACKTICK),
+        ("l_shift", L_SHIFT),
+        ("backslash", BACKSLASH),
+        ("z", Z),
+        ("x", X),
+        ("c", C),
+        ("v", V),
+        ("b", B),
+        ("n", N),
+        ("m", M),
+        ("comma", COMMA),
+        ("period", PERIOD),
+        ("slash", SLASH),
+        ("r_shift", R_SHIFT),
+        ("kp_multiply", KP_MULTIPLY),
+        ("l_alt", L_ALT),
+        ("space", SPACE),
+        ("caps_lock", CAPS_LOCK),
+        ("f1", F1),
+        ("f2", F2),
+        ("f3", F3),
+        ("f4", F4),
+        ("f5", F5),
+        ("f6", F6),
+        ("f7", F7),
+        ("f8", F8),
+        ("f9", F9),
+        ("f10", F10),
+        ("f11", F11),
+        ("f12", F12),
+        ("kp7", KP7),
+        ("kp8", KP8),
+        ("kp9", KP9),
+        ("kp_minus", KP_MINUS),
+        ("kp4", KP4),
+        ("kp5", KP5),
+        ("kp6", KP6),
+        ("kp_plus", KP_PLUS),
+        ("kp1", KP1),
+        ("kp2", KP2),
+        ("kp3", KP3),
+        ("kp0", KP0),
+        ("kp_period", KP_PERIOD),
+        ("backslash2", BACKSLASH2),
+        ("kp_enter", KP_ENTER),
+        ("r_ctrl", R_CTRL),
+        ("kp_divide", KP_DIVIDE),
+        ("print_screen", PRINT_SCREEN),
+        ("r_alt", R_ALT),
+        ("home", HOME),
+        ("up_arrow", UP_ARROW),
+        ("pg_up", PG_UP),
+        ("left_arrow", LEFT_ARROW),
+        ("right_arrow", RIGHT_ARROW),
+        ("end", END),
+        ("down_arrow", DOWN_ARROW),
+        ("pg_down", PG_DOWN),
+        ("insert", INSERT),
+        ("delete", DELETE),
+        ("pause", PAUSE),
+        ("l_super", L_SUPER),
+        ("r_super", R_SUPER),
+        ("menu", MENU),
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bindings() {
+        assert_eq!("w".parse(), Ok(Button::Key(scan_codes::W)));
+        assert_eq!("l_shift".parse(), Ok(Button::Key(scan_codes::L_SHIFT)));
+        assert_eq!("mouse8".parse(), Ok(Button::Mouse(MouseButton::Other(8))));
+        assert!("nope".parse::<Button>().is_err());
+        assert_eq!("fire1".parse(), Ok(Binding::Action(Action::Fire1)));
+        assert_eq!("-steer".parse(), Ok(Binding::Axis(Axis::Steer, -1.0)));
+        assert!("+fire1".parse::<Binding>().is_err());
+
+        let mut bindings = Bindings::new();
+        let mut input = Input::default();
+        bindings.button(&mut input, Button::Key(scan_codes::W), true);
+        bindings.button(&mut input, Button::Key(scan_codes::S), true);
+        assert_eq!(input.axis(Axis::Move), 0.0);
+        bindings.button(&mut input, Button::Key(scan_codes::S), false);
+        assert_eq!(input.axis(Axis::Move), 1.0);
+        bindings.button(&mut input, Button::Mouse(MouseButton::Left), true);
+        assert!(input.pressed(Action::Fire1));
+
+        bindings.bind(&mut input, Button::Key(scan_codes::W), None);
+        assert_eq!(input.axis(Axis::Move), 0.0);
+        bindings.button(&mut input, Button::Key(scan_codes::W), true);
+        assert_eq!(input.axis(Axis::Move), 0.0);
+
+        bindings.release_all(&mut input);
+        assert!(!input.pressed(Action::Fire1));
+    }
+}
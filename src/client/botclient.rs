@@ -0,0 +1,142 @@
+//! Headless bot clients for load testing - `rustcycles botclient`.
+//!
+//! Connects `d_botclient_count` clients to a running server, each sending random inputs
+//! at the client tick rate. Nothing is simulated or rendered on this side
+//! so one process can run many of them.
+//! Prints bandwidth and how many updates each bot gets per second,
+//! tick times are printed by the server itself.
+
+use std::{
+    net::{SocketAddr, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand::{Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+use crate::{
+    common::{
+        messages::{ClientMessage, ServerMessage},
+        net::{self, Connection, TcpConnection},
+        Deg, Input, InputExt,
+    },
+    prelude::*,
+};
+
+pub(crate) fn run(cvars: &Cvars) {
+    let addr: SocketAddr = match cvars.d_botclient_server.parse() {
+        Ok(addr) => addr,
+        Err(err) => {
+            println!("Invalid server address {}: {}", cvars.d_botclient_server, err);
+            return;
+        }
+    };
+
+    let mut bots = Vec::new();
+    for i in 0..cvars.d_botclient_count {
+        let stream = match TcpStream::connect(addr) {
+            Ok(stream) => stream,
+            Err(err) => {
+                println!("Bot {} failed to connect to {}: {}", i, addr, err);
+                return;
+            }
+        };
+        stream.set_nodelay(true).unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let mut conn = TcpConnection::new(stream, addr);
+        // Sending is only an error if the server closed the connection,
+        // receiving will notice that.
+        let _ = conn.send(&net::serialize(ClientMessage::Join));
+        bots.push(conn);
+    }
+    println!("Connected {} bots to {}", bots.len(), addr);
+
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(cvars.d_seed);
+    let mut yaw = 0.0;
+
+    // Same as in ClientGame::update.
+    let dt = Duration::from_secs_f64(1.0 / 60.0);
+    let start = Instant::now();
+    let mut next_tick = start;
+    let mut report = Report::new(start);
+    loop {
+        if cvars.d_botclient_seconds > 0.0
+            && start.elapsed().as_secs_f32() > cvars.d_botclient_seconds
+        {
+            break;
+        }
+
+        bots.retain_mut(|conn| {
+            yaw += rng.gen_range(-5.0..5.0);
+            let mut input = Input::default();
+            input.yaw = Deg(yaw);
+            input.randomize(&mut rng);
+            let _ = conn.send(&net::serialize(ClientMessage::Input(input)));
+
+            let (msgs, closed) = conn.receive_sm();
+            for msg in msgs {
+                if let ServerMessage::Update(_) = msg {
+                    report.updates += 1;
+                }
+            }
+            !closed
+        });
+        if bots.is_empty() {
+            println!("All bots were disconnected");
+            break;
+        }
+
+        report.print_every_second(&bots);
+
+        next_tick += dt;
+        let now = Instant::now();
+        if next_tick > now {
+            thread::sleep(next_tick - now);
+        }
+    }
+}
+
+struct Report {
+    start: Instant,
+    updates: usize,
+    bytes_sent: usize,
+    bytes_received: usize,
+}
+
+impl Report {
+    fn new(start: Instant) -> Self {
+        Self {
+            start,
+            updates: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
+        }
+    }
+
+    fn print_every_second(&mut self, bots: &[TcpConnection]) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        if elapsed < 1.0 {
+            return;
+        }
+
+        // Disconnected bots are no longer counted so this can go down.
+        let bytes_sent: usize = bots.iter().map(|conn| conn.bytes_sent).sum();
+        let bytes_received: usize = bots.iter().map(|conn| conn.bytes_received).sum();
+        let sent = bytes_sent - self.bytes_sent.min(bytes_sent);
+        let received = bytes_received - self.bytes_received.min(bytes_received);
+
+        println!(
+            "bots {:>4}  updates/s per bot {:>5.1}  out {:>8.1} KiB/s  in {:>8.1} KiB/s",
+            bots.len(),
+            self.updates as f64 / bots.len() as f64 / elapsed,
+            sent as f64 / 1024.0 / elapsed,
+            received as f64 / 1024.0 / elapsed,
+        );
+
+        self.start = Instant::now();
+        self.updates = 0;
+        self.bytes_sent = bytes_sent;
+        self.bytes_received = bytes_received;
+    }
+}
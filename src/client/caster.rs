@@ -0,0 +1,149 @@
+//! Casting mode for observers - `cl_caster`.
+//!
+//! Shows colored outlines and nameplates on all cycles
+//! and lets the caster switch between player views using number keys
+//! (1-9 follow a player, 0 goes back to the free camera).
+//! Everything is local, the server still sees the caster as observing.
+//!
+//! LATER Names once the client keeps them, health once there is health.
+//! LATER Real see-through outlines need an outline pass, for now they're boxes made of debug lines.
+
+use fyrox::{
+    core::math::aabb::AxisAlignedBoundingBox,
+    gui::{
+        brush::Brush,
+        message::MessageDirection,
+        text::{TextBuilder, TextMessage},
+        widget::{WidgetBuilder, WidgetMessage},
+        UiNode, UserInterface,
+    },
+};
+
+use crate::{
+    client::game::player_color,
+    common::{
+        entities::{Cycle, Player, PlayerState},
+        GameState,
+    },
+    prelude::*,
+};
+
+/// Half extents of the outline, slightly larger than the cycle's collider.
+const OUTLINE_HALF_EXTENTS: Vec3 = Vec3::new(0.15, 0.3, 0.3);
+
+/// How high above the cycle nameplates are.
+const NAMEPLATE_HEIGHT: f32 = 0.6;
+
+pub(crate) struct Caster {
+    /// Preallocated labels, see `hud_nameplates_max`.
+    nameplates: Vec<Handle<UiNode>>,
+    /// Which playing player to follow, counting from 1 like the number keys, 0 is the free camera.
+    slot: usize,
+}
+
+impl Caster {
+    /// Has to be created before the console because of the z index issue.
+    pub(crate) fn new(cvars: &Cvars, ui: &mut UserInterface) -> Self {
+        let mut nameplates = Vec::new();
+        for _ in 0..cvars.hud_nameplates_max {
+            let handle = TextBuilder::new(WidgetBuilder::new().with_visibility(false))
+                .with_shadow(true)
+                .build(&mut ui.build_ctx());
+            nameplates.push(handle);
+        }
+        Self {
+            nameplates,
+            slot: 0,
+        }
+    }
+
+    /// A number key was pressed.
+    pub(crate) fn select(&mut self, slot: usize) {
+        self.slot = slot;
+    }
+
+    /// Whether the local player is casting.
+    pub(crate) fn active(cvars: &Cvars, gs: &GameState, local_player: Handle<Player>) -> bool {
+        cvars.cl_caster && gs.players[local_player].ps == PlayerState::Observing
+    }
+
+    /// The cycle whose view the caster selected, if any.
+    pub(crate) fn followed_cycle(
+        &self,
+        cvars: &Cvars,
+        gs: &GameState,
+        local_player: Handle<Player>,
+    ) -> Option<Handle<Cycle>> {
+        if self.slot == 0 || !Self::active(cvars, gs, local_player) {
+            return None;
+        }
+        playing_cycles(gs).nth(self.slot - 1)
+    }
+
+    /// Draw outlines and position nameplates.
+    pub(crate) fn update(
+        &mut self,
+        cvars: &Cvars,
+        engine: &mut Engine,
+        gs: &GameState,
+        local_player: Handle<Player>,
+        camera_handle: Handle<Node>,
+    ) {
+        let active = Self::active(cvars, gs, local_player);
+        let frame_size = engine.renderer.get_frame_size();
+        let screen_size = Vector2::new(frame_size.0 as f32, frame_size.1 as f32);
+        let scene = &mut engine.scenes[gs.scene_handle];
+        let ui = &mut engine.user_interface;
+
+        let mut labels = self.nameplates.iter();
+        if active {
+            let aabb =
+                AxisAlignedBoundingBox::from_min_max(-OUTLINE_HALF_EXTENTS, OUTLINE_HALF_EXTENTS);
+            for (i, cycle_handle) in playing_cycles(gs).enumerate() {
+                let cycle = &gs.cycles[cycle_handle];
+                let color = player_color(cvars, gs, cycle.player_handle);
+                let body = &scene.graph[cycle.body_handle];
+                let transform = body.global_transform();
+                let pos = body.global_position();
+                scene.drawing_context.draw_oob(&aabb, transform, color);
+
+                let camera = scene.graph[camera_handle].as_camera();
+                let screen_pos = match camera.project(pos + UP * NAMEPLATE_HEIGHT, screen_size) {
+                    Some(screen_pos) => screen_pos,
+                    None => continue,
+                };
+                let label = match labels.next() {
+                    Some(&label) => label,
+                    None => continue,
+                };
+                // The number is the key to follow them.
+                let text = format!("{} - player {}", i + 1, cycle.player_handle.index());
+                ui.send_message(TextMessage::text(label, MessageDirection::ToWidget, text));
+                ui.send_message(WidgetMessage::foreground(
+                    label,
+                    MessageDirection::ToWidget,
+                    Brush::Solid(color),
+                ));
+                ui.send_message(WidgetMessage::desired_position(
+                    label,
+                    MessageDirection::ToWidget,
+                    screen_pos,
+                ));
+                ui.send_message(WidgetMessage::visibility(label, MessageDirection::ToWidget, true));
+            }
+        }
+
+        // Hide the rest
+        for &label in labels {
+            ui.send_message(WidgetMessage::visibility(label, MessageDirection::ToWidget, false));
+        }
+    }
+}
+
+/// Cycles of playing players in a stable order so number keys keep pointing at the same player.
+fn playing_cycles(gs: &GameState) -> impl Iterator<Item = Handle<Cycle>> + '_ {
+    gs.players
+        .iter()
+        .filter(|player| player.ps == PlayerState::Playing)
+        .filter_map(|player| player.cycle_handle)
+}
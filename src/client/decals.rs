@@ -0,0 +1,168 @@
+//! Marks left on the map by hits - scorches under explosions and projectile impacts.
+//!
+//! Placed from replicated effects so they're only on the client.
+//! Up to `r_decals_max` marks stay on the map, they fade out at the end of `r_decal_time`.
+//!
+//! LATER Changing `r_decals_max` only takes effect after reconnecting.
+//! LATER Decals project on cycles driving through them too, use a different decal layer.
+//! LATER Rail burns once there is a rail weapon.
+//! LATER Proper textures, for now it's a generated round spot.
+
+use std::f32::consts::PI;
+
+use fyrox::{
+    resource::texture::{Texture, TextureKind, TexturePixelKind},
+    scene::decal::DecalBuilder,
+};
+
+use crate::{client::ring::Ring, common::entities::Effect, prelude::*};
+
+/// Width of the texture in pixels, it's square.
+const TEXTURE_SIZE: u32 = 64;
+
+/// Size of the scorch under an explosion in meters.
+const SCORCH_SIZE: f32 = 1.5;
+/// Explosions further than this above the map don't leave a scorch.
+const SCORCH_REACH: f32 = 1.5;
+const SCORCH_COLOR: Color = Color::opaque(20, 20, 20);
+
+/// Size of the mark where a projectile hit the map in meters.
+const IMPACT_SIZE: f32 = 0.4;
+const IMPACT_COLOR: Color = Color::opaque(60, 50, 40);
+
+/// How deep into the surface decals project.
+const DEPTH: f32 = 0.3;
+/// Decals fade out during the last this many seconds.
+const FADE_TIME: f32 = 2.0;
+
+pub(crate) struct Decals {
+    decals: Ring<Decal>,
+}
+
+struct Decal {
+    node: Handle<Node>,
+    /// Game time when the decal disappears, `None` if it's not in use.
+    end: Option<f32>,
+    /// Color without fading.
+    color: Color,
+}
+
+impl Decals {
+    pub(crate) fn new(cvars: &Cvars, scene: &mut Scene) -> Self {
+        let texture = spot_texture();
+        let decals = Ring::new(cvars.r_decals_max, || {
+            let mut builder = DecalBuilder::new(BaseBuilder::new().with_visibility(false));
+            if let Some(texture) = &texture {
+                builder = builder.with_diffuse_texture(texture.clone());
+            }
+            Decal {
+                node: builder.build(&mut scene.graph),
+                end: None,
+                color: Color::WHITE,
+            }
+        });
+        Self { decals }
+    }
+
+    /// Leave a mark for an effect the server sent us if it hit the map.
+    pub(crate) fn spawn(
+        &mut self,
+        cvars: &Cvars,
+        scene: &mut Scene,
+        game_time: f32,
+        effect: Effect,
+    ) {
+        if cvars.r_decal_time <= 0.0 {
+            return;
+        }
+
+        match effect {
+            Effect::Explosion { pos, .. } => {
+                // Explosions are where the cycle got hit, the scorch goes on the map below.
+                let hits =
+                    trace_line(scene, pos, DOWN * SCORCH_REACH, TraceOptions::filter(!IG_ENTITIES));
+                if let Some(hit) = hits.first() {
+                    let (pos, normal) = (hit.position.coords, hit.normal);
+                    self.place(cvars, scene, game_time, pos, normal, SCORCH_SIZE, SCORCH_COLOR);
+                }
+            }
+            Effect::Impact { pos, normal } => {
+                self.place(cvars, scene, game_time, pos, normal, IMPACT_SIZE, IMPACT_COLOR);
+            }
+            Effect::JumpPad { .. } | Effect::Teleport { .. } => {}
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn place(
+        &mut self,
+        cvars: &Cvars,
+        scene: &mut Scene,
+        game_time: f32,
+        pos: Vec3,
+        normal: Vec3,
+        size: f32,
+        color: Color,
+    ) {
+        let decal = self.decals.next_mut();
+        decal.end = Some(game_time + cvars.r_decal_time);
+        decal.color = color;
+
+        // Decals project along their local Y axis.
+        let rot = UnitQuaternion::rotation_between(&UP, &normal)
+            .unwrap_or_else(|| UnitQuaternion::from_axis_angle(&FORWARD_AXIS, PI));
+        // Pseudorandom spin so the same marks next to each other don't look tiled.
+        let spin = UnitQuaternion::from_axis_angle(&UP_AXIS, (pos.x + pos.z) * 10.0);
+        let node = &mut scene.graph[decal.node];
+        node.set_visibility(true);
+        node.local_transform_mut()
+            .set_position(pos)
+            .set_rotation(rot * spin)
+            .set_scale(v!(size, DEPTH, size));
+        node.as_decal_mut().set_color(color);
+    }
+
+    /// Fade out old decals and hide finished ones.
+    pub(crate) fn update(&mut self, scene: &mut Scene, game_time: f32) {
+        for decal in self.decals.iter_mut() {
+            let Some(time) = decal.end else {
+                continue;
+            };
+            let left = time - game_time;
+            if left <= 0.0 {
+                decal.end = None;
+                scene.graph[decal.node].set_visibility(false);
+                continue;
+            }
+
+            let alpha = (left / FADE_TIME).min(1.0);
+            let Color { r, g, b, .. } = decal.color;
+            let color = Color::from_rgba(r, g, b, (alpha * 255.0) as u8);
+            scene.graph[decal.node].as_decal_mut().set_color(color);
+        }
+    }
+}
+
+/// A white round spot with soft edges, `None` if Fyrox rejects it.
+fn spot_texture() -> Option<Texture> {
+    let mut bytes = Vec::with_capacity((TEXTURE_SIZE * TEXTURE_SIZE * 4) as usize);
+    let half = TEXTURE_SIZE as f32 / 2.0;
+    for y in 0..TEXTURE_SIZE {
+        for x in 0..TEXTURE_SIZE {
+            let dx = (x as f32 + 0.5 - half) / half;
+            let dy = (y as f32 + 0.5 - half) / half;
+            let dist = (dx * dx + dy * dy).sqrt();
+            let alpha = ((1.0 - dist) * 2.0).clamp(0.0, 1.0);
+            bytes.extend_from_slice(&[255, 255, 255, (alpha * 255.0) as u8]);
+        }
+    }
+    let kind = TextureKind::Rectangle {
+        width: TEXTURE_SIZE,
+        height: TEXTURE_SIZE,
+    };
+    let texture = Texture::from_bytes(kind, TexturePixelKind::RGBA8, bytes, false);
+    if texture.is_none() {
+        dbg_logf!("failed to create decal texture");
+    }
+    texture
+}
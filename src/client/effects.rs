@@ -0,0 +1,281 @@
+//! Particle effects - explosions, impacts, boost exhaust, map triggers and emotes.
+//!
+//! Each kind has a `Ring` of `r_effects_pool_size` particle systems,
+//! spawning an effect restarts the oldest one.
+//! `a11y_reduced_effects` keeps only the ones which show hits.
+//!
+//! LATER Resize the rings when `r_effects_pool_size` changes, now it needs a reconnect.
+
+use fxhash::FxHashMap;
+use fyrox::{
+    core::{
+        color_gradient::{ColorGradient, GradientPoint},
+        numeric_range::NumericRange,
+    },
+    scene::particle_system::{
+        emitter::{base::BaseEmitterBuilder, sphere::SphereEmitterBuilder},
+        ParticleSystem, ParticleSystemBuilder,
+    },
+};
+
+use crate::{
+    client::ring::Ring,
+    common::{
+        entities::{Cycle, Effect, Player, PlayerState},
+        Axis, GameState,
+    },
+    prelude::*,
+};
+
+/// How long a particle system stays enabled after being spawned.
+/// Should be longer than the particles' lifetime.
+const EXPLOSION_DURATION: f32 = 1.5;
+const IMPACT_DURATION: f32 = 0.5;
+const BOOST_DURATION: f32 = 0.4;
+const EMOTE_DURATION: f32 = 1.5;
+const TELEPORT_DURATION: f32 = 1.0;
+
+/// How often cycles going forward emit a puff of exhaust.
+const BOOST_INTERVAL: f32 = 0.05;
+
+pub(crate) struct Effects {
+    explosions: EffectPool,
+    impacts: EffectPool,
+    boosts: EffectPool,
+    emotes: EffectPool,
+    /// Also used for jump pads.
+    teleports: EffectPool,
+    /// When each cycle last emitted exhaust.
+    last_boost: FxHashMap<Handle<Cycle>, f32>,
+}
+
+impl Effects {
+    pub(crate) fn new(cvars: &Cvars, scene: &mut Scene) -> Self {
+        let size = cvars.r_effects_pool_size;
+        Self {
+            explosions: EffectPool::new(scene, size, EXPLOSION_DURATION, build_explosion),
+            impacts: EffectPool::new(scene, size, IMPACT_DURATION, build_impact),
+            boosts: EffectPool::new(scene, size, BOOST_DURATION, build_boost),
+            emotes: EffectPool::new(scene, size, EMOTE_DURATION, build_emote),
+            teleports: EffectPool::new(scene, size, TELEPORT_DURATION, build_teleport),
+            last_boost: FxHashMap::default(),
+        }
+    }
+
+    /// Show an effect the server sent us.
+    pub(crate) fn spawn(
+        &mut self,
+        cvars: &Cvars,
+        scene: &mut Scene,
+        gs: &GameState,
+        effect: Effect,
+    ) {
+        if !cvars.r_effects {
+            return;
+        }
+
+        match effect {
+            Effect::Explosion { pos, .. } => self.explosions.spawn(scene, gs.game_time, pos, UP),
+            Effect::Impact { pos, normal } => self.impacts.spawn(scene, gs.game_time, pos, normal),
+            _ if cvars.a11y_reduced_effects => {}
+            Effect::JumpPad { pos } => self.teleports.spawn(scene, gs.game_time, pos, UP),
+            Effect::Teleport { from, to } => {
+                self.teleports.spawn(scene, gs.game_time, from, UP);
+                self.teleports.spawn(scene, gs.game_time, to, UP);
+            }
+        }
+    }
+
+    /// Sparkles above the player's cycle, nothing if they don't have one.
+    ///
+    /// LATER A different effect and a sound for each emote.
+    pub(crate) fn emote(
+        &mut self,
+        cvars: &Cvars,
+        scene: &mut Scene,
+        gs: &GameState,
+        player_handle: Handle<Player>,
+    ) {
+        if !cvars.r_effects || cvars.a11y_reduced_effects {
+            return;
+        }
+        if let Some(cycle_handle) = gs.players[player_handle].cycle_handle {
+            let pos = scene.graph[gs.cycles[cycle_handle].body_handle].global_position() + UP;
+            self.emotes.spawn(scene, gs.game_time, pos, UP);
+        }
+    }
+
+    /// Emit exhaust behind accelerating cycles and disable finished effects.
+    ///
+    /// LATER Use `Action::Boost` once there is a boost mechanic, for now any cycle going forward "boosts".
+    pub(crate) fn update(&mut self, cvars: &Cvars, scene: &mut Scene, gs: &GameState) {
+        self.last_boost.retain(|&handle, _| gs.cycles.is_valid_handle(handle));
+
+        if cvars.r_effects && !cvars.a11y_reduced_effects {
+            for (cycle_handle, cycle) in gs.cycles.pair_iter() {
+                let player = &gs.players[cycle.player_handle];
+                if player.ps != PlayerState::Playing || player.input.axis(Axis::Move) <= 0.0 {
+                    continue;
+                }
+
+                let last = self.last_boost.entry(cycle_handle).or_insert(f32::NEG_INFINITY);
+                if gs.game_time - *last < BOOST_INTERVAL {
+                    continue;
+                }
+                *last = gs.game_time;
+
+                let body = &scene.graph[cycle.body_handle];
+                let back = body.global_transform().transform_vector(&BACK).normalize();
+                let pos = body.global_position() + back * 0.3;
+                self.boosts.spawn(scene, gs.game_time, pos, back);
+            }
+        }
+
+        self.explosions.update(scene, gs.game_time);
+        self.impacts.update(scene, gs.game_time);
+        self.boosts.update(scene, gs.game_time);
+        self.emotes.update(scene, gs.game_time);
+        self.teleports.update(scene, gs.game_time);
+    }
+}
+
+/// Particle systems of one kind, reused in a ring.
+struct EffectPool {
+    effects: Ring<PooledEffect>,
+    duration: f32,
+}
+
+struct PooledEffect {
+    node: Handle<Node>,
+    /// Game time when the node should be disabled, `None` if it's not in use.
+    end: Option<f32>,
+}
+
+impl EffectPool {
+    fn new(
+        scene: &mut Scene,
+        size: usize,
+        duration: f32,
+        build: fn(&mut Scene) -> Handle<Node>,
+    ) -> Self {
+        let effects = Ring::new(size, || {
+            let node = build(scene);
+            scene.graph[node].cast_mut::<ParticleSystem>().unwrap().set_enabled(false);
+            PooledEffect { node, end: None }
+        });
+        Self { effects, duration }
+    }
+
+    /// Restart the oldest particle system at `pos`, pointing along `dir`.
+    fn spawn(&mut self, scene: &mut Scene, game_time: f32, pos: Vec3, dir: Vec3) {
+        let effect = self.effects.next_mut();
+        effect.end = Some(game_time + self.duration);
+
+        let rot =
+            UnitQuaternion::rotation_between(&UP, &dir).unwrap_or_else(UnitQuaternion::identity);
+        let node = &mut scene.graph[effect.node];
+        node.local_transform_mut().set_position(pos).set_rotation(rot);
+        let ps = node.cast_mut::<ParticleSystem>().unwrap();
+        ps.clear_particles();
+        ps.set_enabled(true);
+    }
+
+    fn update(&mut self, scene: &mut Scene, game_time: f32) {
+        for effect in self.effects.iter_mut() {
+            if effect.end.map_or(false, |end| end <= game_time) {
+                effect.end = None;
+                scene.graph[effect.node]
+                    .cast_mut::<ParticleSystem>()
+                    .unwrap()
+                    .set_enabled(false);
+            }
+        }
+    }
+}
+
+fn gradient(points: &[(f32, Color)]) -> ColorGradient {
+    let mut gradient = ColorGradient::new();
+    for &(location, color) in points {
+        gradient.add_point(GradientPoint::new(location, color));
+    }
+    gradient
+}
+
+/// A burst of particles which doesn't repeat.
+fn burst(count: u32, radius: f32, lifetime: (f32, f32), size: (f32, f32)) -> BaseEmitterBuilder {
+    BaseEmitterBuilder::new()
+        .with_max_particles(count)
+        .with_spawn_rate(count * 60)
+        .with_resurrect_particles(false)
+        .with_lifetime_range(NumericRange::new(lifetime.0, lifetime.1))
+        .with_size_range(NumericRange::new(size.0, size.1))
+        .with_x_velocity_range(NumericRange::new(-radius, radius))
+        .with_z_velocity_range(NumericRange::new(-radius, radius))
+}
+
+fn build_explosion(scene: &mut Scene) -> Handle<Node> {
+    let emitter = burst(100, 0.08, (0.5, 1.2), (0.1, 0.3))
+        .with_y_velocity_range(NumericRange::new(-0.08, 0.08));
+    ParticleSystemBuilder::new(BaseBuilder::new())
+        .with_acceleration(v!(0 -0.0005 0))
+        .with_color_over_lifetime_gradient(gradient(&[
+            (0.0, Color::opaque(255, 255, 200)),
+            (0.2, ORANGE),
+            (1.0, Color::from_rgba(60, 60, 60, 0)),
+        ]))
+        .with_emitters(vec![SphereEmitterBuilder::new(emitter).with_radius(0.3).build()])
+        .build(&mut scene.graph)
+}
+
+fn build_impact(scene: &mut Scene) -> Handle<Node> {
+    // Local Y is the surface normal so sparks fly away from it.
+    let emitter = burst(20, 0.03, (0.2, 0.4), (0.02, 0.05))
+        .with_y_velocity_range(NumericRange::new(0.02, 0.06));
+    ParticleSystemBuilder::new(BaseBuilder::new())
+        .with_acceleration(v!(0 -0.001 0))
+        .with_color_over_lifetime_gradient(gradient(&[
+            (0.0, WHITE),
+            (1.0, Color::from_rgba(255, 200, 100, 0)),
+        ]))
+        .with_emitters(vec![SphereEmitterBuilder::new(emitter).with_radius(0.05).build()])
+        .build(&mut scene.graph)
+}
+
+fn build_boost(scene: &mut Scene) -> Handle<Node> {
+    // Local Y points backwards from the cycle.
+    let emitter = burst(5, 0.005, (0.2, 0.3), (0.05, 0.1))
+        .with_y_velocity_range(NumericRange::new(0.01, 0.02));
+    ParticleSystemBuilder::new(BaseBuilder::new())
+        .with_color_over_lifetime_gradient(gradient(&[
+            (0.0, CYAN),
+            (1.0, Color::from_rgba(0, 100, 255, 0)),
+        ]))
+        .with_emitters(vec![SphereEmitterBuilder::new(emitter).with_radius(0.05).build()])
+        .build(&mut scene.graph)
+}
+
+fn build_emote(scene: &mut Scene) -> Handle<Node> {
+    let emitter = burst(30, 0.02, (0.8, 1.2), (0.05, 0.1))
+        .with_y_velocity_range(NumericRange::new(0.02, 0.04));
+    ParticleSystemBuilder::new(BaseBuilder::new())
+        .with_acceleration(v!(0 -0.0002 0))
+        .with_color_over_lifetime_gradient(gradient(&[
+            (0.0, YELLOW),
+            (1.0, Color::from_rgba(255, 100, 255, 0)),
+        ]))
+        .with_emitters(vec![SphereEmitterBuilder::new(emitter).with_radius(0.2).build()])
+        .build(&mut scene.graph)
+}
+
+fn build_teleport(scene: &mut Scene) -> Handle<Node> {
+    // Particles rising around the cycle.
+    let emitter = burst(60, 0.01, (0.6, 1.0), (0.05, 0.1))
+        .with_y_velocity_range(NumericRange::new(0.03, 0.05));
+    ParticleSystemBuilder::new(BaseBuilder::new())
+        .with_color_over_lifetime_gradient(gradient(&[
+            (0.0, MAGENTA),
+            (1.0, Color::from_rgba(0, 255, 255, 0)),
+        ]))
+        .with_emitters(vec![SphereEmitterBuilder::new(emitter).with_radius(0.5).build()])
+        .build(&mut scene.graph)
+}
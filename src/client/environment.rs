@@ -0,0 +1,82 @@
+//! Applying the map's `Environment` - skybox, lighting and fog.
+
+use std::f32::consts::PI;
+
+use fyrox::{
+    resource::texture::Texture,
+    scene::{
+        camera::{SkyBox, SkyBoxBuilder},
+        light::{directional::DirectionalLightBuilder, BaseLightBuilder},
+    },
+};
+
+use crate::{common::map::Environment, prelude::*};
+
+/// Load the skybox textures, `None` if the map doesn't have any.
+pub(crate) async fn load_skybox(engine: &Engine, env: &Environment) -> Option<SkyBox> {
+    let paths = &env.skybox;
+    let sides = [
+        &paths.front,
+        &paths.back,
+        &paths.left,
+        &paths.right,
+        &paths.top,
+        &paths.bottom,
+    ];
+    if sides.iter().all(|side| side.is_none()) {
+        return None;
+    }
+
+    // LATER Load in parallel
+    let mut textures: [Option<Texture>; 6] = Default::default();
+    for (texture, path) in textures.iter_mut().zip(sides) {
+        if let Some(path) = path {
+            *texture = engine.resource_manager.request_texture(path).await.ok();
+            if texture.is_none() {
+                dbg_logf!("failed to load skybox texture {}", path);
+            }
+        }
+    }
+    let [front, back, left, right, top, bottom] = textures;
+
+    SkyBoxBuilder {
+        front,
+        back,
+        left,
+        right,
+        top,
+        bottom,
+    }
+    .build()
+    .ok()
+}
+
+/// Set up lighting, call once after the map is loaded.
+pub(crate) fn setup(scene: &mut Scene, env: &Environment) {
+    if let Some([r, g, b]) = env.ambient_light {
+        scene.ambient_lighting_color = Color::opaque(r, g, b);
+    }
+
+    if let Some(sun) = &env.sun {
+        let [x, y, z] = sun.direction;
+        let dir = Vec3::new(x, y, z);
+        // Directional lights shine along their local forward (Z) axis.
+        let rot = UnitQuaternion::rotation_between(&FORWARD, &dir)
+            .unwrap_or_else(|| UnitQuaternion::from_axis_angle(&LEFT_AXIS, PI));
+        let [r, g, b] = sun.color;
+        DirectionalLightBuilder::new(
+            BaseLightBuilder::new(
+                BaseBuilder::new()
+                    .with_local_transform(TransformBuilder::new().with_local_rotation(rot).build()),
+            )
+            .with_color(Color::opaque(r, g, b))
+            .with_intensity(sun.intensity),
+        )
+        .build(&mut scene.graph);
+    }
+
+    if env.fog.is_some() {
+        // LATER Fyrox doesn't have fog yet, implement it in a custom shader or update the engine.
+        dbg_logf!("map has fog but it's not supported yet");
+    }
+}
@@ -0,0 +1,92 @@
+//! Camera feedback - screen shake, view punch and recoil.
+//!
+//! Purely visual, the rotation is added on top of where the player is aiming
+//! and never affects input or gamelogic.
+//! Everything is capped by `cl_feedback_max_deg` and scaled by `cl_feedback_scale`,
+//! setting it to 0 (or `a11y_reduced_effects`) turns it off for people who get motion sick.
+//!
+//! LATER Gamepad rumble driven by the same events (damage, nearby explosions, boost)
+//!     with its own enable and intensity cvars, once there's gamepad input (see `client::bindings`).
+
+use crate::prelude::*;
+
+/// How fast shake goes away, per second.
+const SHAKE_DECAY: f32 = 1.5;
+/// How fast punch returns to center, larger is faster.
+const PUNCH_DECAY: f32 = 10.0;
+
+pub(crate) struct CameraFeedback {
+    /// 0 to 1, the actual shake is this squared so small shakes fade out smoothly.
+    trauma: f32,
+    /// Pitch offset in degrees, negative is up.
+    punch_pitch: f32,
+    /// Yaw offset in degrees.
+    punch_yaw: f32,
+    /// Drives the shake pattern.
+    time: f32,
+}
+
+impl CameraFeedback {
+    pub(crate) fn new() -> Self {
+        Self {
+            trauma: 0.0,
+            punch_pitch: 0.0,
+            punch_yaw: 0.0,
+            time: 0.0,
+        }
+    }
+
+    /// Shake more the closer the explosion is to the camera.
+    pub(crate) fn explosion(&mut self, cvars: &Cvars, camera_pos: Vec3, pos: Vec3) {
+        if cvars.cl_shake_radius <= 0.0 {
+            return;
+        }
+        let closeness = 1.0 - (pos - camera_pos).norm() / cvars.cl_shake_radius;
+        if closeness > 0.0 {
+            self.trauma = (self.trauma + closeness * cvars.cl_shake_explosion).min(1.0);
+        }
+    }
+
+    /// Jerk the view away from where the hit came from.
+    pub(crate) fn damage(&mut self, cvars: &Cvars, from_left: bool) {
+        let side = if from_left { 1.0 } else { -1.0 };
+        self.punch_pitch += cvars.cl_punch_damage;
+        self.punch_yaw += side * cvars.cl_punch_damage * 0.5;
+    }
+
+    pub(crate) fn recoil(&mut self, cvars: &Cvars) {
+        self.punch_pitch -= cvars.cl_punch_recoil;
+    }
+
+    /// Decay everything and return the rotation to add to the camera, in camera space.
+    pub(crate) fn update(&mut self, cvars: &Cvars, dt: f32) -> UnitQuaternion<f32> {
+        self.time += dt;
+        self.trauma = (self.trauma - SHAKE_DECAY * dt).max(0.0);
+        let punch_left = (-PUNCH_DECAY * dt).exp();
+        self.punch_pitch *= punch_left;
+        self.punch_yaw *= punch_left;
+
+        let max = cvars.cl_feedback_max_deg;
+        self.punch_pitch = self.punch_pitch.clamp(-max, max);
+        self.punch_yaw = self.punch_yaw.clamp(-max, max);
+
+        // Sines with unrelated frequencies look random enough and don't need an RNG.
+        let shake = self.trauma * self.trauma * max;
+        let shake_pitch = shake * (self.time * 37.0).sin();
+        let shake_yaw = shake * (self.time * 29.0 + 1.0).sin();
+        let shake_roll = shake * 0.5 * (self.time * 23.0 + 2.0).sin();
+
+        let scale = if cvars.a11y_reduced_effects {
+            0.0
+        } else {
+            cvars.cl_feedback_scale.max(0.0)
+        };
+        let pitch = ((self.punch_pitch + shake_pitch) * scale).clamp(-max, max);
+        let yaw = ((self.punch_yaw + shake_yaw) * scale).clamp(-max, max);
+        let roll = (shake_roll * scale).clamp(-max, max);
+
+        UnitQuaternion::from_axis_angle(&UP_AXIS, yaw.to_radians())
+            * UnitQuaternion::from_axis_angle(&LEFT_AXIS, pitch.to_radians())
+            * UnitQuaternion::from_axis_angle(&FORWARD_AXIS, roll.to_radians())
+    }
+}
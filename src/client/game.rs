@@ -2,40 +2,161 @@
 //!
 //! Mainly receiving updates from the server and updating local state.
 
-use std::{io::ErrorKind, thread, time::Duration};
+use std::{
+    cmp::Reverse, collections::VecDeque, fmt::Display, fs::File, io::BufWriter, mem, sync::Arc,
+    thread, time::Duration,
+};
 
 use fyrox::{
-    gui::{message::MessageDirection, text::TextMessage, UiNode},
-    scene::camera::{CameraBuilder, Projection, SkyBoxBuilder},
+    core::{parking_lot::Mutex, sstorage::ImmutableString},
+    gui::{
+        brush::Brush, message::MessageDirection, text::TextMessage, widget::WidgetMessage, UiNode,
+    },
+    material::PropertyValue,
+    scene::{
+        camera::{CameraBuilder, Projection},
+        debug::Line,
+        mesh::{Mesh, RenderPath},
+    },
 };
 
 use crate::{
+    client::{
+        bindings::Bindings,
+        caster::Caster,
+        decals::Decals,
+        effects::Effects,
+        environment,
+        feedback::CameraFeedback,
+        hud::Hud,
+        input_record::{InputPlayback, InputRecorder},
+        inspector::Inspector,
+        lights::DynamicLights,
+        locale::Strings,
+        mutes::{self, MuteList},
+        presence::{Activity, Presence},
+        probe::Prober,
+        profile::Profile,
+        reconnect::{Reconnect, ReconnectStatus},
+        servers::Servers,
+        smoothing::Smoothing,
+        trails::Trails,
+        viewmodel::ViewModel,
+        wrecks::Wrecks,
+    },
+    commands::GameCommand,
     common::{
-        entities::{Player, PlayerState},
+        desync::{StateSummary, StateSummaryExt},
+        entities::{Effect, Look, MatchPhase, Player, PlayerState, VehicleKind, SKINS, TEAM_NAMES},
         messages::{
-            AddPlayer, ClientMessage, CyclePhysics, Init, PlayerCycle, PlayerInput,
-            PlayerProjectile, ServerMessage, Update,
+            AddPlayer, ClientMessage, CyclePhysics, DebugItems, Init, MatchSummary, NetHandle,
+            NetHandleExt, PlayerCheats, PlayerCycle, PlayerFingerprint, PlayerInput, PlayerKill,
+            PlayerLook, PlayerPowerups, PlayerProjectile, PlayerScore, PlayerTeam, ServerMessage,
+            Update,
         },
-        net::{self, Connection},
-        GameState, Input,
+        net::{self, Connection, NetworkMessage, PingStats, UpdateStats},
+        palette::{team_color, ColorblindMode},
+        theme::ServerThemeExt,
+        Action, Authority, Axis, Deg, GameState, Input,
     },
     debug::{
         self,
-        details::{Lines, DEBUG_SHAPES, DEBUG_TEXTS},
+        details::{self, DebugShapeExt, Lines, DEBUG_SHAPES, DEBUG_TEXTS, DEBUG_WORLD_TEXTS},
     },
     prelude::*,
 };
 
+/// How visible cloaked cycles are to the player and teammates.
+const CLOAK_ALPHA_FRIENDLY: u8 = 100;
+/// How visible cloaked cycles are to enemies.
+const CLOAK_ALPHA_ENEMY: u8 = 15;
+
 /// Game data inside a client process.
 ///
 /// Needs to be connected to a game Server to play. Contains a local copy of the game state
 /// which might not be entirely accurate due to network lag and packet loss.
 pub(crate) struct ClientGame {
     debug_text: Handle<UiNode>,
+    debug_world_texts: Vec<Handle<UiNode>>,
+    pub(crate) inspector: Inspector,
+    pub(crate) caster: Caster,
+    hud: Hud,
+    /// Fire1 closed the message of the day, it's ignored until released.
+    fire1_swallowed: bool,
+    /// Maps keys and buttons to `lp.input`.
+    pub(crate) bindings: Bindings,
+    /// See `r_decal_time`.
+    decals: Decals,
+    effects: Effects,
+    feedback: CameraFeedback,
+    /// See `r_dynamic_lights_max`.
+    lights: DynamicLights,
+    trails: Trails,
+    /// See `r_viewmodel`.
+    view_model: ViewModel,
+    /// See `r_wreck_time`.
+    wrecks: Wrecks,
+    /// See `cl_smooth_time`.
+    smoothing: Smoothing,
     pub(crate) gs: GameState,
     pub(crate) lp: LocalPlayer,
     pub(crate) camera_handle: Handle<Node>,
     conn: Box<dyn Connection>,
+    /// Reused by `network_send` so messages don't allocate once the buffer is large enough.
+    send_buf: NetworkMessage,
+    /// Real time between calls to `update`, newest last. For `d_graph_frametime`.
+    frame_times: VecDeque<f32>,
+    prev_update_time: f32,
+    /// States sent to the server for `d_desync_check`, waiting for a reply. Oldest first.
+    desync_pending: VecDeque<(u32, StateSummary)>,
+    /// The server tick of the last update, see `Init::frame_number`.
+    server_frame: u32,
+    /// `server_frame` of the next `d_desync_check`.
+    desync_next: u32,
+    /// The local player's round trip times as reported by the server, for `ping`.
+    pub(crate) ping: PingStats,
+    /// Updates received each tick, for `hud_net_warnings`.
+    update_stats: UpdateStats,
+    /// Game time when the last update arrived, see `cl_extrapolate_max`.
+    last_update: f32,
+    /// Game time the last update covered, follows the server's `sv_snapshot_rate`.
+    update_interval: f32,
+    /// `input_record`
+    input_recorder: Option<InputRecorder<BufWriter<File>>>,
+    /// `input_play`, replaces the local player's input while playing.
+    input_playback: Option<InputPlayback>,
+    /// The server's `sv_cheats`.
+    pub(crate) cheats_allowed: bool,
+    /// The server's `sv_name`.
+    pub(crate) server_name: String,
+    /// `sv_region` from `Init`.
+    region: String,
+    /// The server's `g_map` from `Init`.
+    pub(crate) map: String,
+    /// From `Init`, sent back after reconnecting.
+    reconnect_token: u64,
+    /// The last `d_remote_debug` sent to the server.
+    remote_debug_sent: bool,
+    /// The last `cl_vehicle` sent to the server.
+    vehicle_sent: VehicleKind,
+    /// Set while the connection is lost, see `cl_reconnect_attempts`.
+    reconnect: Option<Reconnect>,
+    /// See `d_fly_camera`.
+    fly_camera: Option<FlyCamera>,
+    /// Players whose chat is hidden.
+    mutes: MuteList,
+    /// Muted players whose fingerprint we don't know, until they leave.
+    muted_players: Vec<Handle<Player>>,
+    /// Lifetime stats of the local player.
+    profile: Profile,
+    /// Favorites and history.
+    servers: Servers,
+    /// See `probe`.
+    prober: Option<Prober>,
+    /// See `cl_discord_app_id`.
+    presence: Option<Presence>,
+    /// HUD text in `cl_language`.
+    strings: Strings,
 }
 
 impl ClientGame {
@@ -43,75 +164,47 @@ impl ClientGame {
         cvars: &Cvars,
         engine: &mut Engine,
         debug_text: Handle<UiNode>,
+        debug_world_texts: Vec<Handle<UiNode>>,
+        inspector: Inspector,
+        caster: Caster,
+        hud: Hud,
         mut conn: Box<dyn Connection>,
     ) -> Self {
-        let mut gs = GameState::new(cvars, engine).await;
+        let mut gs = GameState::new(cvars, engine, Authority::Client).await;
 
         // LATER Load everything in parallel (i.e. with GameState)
-        // LATER Report error if loading fails
-        let top = engine.resource_manager.request_texture("data/skybox/top.png").await.ok();
+        let skybox = environment::load_skybox(engine, &gs.map.environment).await;
 
         let scene = &mut engine.scenes[gs.scene_handle];
 
-        let camera_handle =
+        environment::setup(scene, &gs.map.environment);
+
+        let mut camera_builder =
             CameraBuilder::new(BaseBuilder::new().with_local_transform(
                 TransformBuilder::new().with_local_position(v!(0 1 -3)).build(),
-            ))
-            .with_skybox(
-                SkyBoxBuilder {
-                    front: None,
-                    back: None,
-                    left: None,
-                    right: None,
-                    top,
-                    bottom: None,
-                }
-                .build()
-                .unwrap(),
-            )
-            .build(&mut scene.graph);
+            ));
+        if let Some(skybox) = skybox {
+            camera_builder = camera_builder.with_skybox(skybox);
+        }
+        let camera_handle = camera_builder.build(&mut scene.graph);
+
+        let decals = Decals::new(cvars, scene);
+        let effects = Effects::new(cvars, scene);
+        let lights = DynamicLights::new(cvars, scene);
+        let view_model = ViewModel::new(scene, camera_handle);
+        let wrecks = Wrecks::new(cvars, scene);
 
         let mut init_attempts = 0;
-        let lp = loop {
+        let init = loop {
             init_attempts += 1;
             let (msg, closed) = conn.receive_one_sm();
             if closed {
                 panic!("connection closed before init"); // LATER Don't crash
             }
             if let Some(msg) = msg {
-                if let ServerMessage::Init(Init {
-                    player_indices,
-                    local_player_index,
-                    player_cycles,
-                    player_projectiles,
-                }) = msg
-                {
-                    for player_index in player_indices {
-                        let player = Player::new(None);
-                        gs.players.spawn_at(player_index, player).unwrap();
-                    }
-                    let local_player_handle = gs.players.handle_from_index(local_player_index);
-                    let lp = LocalPlayer::new(local_player_handle);
-
-                    for PlayerCycle {
-                        player_index,
-                        cycle_index,
-                    } in player_cycles
-                    {
-                        let player_handle = gs.players.handle_from_index(player_index);
-                        gs.spawn_cycle(scene, player_handle, Some(cycle_index));
-                    }
-
-                    for PlayerProjectile {
-                        player_index: _,
-                        projectile_index: _,
-                    } in player_projectiles
-                    {
-                        todo!("init projectiles");
-                    }
-
+                if let ServerMessage::Init(init) = msg {
                     dbg_logf!("init attempts: {}", init_attempts);
-                    break lp;
+                    break init;
                 } else {
                     panic!("First message wasn't init"); // LATER Don't crash
                 }
@@ -121,14 +214,125 @@ impl ClientGame {
             }
             thread::sleep(Duration::from_millis(10));
         };
+        let cheats_allowed = init.cheats_allowed;
+        let server_name = init.server_name.clone();
+        let theme = init.theme.clone().validated();
+        let region = init.region.clone();
+        let map = init.map.clone();
+        let reconnect_token = init.reconnect_token;
+        let server_frame = init.frame_number;
+        let lp = apply_init(cvars, scene, &mut gs, init);
+        let last_update = gs.game_time;
         dbg_logf!("local_player_index is {}", lp.player_handle.index());
 
-        Self {
+        let presence = if cvars.cl_discord_app_id.is_empty() {
+            None
+        } else {
+            match Presence::connect(&cvars.cl_discord_app_id) {
+                Ok(presence) => Some(presence),
+                Err(err) => {
+                    dbg_logf!("failed to connect to discord: {}", err);
+                    None
+                }
+            }
+        };
+
+        let mut cg = Self {
             debug_text,
+            debug_world_texts,
+            inspector,
+            caster,
+            hud,
+            fire1_swallowed: false,
+            bindings: Bindings::new(),
+            decals,
+            effects,
+            feedback: CameraFeedback::new(),
+            lights,
+            trails: Trails::new(),
+            view_model,
+            wrecks,
+            smoothing: Smoothing::default(),
             gs,
             lp,
             camera_handle,
             conn,
+            send_buf: NetworkMessage::default(),
+            frame_times: VecDeque::new(),
+            prev_update_time: 0.0,
+            desync_pending: VecDeque::new(),
+            server_frame,
+            desync_next: 0,
+            ping: PingStats::default(),
+            update_stats: UpdateStats::default(),
+            last_update,
+            update_interval: 1.0 / 60.0,
+            input_recorder: None,
+            input_playback: None,
+            cheats_allowed,
+            server_name,
+            region,
+            map,
+            reconnect_token,
+            remote_debug_sent: false,
+            vehicle_sent: VehicleKind::Standard,
+            reconnect: None,
+            fly_camera: None,
+            mutes: MuteList::load(&cvars.cl_mute_file),
+            muted_players: Vec::new(),
+            profile: Profile::load(&cvars.cl_profile_file),
+            servers: Servers::load(&cvars.cl_servers_file),
+            prober: None,
+            presence,
+            strings: Strings::load(&cvars.cl_language),
+        };
+
+        cg.hud.server(engine, &cg.server_name, &theme);
+        cg.send_client_info(cvars);
+        cg.record_visit(cvars);
+
+        cg
+    }
+
+    /// Remember the server in history, except local games.
+    fn record_visit(&mut self, cvars: &Cvars) {
+        let addr = self.conn.addr();
+        if addr == "local" {
+            return;
+        }
+        self.servers.visit(&addr, &self.server_name, &self.region, &self.map);
+        self.save_servers(cvars);
+    }
+
+    fn save_servers(&self, cvars: &Cvars) {
+        if let Err(err) = self.servers.save(&cvars.cl_servers_file) {
+            dbg_logf!("failed to save server list: {}", err);
+        }
+    }
+
+    /// Everything the server should know about us after connecting.
+    fn send_client_info(&mut self, cvars: &Cvars) {
+        // LATER Allow changing this later from the console.
+        let look = local_look(cvars);
+        self.network_send(ClientMessage::Look(look));
+
+        let fingerprint = mutes::load_identity(&cvars.cl_identity_file);
+        self.network_send(ClientMessage::Fingerprint(fingerprint));
+
+        self.network_send(ClientMessage::UpdateRate(cvars.cl_updaterate));
+
+        self.remote_debug_sent = cvars.d_remote_debug;
+        self.network_send(ClientMessage::RemoteDebug(cvars.d_remote_debug));
+
+        self.vehicle_sent = cvars.cl_vehicle;
+        self.network_send(ClientMessage::Vehicle(cvars.cl_vehicle));
+
+        // LATER Allow logging in later from the console.
+        if !cvars.cl_admin_password.is_empty() {
+            self.network_send(ClientMessage::AdminLogin {
+                password: cvars.cl_admin_password.clone(),
+                remote_log: cvars.cl_remote_log,
+            });
         }
     }
 
@@ -137,24 +341,54 @@ impl ClientGame {
         // https://gafferongames.com/post/fix_your_timestep/
         // https://medium.com/@tglaiel/how-to-make-your-game-run-at-60fps-24c61210fe75
 
+        self.frame_times.push_back(game_time_target - self.prev_update_time);
+        while self.frame_times.len() > cvars.d_graph_frametime_len {
+            self.frame_times.pop_front();
+        }
+        self.prev_update_time = game_time_target;
+
+        if cvars.cl_language != self.strings.language() {
+            self.strings = Strings::load(&cvars.cl_language);
+        }
+
+        if cvars.d_remote_debug != self.remote_debug_sent {
+            self.remote_debug_sent = cvars.d_remote_debug;
+            self.network_send(ClientMessage::RemoteDebug(cvars.d_remote_debug));
+        }
+        if cvars.cl_vehicle != self.vehicle_sent {
+            self.vehicle_sent = cvars.cl_vehicle;
+            self.network_send(ClientMessage::Vehicle(cvars.cl_vehicle));
+        }
+
         let dt = 1.0 / 60.0;
         while self.gs.game_time + dt < game_time_target {
             self.gs.game_time_prev = self.gs.game_time;
             self.gs.game_time += dt;
             self.gs.frame_number += 1;
+            debug::crash::set_frame(self.gs.frame_number, self.gs.game_time);
 
-            self.tick_begin_frame(cvars, engine);
+            {
+                let _timer = dbg_timer!("net");
+                self.tick_begin_frame(cvars, engine);
+            }
 
-            self.gs.tick_before_physics(cvars, engine, dt);
+            self.hot_reload(cvars, engine);
 
-            self.tick_before_physics(cvars, engine, dt);
+            {
+                let _timer = dbg_timer!("gamelogic");
+                self.gs.tick_before_physics(cvars, engine, dt);
+                self.tick_before_physics(cvars, engine, dt);
+            }
 
             // Update animations, transformations, physics, ...
             // There's currently no need to split this into pre_ and post_update like on the client.
             // Dummy control flow and lag since we don't use fyrox plugins.
             let mut cf = fyrox::event_loop::ControlFlow::Poll;
             let mut lag = 0.0;
-            engine.pre_update(dt, &mut cf, &mut lag);
+            {
+                let _timer = dbg_timer!("physics");
+                engine.pre_update(dt, &mut cf, &mut lag);
+            }
             // Sanity check - if the engine starts doing something with these, we'll know.
             assert_eq!(cf, fyrox::event_loop::ControlFlow::Poll);
             assert_eq!(lag, 0.0);
@@ -162,18 +396,123 @@ impl ClientGame {
             // `tick_after_physics` tells the engine to draw debug shapes and text.
             // Any debug calls after it will show up next frame.
             self.gs.debug_engine_updates(cvars, v!(-5 3 3));
-            self.tick_after_physics(cvars, engine, dt);
+            {
+                let _timer = dbg_timer!("debug");
+                self.tick_after_physics(cvars, engine, dt);
+            }
             self.gs.debug_engine_updates(cvars, v!(-6 3 3));
 
             // Update UI
+            let _timer = dbg_timer!("ui");
             engine.post_update(dt);
         }
 
+        self.sys_presence();
+        self.sys_probe(cvars);
+
+        engine.get_window().request_redraw();
+    }
+
+    /// See `probe`, the history is saved once all servers answered or timed out.
+    fn sys_probe(&mut self, cvars: &Cvars) {
+        let Some(prober) = &mut self.prober else {
+            return;
+        };
+        for (addr, ping) in prober.update(cvars) {
+            dbg_logf!("    {} - {} ms", addr, ping);
+            self.servers.set_ping(&addr, ping);
+        }
+        if prober.is_done() {
+            self.prober = None;
+            self.save_servers(cvars);
+        }
+    }
+
+    /// See `cl_discord_app_id`.
+    fn sys_presence(&mut self) {
+        let Some(presence) = &mut self.presence else {
+            return;
+        };
+
+        let mode = if self.gs.players.iter().any(|player| player.team.is_some()) {
+            "teams"
+        } else {
+            "free for all"
+        };
+        let state = match self.gs.phase {
+            MatchPhase::Warmup { .. } => "warmup".to_owned(),
+            MatchPhase::Paused { .. } => "paused".to_owned(),
+            MatchPhase::Live { .. } | MatchPhase::Overtime { .. } => {
+                let player = self.gs.players.try_borrow(self.lp.player_handle);
+                format!("score {}", player.map_or(0, |player| player.score))
+            }
+        };
+        let addr = self.conn.addr();
+        let activity = Activity {
+            details: format!("{} - {}", self.map, mode),
+            state,
+            party_size: self.gs.players.alive_count(),
+            join_secret: (addr != "local").then_some(addr),
+        };
+        if let Err(err) = presence.update(self.gs.game_time, activity) {
+            dbg_logf!("discord presence error: {}", err);
+            self.presence = None;
+        }
+    }
+
+    /// Called instead of `update` while `d_pause` freezes the game.
+    ///
+    /// Only the camera and UI run (using real time) so the frozen world can be looked around
+    /// and inspected. Input is applied to the camera but not sent until the game continues.
+    pub(crate) fn update_paused(&mut self, cvars: &Cvars, engine: &mut Engine, frame_time: f32) {
+        self.lp.input.yaw.0 += self.lp.delta_yaw;
+        self.lp.input.pitch.0 = (self.lp.input.pitch.0 + self.lp.delta_pitch)
+            .clamp(cvars.m_pitch_min, cvars.m_pitch_max);
+        self.lp.delta_yaw = 0.0;
+        self.lp.delta_pitch = 0.0;
+
+        self.update_camera(cvars, engine, frame_time);
+        self.inspector.update(cvars, engine, &self.gs);
+
+        // The console has to keep working so the game can be unpaused.
+        engine.post_update(frame_time);
+
         engine.get_window().request_redraw();
     }
 
     pub(crate) fn send_input(&mut self) {
-        self.network_send(ClientMessage::Input(self.lp.input));
+        // Shooting closes the message of the day, the same press shouldn't also join or fire.
+        if !self.lp.input.pressed(Action::Fire1) {
+            self.fire1_swallowed = false;
+        } else if self.hud.close_motd() {
+            self.fire1_swallowed = true;
+        }
+        self.network_send(ClientMessage::Input(self.cycle_input()));
+    }
+
+    /// What the local player's cycle is doing - while flying the camera, it's not the live input.
+    fn cycle_input(&self) -> Input {
+        let mut input = match &self.fly_camera {
+            Some(fly_camera) => fly_camera.cycle_input,
+            None => self.lp.input,
+        };
+        if self.fire1_swallowed {
+            input.set_pressed(Action::Fire1, false);
+        }
+        input
+    }
+
+    /// Fresh cycle models lost their colors.
+    fn hot_reload(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        let reloaded = self.gs.hot_reload(cvars, engine);
+        if reloaded.cycle_model {
+            let scene = &mut engine.scenes[self.gs.scene_handle];
+            let player_handles: Vec<_> =
+                self.gs.players.pair_iter().map(|(handle, _)| handle).collect();
+            for player_handle in player_handles {
+                apply_look(cvars, scene, &self.gs, self.lp.player_handle, player_handle);
+            }
+        }
     }
 
     /// All once-per-frame networking.
@@ -183,191 +522,691 @@ impl ClientGame {
         // in case some systems update mouse position at a very high rate.
         self.lp.input_prev = self.lp.input;
 
+        // The server tells us about the same changes.
+        self.gs.events.clear();
+
+        let delta_time = self.gs.game_time - self.gs.game_time_prev;
+        soft_assert!(delta_time > 0.0);
+
+        // Unlike the mouse, aim axes set the speed of turning, not the distance.
+        let zoom_factor = if self.lp.input.pressed(Action::Zoom) {
+            cvars.cl_zoom_factor
+        } else {
+            1.0
+        };
+        let aim_speed = cvars.cl_aim_axis_speed * delta_time / zoom_factor;
+        self.lp.delta_yaw -= self.lp.input.axis(Axis::AimYaw) * aim_speed;
+        self.lp.delta_pitch += self.lp.input.axis(Axis::AimPitch) * aim_speed;
+
         self.lp.input.yaw.0 += self.lp.delta_yaw; // LATER Normalize to [0, 360°) or something
         self.lp.input.pitch.0 = (self.lp.input.pitch.0 + self.lp.delta_pitch)
             .clamp(cvars.m_pitch_min, cvars.m_pitch_max);
 
-        let delta_time = self.gs.game_time - self.gs.game_time_prev;
-        soft_assert!(delta_time > 0.0);
         self.lp.input.yaw_speed.0 = self.lp.delta_yaw / delta_time;
         self.lp.input.pitch_speed.0 = self.lp.delta_pitch / delta_time;
 
         self.lp.delta_yaw = 0.0;
         self.lp.delta_pitch = 0.0;
 
+        self.input_record_tick(engine, delta_time);
+
+        if self.reconnect.is_some() {
+            self.sys_reconnect(cvars, engine);
+            self.sys_extrapolate(cvars, engine);
+            return;
+        }
+
         self.send_input();
 
         let scene = &mut engine.scenes[self.gs.scene_handle];
 
         scene.drawing_context.clear_lines();
 
-        let (msgs, _) = self.conn.receive_sm();
+        let (msgs, closed) = self.conn.receive_sm();
+        let mut update_ticks = 0;
         for msg in msgs {
             match msg {
                 ServerMessage::Init(_) => {
                     // LATER Make this type safe? Init part of handshake?
                     panic!("Received unexpected init")
                 }
-                ServerMessage::AddPlayer(AddPlayer { player_index, name }) => {
+                ServerMessage::AddPlayer(AddPlayer {
+                    player_handle,
+                    name,
+                }) => {
                     let player = Player::new(None);
-                    self.gs.players.spawn_at(player_index, player).unwrap();
+                    self.gs.players.spawn_at_handle(player_handle.handle(), player).unwrap();
                     dbg_logd!("player {} added", name);
                 }
-                ServerMessage::RemovePlayer { player_index } => {
-                    let player_handle = self.gs.players.handle_from_index(player_index);
+                ServerMessage::RemovePlayer { player_handle } => {
+                    let Some(player_handle) = resolve(&self.gs.players, player_handle) else {
+                        continue;
+                    };
                     self.gs.free_player(scene, player_handle);
+                    self.muted_players.retain(|&muted| muted != player_handle);
                 }
-                ServerMessage::Observe { player_index } => {
-                    self.gs.players.at_mut(player_index).unwrap().ps = PlayerState::Observing;
-                    dbg_logf!("player {} is now observing", player_index);
+                ServerMessage::Observe { player_handle } => {
+                    let Some(player_handle) = resolve(&self.gs.players, player_handle) else {
+                        continue;
+                    };
+                    self.gs.players[player_handle].ps = PlayerState::Observing;
+                    dbg_logf!("player {} is now observing", player_handle.index());
                 }
                 ServerMessage::Spectate {
-                    player_index,
-                    spectatee_index,
+                    player_handle,
+                    spectatee_handle,
                 } => {
-                    let spectatee_handle = self.gs.players.handle_from_index(spectatee_index);
-                    self.gs.players.at_mut(player_index).unwrap().ps =
+                    let (Some(player_handle), Some(spectatee_handle)) = (
+                        resolve(&self.gs.players, player_handle),
+                        resolve(&self.gs.players, spectatee_handle),
+                    ) else {
+                        continue;
+                    };
+                    self.gs.players[player_handle].ps =
                         PlayerState::Spectating { spectatee_handle };
                     dbg_logf!(
                         "player {} is now spectating player {}",
-                        player_index,
-                        spectatee_index
+                        player_handle.index(),
+                        spectatee_handle.index()
                     );
                 }
-                ServerMessage::Join { player_index } => {
-                    self.gs.players.at_mut(player_index).unwrap().ps = PlayerState::Playing;
-                    dbg_logf!("player {} is now playing", player_index);
+                ServerMessage::Join { player_handle } => {
+                    let Some(player_handle) = resolve(&self.gs.players, player_handle) else {
+                        continue;
+                    };
+                    self.gs.players[player_handle].ps = PlayerState::Playing;
+                    dbg_logf!("player {} is now playing", player_handle.index());
                 }
                 ServerMessage::SpawnCycle(PlayerCycle {
-                    player_index,
-                    cycle_index,
+                    player_handle,
+                    cycle_handle,
+                    vehicle,
                 }) => {
-                    let player_handle = self.gs.players.handle_from_index(player_index);
-                    self.gs.spawn_cycle(scene, player_handle, Some(cycle_index));
+                    let Some(player_handle) = resolve(&self.gs.players, player_handle) else {
+                        continue;
+                    };
+                    let cycle_handle = Some(cycle_handle.handle());
+                    self.gs.spawn_cycle(cvars, scene, player_handle, vehicle, cycle_handle);
+                    apply_look(cvars, scene, &self.gs, self.lp.player_handle, player_handle);
                 }
-                ServerMessage::DespawnCycle { cycle_index } => {
-                    dbg_logd!(cycle_index);
-                    todo!("despawn cycle");
+                ServerMessage::PlayerLook(PlayerLook {
+                    player_handle,
+                    look,
+                }) => {
+                    let Some(player_handle) = resolve(&self.gs.players, player_handle) else {
+                        continue;
+                    };
+                    self.gs.players[player_handle].look = look;
+                    apply_look(cvars, scene, &self.gs, self.lp.player_handle, player_handle);
+                }
+                ServerMessage::PlayerFingerprint(PlayerFingerprint {
+                    player_handle,
+                    fingerprint,
+                }) => {
+                    let Some(player_handle) = resolve(&self.gs.players, player_handle) else {
+                        continue;
+                    };
+                    self.gs.players[player_handle].fingerprint = Some(fingerprint);
+                }
+                ServerMessage::Ready {
+                    player_handle,
+                    ready,
+                } => {
+                    let Some(player_handle) = resolve(&self.gs.players, player_handle) else {
+                        continue;
+                    };
+                    self.gs.players[player_handle].ready = ready;
+                    dbg_logf!("player {} ready: {}", player_handle.index(), ready);
+                }
+                ServerMessage::PlayerTeam(PlayerTeam {
+                    player_handle,
+                    team,
+                }) => {
+                    let Some(player_handle) = resolve(&self.gs.players, player_handle) else {
+                        continue;
+                    };
+                    self.gs.players[player_handle].team = team;
+                    apply_look(cvars, scene, &self.gs, self.lp.player_handle, player_handle);
+                    let name = match team {
+                        Some(team) => {
+                            TEAM_NAMES.get(usize::from(team)).copied().unwrap_or("unknown")
+                        }
+                        None => "none",
+                    };
+                    dbg_logf!("player {} is now on team {}", player_handle.index(), name);
+                }
+                ServerMessage::PlayerCheats(PlayerCheats {
+                    player_handle,
+                    cheats,
+                }) => {
+                    let Some(player_handle) = resolve(&self.gs.players, player_handle) else {
+                        continue;
+                    };
+                    self.gs.set_cheats(scene, player_handle, cheats);
+                    dbg_logf!("player {} cheats: {:?}", player_handle.index(), cheats);
+                }
+                ServerMessage::CheatsAllowed(allowed) => {
+                    self.cheats_allowed = allowed;
+                    dbg_logf!("sv_cheats is now {}", allowed);
+                }
+                ServerMessage::Chat(line) => {
+                    if self.is_muted(line.player_index) {
+                        continue;
+                    }
+                    let text = line.to_string();
+                    dbg_logf!("{}", text);
+                    self.hud.chat(self.gs.game_time, text);
+                }
+                ServerMessage::Emote {
+                    player_handle,
+                    emote,
+                } => {
+                    let Some(player_handle) = resolve(&self.gs.players, player_handle) else {
+                        continue;
+                    };
+                    let player_index = player_handle.index();
+                    self.effects.emote(cvars, scene, &self.gs, player_handle);
+                    if self.is_muted(Some(player_index)) {
+                        continue;
+                    }
+                    let emote = self.strings.get(&format!("emote.{}", emote)).to_owned();
+                    let args: &[(&str, &dyn Display)] =
+                        &[("player", &player_index), ("emote", &emote)];
+                    let text = self.strings.format("chat.emote", args);
+                    dbg_logf!("{}", text);
+                    self.hud.chat(self.gs.game_time, text);
+                }
+                ServerMessage::OutOfBounds {
+                    player_index,
+                    time_left,
+                } => {
+                    if player_index == self.lp.player_handle.index() {
+                        self.hud.out_of_bounds(self.gs.game_time, time_left);
+                    }
+                }
+                ServerMessage::Respawn {
+                    player_index,
+                    time_left,
+                } => {
+                    if player_index == self.lp.player_handle.index() {
+                        self.hud.respawn(self.gs.game_time, time_left);
+                    }
+                }
+                ServerMessage::Motd(text) => {
+                    dbg_logf!("message of the day:\n{}", text);
+                    self.hud.motd(&text);
+                }
+                ServerMessage::Ping(id) => self.network_send(ClientMessage::Pong(id)),
+                ServerMessage::Pickup {
+                    player_handle,
+                    pickup_index,
+                } => {
+                    // LATER A sound for whoever took it.
+                    dbg_logf!("player {} took pickup {}", player_handle.index, pickup_index);
+                }
+                ServerMessage::Kill(kill) => {
+                    // Before the victim's cycle is despawned.
+                    let (killer_index, victim_index) = (kill.killer_index, kill.victim_index);
+                    self.wrecks.spawn(cvars, scene, &self.gs, killer_index, victim_index);
+                    self.hud.kill(self.gs.game_time, kill_text(&self.strings, &kill));
+                }
+                ServerMessage::MatchSummary(summary) => {
+                    if self.profile.record(&summary, self.lp.player_handle.index()) {
+                        if let Err(err) = self.profile.save(&cvars.cl_profile_file) {
+                            dbg_logf!("failed to save profile: {}", err);
+                        }
+                    }
+                    // The last ping in history doesn't need to be saved more often.
+                    self.save_servers(cvars);
+                    let text = summary_text(&self.strings, &summary);
+                    dbg_logf!("{}", text);
+                    self.hud.summary(self.gs.game_time, text);
+                }
+                ServerMessage::Scores(scores) => {
+                    for PlayerScore {
+                        player_handle,
+                        score,
+                        assists,
+                        ping,
+                    } in scores
+                    {
+                        let Some(player_handle) = resolve(&self.gs.players, player_handle) else {
+                            continue;
+                        };
+                        let player = &mut self.gs.players[player_handle];
+                        player.score = score;
+                        player.assists = assists;
+                        player.ping = ping;
+                        if player_handle == self.lp.player_handle {
+                            if let Some(ping) = ping {
+                                self.ping.add(f32::from(ping));
+                                self.servers.set_ping(&self.conn.addr(), ping);
+                            }
+                        }
+                    }
+                }
+                ServerMessage::MatchPhase(phase) => {
+                    match phase {
+                        MatchPhase::Live { .. }
+                            if matches!(self.gs.phase, MatchPhase::Warmup { .. }) =>
+                        {
+                            dbg_logf!("match started")
+                        }
+                        MatchPhase::Overtime { rule, .. } => dbg_logf!("overtime: {}", rule),
+                        MatchPhase::Paused { .. } => dbg_logf!("match paused"),
+                        _ => {}
+                    }
+                    self.gs.phase = phase;
+                }
+                ServerMessage::DespawnCycle { cycle_handle } => {
+                    let Some(cycle_handle) = resolve(&self.gs.cycles, cycle_handle) else {
+                        continue;
+                    };
+                    self.gs.despawn_cycle(scene, cycle_handle);
+                }
+                ServerMessage::Skipped { ticks, effects } => {
+                    update_ticks += ticks;
+                    self.spawn_effects(cvars, scene, effects);
                 }
                 ServerMessage::Update(Update {
+                    ticks,
                     player_inputs,
                     cycle_physics,
-                    debug_texts,
-                    debug_shapes,
+                    platform_poses,
+                    pickups,
+                    player_powerups,
+                    effects,
                 }) => {
+                    update_ticks += ticks;
                     for PlayerInput {
-                        player_index,
+                        player_handle,
                         input,
                     } in player_inputs
                     {
-                        self.gs.players.at_mut(player_index).unwrap().input = input;
+                        let Some(player_handle) = resolve(&self.gs.players, player_handle) else {
+                            continue;
+                        };
+                        self.gs.players[player_handle].input = input;
                     }
 
                     for CyclePhysics {
-                        cycle_index,
+                        cycle_handle,
                         translation,
                         rotation,
                         velocity,
                     } in cycle_physics
                     {
-                        let cycle = self.gs.cycles.at_mut(cycle_index).unwrap();
-                        let body = scene.graph[cycle.body_handle].as_rigid_body_mut();
+                        let Some(cycle_handle) = resolve(&self.gs.cycles, cycle_handle) else {
+                            continue;
+                        };
+                        let body_handle = self.gs.cycles[cycle_handle].body_handle;
+                        let body = scene.graph[body_handle].as_rigid_body_mut();
+                        let old = **body.local_transform().position();
+                        self.smoothing.correct(cvars, cycle_handle, old, translation);
                         body.local_transform_mut().set_position(translation);
                         body.local_transform_mut().set_rotation(rotation);
                         body.set_lin_vel(velocity);
                     }
 
-                    DEBUG_TEXTS.with(|texts| {
-                        let mut texts = texts.borrow_mut();
-                        texts.extend(debug_texts);
+                    for (&body_handle, pose) in self.gs.platform_handles.iter().zip(platform_poses)
+                    {
+                        let transform = scene.graph[body_handle].local_transform_mut();
+                        transform.set_position(pose.translation);
+                        transform.set_rotation(pose.rotation);
+                    }
+
+                    for (&handle, available) in self.gs.pickup_handles.iter().zip(pickups) {
+                        scene.graph[handle].set_visibility(available);
+                    }
+
+                    for PlayerPowerups {
+                        player_handle,
+                        armor,
+                        cloaked,
+                    } in player_powerups
+                    {
+                        let Some(player_handle) = resolve(&self.gs.players, player_handle) else {
+                            continue;
+                        };
+                        let player = &mut self.gs.players[player_handle];
+                        player.armor = armor;
+                        if player.cloaked != cloaked {
+                            player.cloaked = cloaked;
+                            let viewer = self.lp.player_handle;
+                            apply_look(cvars, scene, &self.gs, viewer, player_handle);
+                        }
+                    }
+
+                    self.spawn_effects(cvars, scene, effects);
+                }
+                ServerMessage::Debug(DebugItems {
+                    texts,
+                    shapes,
+                    world_texts,
+                }) => {
+                    DEBUG_TEXTS.with(|debug_texts| {
+                        let mut debug_texts = debug_texts.borrow_mut();
+                        debug_texts.extend(texts);
                     });
 
-                    DEBUG_SHAPES.with(|shapes| {
-                        let mut shapes = shapes.borrow_mut();
-                        shapes.extend(debug_shapes);
+                    DEBUG_SHAPES.with(|debug_shapes| {
+                        let mut debug_shapes = debug_shapes.borrow_mut();
+                        debug_shapes.extend(shapes);
+                    });
+
+                    DEBUG_WORLD_TEXTS.with(|debug_world_texts| {
+                        let mut debug_world_texts = debug_world_texts.borrow_mut();
+                        debug_world_texts.extend(world_texts);
                     })
                 }
+                ServerMessage::Log(lines) => {
+                    // The lines already contain the server's endpoint name.
+                    for line in lines {
+                        dbg_logf!("remote {}", line.text);
+                    }
+                }
+                ServerMessage::DesyncCheck {
+                    frame_number,
+                    hash,
+                    state,
+                } => {
+                    self.desync_check_receive(frame_number, hash, state);
+                }
+            }
+        }
+
+        self.update_stats.add(update_ticks);
+        self.hud.net_stats(self.ping.last(), self.update_stats.loss());
+        if update_ticks > 0 {
+            self.last_update = self.gs.game_time;
+            self.update_interval = update_ticks as f32 / 60.0;
+            self.server_frame += update_ticks;
+            // Right after the update, before prediction changes anything.
+            if cvars.d_desync_check && self.server_frame >= self.desync_next {
+                self.desync_next = self.server_frame + cvars.d_desync_check_interval as u32;
+                self.desync_check_send(cvars, engine);
+            }
+        }
+        self.sys_extrapolate(cvars, engine);
+
+        if closed {
+            self.connection_lost();
+        }
+    }
+
+    /// Without updates, cycles keep going on the last input from the server for a while
+    /// and then stop so they don't drift too far.
+    /// `Smoothing` blends them back once updates resume.
+    fn sys_extrapolate(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        // Counted from when the next update was due so a low `sv_snapshot_rate`
+        // doesn't freeze cycles between updates.
+        let max = self.update_interval + cvars.cl_extrapolate_max / 1000.0;
+        if self.gs.game_time - self.last_update <= max {
+            return;
+        }
+        if self.gs.game_time_prev - self.last_update <= max {
+            dbg_logf!("no updates for {} ms, stopping cycles", max * 1000.0);
+        }
+
+        let scene = &mut engine.scenes[self.gs.scene_handle];
+        for cycle in &self.gs.cycles {
+            // The local player's cycle is predicted from local input.
+            if cycle.player_handle == self.lp.player_handle {
+                continue;
+            }
+            // Keep the aim so they still face the same way.
+            self.gs.players[cycle.player_handle].input.release_all_keys();
+            let body = scene.graph[cycle.body_handle].as_rigid_body_mut();
+            body.set_lin_vel(Vector3::zeros());
+        }
+    }
+
+    fn spawn_effects(&mut self, cvars: &Cvars, scene: &mut Scene, effects: Vec<Effect>) {
+        for effect in effects {
+            if let Effect::Explosion { pos, victim_index } = effect {
+                let camera = &scene.graph[self.camera_handle];
+                let camera_pos = camera.global_position();
+                self.feedback.explosion(cvars, camera_pos, pos);
+                if victim_index == Some(self.lp.player_handle.index()) {
+                    let from_left = (pos - camera_pos).dot(&camera.left_vec_normed()) > 0.0;
+                    self.feedback.damage(cvars, from_left);
+                }
             }
+            self.decals.spawn(cvars, scene, self.gs.game_time, effect);
+            self.effects.spawn(cvars, scene, &self.gs, effect);
+            self.lights.spawn(cvars, self.gs.game_time, effect);
         }
     }
 
+    fn connection_lost(&mut self) {
+        if self.reconnect.is_none() {
+            dbg_logf!("connection to server lost");
+            self.reconnect = Some(Reconnect::new(self.gs.game_time));
+        }
+    }
+
+    /// See `cl_reconnect_attempts`.
+    fn sys_reconnect(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        let reconnect = self.reconnect.as_mut().unwrap();
+        // Local games have nothing to reconnect to.
+        let status = match self.conn.addr().parse() {
+            Ok(addr) => reconnect.poll(cvars, self.gs.game_time, addr),
+            Err(_) => ReconnectStatus::Failed,
+        };
+        match status {
+            ReconnectStatus::Waiting => {
+                let attempts = (reconnect.attempt, cvars.cl_reconnect_attempts);
+                self.hud.reconnecting(Some(attempts));
+            }
+            ReconnectStatus::Connected(conn, init) => {
+                self.reconnect = None;
+                self.hud.reconnecting(None);
+                self.conn = Box::new(conn);
+                self.reinit(cvars, engine, *init);
+            }
+            ReconnectStatus::Failed => {
+                dbg_logf!("Server disconnected, exitting");
+                std::process::exit(0);
+            }
+        }
+    }
+
+    /// Replace all players with the ones from the new connection and ask for our old state back.
+    fn reinit(&mut self, cvars: &Cvars, engine: &mut Engine, init: Init) {
+        let scene = &mut engine.scenes[self.gs.scene_handle];
+        let player_handles: Vec<_> =
+            self.gs.players.pair_iter().map(|(handle, _)| handle).collect();
+        for player_handle in player_handles {
+            self.gs.free_player(scene, player_handle);
+        }
+
+        self.cheats_allowed = init.cheats_allowed;
+        self.server_name = init.server_name.clone();
+        self.region = init.region.clone();
+        self.map = init.map.clone();
+        let theme = init.theme.clone().validated();
+        let token = mem::replace(&mut self.reconnect_token, init.reconnect_token);
+        self.server_frame = init.frame_number;
+        self.lp = apply_init(cvars, scene, &mut self.gs, init);
+        self.desync_pending.clear();
+        self.desync_next = 0;
+        self.last_update = self.gs.game_time;
+        dbg_logf!("reconnected, local_player_index is {}", self.lp.player_handle.index());
+        self.hud.server(engine, &self.server_name, &theme);
+
+        self.send_client_info(cvars);
+        self.network_send(ClientMessage::Reconnect(token));
+    }
+
     fn tick_before_physics(&mut self, cvars: &Cvars, engine: &mut Engine, dt: f32) {
         // Join / spec
         let ps = self.gs.players[self.lp.player_handle].ps;
-        if ps == PlayerState::Observing && self.lp.input.fire1 {
+        let input = self.cycle_input();
+        if ps == PlayerState::Observing && input.pressed(Action::Fire1) {
             self.network_send(ClientMessage::Join);
-        } else if ps == PlayerState::Playing && self.lp.input.fire2 {
+        } else if ps == PlayerState::Playing && input.pressed(Action::Fire2) {
             self.network_send(ClientMessage::Observe);
         }
 
+        // Camera feedback, applied in `update_camera`.
+        if ps == PlayerState::Playing && input.pressed(Action::Fire1) {
+            self.feedback.recoil(cvars);
+            self.view_model.fire(self.gs.game_time);
+        }
+
+        let scene = &mut engine.scenes[self.gs.scene_handle];
+        self.smoothing.tick(cvars, scene, &self.gs, dt);
+
+        self.update_camera(cvars, engine, dt);
+
         let scene = &mut engine.scenes[self.gs.scene_handle];
+        let cycle_handle = self.gs.players[self.lp.player_handle].cycle_handle;
+        let velocity = match cycle_handle {
+            Some(cycle_handle) if ps == PlayerState::Playing && self.fly_camera.is_none() => {
+                let body_handle = self.gs.cycles[cycle_handle].body_handle;
+                Some(scene.graph[body_handle].as_rigid_body().lin_vel())
+            }
+            _ => None,
+        };
+        let game_time = self.gs.game_time;
+        self.view_model
+            .update(cvars, scene, self.camera_handle, game_time, dt, velocity);
+
+        // Testing
+        for cycle in &self.gs.cycles {
+            let _cat = dbg_category!(Cycles);
+            let body = &scene.graph[cycle.body_handle];
+            let body_pos = body.global_position();
+            dbg_cross!(body_pos, 3.0);
+            dbg_rot!(body_pos, **body.local_transform().rotation(), 0.0, 0.5);
+        }
+
+        // LATER Intersect with each pole (currently it probably assumes they're all one object)
+        let hits = trace_line(scene, 0.5 * DOWN + BACK, FORWARD, TraceOptions::default());
+        for hit in hits {
+            dbg_cross!(hit.position.coords, 0.0);
+        }
+
+        // Examples of all the debug shapes
+
+        dbg_line!(v!(25 5 5), v!(25 5 7));
+
+        dbg_arrow!(v!(20 5 5), v!(0 0 2)); // Forward
+        dbg_arrow!(v!(20 5 5), v!(0 0 -1)); // Back
+        dbg_arrow!(v!(20 5 5), v!(-1 0 0)); // Left
+        dbg_arrow!(v!(20 5 5), v!(1 0 0)); // Right
+        dbg_arrow!(v!(20 5 5), v!(0 1 0)); // Up
+        dbg_arrow!(v!(20 5 5), v!(0 -1 0)); // Down
+
+        dbg_arrow!(v!(20 10 5), v!(1 1 1), 0.0, BLUE);
+        dbg_arrow!(v!(20 10 6), v!(2 2 2), 0.0, BLUE2);
 
-        let player_cycle_handle = self.gs.players[self.lp.player_handle].cycle_handle.unwrap();
-        let player_body_handle = self.gs.cycles[player_cycle_handle].body_handle;
-        let player_cycle_pos = **scene.graph[player_body_handle].local_transform().position();
+        dbg_cross!(v!(15 5 5), 0.0, CYAN);
+
+        dbg_rot!(v!(10 5 5), UnitQuaternion::default());
+
+        dbg_arrow!(v!(15 10 5), v!(0 0 2), 0.0, GREEN);
+        dbg_arrow!(v!(15 10 5), v!(0 0.01 2), 0.0, RED);
+        dbg_arrow!(v!(15 11 5), v!(0 0 2), 0.0, RED);
+        dbg_arrow!(v!(15 11 5), v!(0 0.01 2), 0.0, GREEN);
+        dbg_arrow!(v!(15 12 5), v!(0 0 2), 0.0, RED);
+        dbg_arrow!(v!(15 12 5), v!(0 0 2), 0.0, GREEN);
+    }
+
+    /// Position the camera based on the local player's (or followed player's) view.
+    fn update_camera(&mut self, cvars: &Cvars, engine: &mut Engine, dt: f32) {
+        if cvars.d_fly_camera != self.fly_camera.is_some() {
+            self.fly_camera_toggle(engine);
+        }
+
+        let ps = self.gs.players[self.lp.player_handle].ps;
+        let scene = &mut engine.scenes[self.gs.scene_handle];
+
+        // Observers don't have a cycle.
+        let player_cycle_pos = self.gs.players[self.lp.player_handle].cycle_handle.map(|handle| {
+            let body_handle = self.gs.cycles[handle].body_handle;
+            **scene.graph[body_handle].local_transform().position() + self.smoothing.offset(handle)
+        });
+
+        // Casters can watch from another player's view.
+        let followed = if self.fly_camera.is_some() {
+            None
+        } else {
+            self.caster.followed_cycle(cvars, &self.gs, self.lp.player_handle)
+        };
+        let (view_input, view_cycle_pos) = match followed {
+            Some(cycle_handle) => {
+                let cycle = &self.gs.cycles[cycle_handle];
+                let body = &scene.graph[cycle.body_handle];
+                let pos = **body.local_transform().position() + self.smoothing.offset(cycle_handle);
+                (self.gs.players[cycle.player_handle].input, Some(pos))
+            }
+            None => (self.lp.input, player_cycle_pos),
+        };
 
         let camera = &mut scene.graph[self.camera_handle];
 
         // Camera turning
-        let yaw_angle = self.lp.input.yaw.0.to_radians();
+        let yaw_angle = view_input.yaw.0.to_radians();
         let yaw = UnitQuaternion::from_axis_angle(&UP_AXIS, yaw_angle);
 
-        let pitch_angle = self.lp.input.pitch.0.to_radians();
+        let pitch_angle = view_input.pitch.0.to_radians();
         let pitch_axis = yaw * LEFT_AXIS;
         let pitch = UnitQuaternion::from_axis_angle(&pitch_axis, pitch_angle);
 
         let cam_rot = pitch * yaw;
         camera.local_transform_mut().set_rotation(cam_rot);
 
-        dbg_rot!(v!(0 7 0), cam_rot);
-        dbg_arrow!(v!(0 5 0), cam_rot * FORWARD);
+        {
+            let _cat = dbg_category!(Camera);
+            dbg_rot!(v!(0 7 0), cam_rot);
+            dbg_arrow!(v!(0 5 0), cam_rot * FORWARD);
+        }
 
         // Camera movement
         let camera_pos_old = **camera.local_transform().position();
         let trace_opts = TraceOptions::filter(!IG_ENTITIES).with_end(true);
-        if ps == PlayerState::Observing {
+        if let Some(fly_camera) = &mut self.fly_camera {
+            // Goes through everything, unlike observers.
+            let dir =
+                free_move_dir(&self.lp.input, cam_rot * FORWARD, cam_rot * LEFT, cam_rot * UP);
+            fly_camera.pos += dir * dt * cvars.d_fly_camera_speed;
+            camera.local_transform_mut().set_position(fly_camera.pos);
+        } else if ps == PlayerState::Observing && followed.is_none() {
             let forward = camera.forward_vec_normed();
             let left = camera.left_vec_normed();
             let up = camera.up_vec_normed();
-            let mut delta = Vec3::zeros();
-            if self.lp.input.forward {
-                delta += forward * dt * cvars.cl_camera_speed;
-            }
-            if self.lp.input.backward {
-                delta += -forward * dt * cvars.cl_camera_speed;
-            }
-            if self.lp.input.left {
-                delta += left * dt * cvars.cl_camera_speed;
-            }
-            if self.lp.input.right {
-                delta += -left * dt * cvars.cl_camera_speed;
-            }
-            if self.lp.input.up {
-                delta += up * dt * cvars.cl_camera_speed;
-            }
-            if self.lp.input.down {
-                delta += -up * dt * cvars.cl_camera_speed;
-            }
+            let delta =
+                free_move_dir(&self.lp.input, forward, left, up) * dt * cvars.cl_camera_speed;
 
             let hits = trace_line(scene, camera_pos_old, delta, trace_opts);
             let new_pos = hits[0].position.coords;
             scene.graph[self.camera_handle].local_transform_mut().set_position(new_pos);
-        } else if ps == PlayerState::Playing {
-            let up = UP * cvars.cl_camera_3rd_person_up;
-            let back = cam_rot * BACK * cvars.cl_camera_3rd_person_back;
-
-            let hits = trace_line(scene, player_cycle_pos, up, trace_opts);
-            let hits = trace_line(scene, hits[0].position, back, trace_opts);
-            let new_pos = hits[0].position.coords;
-            scene.graph[self.camera_handle].local_transform_mut().set_position(new_pos);
+        } else if ps == PlayerState::Playing || followed.is_some() {
+            // The server sends the cycle before the join so this should always be there.
+            if let Some(view_cycle_pos) = view_cycle_pos {
+                let up = UP * cvars.cl_camera_3rd_person_up;
+                let back = cam_rot * BACK * cvars.cl_camera_3rd_person_back;
+
+                let hits = trace_line(scene, view_cycle_pos, up, trace_opts);
+                let hits = trace_line(scene, hits[0].position, back, trace_opts);
+                let new_pos = hits[0].position.coords;
+                scene.graph[self.camera_handle].local_transform_mut().set_position(new_pos);
+            }
         } else {
             unreachable!(); // LATER Spectating
         }
 
+        // Camera feedback - only changes where the camera looks, not where the player aims.
+        let feedback_rot = self.feedback.update(cvars, dt);
+        scene.graph[self.camera_handle]
+            .local_transform_mut()
+            .set_rotation(cam_rot * feedback_rot);
+
         // Camera zoom
         let camera = scene.graph[self.camera_handle].as_camera_mut();
         if let Projection::Perspective(perspective) = camera.projection_mut() {
-            let zoom_factor = if self.lp.input.zoom {
+            let zoom_factor = if self.lp.input.pressed(Action::Zoom) {
                 cvars.cl_zoom_factor
             } else {
                 1.0
@@ -378,43 +1217,28 @@ impl ClientGame {
         } else {
             unreachable!();
         }
+    }
 
-        // Testing
-        for cycle in &self.gs.cycles {
-            let body_pos = scene.graph[cycle.body_handle].global_position();
-            dbg_cross!(body_pos, 3.0);
-        }
-
-        // LATER Intersect with each pole (currently it probably assumes they're all one object)
-        let hits = trace_line(scene, 0.5 * DOWN + BACK, FORWARD, TraceOptions::default());
-        for hit in hits {
-            dbg_cross!(hit.position.coords, 0.0);
+    /// Start flying from where the camera is or return to the cycle.
+    fn fly_camera_toggle(&mut self, engine: &Engine) {
+        match self.fly_camera.take() {
+            Some(fly_camera) => {
+                // Look where the cycle was looking so it doesn't suddenly turn.
+                self.lp.input.yaw = fly_camera.cycle_input.yaw;
+                self.lp.input.pitch = fly_camera.cycle_input.pitch;
+                dbg_logf!("fly camera off");
+            }
+            None => {
+                let scene = &engine.scenes[self.gs.scene_handle];
+                let pos = **scene.graph[self.camera_handle].local_transform().position();
+                let mut cycle_input = self.lp.input;
+                cycle_input.release_all_keys();
+                cycle_input.yaw_speed = Deg(0.0);
+                cycle_input.pitch_speed = Deg(0.0);
+                self.fly_camera = Some(FlyCamera { pos, cycle_input });
+                dbg_logf!("fly camera on");
+            }
         }
-
-        // Examples of all the debug shapes
-
-        dbg_line!(v!(25 5 5), v!(25 5 7));
-
-        dbg_arrow!(v!(20 5 5), v!(0 0 2)); // Forward
-        dbg_arrow!(v!(20 5 5), v!(0 0 -1)); // Back
-        dbg_arrow!(v!(20 5 5), v!(-1 0 0)); // Left
-        dbg_arrow!(v!(20 5 5), v!(1 0 0)); // Right
-        dbg_arrow!(v!(20 5 5), v!(0 1 0)); // Up
-        dbg_arrow!(v!(20 5 5), v!(0 -1 0)); // Down
-
-        dbg_arrow!(v!(20 10 5), v!(1 1 1), 0.0, BLUE);
-        dbg_arrow!(v!(20 10 6), v!(2 2 2), 0.0, BLUE2);
-
-        dbg_cross!(v!(15 5 5), 0.0, CYAN);
-
-        dbg_rot!(v!(10 5 5), UnitQuaternion::default());
-
-        dbg_arrow!(v!(15 10 5), v!(0 0 2), 0.0, GREEN);
-        dbg_arrow!(v!(15 10 5), v!(0 0.01 2), 0.0, RED);
-        dbg_arrow!(v!(15 11 5), v!(0 0 2), 0.0, RED);
-        dbg_arrow!(v!(15 11 5), v!(0 0.01 2), 0.0, GREEN);
-        dbg_arrow!(v!(15 12 5), v!(0 0 2), 0.0, RED);
-        dbg_arrow!(v!(15 12 5), v!(0 0 2), 0.0, GREEN);
     }
 
     fn tick_after_physics(&mut self, cvars: &Cvars, engine: &mut Engine, dt: f32) {
@@ -432,6 +1256,19 @@ impl ClientGame {
             scene.graph.physics.draw(&mut scene.drawing_context);
         }
 
+        self.trails.update(cvars, scene, &self.gs, self.camera_handle);
+        self.effects.update(cvars, scene, &self.gs);
+        self.decals.update(scene, self.gs.game_time);
+        let camera_pos = scene.graph[self.camera_handle].global_position();
+        self.lights.update(cvars, scene, &self.gs, camera_pos);
+        self.wrecks.update(scene, self.gs.game_time, dt);
+
+        if cvars.d_graph_frametime {
+            let frame_size = engine.renderer.get_frame_size();
+            let aspect = frame_size.0 as f32 / frame_size.1 as f32;
+            self.graph_frametime(cvars, scene, aspect);
+        }
+
         // Testing
         for cycle in &self.gs.cycles {
             let body_pos = scene.graph[cycle.body_handle].global_position();
@@ -442,6 +1279,19 @@ impl ClientGame {
             dbg_line!(body_pos, body_pos + UP, 0.0, BLUE2);
         }
 
+        self.inspector.update(cvars, engine, &self.gs);
+        self.caster
+            .update(cvars, engine, &self.gs, self.lp.player_handle, self.camera_handle);
+        self.hud.update(
+            cvars,
+            engine,
+            &self.gs,
+            &self.strings,
+            self.lp.player_handle,
+            self.lp.input.pressed(Action::Score),
+        );
+        let scene = &mut engine.scenes[self.gs.scene_handle];
+
         DEBUG_SHAPES.with(|shapes| {
             // Sometimes debug shapes overlap and only the last one gets drawn.
             // This is especially common when both client and server wanna draw.
@@ -462,6 +1312,9 @@ impl ClientGame {
             }
         });
 
+        self.draw_world_texts(cvars, engine, dt);
+
+        let scene = &mut engine.scenes[self.gs.scene_handle];
         let mut debug_string = String::new();
         if cvars.d_draw_text {
             if cvars.d_engine_stats {
@@ -470,6 +1323,24 @@ impl ClientGame {
                 debug_string.push('\n');
                 debug_string.push('\n');
             }
+            if cvars.d_draw_asserts {
+                let lines = debug::details::asserts_text();
+                if !lines.is_empty() {
+                    debug_string.push_str("soft asserts failed:\n");
+                    for line in lines {
+                        debug_string.push_str(&line);
+                        debug_string.push('\n');
+                    }
+                    debug_string.push('\n');
+                }
+            }
+            if cvars.d_draw_timers {
+                for line in debug::details::timers_text() {
+                    debug_string.push_str(&line);
+                    debug_string.push('\n');
+                }
+                debug_string.push('\n');
+            }
             DEBUG_TEXTS.with(|texts| {
                 let texts = texts.borrow();
                 for text in texts.iter() {
@@ -489,19 +1360,634 @@ impl ClientGame {
         debug::details::clear_expired();
     }
 
+    /// Draw a bar for each recent frame time in the bottom left corner of the screen.
+    ///
+    /// Unlike an average, this makes it easy to spot individual hitches.
+    /// The horizontal lines are 60 and 30 FPS.
+    fn graph_frametime(&self, cvars: &Cvars, scene: &mut Scene, aspect: f32) {
+        let camera = &scene.graph[self.camera_handle];
+        let fov = match camera.as_camera().projection() {
+            Projection::Perspective(perspective) => perspective.fov,
+            Projection::Orthographic(_) => return,
+        };
+
+        // The graph is drawn in world space on a plane in front of the camera
+        // so it has to be recalculated every frame.
+        let dist = cvars.d_graph_frametime_distance;
+        let half_height = (fov / 2.0).tan() * dist;
+        let half_width = half_height * aspect;
+        let forward = camera.forward_vec_normed();
+        let left = camera.left_vec_normed();
+        let up = camera.up_vec_normed();
+        let origin = camera.global_position() + forward * dist + left * half_width * 0.95
+            - up * half_height * 0.9;
+
+        let width = half_width * 0.8;
+        let bar_width = width / cvars.d_graph_frametime_len as f32;
+        // Full height of the graph is 50 ms.
+        let height_per_ms = half_height * 0.6 / 50.0;
+
+        let mut add_line = |begin, end, color| {
+            scene.drawing_context.add_line(Line { begin, end, color });
+        };
+
+        for (i, &frame_time) in self.frame_times.iter().enumerate() {
+            let ms = frame_time * 1000.0;
+            let color = if ms <= 1000.0 / 60.0 {
+                GREEN
+            } else if ms <= 1000.0 / 30.0 {
+                YELLOW
+            } else {
+                RED
+            };
+            let begin = origin - left * bar_width * i as f32;
+            let end = begin + up * ms.min(50.0) * height_per_ms;
+            add_line(begin, end, color);
+        }
+
+        for (ms, color) in [(1000.0_f32 / 60.0, CYAN), (1000.0 / 30.0, MAGENTA)] {
+            let begin = origin + up * ms * height_per_ms;
+            let end = begin - left * width;
+            add_line(begin, end, color);
+        }
+    }
+
+    /// Position a label over each world text.
+    ///
+    /// Texts behind the camera and those that don't fit into the preallocated labels are hidden.
+    fn draw_world_texts(&mut self, cvars: &Cvars, engine: &mut Engine, dt: f32) {
+        let scene = &engine.scenes[self.gs.scene_handle];
+        let camera = scene.graph[self.camera_handle].as_camera();
+        let frame_size = engine.renderer.get_frame_size();
+        let screen_size = Vector2::new(frame_size.0 as f32, frame_size.1 as f32);
+
+        DEBUG_WORLD_TEXTS.with(|texts| {
+            let mut texts = texts.borrow_mut();
+            let mut labels = self.debug_world_texts.iter();
+            for text in texts.iter_mut() {
+                text.time -= dt;
+
+                if !cvars.d_draw || !cvars.d_draw_world_texts {
+                    continue;
+                }
+                let screen_pos = match camera.project(text.pos, screen_size) {
+                    Some(screen_pos) => screen_pos,
+                    None => continue,
+                };
+                let label = match labels.next() {
+                    Some(&label) => label,
+                    None => continue,
+                };
+
+                let ui = &mut engine.user_interface;
+                ui.send_message(TextMessage::text(
+                    label,
+                    MessageDirection::ToWidget,
+                    text.text.clone(),
+                ));
+                ui.send_message(WidgetMessage::foreground(
+                    label,
+                    MessageDirection::ToWidget,
+                    Brush::Solid(details::from_rgba(text.color)),
+                ));
+                ui.send_message(WidgetMessage::desired_position(
+                    label,
+                    MessageDirection::ToWidget,
+                    screen_pos,
+                ));
+                ui.send_message(WidgetMessage::visibility(label, MessageDirection::ToWidget, true));
+            }
+
+            // Hide the rest
+            for &label in labels {
+                engine.user_interface.send_message(WidgetMessage::visibility(
+                    label,
+                    MessageDirection::ToWidget,
+                    false,
+                ));
+            }
+        });
+    }
+
+    /// Run a console command which needs the game.
+    pub(crate) fn game_command(&mut self, cvars: &Cvars, command: GameCommand) {
+        match command {
+            GameCommand::Ready(ready) => self.network_send(ClientMessage::Ready(ready)),
+            GameCommand::Team(team) => self.network_send(ClientMessage::Team(team)),
+            GameCommand::Cheat(cheat) => self.network_send(ClientMessage::Cheat(cheat)),
+            GameCommand::Say(line) => self.network_send(ClientMessage::Chat(line)),
+            GameCommand::Emote(emote) => self.network_send(ClientMessage::Emote(emote)),
+            GameCommand::Bind(button, binding) => {
+                self.bindings.bind(&mut self.lp.input, button, binding)
+            }
+            GameCommand::InputRecord(path) => {
+                self.input_record_stop();
+                if let Some(path) = path {
+                    match InputRecorder::create(&path) {
+                        Ok(recorder) => {
+                            dbg_logf!("recording input to {}", path);
+                            self.input_recorder = Some(recorder);
+                        }
+                        Err(err) => dbg_logf!("failed to record input to {}: {}", path, err),
+                    }
+                }
+            }
+            GameCommand::InputPlay(path) => {
+                self.input_play_stop();
+                if let Some(path) = path {
+                    match InputPlayback::load(&path) {
+                        Ok(playback) => {
+                            dbg_logf!("playing input from {}", path);
+                            self.input_playback = Some(playback);
+                        }
+                        Err(err) => dbg_logf!("failed to play input: {}", err),
+                    }
+                }
+            }
+            GameCommand::Mute(player_index) => self.mute(player_index, true),
+            GameCommand::Unmute(player_index) => self.mute(player_index, false),
+            GameCommand::MuteList => {
+                for &fingerprint in self.mutes.muted() {
+                    let connected = self
+                        .gs
+                        .players
+                        .pair_iter()
+                        .find(|(_, player)| player.fingerprint == Some(fingerprint));
+                    match connected {
+                        Some((player_handle, _)) => dbg_logf!(
+                            "    {} - player {}",
+                            mutes::format_fingerprint(fingerprint),
+                            player_handle.index()
+                        ),
+                        None => dbg_logf!("    {}", mutes::format_fingerprint(fingerprint)),
+                    }
+                }
+                for &player_handle in &self.muted_players {
+                    dbg_logf!("    player {} until they leave", player_handle.index());
+                }
+            }
+            GameCommand::FavoriteAdd(addr) => {
+                if self.servers.add_favorite(&addr) {
+                    self.save_servers(cvars);
+                    dbg_logf!("added {} to favorites", addr);
+                } else {
+                    dbg_logf!("{} is already a favorite", addr);
+                }
+            }
+            GameCommand::FavoriteRemove(addr) => {
+                if self.servers.remove_favorite(&addr) {
+                    self.save_servers(cvars);
+                    dbg_logf!("removed {} from favorites", addr);
+                } else {
+                    dbg_logf!("{} isn't a favorite", addr);
+                }
+            }
+            GameCommand::Favorites => {
+                for addr in &self.servers.favorites {
+                    dbg_logf!("    {}", addr);
+                }
+            }
+            GameCommand::History { by_ping } => {
+                for line in self.servers.history_lines(by_ping) {
+                    dbg_logf!("    {}", line);
+                }
+            }
+            GameCommand::Probe => {
+                let addrs = self.servers.history.iter().map(|visit| visit.addr.clone()).collect();
+                match Prober::new(addrs) {
+                    Ok(prober) => self.prober = Some(prober),
+                    Err(err) => dbg_logf!("failed to start probing: {}", err),
+                }
+            }
+            GameCommand::Motd => {
+                if !self.hud.show_motd() {
+                    dbg_logf!("the server has no message of the day");
+                }
+            }
+            GameCommand::Stats => {
+                for line in self.profile.lines() {
+                    dbg_logf!("    {}", line);
+                }
+            }
+            GameCommand::Rewind(_)
+            | GameCommand::Resume
+            | GameCommand::NetStats(_)
+            | GameCommand::SaveState(_)
+            | GameCommand::LoadState(_) => {
+                soft_assert!(false, "the client process handles commands for the local server");
+            }
+        }
+    }
+
+    /// Whether chat from this player should be hidden, `None` is the server.
+    fn is_muted(&self, player_index: Option<u32>) -> bool {
+        player_index.map_or(false, |player_index| {
+            let Some(player) = self.gs.players.at(player_index) else {
+                return false;
+            };
+            let player_handle = self.gs.players.handle_from_index(player_index);
+            self.mutes.is_muted(player.fingerprint) || self.muted_players.contains(&player_handle)
+        })
+    }
+
+    /// `mute` and `unmute`
+    fn mute(&mut self, player_index: u32, mute: bool) {
+        if player_index == self.lp.player_handle.index() {
+            dbg_logf!("you can't mute yourself");
+            return;
+        }
+        let Some(player) = self.gs.players.at(player_index) else {
+            dbg_logf!("no player {}", player_index);
+            return;
+        };
+        // The server only sends fingerprints to admins.
+        let Some(fingerprint) = player.fingerprint else {
+            let player_handle = self.gs.players.handle_from_index(player_index);
+            let muted = self.muted_players.contains(&player_handle);
+            match (muted, mute) {
+                (false, true) => {
+                    self.muted_players.push(player_handle);
+                    dbg_logf!("muted player {} until they leave", player_index);
+                }
+                (true, false) => {
+                    self.muted_players.retain(|&muted| muted != player_handle);
+                    dbg_logf!("unmuted player {}", player_index);
+                }
+                (true, true) => dbg_logf!("player {} is already muted", player_index),
+                (false, false) => dbg_logf!("player {} isn't muted", player_index),
+            }
+            return;
+        };
+        let res = if mute {
+            self.mutes.mute(fingerprint)
+        } else {
+            self.mutes.unmute(fingerprint)
+        };
+        match (res, mute) {
+            (Ok(true), true) => dbg_logf!("muted player {}", player_index),
+            (Ok(true), false) => dbg_logf!("unmuted player {}", player_index),
+            (Ok(false), true) => dbg_logf!("player {} is already muted", player_index),
+            (Ok(false), false) => dbg_logf!("player {} isn't muted", player_index),
+            (Err(err), _) => dbg_logf!("failed to save mutes: {}", err),
+        }
+    }
+
+    /// Replace the input with the recorded one and/or record it.
+    ///
+    /// Only while playing so recordings start and end with the cycle on the map.
+    /// Dead players waiting to respawn have no cycle so they're skipped too.
+    fn input_record_tick(&mut self, engine: &Engine, dt: f32) {
+        let player = &self.gs.players[self.lp.player_handle];
+        if player.ps != PlayerState::Playing {
+            return;
+        }
+        let Some(cycle_handle) = player.cycle_handle else {
+            return;
+        };
+        let body_handle = self.gs.cycles[cycle_handle].body_handle;
+        let scene = &engine.scenes[self.gs.scene_handle];
+        let pos = **scene.graph[body_handle].local_transform().position();
+
+        if let Some(playback) = &mut self.input_playback {
+            match playback.next_input() {
+                Some(mut input) => {
+                    // Keep the current times, only what the player did is replayed.
+                    input.real_time = self.lp.input.real_time;
+                    input.game_time = self.lp.input.game_time;
+                    self.lp.input = input;
+                    playback.stats.add(pos, dt);
+                }
+                None => {
+                    self.input_play_stop();
+                    self.bindings.release_all(&mut self.lp.input);
+                }
+            }
+        }
+
+        if let Some(recorder) = &mut self.input_recorder {
+            recorder.stats.add(pos, dt);
+            if let Err(err) = recorder.record(&self.lp.input) {
+                dbg_logf!("failed to record input to {}: {}", recorder.path, err);
+                self.input_recorder = None;
+            }
+        }
+    }
+
+    fn input_record_stop(&mut self) {
+        if let Some(recorder) = self.input_recorder.take() {
+            let summary = recorder.stats.summary();
+            let path = recorder.path.clone();
+            match recorder.finish() {
+                Ok(_) => dbg_logf!("input recording {} finished: {}", path, summary),
+                Err(err) => dbg_logf!("failed to finish input recording {}: {}", path, err),
+            }
+        }
+    }
+
+    fn input_play_stop(&mut self) {
+        if let Some(playback) = self.input_playback.take() {
+            let summary = playback.stats.summary();
+            dbg_logf!("input playback {} finished: {}", playback.path, summary);
+        }
+    }
+
+    fn desync_check_send(&mut self, cvars: &Cvars, engine: &Engine) {
+        let scene = &engine.scenes[self.gs.scene_handle];
+        let state = StateSummary::new(cvars, &self.gs, scene);
+        let msg = ClientMessage::DesyncCheck {
+            frame_number: self.server_frame,
+            hash: state.hash(),
+        };
+        self.network_send(msg);
+
+        // Replies can get lost when the server restarts, don't keep them forever.
+        if self.desync_pending.len() >= 16 {
+            self.desync_pending.pop_front();
+        }
+        self.desync_pending.push_back((self.server_frame, state));
+    }
+
+    fn desync_check_receive(
+        &mut self,
+        frame_number: u32,
+        server_hash: u64,
+        server_state: Option<StateSummary>,
+    ) {
+        // Replies arrive in order so anything older was lost or too old for the server.
+        while let Some((pending_frame, state)) = self.desync_pending.pop_front() {
+            if pending_frame != frame_number {
+                continue;
+            }
+            if state.hash() != server_hash {
+                let diff = server_state
+                    .and_then(|server_state| state.first_difference(&server_state))
+                    .unwrap_or_else(|| "unknown".to_owned());
+                dbg_logf!("desync at server tick {}: first difference {}", frame_number, diff);
+            }
+            return;
+        }
+        dbg_logf!("desync check reply for unknown tick {}", frame_number);
+    }
+
+    /// Messages are dropped while reconnecting.
     fn network_send(&mut self, msg: ClientMessage) {
-        let network_msg = net::serialize(msg);
-        let res = self.conn.send(&network_msg);
-        if let Err(ref e) = res {
-            if e.kind() == ErrorKind::ConnectionReset {
-                dbg_logf!("Server disconnected, exitting");
-                std::process::exit(0);
+        if self.reconnect.is_some() {
+            return;
+        }
+        net::serialize_into(&mut self.send_buf, msg);
+        if let Err(err) = self.conn.send(&self.send_buf) {
+            dbg_logf!("send error: {}", err);
+            self.connection_lost();
+        }
+    }
+}
+
+/// Read `cl_color` and `cl_skin`, invalid values are replaced by defaults.
+fn local_look(cvars: &Cvars) -> Look {
+    let mut look = Look::default();
+    match Look::parse_color(&cvars.cl_color) {
+        Some(color) => look.color = color,
+        None => dbg_logf!("invalid cl_color {}, expected rrggbb", cvars.cl_color),
+    }
+    match SKINS.iter().position(|&skin| skin == cvars.cl_skin) {
+        Some(index) => look.skin = index as u8,
+        None => dbg_logf!("unknown cl_skin {}, available: {:?}", cvars.cl_skin, SKINS),
+    }
+    look
+}
+
+/// A line for the kill feed.
+fn kill_text(strings: &Strings, kill: &PlayerKill) -> String {
+    let victim = ("victim", &kill.victim_index as &dyn Display);
+    let mut text = if kill.killer_index == kill.victim_index {
+        strings.format("kill.died", &[victim])
+    } else {
+        strings.format("kill.killed", &[("killer", &kill.killer_index), victim])
+    };
+    if !kill.assist_indices.is_empty() {
+        let assists: Vec<_> = kill.assist_indices.iter().map(u32::to_string).collect();
+        text.push_str(&strings.format("kill.assist", &[("players", &assists.join(", "))]));
+    }
+    text
+}
+
+/// Awards and everyone's stats, best first.
+fn summary_text(strings: &Strings, summary: &MatchSummary) -> String {
+    let mut text = match summary.winner_index {
+        Some(index) => strings.format("summary.winner", &[("player", &index)]),
+        None => strings.get("summary.round_over").to_owned(),
+    };
+    text.push_str("\n\n");
+    for award in &summary.awards {
+        let args: &[(&str, &dyn Display)] = &[
+            ("award", &strings.get(award.kind.key())),
+            ("player", &award.player_index),
+        ];
+        text.push_str(&format!("{}\n", strings.format("summary.award", args)));
+    }
+
+    let mut players: Vec<_> = summary.players.iter().collect();
+    players.sort_by_key(|player| (Reverse(player.frags), player.player_index));
+    text.push_str(&format!("\n{}\n", strings.get("summary.header")));
+    for player in players {
+        let accuracy = if player.shots > 0 {
+            format!("{:.0}%", 100.0 * player.hits as f32 / player.shots as f32)
+        } else {
+            "-".to_owned()
+        };
+        text.push_str(&format!(
+            "{}    {}    {}    {}    {}\n",
+            player.player_index, player.frags, player.deaths, accuracy, player.best_streak
+        ));
+    }
+    text
+}
+
+/// The team's color, otherwise the player's chosen color.
+///
+/// In colorblind mode, players without a team get a color from the palette instead.
+pub(crate) fn player_color(cvars: &Cvars, gs: &GameState, player_handle: Handle<Player>) -> Color {
+    let player = &gs.players[player_handle];
+    match player.team {
+        Some(team) => team_color(cvars.cl_colorblind_mode, usize::from(team)),
+        None if cvars.cl_colorblind_mode == ColorblindMode::Off => {
+            let [r, g, b] = player.look.color;
+            Color::opaque(r, g, b)
+        }
+        None => team_color(cvars.cl_colorblind_mode, player_handle.index() as usize),
+    }
+}
+
+/// Add everything from `Init` to a game state without any players.
+fn apply_init(cvars: &Cvars, scene: &mut Scene, gs: &mut GameState, init: Init) -> LocalPlayer {
+    let Init {
+        player_handles,
+        local_player_handle,
+        player_cycles,
+        player_projectiles,
+        player_looks,
+        player_fingerprints,
+        ready_handles,
+        player_teams,
+        player_cheats,
+        cheats_allowed: _,
+        phase,
+        server_name: _,
+        region: _,
+        map: _,
+        theme: _,
+        reconnect_token: _,
+        frame_number: _,
+    } = init;
+
+    // Everything here refers to players spawned from the same message
+    // so handles are used directly, a mismatch is a server bug.
+    for player_handle in player_handles {
+        let player = Player::new(None);
+        gs.players.spawn_at_handle(player_handle.handle(), player).unwrap();
+    }
+    for PlayerLook {
+        player_handle,
+        look,
+    } in player_looks
+    {
+        gs.players[player_handle.handle()].look = look;
+    }
+    for PlayerFingerprint {
+        player_handle,
+        fingerprint,
+    } in player_fingerprints
+    {
+        gs.players[player_handle.handle()].fingerprint = Some(fingerprint);
+    }
+    for player_handle in ready_handles {
+        gs.players[player_handle.handle()].ready = true;
+    }
+    for PlayerTeam {
+        player_handle,
+        team,
+    } in player_teams
+    {
+        gs.players[player_handle.handle()].team = team;
+    }
+    gs.phase = phase;
+    let local_player_handle = local_player_handle.handle();
+    let lp = LocalPlayer::new(local_player_handle);
+
+    for PlayerCycle {
+        player_handle,
+        cycle_handle,
+        vehicle,
+    } in player_cycles
+    {
+        let player_handle = player_handle.handle();
+        let cycle_handle = Some(cycle_handle.handle());
+        gs.spawn_cycle(cvars, scene, player_handle, vehicle, cycle_handle);
+        apply_look(cvars, scene, gs, local_player_handle, player_handle);
+    }
+
+    // After spawning cycles because cheats change their physics.
+    for PlayerCheats {
+        player_handle,
+        cheats,
+    } in player_cheats
+    {
+        gs.set_cheats(scene, player_handle.handle(), cheats);
+    }
+
+    for PlayerProjectile {
+        player_handle: _,
+        projectile_handle: _,
+    } in player_projectiles
+    {
+        todo!("init projectiles");
+    }
+
+    lp
+}
+
+/// The entity a message from the server is about.
+///
+/// `None` (and logged) if it doesn't exist (anymore)
+/// so the message is skipped instead of changing whatever now uses the same slot.
+fn resolve<T>(pool: &Pool<T>, net_handle: NetHandle) -> Option<Handle<T>> {
+    let handle = net_handle.get(pool);
+    if handle.is_none() {
+        dbg_logf!("ignoring message about unknown entity {:?}", net_handle);
+    }
+    handle
+}
+
+/// Tint the meshes of the player's cycle (if any) with the player's color.
+///
+/// Cloaked cycles are see-through, enemies of the `viewer` almost completely.
+fn apply_look(
+    cvars: &Cvars,
+    scene: &mut Scene,
+    gs: &GameState,
+    viewer: Handle<Player>,
+    player_handle: Handle<Player>,
+) {
+    let player = &gs.players[player_handle];
+    let Some(cycle_handle) = player.cycle_handle else {
+        return;
+    };
+    let body_handle = gs.cycles[cycle_handle].body_handle;
+    let mut color = player_color(cvars, gs, player_handle);
+    if player.cloaked {
+        let friendly = player_handle == viewer
+            || (player.team.is_some() && player.team == gs.players[viewer].team);
+        color.a = if friendly {
+            CLOAK_ALPHA_FRIENDLY
+        } else {
+            CLOAK_ALPHA_ENEMY
+        };
+    }
+    // Deferred rendering ignores alpha.
+    let render_path = if player.cloaked {
+        RenderPath::Forward
+    } else {
+        RenderPath::Deferred
+    };
+
+    let handles: Vec<_> = scene.graph.traverse_handle_iter(body_handle).collect();
+    for handle in handles {
+        if let Some(mesh) = scene.graph[handle].cast_mut::<Mesh>() {
+            mesh.set_render_path(render_path);
+            for surface in mesh.surfaces_mut() {
+                // Materials are shared by all instances of a model, each cycle needs its own.
+                let mut material = surface.material().lock().clone();
+                let res = material.set_property(
+                    &ImmutableString::new("diffuseColor"),
+                    PropertyValue::Color(color),
+                );
+                soft_assert!(res.is_ok(), "failed to set cycle color: {:?}", res);
+                surface.set_material(Arc::new(Mutex::new(material)));
             }
         }
-        res.unwrap();
     }
 }
 
+/// See `d_fly_camera`.
+#[derive(Debug)]
+struct FlyCamera {
+    pos: Vec3,
+    /// Sent to the server instead of the live input while flying.
+    cycle_input: Input,
+}
+
+/// Which way a free camera moves given how it's oriented, not normalized.
+fn free_move_dir(input: &Input, forward: Vec3, left: Vec3, up: Vec3) -> Vec3 {
+    let mut dir = forward * input.axis(Axis::Move) - left * input.axis(Axis::Steer);
+    if input.pressed(Action::Up) {
+        dir += up;
+    }
+    if input.pressed(Action::Down) {
+        dir -= up;
+    }
+    dir
+}
+
 /// State of the local player
 ///
 /// LATER maybe just merge into ClientGame?
@@ -0,0 +1,737 @@
+//! The heads-up display - match status, timers, armor, chat, kills, the scoreboard
+//! with the server's icon,
+//! the summary at the end of a round, the server's message of the day
+//! and warnings such as being out of bounds or a bad connection.
+//!
+//! See the `a11y_` cvars for the font, high contrast and reduced effects.
+//!
+//! LATER Health, ...
+//! LATER Sound cues for visual events once there's audio.
+
+use std::{cmp::Reverse, collections::VecDeque, fmt::Display, fs, mem};
+
+use fyrox::{
+    gui::{
+        border::BorderBuilder,
+        brush::Brush,
+        image::{ImageBuilder, ImageMessage},
+        message::MessageDirection,
+        text::{TextBuilder, TextMessage},
+        ttf::{Font, SharedFont},
+        widget::{WidgetBuilder, WidgetMessage},
+        HorizontalAlignment, UiNode, UserInterface,
+    },
+    resource::texture::{CompressionOptions, Texture},
+    utils::into_gui_texture,
+};
+
+use crate::{
+    client::locale::Strings,
+    common::{
+        entities::{MatchPhase, OvertimeRule, Player, PlayerState, TEAM_NAMES},
+        theme::{ServerTheme, MAX_ICON_SIZE},
+        GameState,
+    },
+    prelude::*,
+};
+
+/// Distance of the status text from the top of the screen in pixels.
+const STATUS_TOP: f32 = 40.0;
+
+/// Position of the chat from the bottom left corner of the screen in pixels.
+const CHAT_LEFT: f32 = 20.0;
+const CHAT_BOTTOM: f32 = 200.0;
+
+/// Position of the kill feed from the top right corner of the screen in pixels.
+const KILL_FEED_RIGHT: f32 = 20.0;
+const KILL_FEED_TOP: f32 = 20.0;
+const KILL_FEED_WIDTH: f32 = 400.0;
+
+/// Position of connection warnings from the bottom right corner of the screen in pixels.
+const NET_RIGHT: f32 = 20.0;
+const NET_BOTTOM: f32 = 60.0;
+const NET_WIDTH: f32 = 200.0;
+
+/// Height of the server's name above the scoreboard in pixels.
+const SCOREBOARD_HEADER_HEIGHT: f32 = 30.0;
+
+/// Distance of armor and powerups from the bottom of the screen in pixels.
+const VITALS_BOTTOM: f32 = 60.0;
+
+/// Covers the screen while out of bounds.
+const OUT_OF_BOUNDS_TINT: Color = Color::from_rgba(255, 0, 0, 50);
+
+/// Colors with `a11y_high_contrast`.
+const HIGH_CONTRAST_TEXT: Color = Color::from_rgba(255, 255, 0, 255);
+const HIGH_CONTRAST_TINT: Color = Color::from_rgba(255, 0, 0, 100);
+
+pub(crate) struct Hud {
+    /// Colors the whole screen.
+    tint: Handle<UiNode>,
+    /// Warmup, time left and overtime, centered at the top of the screen.
+    status: Handle<UiNode>,
+    /// Armor and powerups, centered at the bottom of the screen.
+    vitals: Handle<UiNode>,
+    /// Shown while holding the score key.
+    scoreboard: Handle<UiNode>,
+    /// The server's name in its accent color above the scoreboard.
+    scoreboard_header: Handle<UiNode>,
+    /// The server's icon above its name.
+    scoreboard_icon: Handle<UiNode>,
+    /// Whether the server sent a valid icon.
+    has_icon: bool,
+    /// See `sv_accent_color`.
+    accent: Option<Color>,
+    /// Shown for `hud_summary_time` after a round ends, hides the scoreboard.
+    summary: Handle<UiNode>,
+    /// Game time when the summary was received and its text.
+    last_summary: Option<(f32, String)>,
+    /// The server's message of the day until the player closes it.
+    motd: Handle<UiNode>,
+    /// The message already formatted and whether it's shown.
+    motd_text: Option<(String, bool)>,
+    /// The last few chat lines, bottom left.
+    chat: Handle<UiNode>,
+    /// Game time when each line was received and the line, oldest first.
+    chat_lines: VecDeque<(f32, String)>,
+    /// The last few kills, top right.
+    kill_feed: Handle<UiNode>,
+    /// Game time when each line was received and the line, oldest first.
+    kill_lines: VecDeque<(f32, String)>,
+    /// Game time when the local player gets killed for being out of bounds.
+    out_of_bounds: Option<f32>,
+    /// Game time when the local player gets a new cycle after dying.
+    respawn: Option<f32>,
+    /// Attempts made and allowed while reconnecting.
+    reconnecting: Option<(u32, u32)>,
+    /// High ping and lost updates, bottom right.
+    net: Handle<UiNode>,
+    /// The last ping in milliseconds and fraction of lost updates.
+    net_stats: (Option<f32>, f32),
+    /// Whether the widgets currently have `a11y_high_contrast` colors.
+    high_contrast: bool,
+}
+
+impl Hud {
+    /// Has to be created before the console because of the z index issue.
+    pub(crate) fn new(cvars: &Cvars, ui: &mut UserInterface) -> Self {
+        // Created first so it's behind the text.
+        let tint = BorderBuilder::new(
+            WidgetBuilder::new()
+                .with_background(Brush::Solid(OUT_OF_BOUNDS_TINT))
+                .with_visibility(false),
+        )
+        .build(&mut ui.build_ctx());
+        let status =
+            TextBuilder::new(WidgetBuilder::new().with_foreground(Brush::Solid(Color::WHITE)))
+                .with_shadow(true)
+                .with_horizontal_text_alignment(HorizontalAlignment::Center)
+                .build(&mut ui.build_ctx());
+        let vitals =
+            TextBuilder::new(WidgetBuilder::new().with_foreground(Brush::Solid(Color::WHITE)))
+                .with_shadow(true)
+                .with_horizontal_text_alignment(HorizontalAlignment::Center)
+                .build(&mut ui.build_ctx());
+        let scoreboard = TextBuilder::new(
+            WidgetBuilder::new()
+                .with_foreground(Brush::Solid(Color::WHITE))
+                .with_visibility(false),
+        )
+        .with_shadow(true)
+        .with_horizontal_text_alignment(HorizontalAlignment::Center)
+        .build(&mut ui.build_ctx());
+        let scoreboard_header = TextBuilder::new(
+            WidgetBuilder::new()
+                .with_foreground(Brush::Solid(Color::WHITE))
+                .with_visibility(false),
+        )
+        .with_shadow(true)
+        .with_horizontal_text_alignment(HorizontalAlignment::Center)
+        .build(&mut ui.build_ctx());
+        let scoreboard_icon = ImageBuilder::new(
+            WidgetBuilder::new()
+                .with_width(MAX_ICON_SIZE as f32)
+                .with_height(MAX_ICON_SIZE as f32)
+                .with_visibility(false),
+        )
+        .build(&mut ui.build_ctx());
+        let summary = TextBuilder::new(
+            WidgetBuilder::new()
+                .with_foreground(Brush::Solid(Color::WHITE))
+                .with_visibility(false),
+        )
+        .with_shadow(true)
+        .with_horizontal_text_alignment(HorizontalAlignment::Center)
+        .build(&mut ui.build_ctx());
+        let motd = TextBuilder::new(
+            WidgetBuilder::new()
+                .with_foreground(Brush::Solid(Color::WHITE))
+                .with_visibility(false),
+        )
+        .with_shadow(true)
+        .with_horizontal_text_alignment(HorizontalAlignment::Center)
+        .build(&mut ui.build_ctx());
+        let chat =
+            TextBuilder::new(WidgetBuilder::new().with_foreground(Brush::Solid(Color::WHITE)))
+                .with_shadow(true)
+                .build(&mut ui.build_ctx());
+        let kill_feed = TextBuilder::new(
+            WidgetBuilder::new()
+                .with_foreground(Brush::Solid(Color::WHITE))
+                .with_width(KILL_FEED_WIDTH),
+        )
+        .with_shadow(true)
+        .with_horizontal_text_alignment(HorizontalAlignment::Right)
+        .build(&mut ui.build_ctx());
+        let net = TextBuilder::new(
+            WidgetBuilder::new()
+                .with_foreground(Brush::Solid(Color::WHITE))
+                .with_width(NET_WIDTH),
+        )
+        .with_shadow(true)
+        .with_horizontal_text_alignment(HorizontalAlignment::Right)
+        .build(&mut ui.build_ctx());
+        let hud = Self {
+            tint,
+            status,
+            vitals,
+            scoreboard,
+            scoreboard_header,
+            scoreboard_icon,
+            has_icon: false,
+            accent: None,
+            summary,
+            last_summary: None,
+            motd,
+            motd_text: None,
+            chat,
+            chat_lines: VecDeque::new(),
+            kill_feed,
+            kill_lines: VecDeque::new(),
+            out_of_bounds: None,
+            respawn: None,
+            reconnecting: None,
+            net,
+            net_stats: (None, 0.0),
+            high_contrast: false,
+        };
+
+        if let Some(font) = load_font(cvars) {
+            for text in hud.texts() {
+                ui.send_message(TextMessage::font(text, MessageDirection::ToWidget, font.clone()));
+            }
+        }
+
+        hud
+    }
+
+    fn texts(&self) -> [Handle<UiNode>; 9] {
+        [
+            self.status,
+            self.vitals,
+            self.scoreboard,
+            self.scoreboard_header,
+            self.summary,
+            self.motd,
+            self.chat,
+            self.kill_feed,
+            self.net,
+        ]
+    }
+
+    /// The server's name, icon and accent color for the scoreboard header, call after each `Init`.
+    pub(crate) fn server(&mut self, engine: &mut Engine, name: &str, theme: &ServerTheme) {
+        let ui = &mut engine.user_interface;
+        ui.send_message(TextMessage::text(
+            self.scoreboard_header,
+            MessageDirection::ToWidget,
+            name.to_owned(),
+        ));
+
+        self.accent = theme.accent.map(|[r, g, b]| Color::opaque(r, g, b));
+        if !self.high_contrast {
+            ui.send_message(WidgetMessage::foreground(
+                self.scoreboard_header,
+                MessageDirection::ToWidget,
+                Brush::Solid(self.accent.unwrap_or(Color::WHITE)),
+            ));
+        }
+
+        // Already validated so this only fails if the PNG is broken after the header.
+        let texture = if theme.icon.is_empty() {
+            None
+        } else {
+            match Texture::load_from_memory(&theme.icon, CompressionOptions::NoCompression, false) {
+                Ok(texture) => Some(into_gui_texture(texture)),
+                Err(err) => {
+                    dbg_logf!("failed to load server icon: {:?}", err);
+                    None
+                }
+            }
+        };
+        self.has_icon = texture.is_some();
+        ui.send_message(ImageMessage::texture(
+            self.scoreboard_icon,
+            MessageDirection::ToWidget,
+            texture,
+        ));
+    }
+
+    /// Show a line in the chat, it disappears after `hud_chat_time`.
+    pub(crate) fn chat(&mut self, game_time: f32, line: String) {
+        self.chat_lines.push_back((game_time, line));
+    }
+
+    /// Show a line in the kill feed, it disappears after `hud_kill_feed_time`.
+    pub(crate) fn kill(&mut self, game_time: f32, line: String) {
+        self.kill_lines.push_back((game_time, line));
+    }
+
+    /// Show what happened in the round.
+    pub(crate) fn summary(&mut self, game_time: f32, text: String) {
+        self.last_summary = Some((game_time, text));
+    }
+
+    /// Show the server's message of the day, see `sv_motd`.
+    pub(crate) fn motd(&mut self, text: &str) {
+        self.motd_text = Some((format_motd(text), true));
+    }
+
+    /// Show the message of the day again, returns false if the server didn't send any.
+    pub(crate) fn show_motd(&mut self) -> bool {
+        match &mut self.motd_text {
+            Some((_, shown)) => {
+                *shown = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns false if it wasn't shown.
+    pub(crate) fn close_motd(&mut self) -> bool {
+        match &mut self.motd_text {
+            Some((_, shown)) => mem::replace(shown, false),
+            None => false,
+        }
+    }
+
+    /// Start or stop the out of bounds countdown.
+    pub(crate) fn out_of_bounds(&mut self, game_time: f32, time_left: Option<f32>) {
+        self.out_of_bounds = time_left.map(|time_left| game_time + time_left);
+    }
+
+    pub(crate) fn respawn(&mut self, game_time: f32, time_left: f32) {
+        self.respawn = Some(game_time + time_left);
+    }
+
+    /// Ping in milliseconds (if measured) and the fraction of updates lost, see `hud_net_warnings`.
+    pub(crate) fn net_stats(&mut self, ping: Option<f32>, loss: f32) {
+        self.net_stats = (ping, loss);
+    }
+
+    /// Show or hide reconnect progress.
+    pub(crate) fn reconnecting(&mut self, attempts: Option<(u32, u32)>) {
+        self.reconnecting = attempts;
+    }
+
+    pub(crate) fn update(
+        &mut self,
+        cvars: &Cvars,
+        engine: &mut Engine,
+        gs: &GameState,
+        strings: &Strings,
+        local_player: Handle<Player>,
+        show_scoreboard: bool,
+    ) {
+        let mut text = match gs.phase {
+            MatchPhase::Warmup { deadline } => warmup_text(strings, gs, local_player, deadline),
+            MatchPhase::Live { end: Some(end) } => clock(end - gs.game_time),
+            MatchPhase::Live { end: None } => String::new(),
+            MatchPhase::Overtime { rule, end } => overtime_text(strings, rule, end, gs.game_time),
+            MatchPhase::Paused { deadline } => {
+                let time_left = clock(deadline - gs.game_time);
+                let hint = strings.get("hud.paused_hint");
+                format!("{}\n{}\n{}", strings.get("hud.paused"), time_left, hint)
+            }
+        };
+        // Autobalance can move us mid-match so always show it.
+        if let Some(team) = gs.players[local_player].team {
+            let name = TEAM_NAMES.get(usize::from(team)).unwrap_or(&"unknown");
+            let team = strings.format("hud.team", &[("team", &name.to_uppercase())]);
+            text.insert_str(0, &format!("{}\n", team));
+        }
+        if let Some(deadline) = self.out_of_bounds {
+            let time_left = (deadline - gs.game_time).max(0.0);
+            let warning = strings.get("hud.out_of_bounds");
+            text.push_str(&format!("\n{}\n{:.1}", warning, time_left));
+        }
+        if let Some(time) = self.respawn.filter(|&time| time > gs.game_time) {
+            let seconds = format!("{:.1}", time - gs.game_time);
+            let args: &[(&str, &dyn Display)] = &[("seconds", &seconds)];
+            text.push_str(&format!("\n{}", strings.format("hud.respawn", args)));
+        }
+        if let Some((attempt, attempts)) = self.reconnecting {
+            let args: &[(&str, &dyn Display)] = &[("attempt", &attempt), ("attempts", &attempts)];
+            text.push_str(&format!("\n{}", strings.format("hud.reconnecting", args)));
+        }
+
+        // The text is centered inside the widget so make it as wide as the screen.
+        let frame_size = engine.renderer.get_frame_size();
+        let ui = &mut engine.user_interface;
+
+        if cvars.a11y_high_contrast != self.high_contrast {
+            self.high_contrast = cvars.a11y_high_contrast;
+            let (text_color, tint_color) = if self.high_contrast {
+                (HIGH_CONTRAST_TEXT, HIGH_CONTRAST_TINT)
+            } else {
+                (Color::WHITE, OUT_OF_BOUNDS_TINT)
+            };
+            for text in self.texts() {
+                ui.send_message(WidgetMessage::foreground(
+                    text,
+                    MessageDirection::ToWidget,
+                    Brush::Solid(text_color),
+                ));
+            }
+            if let Some(accent) = self.accent.filter(|_| !self.high_contrast) {
+                ui.send_message(WidgetMessage::foreground(
+                    self.scoreboard_header,
+                    MessageDirection::ToWidget,
+                    Brush::Solid(accent),
+                ));
+            }
+            ui.send_message(WidgetMessage::background(
+                self.tint,
+                MessageDirection::ToWidget,
+                Brush::Solid(tint_color),
+            ));
+        }
+
+        ui.send_message(WidgetMessage::width(
+            self.status,
+            MessageDirection::ToWidget,
+            frame_size.0 as f32,
+        ));
+        ui.send_message(WidgetMessage::desired_position(
+            self.status,
+            MessageDirection::ToWidget,
+            Vector2::new(0.0, STATUS_TOP),
+        ));
+        ui.send_message(TextMessage::text(self.status, MessageDirection::ToWidget, text));
+
+        ui.send_message(WidgetMessage::visibility(
+            self.tint,
+            MessageDirection::ToWidget,
+            self.out_of_bounds.is_some() && !cvars.a11y_reduced_effects,
+        ));
+        ui.send_message(WidgetMessage::width(
+            self.tint,
+            MessageDirection::ToWidget,
+            frame_size.0 as f32,
+        ));
+        ui.send_message(WidgetMessage::height(
+            self.tint,
+            MessageDirection::ToWidget,
+            frame_size.1 as f32,
+        ));
+
+        let player = &gs.players[local_player];
+        let mut vitals = Vec::new();
+        if player.armor > 0 {
+            vitals.push(strings.format("hud.armor", &[("armor", &player.armor)]));
+        }
+        if player.cloaked {
+            vitals.push(strings.get("hud.cloaked").to_owned());
+        }
+        ui.send_message(WidgetMessage::width(
+            self.vitals,
+            MessageDirection::ToWidget,
+            frame_size.0 as f32,
+        ));
+        ui.send_message(WidgetMessage::desired_position(
+            self.vitals,
+            MessageDirection::ToWidget,
+            Vector2::new(0.0, frame_size.1 as f32 - VITALS_BOTTOM),
+        ));
+        ui.send_message(TextMessage::text(
+            self.vitals,
+            MessageDirection::ToWidget,
+            vitals.join("   "),
+        ));
+
+        self.chat_lines.retain(|&(time, _)| gs.game_time - time <= cvars.hud_chat_time);
+        while self.chat_lines.len() > cvars.hud_chat_lines {
+            self.chat_lines.pop_front();
+        }
+        let chat: Vec<_> = self.chat_lines.iter().map(|(_, line)| line.as_str()).collect();
+        ui.send_message(WidgetMessage::desired_position(
+            self.chat,
+            MessageDirection::ToWidget,
+            Vector2::new(CHAT_LEFT, frame_size.1 as f32 - CHAT_BOTTOM),
+        ));
+        ui.send_message(TextMessage::text(self.chat, MessageDirection::ToWidget, chat.join("\n")));
+
+        self.kill_lines
+            .retain(|&(time, _)| gs.game_time - time <= cvars.hud_kill_feed_time);
+        while self.kill_lines.len() > cvars.hud_kill_feed_lines {
+            self.kill_lines.pop_front();
+        }
+        let kills: Vec<_> = self.kill_lines.iter().map(|(_, line)| line.as_str()).collect();
+        ui.send_message(WidgetMessage::desired_position(
+            self.kill_feed,
+            MessageDirection::ToWidget,
+            Vector2::new(frame_size.0 as f32 - KILL_FEED_RIGHT - KILL_FEED_WIDTH, KILL_FEED_TOP),
+        ));
+        ui.send_message(TextMessage::text(
+            self.kill_feed,
+            MessageDirection::ToWidget,
+            kills.join("\n"),
+        ));
+
+        ui.send_message(WidgetMessage::desired_position(
+            self.net,
+            MessageDirection::ToWidget,
+            Vector2::new(
+                frame_size.0 as f32 - NET_RIGHT - NET_WIDTH,
+                frame_size.1 as f32 - NET_BOTTOM,
+            ),
+        ));
+        ui.send_message(TextMessage::text(
+            self.net,
+            MessageDirection::ToWidget,
+            net_text(cvars, strings, self.net_stats),
+        ));
+
+        if let Some((time, _)) = self.last_summary {
+            if gs.game_time - time > cvars.hud_summary_time {
+                self.last_summary = None;
+            }
+        }
+        ui.send_message(WidgetMessage::visibility(
+            self.summary,
+            MessageDirection::ToWidget,
+            self.last_summary.is_some(),
+        ));
+        if let Some((_, text)) = &self.last_summary {
+            ui.send_message(WidgetMessage::width(
+                self.summary,
+                MessageDirection::ToWidget,
+                frame_size.0 as f32,
+            ));
+            ui.send_message(WidgetMessage::desired_position(
+                self.summary,
+                MessageDirection::ToWidget,
+                Vector2::new(0.0, frame_size.1 as f32 / 4.0),
+            ));
+            ui.send_message(TextMessage::text(
+                self.summary,
+                MessageDirection::ToWidget,
+                text.clone(),
+            ));
+        }
+
+        // The summary is more important, the message stays until closed.
+        let motd = self.motd_text.as_ref().filter(|(_, shown)| *shown);
+        let show_motd = motd.is_some() && self.last_summary.is_none();
+        ui.send_message(WidgetMessage::visibility(
+            self.motd,
+            MessageDirection::ToWidget,
+            show_motd,
+        ));
+        if let Some((text, _)) = motd.filter(|_| show_motd) {
+            ui.send_message(WidgetMessage::width(
+                self.motd,
+                MessageDirection::ToWidget,
+                frame_size.0 as f32,
+            ));
+            ui.send_message(WidgetMessage::desired_position(
+                self.motd,
+                MessageDirection::ToWidget,
+                Vector2::new(0.0, frame_size.1 as f32 / 4.0),
+            ));
+            let text = format!("{}\n\n{}", text, strings.get("hud.motd_hint"));
+            ui.send_message(TextMessage::text(self.motd, MessageDirection::ToWidget, text));
+        }
+
+        let show_scoreboard = show_scoreboard && self.last_summary.is_none() && !show_motd;
+        ui.send_message(WidgetMessage::visibility(
+            self.scoreboard,
+            MessageDirection::ToWidget,
+            show_scoreboard,
+        ));
+        ui.send_message(WidgetMessage::visibility(
+            self.scoreboard_header,
+            MessageDirection::ToWidget,
+            show_scoreboard,
+        ));
+        ui.send_message(WidgetMessage::visibility(
+            self.scoreboard_icon,
+            MessageDirection::ToWidget,
+            show_scoreboard && self.has_icon,
+        ));
+        if show_scoreboard {
+            let top = frame_size.1 as f32 / 4.0 - SCOREBOARD_HEADER_HEIGHT;
+            ui.send_message(WidgetMessage::width(
+                self.scoreboard_header,
+                MessageDirection::ToWidget,
+                frame_size.0 as f32,
+            ));
+            ui.send_message(WidgetMessage::desired_position(
+                self.scoreboard_header,
+                MessageDirection::ToWidget,
+                Vector2::new(0.0, top),
+            ));
+            let icon_size = MAX_ICON_SIZE as f32;
+            ui.send_message(WidgetMessage::desired_position(
+                self.scoreboard_icon,
+                MessageDirection::ToWidget,
+                Vector2::new((frame_size.0 as f32 - icon_size) / 2.0, top - icon_size),
+            ));
+
+            ui.send_message(WidgetMessage::width(
+                self.scoreboard,
+                MessageDirection::ToWidget,
+                frame_size.0 as f32,
+            ));
+            ui.send_message(WidgetMessage::desired_position(
+                self.scoreboard,
+                MessageDirection::ToWidget,
+                Vector2::new(0.0, frame_size.1 as f32 / 4.0),
+            ));
+            ui.send_message(TextMessage::text(
+                self.scoreboard,
+                MessageDirection::ToWidget,
+                scoreboard_text(strings, gs),
+            ));
+        }
+    }
+}
+
+/// Headings are uppercase and followed by a blank line, list items are indented.
+fn format_motd(text: &str) -> String {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        if let Some(heading) = line.strip_prefix("# ") {
+            lines.push(heading.to_uppercase());
+            lines.push(String::new());
+        } else if let Some(item) = line.strip_prefix("- ") {
+            lines.push(format!("  * {}", item));
+        } else {
+            lines.push(line.to_owned());
+        }
+    }
+    lines.join("\n")
+}
+
+/// `a11y_font` if it's set and can be loaded.
+fn load_font(cvars: &Cvars) -> Option<SharedFont> {
+    if cvars.a11y_font.is_empty() {
+        return None;
+    }
+    let res = fs::read(&cvars.a11y_font).map_err(|err| err.to_string()).and_then(|data| {
+        Font::from_memory(data, cvars.a11y_font_size, Font::default_char_set())
+            .map_err(|err| err.to_owned())
+    });
+    match res {
+        Ok(font) => Some(SharedFont::new(font)),
+        Err(err) => {
+            dbg_logf!("failed to load font {}: {}", cvars.a11y_font, err);
+            None
+        }
+    }
+}
+
+/// Only what's over the thresholds so there's nothing on screen with a good connection.
+fn net_text(cvars: &Cvars, strings: &Strings, (ping, loss): (Option<f32>, f32)) -> String {
+    if !cvars.hud_net_warnings {
+        return String::new();
+    }
+    let mut lines = Vec::new();
+    if let Some(ping) = ping.filter(|&ping| ping > cvars.hud_net_warn_ping) {
+        lines.push(strings.format("hud.net_ping", &[("ping", &ping.round())]));
+    }
+    if loss > cvars.hud_net_warn_loss {
+        let percent = (loss * 100.0).round();
+        lines.push(strings.format("hud.net_loss", &[("percent", &percent)]));
+    }
+    lines.join("\n")
+}
+
+/// One line per player, best first.
+fn scoreboard_text(strings: &Strings, gs: &GameState) -> String {
+    let mut players: Vec<_> = gs.players.pair_iter().collect();
+    players.sort_by_key(|(handle, player)| (Reverse(player.score), handle.index()));
+
+    let mut text = format!("{}\n", strings.get("hud.scoreboard"));
+    for (handle, player) in players {
+        let team = player.team.and_then(|team| TEAM_NAMES.get(usize::from(team))).unwrap_or(&"-");
+        let ping = player.ping.map_or("-".to_owned(), |ping| format!("{} ms", ping));
+        text.push_str(&format!(
+            "{}    {}    {}    {}    {}\n",
+            handle.index(),
+            team,
+            player.score,
+            player.assists,
+            ping
+        ));
+    }
+    text
+}
+
+fn warmup_text(
+    strings: &Strings,
+    gs: &GameState,
+    local_player: Handle<Player>,
+    deadline: Option<f32>,
+) -> String {
+    let (ready, playing) = gs.ready_count();
+    let mut text = strings.format("hud.warmup", &[("ready", &ready), ("playing", &playing)]);
+    if let Some(deadline) = deadline {
+        let left = (deadline - gs.game_time).max(0.0).ceil();
+        text.push_str(&strings.format("hud.warmup_starting", &[("seconds", &left)]));
+    }
+
+    let player = &gs.players[local_player];
+    if player.ps == PlayerState::Playing && !player.ready {
+        text.push_str(&format!("\n{}", strings.get("hud.warmup_hint")));
+    }
+    text
+}
+
+fn overtime_text(
+    strings: &Strings,
+    rule: OvertimeRule,
+    end: Option<f32>,
+    game_time: f32,
+) -> String {
+    let detail = match (rule, end) {
+        (_, Some(end)) => clock(end - game_time),
+        (OvertimeRule::GoldenFrag, None) => strings.get("hud.golden_frag").to_owned(),
+        (OvertimeRule::SuddenDeath, None) => strings.get("hud.sudden_death").to_owned(),
+        (_, None) => String::new(),
+    };
+    format!("{}\n{}", strings.get("hud.overtime"), detail)
+}
+
+/// Minutes and seconds, rounded up so it shows 0:00 only when the time is up.
+fn clock(seconds: f32) -> String {
+    let seconds = seconds.max(0.0).ceil() as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_motd() {
+        assert_eq!(format_motd(""), "");
+        assert_eq!(
+            format_motd("# Rules\n- be nice\n- no cheats\nhave fun"),
+            "RULES\n\n  * be nice\n  * no cheats\nhave fun"
+        );
+        // Only at the start of a line and with a space.
+        assert_eq!(format_motd("a # b\n#c\n-d"), "a # b\n#c\n-d");
+    }
+}
@@ -0,0 +1,168 @@
+//! Recording the local player's inputs and playing them back - `input_record` and `input_play`.
+//!
+//! Meant for tuning movement: record a run, change cvars (e.g. `g_wheel_acceleration`),
+//! play it back and compare the stats printed when the recording and the playback end.
+//! Only ticks when the local player is playing are recorded and played back.
+//!
+//! The file starts with `MAGIC` followed by one bincode-serialized `Input` per tick.
+//! Inputs are sent to the server tick by tick like normal input
+//! so results only match exactly in a local game, with a remote server network timing matters.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+};
+
+use crate::{common::Input, prelude::*};
+
+const MAGIC: &[u8] = b"RCINPUT1";
+
+/// Writes inputs tick by tick.
+pub(crate) struct InputRecorder<W: Write> {
+    out: W,
+    pub(crate) path: String,
+    pub(crate) stats: MovementStats,
+}
+
+impl InputRecorder<BufWriter<File>> {
+    pub(crate) fn create(path: &str) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), path)
+    }
+}
+
+impl<W: Write> InputRecorder<W> {
+    pub(crate) fn new(mut out: W, path: &str) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+        Ok(Self {
+            out,
+            path: path.to_owned(),
+            stats: MovementStats::default(),
+        })
+    }
+
+    pub(crate) fn record(&mut self, input: &Input) -> io::Result<()> {
+        bincode::serialize_into(&mut self.out, input).map_err(|err| match *err {
+            bincode::ErrorKind::Io(err) => err,
+            err => panic!("bincode failed to serialize input: {}", err),
+        })
+    }
+
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Inputs loaded from a file, returned one per tick.
+pub(crate) struct InputPlayback {
+    inputs: Vec<Input>,
+    next: usize,
+    pub(crate) path: String,
+    pub(crate) stats: MovementStats,
+}
+
+impl InputPlayback {
+    pub(crate) fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|err| format!("{}: {}", path, err))?;
+        let inputs = parse(&bytes).map_err(|err| format!("{}: {}", path, err))?;
+        Ok(Self {
+            inputs,
+            next: 0,
+            path: path.to_owned(),
+            stats: MovementStats::default(),
+        })
+    }
+
+    pub(crate) fn next_input(&mut self) -> Option<Input> {
+        let input = self.inputs.get(self.next).copied();
+        self.next += 1;
+        input
+    }
+}
+
+fn parse(bytes: &[u8]) -> Result<Vec<Input>, String> {
+    let mut rest = bytes.strip_prefix(MAGIC).ok_or("not an input recording")?;
+    let mut inputs = Vec::new();
+    while !rest.is_empty() {
+        match bincode::deserialize_from(&mut rest) {
+            Ok(input) => inputs.push(input),
+            Err(err) => {
+                // The client probably crashed while writing, keep what's complete.
+                dbg_logf!("input recording is truncated after {} ticks: {}", inputs.len(), err);
+                break;
+            }
+        }
+    }
+    Ok(inputs)
+}
+
+/// How the local cycle moved while recording or playing back.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MovementStats {
+    ticks: usize,
+    distance: f32,
+    max_speed: f32,
+    start: Option<Vec3>,
+    last: Option<Vec3>,
+}
+
+impl MovementStats {
+    /// Add the cycle's position at the start of a tick which took `dt` seconds.
+    pub(crate) fn add(&mut self, pos: Vec3, dt: f32) {
+        if let Some(last) = self.last {
+            let distance = (pos - last).magnitude();
+            self.distance += distance;
+            self.max_speed = self.max_speed.max(distance / dt);
+        }
+        self.start.get_or_insert(pos);
+        self.last = Some(pos);
+        self.ticks += 1;
+    }
+
+    pub(crate) fn summary(&self) -> String {
+        let (start, end) = match (self.start, self.last) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return "no ticks".to_owned(),
+        };
+        format!(
+            "{} ticks, distance {:.2} m, max speed {:.2} m/s, end {:.2} {:.2} {:.2} ({:.2} m from start)",
+            self.ticks,
+            self.distance,
+            self.max_speed,
+            end.x,
+            end.y,
+            end.z,
+            (end - start).magnitude(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{Action, Axis, Deg};
+
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let mut first = Input::default();
+        first.set_axis(Axis::Move, 1.0);
+        let mut second = Input::default();
+        second.yaw = Deg(45.0);
+        second.set_pressed(Action::Fire1, true);
+
+        let mut recorder = InputRecorder::new(Vec::new(), "test").unwrap();
+        recorder.record(&first).unwrap();
+        recorder.record(&second).unwrap();
+        let bytes = recorder.finish().unwrap();
+
+        let inputs = parse(&bytes).unwrap();
+        assert_eq!(format!("{:?}", inputs), format!("{:?}", [first, second]));
+
+        // A partially written last tick is dropped.
+        let inputs = parse(&bytes[..bytes.len() - 1]).unwrap();
+        assert_eq!(inputs.len(), 1);
+
+        assert!(parse(b"garbage").is_err());
+    }
+}
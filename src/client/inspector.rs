@@ -0,0 +1,209 @@
+//! A debug panel listing all entities in the client's `GameState`.
+//!
+//! Toggled by `d_inspector`. Clicking a row highlights the entity in the world.
+//! The mouse has to be ungrabbed (ESC) to click.
+
+use fyrox::gui::{
+    brush::Brush,
+    button::{ButtonBuilder, ButtonMessage},
+    message::{MessageDirection, UiMessage},
+    stack_panel::StackPanelBuilder,
+    text::{TextBuilder, TextMessage},
+    widget::{WidgetBuilder, WidgetMessage},
+    UiNode, UserInterface,
+};
+
+use crate::{
+    common::{
+        entities::{Cycle, Player, Projectile},
+        GameState,
+    },
+    prelude::*,
+};
+
+/// Something shown in the inspector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Entity {
+    Player(Handle<Player>),
+    Cycle(Handle<Cycle>),
+    Projectile(Handle<Projectile>),
+}
+
+pub(crate) struct Inspector {
+    panel: Handle<UiNode>,
+    /// Preallocated rows, see `d_inspector_rows_max`.
+    rows: Vec<Row>,
+    selected: Option<Entity>,
+}
+
+struct Row {
+    button: Handle<UiNode>,
+    text: Handle<UiNode>,
+    /// What was shown in this row last frame.
+    entity: Option<Entity>,
+}
+
+impl Inspector {
+    /// Has to be created before the console because of the z index issue.
+    pub(crate) fn new(cvars: &Cvars, ui: &mut UserInterface) -> Self {
+        let mut rows = Vec::new();
+        for _ in 0..cvars.d_inspector_rows_max {
+            let text = TextBuilder::new(WidgetBuilder::new().with_foreground(Brush::Solid(WHITE)))
+                .build(&mut ui.build_ctx());
+            let button =
+                ButtonBuilder::new(WidgetBuilder::new().with_width(cvars.d_inspector_width))
+                    .with_content(text)
+                    .build(&mut ui.build_ctx());
+            rows.push(Row {
+                button,
+                text,
+                entity: None,
+            });
+        }
+
+        let panel = StackPanelBuilder::new(
+            WidgetBuilder::new()
+                .with_visibility(false)
+                .with_children(rows.iter().map(|row| row.button)),
+        )
+        .build(&mut ui.build_ctx());
+
+        Self {
+            panel,
+            rows,
+            selected: None,
+        }
+    }
+
+    /// Keep the panel in the top right corner.
+    pub(crate) fn resized(&self, cvars: &Cvars, ui: &mut UserInterface, width: f32) {
+        ui.send_message(WidgetMessage::desired_position(
+            self.panel,
+            MessageDirection::ToWidget,
+            Vector2::new(width - cvars.d_inspector_width, 0.0),
+        ));
+    }
+
+    pub(crate) fn ui_message(&mut self, msg: &UiMessage) {
+        if msg.direction != MessageDirection::FromWidget {
+            return;
+        }
+        if let Some(ButtonMessage::Click) = msg.data() {
+            let row = self.rows.iter().find(|row| row.button == msg.destination());
+            if let Some(row) = row {
+                // Clicking the selected entity again deselects it.
+                if self.selected == row.entity {
+                    self.selected = None;
+                } else {
+                    self.selected = row.entity;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn update(&mut self, cvars: &Cvars, engine: &mut Engine, gs: &GameState) {
+        let ui = &mut engine.user_interface;
+        ui.send_message(WidgetMessage::visibility(
+            self.panel,
+            MessageDirection::ToWidget,
+            cvars.d_inspector,
+        ));
+        if !cvars.d_inspector {
+            return;
+        }
+
+        let scene = &engine.scenes[gs.scene_handle];
+
+        let mut entries = Vec::new();
+        for (player_handle, player) in gs.players.pair_iter() {
+            let cycle = match player.cycle_handle {
+                Some(cycle_handle) => fmt_handle(cycle_handle),
+                None => "-".to_owned(),
+            };
+            let text =
+                format!("player {} {:?} cycle {}", fmt_handle(player_handle), player.ps, cycle);
+            entries.push((Entity::Player(player_handle), text));
+        }
+        for (cycle_handle, cycle) in gs.cycles.pair_iter() {
+            let body = scene.graph[cycle.body_handle].as_rigid_body();
+            let (roll, pitch, yaw) = body.local_transform().rotation().euler_angles();
+            let text = format!(
+                "cycle {} owner {} pos {} vel {} rot {}",
+                fmt_handle(cycle_handle),
+                fmt_handle(cycle.player_handle),
+                fmt_vec(**body.local_transform().position()),
+                fmt_vec(body.lin_vel()),
+                fmt_vec(v!(roll pitch yaw).map(f32::to_degrees)),
+            );
+            entries.push((Entity::Cycle(cycle_handle), text));
+        }
+        for (proj_handle, proj) in gs.projectiles.pair_iter() {
+            let text = format!(
+                "proj {} owner {} pos {} vel {} age {:.2}",
+                fmt_handle(proj_handle),
+                fmt_handle(proj.player_handle),
+                fmt_vec(proj.pos),
+                fmt_vec(proj.vel),
+                gs.game_time - proj.time_fired,
+            );
+            entries.push((Entity::Projectile(proj_handle), text));
+        }
+
+        if entries.len() > self.rows.len() {
+            // LATER Scrolling
+            dbg_textf!("inspector: {} more entities not shown", entries.len() - self.rows.len());
+        }
+
+        let mut entries = entries.into_iter();
+        for row in &mut self.rows {
+            let (entity, text) = match entries.next() {
+                Some((entity, text)) => (Some(entity), text),
+                None => (None, String::new()),
+            };
+            let text = if entity.is_some() && entity == self.selected {
+                format!("> {}", text)
+            } else {
+                text
+            };
+            row.entity = entity;
+            ui.send_message(TextMessage::text(row.text, MessageDirection::ToWidget, text));
+            ui.send_message(WidgetMessage::visibility(
+                row.button,
+                MessageDirection::ToWidget,
+                entity.is_some(),
+            ));
+        }
+
+        // Highlight
+        if let Some(selected) = self.selected {
+            let pos = match selected {
+                Entity::Player(handle) => gs
+                    .players
+                    .try_borrow(handle)
+                    .and_then(|player| player.cycle_handle)
+                    .and_then(|cycle_handle| gs.cycles.try_borrow(cycle_handle))
+                    .map(|cycle| scene.graph[cycle.body_handle].global_position()),
+                Entity::Cycle(handle) => gs
+                    .cycles
+                    .try_borrow(handle)
+                    .map(|cycle| scene.graph[cycle.body_handle].global_position()),
+                Entity::Projectile(handle) => {
+                    gs.projectiles.try_borrow(handle).map(|proj| proj.pos)
+                }
+            };
+            match pos {
+                Some(pos) => dbg_sphere!(pos, cvars.d_inspector_highlight_radius, 0.0, YELLOW),
+                // The entity no longer exists.
+                None => self.selected = None,
+            }
+        }
+    }
+}
+
+fn fmt_handle<T>(handle: Handle<T>) -> String {
+    format!("{}:{}", handle.index(), handle.generation())
+}
+
+fn fmt_vec(v: Vec3) -> String {
+    format!("[{:.2} {:.2} {:.2}]", v.x, v.y, v.z)
+}
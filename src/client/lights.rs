@@ -0,0 +1,168 @@
+//! Point lights following projectiles and flashing at explosions and impacts.
+//!
+//! There are `r_dynamic_lights_max` point light nodes. Every frame they're moved
+//! to the flashes and projectiles closest to the camera, the rest aren't lit.
+//! Flashes fade out quickly, projectile lights stay while the projectile flies.
+//!
+//! LATER Nodes are only created in `new` so raising `r_dynamic_lights_max` needs a reconnect,
+//! lowering it works immediately.
+//! LATER Rail beams once there is a rail weapon.
+
+use fyrox::scene::light::{point::PointLightBuilder, BaseLightBuilder};
+
+use crate::{
+    client::game::player_color,
+    common::{entities::Effect, GameState},
+    prelude::*,
+};
+
+const EXPLOSION_DURATION: f32 = 0.4;
+const EXPLOSION_INTENSITY: f32 = 3.0;
+const EXPLOSION_RADIUS: f32 = 8.0;
+const EXPLOSION_COLOR: Color = Color::opaque(255, 160, 60);
+
+const IMPACT_DURATION: f32 = 0.15;
+const IMPACT_INTENSITY: f32 = 1.5;
+const IMPACT_RADIUS: f32 = 3.0;
+const IMPACT_COLOR: Color = Color::opaque(255, 220, 150);
+
+/// Projectiles are lit by their shooter's color.
+const PROJECTILE_INTENSITY: f32 = 1.5;
+const PROJECTILE_RADIUS: f32 = 4.0;
+
+pub(crate) struct DynamicLights {
+    nodes: Vec<Handle<Node>>,
+    flashes: Vec<Flash>,
+    /// Everything that wants a light this frame, kept to avoid allocating every frame.
+    lights: Vec<Light>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Flash {
+    pos: Vec3,
+    start: f32,
+    duration: f32,
+    intensity: f32,
+    radius: f32,
+    color: Color,
+}
+
+/// What one light node shows this frame.
+#[derive(Debug, Clone, Copy)]
+struct Light {
+    pos: Vec3,
+    intensity: f32,
+    radius: f32,
+    color: Color,
+}
+
+impl DynamicLights {
+    pub(crate) fn new(cvars: &Cvars, scene: &mut Scene) -> Self {
+        let nodes = (0..cvars.r_dynamic_lights_max)
+            .map(|_| {
+                let base = BaseBuilder::new().with_visibility(false);
+                // Shadows from this many lights would be too slow.
+                PointLightBuilder::new(BaseLightBuilder::new(base).cast_shadows(false))
+                    .build(&mut scene.graph)
+            })
+            .collect();
+        Self {
+            nodes,
+            flashes: Vec::new(),
+            lights: Vec::new(),
+        }
+    }
+
+    /// Flash for an effect the server sent us.
+    pub(crate) fn spawn(&mut self, cvars: &Cvars, game_time: f32, effect: Effect) {
+        if cvars.r_dynamic_lights_max == 0 {
+            return;
+        }
+
+        let flash = match effect {
+            Effect::Explosion { pos, .. } => Flash {
+                pos,
+                start: game_time,
+                duration: EXPLOSION_DURATION,
+                intensity: EXPLOSION_INTENSITY,
+                radius: EXPLOSION_RADIUS,
+                color: EXPLOSION_COLOR,
+            },
+            Effect::Impact { pos, normal } => Flash {
+                // Slightly in front of the wall so it lights it.
+                pos: pos + normal * 0.2,
+                start: game_time,
+                duration: IMPACT_DURATION,
+                intensity: IMPACT_INTENSITY,
+                radius: IMPACT_RADIUS,
+                color: IMPACT_COLOR,
+            },
+            Effect::JumpPad { .. } | Effect::Teleport { .. } => return,
+        };
+        self.flashes.push(flash);
+    }
+
+    /// Move lights to the projectiles and flashes closest to the camera.
+    pub(crate) fn update(
+        &mut self,
+        cvars: &Cvars,
+        scene: &mut Scene,
+        gs: &GameState,
+        camera_pos: Vec3,
+    ) {
+        self.flashes.retain(|flash| gs.game_time - flash.start < flash.duration);
+
+        let max = cvars.r_dynamic_lights_max.min(self.nodes.len());
+        self.lights.clear();
+        if max > 0 {
+            for flash in &self.flashes {
+                let left = 1.0 - (gs.game_time - flash.start) / flash.duration;
+                self.lights.push(Light {
+                    pos: flash.pos,
+                    // Quadratic so it's bright only for a moment.
+                    intensity: flash.intensity * left * left,
+                    radius: flash.radius,
+                    color: flash.color,
+                });
+            }
+            for proj in &gs.projectiles {
+                // The shooter might have disconnected.
+                let color = if gs.players.is_valid_handle(proj.player_handle) {
+                    player_color(cvars, gs, proj.player_handle)
+                } else {
+                    Color::WHITE
+                };
+                self.lights.push(Light {
+                    pos: proj.pos,
+                    intensity: PROJECTILE_INTENSITY,
+                    radius: PROJECTILE_RADIUS,
+                    color,
+                });
+            }
+            // The order of the shown ones doesn't matter, only which ones they are.
+            if self.lights.len() > max {
+                self.lights.select_nth_unstable_by(max, |a, b| {
+                    let a = (a.pos - camera_pos).norm_squared();
+                    let b = (b.pos - camera_pos).norm_squared();
+                    a.total_cmp(&b)
+                });
+                self.lights.truncate(max);
+            }
+        }
+
+        let mut lights = self.lights.iter();
+        for &node_handle in &self.nodes {
+            let node = &mut scene.graph[node_handle];
+            let Some(light) = lights.next() else {
+                node.set_visibility(false);
+                continue;
+            };
+            node.set_visibility(true);
+            node.local_transform_mut().set_position(light.pos);
+            let point = node.as_point_light_mut();
+            point.set_radius(light.radius);
+            point.set_intensity(light.intensity);
+            point.set_color(light.color);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Translations of HUD strings - `cl_language`.
+//!
+//! Each language is a RON map from keys to text in `data/lang/<language>.ron`.
+//! Text can contain named arguments such as `{armor}`.
+//! English is built in so there's always something to fall back to
+//! when a translation is missing a key. Unknown keys are shown as is so they're easy to spot.
+//!
+//! LATER Console help and server messages - the server would have to send keys instead of text.
+//! LATER Plurals.
+
+use std::{fmt::Display, fs};
+
+use fxhash::FxHashMap;
+
+const ENGLISH: &str = include_str!("../../data/lang/en.ron");
+
+pub(crate) struct Strings {
+    language: String,
+    text: FxHashMap<String, String>,
+}
+
+impl Strings {
+    /// English with the language's text on top, errors are logged.
+    pub(crate) fn load(language: &str) -> Self {
+        let translation = if language == "en" {
+            Ok(String::new())
+        } else {
+            let path = format!("data/lang/{}.ron", language);
+            fs::read_to_string(&path).map_err(|err| format!("{}: {}", path, err))
+        };
+        let res = translation.and_then(|translation| Self::parse(language, &translation));
+        match res {
+            Ok(strings) => strings,
+            Err(err) => {
+                dbg_logf!("failed to load language {}: {}", language, err);
+                Self::parse(language, "").unwrap()
+            }
+        }
+    }
+
+    /// An empty translation means only English.
+    fn parse(language: &str, translation: &str) -> Result<Self, String> {
+        let mut text: FxHashMap<String, String> =
+            ron::from_str(ENGLISH).map_err(|err| format!("built in English: {}", err))?;
+        if !translation.is_empty() {
+            let translation: FxHashMap<String, String> =
+                ron::from_str(translation).map_err(|err| err.to_string())?;
+            text.extend(translation);
+        }
+        Ok(Self {
+            language: language.to_owned(),
+            text,
+        })
+    }
+
+    pub(crate) fn language(&self) -> &str {
+        &self.language
+    }
+
+    pub(crate) fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.text.get(key).map_or(key, String::as_str)
+    }
+
+    /// Replace `{name}` with the value of each argument.
+    pub(crate) fn format(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        let mut text = self.get(key).to_owned();
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), &value.to_string());
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strings() {
+        let strings = Strings::parse("xx", r#"{ "hud.cloaked": "SKRYTÝ" }"#).unwrap();
+        assert_eq!(strings.get("hud.cloaked"), "SKRYTÝ");
+        // Falls back to English.
+        assert_eq!(strings.format("hud.armor", &[("armor", &50)]), "ARMOR 50");
+        assert_eq!(strings.get("no.such.key"), "no.such.key");
+
+        // The built in English and all translations can be parsed.
+        for entry in fs::read_dir("data/lang").unwrap() {
+            let path = entry.unwrap().path();
+            let translation = fs::read_to_string(&path).unwrap();
+            Strings::parse("test", &translation).unwrap();
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Muting other players locally - `mute`, `unmute` and `mutelist`.
+//!
+//! Players are remembered by their fingerprint so mutes last across reconnects and sessions.
+//! Each client generates a random fingerprint the first time it runs and keeps it in `cl_identity_file`.
+//! Muted fingerprints are kept in `cl_mute_file`, one per line in hex.
+//!
+//! The server only tells admins other players' fingerprints
+//! because knowing one is enough to pretend to be that player.
+//! Everyone else's mutes only last until the muted player leaves.
+//!
+//! LATER Fingerprints aren't verified so players can change theirs to get around mutes.
+//! LATER Mute voice chat too once there is any.
+
+use std::{
+    fs, io, process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rand::{RngCore, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+/// Load this client's fingerprint or generate and save a new one.
+pub(crate) fn load_identity(path: &str) -> u64 {
+    match fs::read_to_string(path) {
+        Ok(text) => match parse_fingerprint(text.trim()) {
+            Some(fingerprint) => return fingerprint,
+            None => dbg_logf!("invalid identity in {}, generating a new one", path),
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => dbg_logf!("failed to read identity from {}: {}", path, err),
+    }
+
+    // Only needs to differ between clients, not to be unpredictable.
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    let seed = nanos as u64 ^ (u64::from(process::id()) << 32);
+    let fingerprint = Xoshiro256PlusPlus::seed_from_u64(seed).next_u64();
+    match fs::write(path, format_fingerprint(fingerprint) + "\n") {
+        Ok(()) => dbg_logf!("saved new identity to {}", path),
+        Err(err) => dbg_logf!("failed to save identity to {}: {}", path, err),
+    }
+    fingerprint
+}
+
+pub(crate) fn format_fingerprint(fingerprint: u64) -> String {
+    format!("{:016x}", fingerprint)
+}
+
+fn parse_fingerprint(s: &str) -> Option<u64> {
+    if s.len() != 16 {
+        return None;
+    }
+    u64::from_str_radix(s, 16).ok()
+}
+
+/// Fingerprints of muted players, saved after every change.
+#[derive(Debug)]
+pub(crate) struct MuteList {
+    path: String,
+    muted: Vec<u64>,
+}
+
+impl MuteList {
+    /// A missing file means nobody is muted.
+    pub(crate) fn load(path: &str) -> Self {
+        let muted = match fs::read_to_string(path) {
+            Ok(text) => parse(&text),
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    dbg_logf!("failed to read mutes from {}: {}", path, err);
+                }
+                Vec::new()
+            }
+        };
+        Self {
+            path: path.to_owned(),
+            muted,
+        }
+    }
+
+    pub(crate) fn is_muted(&self, fingerprint: Option<u64>) -> bool {
+        fingerprint.map_or(false, |fingerprint| self.muted.contains(&fingerprint))
+    }
+
+    pub(crate) fn muted(&self) -> &[u64] {
+        &self.muted
+    }
+
+    /// Returns false if the fingerprint was already muted.
+    pub(crate) fn mute(&mut self, fingerprint: u64) -> io::Result<bool> {
+        if self.muted.contains(&fingerprint) {
+            return Ok(false);
+        }
+        self.muted.push(fingerprint);
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Returns false if the fingerprint wasn't muted.
+    pub(crate) fn unmute(&mut self, fingerprint: u64) -> io::Result<bool> {
+        let len = self.muted.len();
+        self.muted.retain(|&muted| muted != fingerprint);
+        if self.muted.len() == len {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let text: String = self
+            .muted
+            .iter()
+            .map(|&fingerprint| format_fingerprint(fingerprint) + "\n")
+            .collect();
+        fs::write(&self.path, text)
+    }
+}
+
+/// One fingerprint per line, blank lines and lines starting with `#` are ignored.
+fn parse(text: &str) -> Vec<u64> {
+    let mut muted = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_fingerprint(line) {
+            Some(fingerprint) => muted.push(fingerprint),
+            None => dbg_logf!("ignoring invalid fingerprint in mute list: {}", line),
+        }
+    }
+    muted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = "# muted\n00000000000000ff\n\n  0123456789abcdef  \nnope\n";
+        assert_eq!(parse(text), vec![0xff, 0x0123456789abcdef]);
+        assert_eq!(format_fingerprint(0xff), "00000000000000ff");
+        assert_eq!(parse_fingerprint("ff"), None);
+    }
+}
@@ -0,0 +1,186 @@
+//! Discord Rich Presence - `cl_discord_app_id`.
+//!
+//! Shows the map, mode, score and number of players in the user's Discord profile.
+//! Talks to the local Discord client directly over its IPC socket,
+//! the protocol is just length-prefixed JSON frames.
+//! When connected to a remote server, the join secret is the server's address
+//! so friends can be invited.
+//!
+//! LATER Windows (named pipes).
+//! LATER Act on ACTIVITY_JOIN once the client can connect to another server at runtime.
+//! LATER Max party size once the server sends `sv_max_players`.
+
+use std::{
+    fmt::Write as _,
+    io::{self, ErrorKind, Read, Write},
+    process,
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+use crate::common::json::json_string;
+
+/// Discord drops updates sent more often than this.
+const UPDATE_INTERVAL: f32 = 5.0;
+
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Activity {
+    /// First line, e.g. the map and mode.
+    pub(crate) details: String,
+    /// Second line, e.g. the score.
+    pub(crate) state: String,
+    pub(crate) party_size: u32,
+    /// Lets others ask to join, `None` in local games.
+    pub(crate) join_secret: Option<String>,
+}
+
+pub(crate) struct Presence {
+    #[cfg(unix)]
+    stream: UnixStream,
+    /// The last activity sent so it's only sent again when something changes.
+    sent: Option<Activity>,
+    /// Game time when the next update can be sent.
+    next_update: f32,
+    nonce: u32,
+}
+
+impl Presence {
+    #[cfg(unix)]
+    pub(crate) fn connect(app_id: &str) -> io::Result<Self> {
+        let dir = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok())
+            .unwrap_or_else(|| "/tmp".to_owned());
+        // Discord uses the first free one of 10 sockets.
+        let mut res = Err(io::Error::from(ErrorKind::NotFound));
+        for i in 0..10 {
+            res = UnixStream::connect(format!("{}/discord-ipc-{}", dir, i));
+            if res.is_ok() {
+                break;
+            }
+        }
+        let mut stream = res?;
+
+        let handshake = format!(r#"{{"v":1,"client_id":{}}}"#, json_string(app_id));
+        stream.write_all(&frame(OP_HANDSHAKE, &handshake))?;
+        // Replies are only read to keep the socket's buffer from filling up.
+        stream.set_nonblocking(true)?;
+
+        Ok(Self {
+            stream,
+            sent: None,
+            next_update: 0.0,
+            nonce: 0,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn connect(_app_id: &str) -> io::Result<Self> {
+        Err(io::Error::new(ErrorKind::Unsupported, "only implemented on unix"))
+    }
+
+    /// Send the activity if it changed and enough time passed since the last update.
+    ///
+    /// Returns an error when Discord goes away, the caller should drop this.
+    pub(crate) fn update(&mut self, game_time: f32, activity: Activity) -> io::Result<()> {
+        if game_time < self.next_update || self.sent.as_ref() == Some(&activity) {
+            return Ok(());
+        }
+        self.next_update = game_time + UPDATE_INTERVAL;
+        self.nonce += 1;
+        let json = activity_json(&activity, process::id(), self.nonce);
+        self.send(&frame(OP_FRAME, &json))?;
+        self.sent = Some(activity);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        let mut buf = [0; 4096];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(io::Error::from(ErrorKind::UnexpectedEof)),
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+        self.stream.write_all(frame)
+    }
+
+    #[cfg(not(unix))]
+    fn send(&mut self, _frame: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Header is the opcode and length, both little endian.
+fn frame(op: u32, json: &str) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(8 + json.len());
+    frame.extend_from_slice(&op.to_le_bytes());
+    frame.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    frame.extend_from_slice(json.as_bytes());
+    frame
+}
+
+fn activity_json(activity: &Activity, pid: u32, nonce: u32) -> String {
+    let mut json = String::new();
+    write!(
+        json,
+        r#"{{"cmd":"SET_ACTIVITY","nonce":"{}","args":{{"pid":{},"activity":{{"details":{},"state":{}"#,
+        nonce,
+        pid,
+        json_string(&activity.details),
+        json_string(&activity.state),
+    )
+    .unwrap();
+    if let Some(secret) = &activity.join_secret {
+        // Discord needs a party to show the join button.
+        let size = activity.party_size.max(1);
+        write!(
+            json,
+            r#","party":{{"id":{},"size":[{},{}]}},"secrets":{{"join":{}}}"#,
+            json_string(secret),
+            size,
+            size,
+            json_string(secret),
+        )
+        .unwrap();
+    }
+    json.push_str("}}}");
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activity_json() {
+        let mut activity = Activity {
+            details: "arena - free for all".to_owned(),
+            state: "score 3".to_owned(),
+            party_size: 2,
+            join_secret: None,
+        };
+        assert_eq!(
+            activity_json(&activity, 42, 1),
+            r#"{"cmd":"SET_ACTIVITY","nonce":"1","args":{"pid":42,"activity":{"details":"arena - free for all","state":"score 3"}}}"#
+        );
+
+        activity.join_secret = Some("1.2.3.4:26000".to_owned());
+        let json = activity_json(&activity, 42, 2);
+        assert!(json.ends_with(
+            r#""party":{"id":"1.2.3.4:26000","size":[2,2]},"secrets":{"join":"1.2.3.4:26000"}}}}"#
+        ));
+
+        let frame = frame(OP_FRAME, &json);
+        assert_eq!(frame[..4], 1u32.to_le_bytes());
+        assert_eq!(frame[4..8], (json.len() as u32).to_le_bytes());
+        assert_eq!(&frame[8..], json.as_bytes());
+    }
+}
@@ -0,0 +1,145 @@
+//! Measuring ping to servers in the history without joining them - `probe`.
+//!
+//! Probes are small UDP packets the server sends back, see `server::probe`.
+//! All servers are probed in parallel over one nonblocking socket
+//! but new probes only start at `cl_probe_rate` so a long list doesn't cause a burst.
+//!
+//! LATER Resolving hostnames blocks, only IP addresses are instant.
+
+use std::{
+    io::{self, ErrorKind},
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    common::net::{PROBE_LEN, PROBE_MAGIC},
+    prelude::*,
+};
+
+/// Servers which don't answer in time are left without a ping.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+pub(crate) struct Prober {
+    socket: UdpSocket,
+    /// Addresses which weren't probed yet, the next one last.
+    queue: Vec<String>,
+    /// Probes waiting for a reply.
+    in_flight: Vec<InFlight>,
+    next_token: u64,
+    /// When the next probe can start.
+    next_send: Instant,
+}
+
+struct InFlight {
+    addr: String,
+    socket_addr: SocketAddr,
+    token: u64,
+    sent: Instant,
+}
+
+impl Prober {
+    pub(crate) fn new(mut addrs: Vec<String>) -> io::Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_nonblocking(true)?;
+        addrs.reverse();
+        Ok(Self {
+            socket,
+            queue: addrs,
+            in_flight: Vec::new(),
+            next_token: 0,
+            next_send: Instant::now(),
+        })
+    }
+
+    /// Nothing left to send or wait for.
+    pub(crate) fn is_done(&self) -> bool {
+        self.queue.is_empty() && self.in_flight.is_empty()
+    }
+
+    /// Start probes which are due and collect replies.
+    ///
+    /// Returns the servers which answered and their round trip time in milliseconds.
+    pub(crate) fn update(&mut self, cvars: &Cvars) -> Vec<(String, u16)> {
+        let now = Instant::now();
+        while now >= self.next_send {
+            let Some(addr) = self.queue.pop() else {
+                break;
+            };
+            self.send(addr, now);
+            if cvars.cl_probe_rate > 0.0 {
+                self.next_send += Duration::from_secs_f32(1.0 / cvars.cl_probe_rate);
+            }
+        }
+        // Don't let probes pile up while the game wasn't updating.
+        self.next_send = self.next_send.max(now);
+
+        let mut pings = Vec::new();
+        let mut buf = [0; PROBE_LEN];
+        loop {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, from)) => {
+                    if len != PROBE_LEN || !buf.starts_with(&PROBE_MAGIC) {
+                        continue;
+                    }
+                    let token = u64::from_le_bytes(buf[PROBE_MAGIC.len()..].try_into().unwrap());
+                    let i = self
+                        .in_flight
+                        .iter()
+                        .position(|probe| probe.token == token && probe.socket_addr == from);
+                    if let Some(i) = i {
+                        let probe = self.in_flight.swap_remove(i);
+                        let ms = probe.sent.elapsed().as_millis().min(u16::MAX.into()) as u16;
+                        pings.push((probe.addr, ms));
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    // On Windows a server which isn't listening shows up as an error here.
+                    dbg_logf!("probe receive error: {}", err);
+                    break;
+                }
+            }
+        }
+
+        self.in_flight.retain(|probe| {
+            let waiting = now.duration_since(probe.sent) < TIMEOUT;
+            if !waiting {
+                dbg_logf!("no reply from {}", probe.addr);
+            }
+            waiting
+        });
+
+        pings
+    }
+
+    fn send(&mut self, addr: String, now: Instant) {
+        let socket_addr = match addr.to_socket_addrs().map(|mut addrs| addrs.next()) {
+            Ok(Some(socket_addr)) => socket_addr,
+            Ok(None) => {
+                dbg_logf!("can't probe {}: no address", addr);
+                return;
+            }
+            Err(err) => {
+                dbg_logf!("can't probe {}: {}", addr, err);
+                return;
+            }
+        };
+
+        let token = self.next_token;
+        self.next_token += 1;
+        let mut buf = [0; PROBE_LEN];
+        buf[..PROBE_MAGIC.len()].copy_from_slice(&PROBE_MAGIC);
+        buf[PROBE_MAGIC.len()..].copy_from_slice(&token.to_le_bytes());
+        if let Err(err) = self.socket.send_to(&buf, socket_addr) {
+            dbg_logf!("can't probe {}: {}", addr, err);
+            return;
+        }
+        self.in_flight.push(InFlight {
+            addr,
+            socket_addr,
+            token,
+            sent: now,
+        });
+    }
+}
@@ -30,17 +30,28 @@ use fyrox::{
 };
 
 use crate::{
-    client::game::ClientGame,
-    common::net::{LocalConnection, LocalListener, TcpConnection},
+    client::{
+        bindings::Button, caster::Caster, game::ClientGame, hud::Hud, inspector::Inspector,
+        window::WindowIntegration,
+    },
+    commands::{CvarsWithCommands, GameCommand},
+    common::{
+        net::{LocalConnection, LocalListener, TcpConnection},
+        Action,
+    },
     debug,
     prelude::*,
-    server::game::ServerGame,
+    server::{game::ServerGame, rewind::RewindBuffer},
 };
 
 /// The process that runs a player's game client.
 pub(crate) struct ClientProcess {
     cvars: Cvars,
     clock: Instant,
+    /// Real time spent paused by `d_pause`, the game runs this much behind real time.
+    paused_time: f32,
+    /// Real time at the start of the last `update`.
+    prev_update_time: f32,
     mouse_grabbed: bool,
     shift_pressed: bool,
     pub(crate) engine: Engine,
@@ -48,6 +59,7 @@ pub(crate) struct ClientProcess {
     debug_text: Handle<UiNode>,
     sg: Option<ServerGame>,
     cg: ClientGame,
+    window: WindowIntegration,
     pub(crate) exit: bool,
 }
 
@@ -78,6 +90,25 @@ impl ClientProcess {
                 .with_wrap(WrapMode::Letter)
                 .build(&mut engine.user_interface.build_ctx());
 
+        // Labels for dbg_worldtext!(), they're positioned each frame.
+        // They're created upfront because of the z index issue below.
+        let mut debug_world_texts = Vec::new();
+        for _ in 0..cvars.d_draw_world_texts_max {
+            let handle = TextBuilder::new(WidgetBuilder::new().with_visibility(false))
+                .with_shadow(cvars.d_draw_text_shadow)
+                .with_shadow_dilation(cvars.d_draw_text_shadow_dilation)
+                .with_shadow_offset(Vector2::new(
+                    cvars.d_draw_text_shadow_offset_x,
+                    cvars.d_draw_text_shadow_offset_y,
+                ))
+                .build(&mut engine.user_interface.build_ctx());
+            debug_world_texts.push(handle);
+        }
+
+        let inspector = Inspector::new(&cvars, &mut engine.user_interface);
+        let caster = Caster::new(&cvars, &mut engine.user_interface);
+        let hud = Hud::new(&cvars, &mut engine.user_interface);
+
         // Z index doesn't work, console has to be created after debug_text (and any other UI):
         // https://github.com/FyroxEngine/Fyrox/issues/356
         let console = FyroxConsole::new(&mut engine.user_interface);
@@ -98,12 +129,24 @@ impl ClientProcess {
             // and send init data into it so the client can read it during creation.
             // Otherwise the client would remain stuck.
             // Yes, this is really ugly.
-            sg.accept_new_connections(&mut engine);
-
-            let cg = ClientGame::new(&cvars, &mut engine, debug_text, Box::new(conn2)).await;
+            sg.accept_new_connections(&cvars, &mut engine);
+            sg.rewind_buffer = Some(RewindBuffer::default());
+
+            let cg = ClientGame::new(
+                &cvars,
+                &mut engine,
+                debug_text,
+                debug_world_texts,
+                inspector,
+                caster,
+                hud,
+                Box::new(conn2),
+            )
+            .await;
 
             (Some(sg), cg)
         } else {
+            // LATER Pick the server in a server browser, see the TODO list in main.rs.
             let addr = SocketAddr::from_str("127.0.0.1:26000").unwrap();
 
             let mut connect_attempts = 0;
@@ -124,16 +167,29 @@ impl ClientProcess {
             stream.set_nonblocking(true).unwrap();
 
             let conn = TcpConnection::new(stream, addr);
-            let cg = ClientGame::new(&cvars, &mut engine, debug_text, Box::new(conn)).await;
+            let cg = ClientGame::new(
+                &cvars,
+                &mut engine,
+                debug_text,
+                debug_world_texts,
+                inspector,
+                caster,
+                hud,
+                Box::new(conn),
+            )
+            .await;
 
             (None, cg)
         };
 
         let exit = cvars.d_exit_after_one_frame;
+        let window = WindowIntegration::new(&engine);
 
         Self {
             cvars,
             clock: Instant::now(),
+            paused_time: 0.0,
+            prev_update_time: 0.0,
             mouse_grabbed: false,
             shift_pressed: false,
             engine,
@@ -141,6 +197,7 @@ impl ClientProcess {
             debug_text,
             sg,
             cg,
+            window,
             exit,
         }
     }
@@ -166,6 +223,10 @@ impl ClientProcess {
             size.width as f32,
         ));
 
+        self.cg
+            .inspector
+            .resized(&self.cvars, &mut self.engine.user_interface, size.width as f32);
+
         self.console.resized(
             &mut self.engine.user_interface,
             size.width as f32,
@@ -191,6 +252,8 @@ impl ClientProcess {
             self.set_mouse_grab(false);
         }
 
+        self.window.focused(focus);
+
         // LATER pause/unpause
     }
 
@@ -224,7 +287,7 @@ impl ClientProcess {
 
     /// Input that is handled regardless of whether we're in menu/console/game.
     fn client_input(&mut self, input: KeyboardInput) {
-        use scan_codes::*;
+        use crate::client::bindings::scan_codes::*;
 
         let pressed = input.state == ElementState::Pressed;
 
@@ -256,7 +319,7 @@ impl ClientProcess {
 
     fn open_console(&mut self) {
         self.console.open(&mut self.engine.user_interface, self.mouse_grabbed);
-        self.cg.lp.input.release_all_keys();
+        self.cg.bindings.release_all(&mut self.cg.lp.input);
         self.set_mouse_grab(false);
     }
 
@@ -267,28 +330,18 @@ impl ClientProcess {
 
     /// Input that is handdled only when we're in game.
     fn game_input(&mut self, input: KeyboardInput) {
-        use scan_codes::*;
+        use crate::client::bindings::scan_codes::*;
 
         let pressed = input.state == ElementState::Pressed;
 
+        // LATER Make caster keys bindable too.
         match input.scancode {
-            W => self.cg.lp.input.forward = pressed,
-            A => self.cg.lp.input.left = pressed,
-            S => self.cg.lp.input.backward = pressed,
-            D => self.cg.lp.input.right = pressed,
-            SPACE => self.cg.lp.input.up = pressed,
-            L_SHIFT => self.cg.lp.input.down = pressed,
-            Q => self.cg.lp.input.prev_weapon = pressed,
-            E => self.cg.lp.input.next_weapon = pressed,
-            R => self.cg.lp.input.reload = pressed,
-            F => self.cg.lp.input.flag = pressed,
-            G => self.cg.lp.input.grenade = pressed,
-            M => self.cg.lp.input.map = pressed,
-            TAB => self.cg.lp.input.score = pressed,
-            ENTER => self.cg.lp.input.chat = pressed,
-            PAUSE => self.cg.lp.input.pause = pressed,
-            F12 => self.cg.lp.input.screenshot = pressed,
-            _ => (),
+            NUM1..=NUM9 if pressed => self.cg.caster.select((input.scancode - NUM1 + 1) as usize),
+            NUM0 if pressed => self.cg.caster.select(0),
+            scancode => {
+                let button = Button::Key(scancode);
+                self.cg.bindings.button(&mut self.cg.lp.input, button, pressed);
+            }
         }
 
         self.cg.lp.input.real_time = self.real_time();
@@ -322,14 +375,8 @@ impl ClientProcess {
             self.set_mouse_grab(true);
 
             let pressed = state == ElementState::Pressed;
-            match button {
-                MouseButton::Left => self.cg.lp.input.fire1 = pressed,
-                MouseButton::Right => self.cg.lp.input.fire2 = pressed,
-                MouseButton::Middle => self.cg.lp.input.zoom = pressed,
-                MouseButton::Other(8) => self.cg.lp.input.marker1 = pressed,
-                MouseButton::Other(9) => self.cg.lp.input.marker2 = pressed,
-                MouseButton::Other(_) => {}
-            }
+            let button = Button::Mouse(button);
+            self.cg.bindings.button(&mut self.cg.lp.input, button, pressed);
 
             self.cg.lp.input.real_time = self.real_time();
             self.cg.lp.input.game_time = self.cg.gs.game_time;
@@ -400,7 +447,7 @@ impl ClientProcess {
         // based on real time from last event. Instead, save the cumulative delta
         // and update angles/speeds once per frame.
 
-        let zoom_factor = if self.cg.lp.input.zoom {
+        let zoom_factor = if self.cg.lp.input.pressed(Action::Zoom) {
             self.cvars.cl_zoom_factor
         } else {
             1.0
@@ -419,7 +466,72 @@ impl ClientProcess {
     pub(crate) fn ui_message(&mut self, msg: &UiMessage) {
         self.ui_message_logging(msg);
 
-        self.console.ui_message(&mut self.engine.user_interface, &mut self.cvars, msg);
+        self.cg.inspector.ui_message(msg);
+
+        let mut cvars = CvarsWithCommands::new(&mut self.cvars).with_ping(&self.cg.ping);
+        self.console.ui_message(&mut self.engine.user_interface, &mut cvars, msg);
+        for command in cvars.take_game_commands() {
+            match command {
+                GameCommand::Rewind(seconds) => self.rewind(seconds),
+                GameCommand::Resume => self.cvars.d_pause = false,
+                GameCommand::NetStats(seconds) => self.net_stats(seconds),
+                GameCommand::SaveState(path) => self.savestate(&path),
+                GameCommand::LoadState(path) => self.loadstate(&path),
+                command => self.cg.game_command(&self.cvars, command),
+            }
+        }
+    }
+
+    /// Rewinding needs the local server so it's handled here instead of in `ClientGame`.
+    fn rewind(&mut self, seconds: f32) {
+        let Some(sg) = &mut self.sg else {
+            dbg_logf!("rewind only works in local games");
+            return;
+        };
+        match sg.rewind(&mut self.engine, seconds) {
+            Ok(rewound) => {
+                // Pause so the player can see it happen again, possibly step by step.
+                self.cvars.d_pause = true;
+                dbg_logf!(
+                    "rewound {:.2} s, paused - `d_singlestep 1` to step, `resume` to continue",
+                    rewound
+                );
+            }
+            Err(err) => dbg_logf!("failed to rewind: {}", err),
+        }
+    }
+
+    fn savestate(&self, path: &str) {
+        let Some(sg) = &self.sg else {
+            dbg_logf!("savestate only works in local games");
+            return;
+        };
+        match sg.savestate(&self.cvars, &self.engine, path) {
+            Ok(()) => dbg_logf!("saved state to {}", path),
+            Err(err) => dbg_logf!("failed to save state to {}: {}", path, err),
+        }
+    }
+
+    fn loadstate(&mut self, path: &str) {
+        let Some(sg) = &mut self.sg else {
+            dbg_logf!("loadstate only works in local games");
+            return;
+        };
+        match sg.loadstate(&self.cvars, &mut self.engine, path) {
+            Ok(players) => dbg_logf!("loaded state from {}, restored {} players", path, players),
+            Err(err) => dbg_logf!("failed to load state from {}: {}", path, err),
+        }
+    }
+
+    /// The client doesn't know message sizes, only the local server does.
+    fn net_stats(&self, seconds: f32) {
+        let Some(sg) = &self.sg else {
+            dbg_logf!("net_stats only works in local games");
+            return;
+        };
+        for line in sg.net_stats.lines(sg.gs.game_time, seconds) {
+            dbg_logf!("    {}", line);
+        }
     }
 
     fn ui_message_logging(&mut self, msg: &UiMessage) {
@@ -459,26 +571,66 @@ impl ClientProcess {
         // and post_update also gets called twice which means a lot of work is done
         // twice per frame unnecessarily but at least those other things don't affect gameplay.
 
+        // The previous frame ended with rendering (outside this function).
+        debug::details::timers_end_frame(&self.cvars);
+
         let old_name = debug::details::endpoint_name();
 
-        let target = self.real_time();
+        // Client-side cheats - with a listen server, don't wait for it to tell us sv_cheats changed.
+        let cheats_allowed = self.cg.cheats_allowed || (self.sg.is_some() && self.cvars.sv_cheats);
+        if self.cvars.d_fly_camera && !cheats_allowed {
+            dbg_logf!("d_fly_camera needs sv_cheats");
+            self.cvars.d_fly_camera = false;
+        }
+
+        // While paused, game time stands still, except for one tick with `d_singlestep`.
+        let real_time = self.real_time();
+        let frame_time = real_time - self.prev_update_time;
+        self.prev_update_time = real_time;
+        let mut frozen = false;
+        if self.cvars.d_pause {
+            self.paused_time += frame_time;
+            if self.cvars.d_singlestep {
+                // Same as in ClientGame::update and ServerGame::update.
+                self.paused_time -= 1.0 / 60.0;
+                self.cvars.d_singlestep = false;
+            } else {
+                frozen = true;
+            }
+        }
+
+        let target = real_time - self.paused_time;
         if let Some(sg) = &mut self.sg {
             debug::details::set_endpoint("locl");
             self.engine.scenes[sg.gs.scene_handle].enabled = false;
         }
 
-        self.cg.update(&self.cvars, &mut self.engine, target);
+        if frozen {
+            self.cg.update_paused(&self.cvars, &mut self.engine, frame_time);
+        } else {
+            self.cg.update(&self.cvars, &mut self.engine, target);
+        }
+        self.window.update(&self.cvars, &self.engine, &self.cg);
 
         // New target time because:
         //  - We want to run as much forward as we can.
         //  - When using separate processes, cl and sv need to synchronize their game_time.
         //    This forces us to do it even locally and therefore test that it works properly.
-        let target = self.real_time();
+        // Not while paused though, the server would get ahead of the client.
+        let target = if self.cvars.d_pause {
+            target
+        } else {
+            self.real_time() - self.paused_time
+        };
         if let Some(sg) = &mut self.sg {
             debug::details::set_endpoint("losv");
             self.engine.scenes[self.cg.gs.scene_handle].enabled = false;
             self.engine.scenes[sg.gs.scene_handle].enabled = true;
             sg.update(&self.cvars, &mut self.engine, target);
+            if sg.restart_requested {
+                sg.restart_requested = false;
+                dbg_logf!("restart_update only works on dedicated servers");
+            }
 
             // The client scene has to be reenabled here, not before running `cg.update()`,
             // so that it gets rendered.
@@ -500,151 +652,3 @@ impl ClientProcess {
         self.clock.elapsed().as_secs_f32()
     }
 }
-
-/// Layout independant scancodes.
-///
-/// This is a separate mod so you can glob-import it.
-#[rustfmt::skip]
-// ...and also so I can stop rustfmt from mangling it.
-// Seriously, remove #[rustfmt::skip] and see what it does, I dare you.
-// I've never seen anybody ever format comments like that
-// and rustfmt does it by default without a way to disable it.
-// I. Just. Hate. It.
-mod scan_codes {
-    #![allow(dead_code)]
-
-    use fyrox::event::ScanCode;
-
-    // Apparently there are different numbering schemes all called "scancodes".
-    // This image is the least inaccurate for the one in winit (on Kubuntu 22.04):
-    // https://forum.thegamecreators.com/thread/145420
-    // Note that many keys are different (e.g. R_ALT, KP_ENTER, arrows, ...).
-
-    pub(crate) const ESC: ScanCode = 1;
-    pub(crate) const NUM1: ScanCode = 2;
-    pub(crate) const NUM2: ScanCode = 3;
-    pub(crate) const NUM3: ScanCode = 4;
-    pub(crate) const NUM4: ScanCode = 5;
-    pub(crate) const NUM5: ScanCode = 6;
-    pub(crate) const NUM6: ScanCode = 7;
-    pub(crate) const NUM7: ScanCode = 8;
-    pub(crate) const NUM8: ScanCode = 9;
-    pub(crate) const NUM9: ScanCode = 10;
-    pub(crate) const NUM0: ScanCode = 11;
-    pub(crate) const MINUS: ScanCode = 12;
-    pub(crate) const EQUALS: ScanCode = 13;
-    pub(crate) const BACKSPACE: ScanCode = 14;
-    pub(crate) const TAB: ScanCode = 15;
-    pub(crate) const Q: ScanCode = 16;
-    pub(crate) const W: ScanCode = 17;
-    pub(crate) const E: ScanCode = 18;
-    pub(crate) const R: ScanCode = 19;
-    pub(crate) const T: ScanCode = 20;
-    pub(crate) const Y: ScanCode = 21;
-    pub(crate) const U: ScanCode = 22;
-    pub(crate) const I: ScanCode = 23;
-    pub(crate) const O: ScanCode = 24;
-    pub(crate) const P: ScanCode = 25;
-    pub(crate) const LBRACKET: ScanCode = 26;
-    pub(crate) const RBRACKET: ScanCode = 27;
-    pub(crate) const ENTER: ScanCode = 28;
-    pub(crate) const L_CTRL: ScanCode = 29;
-    pub(crate) const A: ScanCode = 30;
-    pub(crate) const S: ScanCode = 31;
-    pub(crate) const D: ScanCode = 32;
-    pub(crate) const F: ScanCode = 33;
-    pub(crate) const G: ScanCode = 34;
-    pub(crate) const H: ScanCode = 35;
-    pub(crate) const J: ScanCode = 36;
-    pub(crate) const K: ScanCode = 37;
-    pub(crate) const L: ScanCode = 38;
-    pub(crate) const SEMICOLON: ScanCode = 39;
-    pub(crate) const APOSTROPHE: ScanCode = 40;
-    pub(crate) const BACKTICK: ScanCode = 41;
-    pub(crate) const L_SHIFT: ScanCode = 42;
-    pub(crate) const BACKSLASH: ScanCode = 43;
-    pub(crate) const Z: ScanCode = 44;
-    pub(crate) const X: ScanCode = 45;
-    pub(crate) const C: ScanCode = 46;
-    pub(crate) const V: ScanCode = 47;
-    pub(crate) const B: ScanCode = 48;
-    pub(crate) const N: ScanCode = 49;
-    pub(crate) const M: ScanCode = 50;
-    pub(crate) const COMMA: ScanCode = 51;
-    pub(crate) const PERIOD: ScanCode = 52;
-    pub(crate) const SLASH: ScanCode = 53;
-    pub(crate) const R_SHIFT: ScanCode = 54;
-    pub(crate) const KP_MULTIPLY: ScanCode = 55;
-    pub(crate) const L_ALT: ScanCode = 56;
-    pub(crate) const SPACE: ScanCode = 57;
-    pub(crate) const CAPS_LOCK: ScanCode = 58;
-    pub(crate) const F1: ScanCode = 59;
-    pub(crate) const F2: ScanCode = 60;
-    pub(crate) const F3: ScanCode = 61;
-    pub(crate) const F4: ScanCode = 62;
-    pub(crate) const F5: ScanCode = 63;
-    pub(crate) const F6: ScanCode = 64;
-    pub(crate) const F7: ScanCode = 65;
-    pub(crate) const F8: ScanCode = 66;
-    pub(crate) const F9: ScanCode = 67;
-    pub(crate) const F10: ScanCode = 68;
-    pub(crate) const F11: ScanCode = 69;
-    pub(crate) const F12: ScanCode = 70;
-    pub(crate) const KP7: ScanCode = 71;
-    pub(crate) const KP8: ScanCode = 72;
-    pub(crate) const KP9: ScanCode = 73;
-    pub(crate) const KP_MINUS: ScanCode = 74;
-    pub(crate) const KP4: ScanCode = 75;
-    pub(crate) const KP5: ScanCode = 76;
-    pub(crate) const KP6: ScanCode = 77;
-    pub(crate) const KP_PLUS: ScanCode = 78;
-    pub(crate) const KP1: ScanCode = 79;
-    pub(crate) const KP2: ScanCode = 80;
-    pub(crate) const KP3: ScanCode = 81;
-    pub(crate) const KP0: ScanCode = 82;
-    pub(crate) const KP_PERIOD: ScanCode = 83;
-    // 84
-    // 85
-    pub(crate) const BACKSLASH2: ScanCode = 86; // Between LSHIFT and Z, not on all keyboards
-    // 87
-    // 88
-    // 89
-    // 90
-    // 91
-    // 92
-    // 93
-    // 94
-    // 95
-    pub(crate) const KP_ENTER: ScanCode = 96;
-    pub(crate) const R_CTRL: ScanCode = 97;
-    pub(crate) const KP_DIVIDE: ScanCode = 98;
-    pub(crate) const PRINT_SCREEN: ScanCode = 99;
-    pub(crate) const R_ALT: ScanCode = 100;
-    // 101
-    pub(crate) const HOME: ScanCode = 102;
-    pub(crate) const UP_ARROW: ScanCode = 103;
-    pub(crate) const PG_UP: ScanCode = 104;
-    pub(crate) const LEFT_ARROW: ScanCode = 105;
-    pub(crate) const RIGHT_ARROW: ScanCode = 106;
-    pub(crate) const END: ScanCode = 107;
-    pub(crate) const DOWN_ARROW: ScanCode = 108;
-    pub(crate) const PG_DOWN: ScanCode = 109;
-    pub(crate) const INSERT: ScanCode = 110;
-    pub(crate) const DELETE: ScanCode = 111;
-    // 112
-    // 113
-    // 114
-    // 115
-    // 116
-    // 117
-    // 118
-    pub(crate) const PAUSE: ScanCode = 119;
-    // 120
-    // 121
-    // 122
-    // 123
-    // 124
-    pub(crate) const L_SUPER: ScanCode = 125;
-    pub(crate) const R_SUPER: ScanCode = 126;
-    pub(crate) const MENU: ScanCode = 127;
-}
@@ -0,0 +1,125 @@
+//! Lifetime statistics of the local player - `stats`.
+//!
+//! Updated from the summary the server sends at the end of each round
+//! and kept in `cl_profile_file` so they last across sessions.
+//!
+//! LATER Favorite weapon once there's more than one.
+//! LATER Show it on a profile page once there's a menu.
+
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::messages::MatchSummary;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct Profile {
+    pub(crate) matches_played: u32,
+    pub(crate) wins: u32,
+    pub(crate) frags: u32,
+    pub(crate) deaths: u32,
+    pub(crate) shots: u32,
+    pub(crate) hits: u32,
+    pub(crate) best_streak: u32,
+}
+
+impl Profile {
+    /// A missing or invalid file means a new profile.
+    pub(crate) fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(text) => match ron::from_str(&text) {
+                Ok(profile) => profile,
+                Err(err) => {
+                    dbg_logf!("invalid profile in {}: {}", path, err);
+                    Self::default()
+                }
+            },
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    dbg_logf!("failed to read profile from {}: {}", path, err);
+                }
+                Self::default()
+            }
+        }
+    }
+
+    pub(crate) fn save(&self, path: &str) -> Result<(), String> {
+        let text =
+            ron::ser::to_string_pretty(self, Default::default()).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| err.to_string())
+    }
+
+    /// Add the player's stats from the round, returns false if they weren't in it.
+    pub(crate) fn record(&mut self, summary: &MatchSummary, player_index: u32) -> bool {
+        let Some(player) = summary.players.iter().find(|p| p.player_index == player_index) else {
+            return false;
+        };
+        // The stats come from the server so they can be anything.
+        self.matches_played = self.matches_played.saturating_add(1);
+        if summary.winner_index == Some(player_index) {
+            self.wins = self.wins.saturating_add(1);
+        }
+        self.frags = self.frags.saturating_add(player.frags);
+        self.deaths = self.deaths.saturating_add(player.deaths);
+        self.shots = self.shots.saturating_add(player.shots);
+        self.hits = self.hits.saturating_add(player.hits);
+        self.best_streak = self.best_streak.max(player.best_streak);
+        true
+    }
+
+    /// Kills per death, deaths count as at least 1 so it's the number of frags when there's none.
+    pub(crate) fn kd(&self) -> f32 {
+        self.frags as f32 / self.deaths.max(1) as f32
+    }
+
+    /// Lines for the console.
+    pub(crate) fn lines(&self) -> Vec<String> {
+        let accuracy = if self.shots > 0 {
+            format!("{:.0}%", 100.0 * self.hits as f32 / self.shots as f32)
+        } else {
+            "-".to_owned()
+        };
+        vec![
+            format!("matches played: {} ({} won)", self.matches_played, self.wins),
+            format!("frags: {}, deaths: {}, K/D: {:.2}", self.frags, self.deaths, self.kd()),
+            format!("accuracy: {}", accuracy),
+            format!("best streak: {}", self.best_streak),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::messages::PlayerSummary;
+
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let summary = MatchSummary {
+            winner_index: Some(1),
+            players: vec![PlayerSummary {
+                player_index: 1,
+                frags: 6,
+                deaths: 4,
+                shots: 20,
+                hits: 8,
+                best_streak: 3,
+            }],
+            awards: Vec::new(),
+        };
+        let mut profile = Profile::default();
+        assert!(!profile.record(&summary, 2));
+        assert!(profile.record(&summary, 1));
+        assert!(profile.record(&summary, 1));
+        assert_eq!(profile.matches_played, 2);
+        assert_eq!(profile.wins, 2);
+        assert_eq!(profile.kd(), 1.5);
+        assert_eq!(profile.best_streak, 3);
+
+        let text = ron::ser::to_string(&profile).unwrap();
+        assert_eq!(ron::from_str::<Profile>(&text).unwrap(), profile);
+        assert_eq!(ron::from_str::<Profile>("(wins: 3)").unwrap().wins, 3);
+    }
+}
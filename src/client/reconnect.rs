@@ -0,0 +1,117 @@
+//! Reconnecting when the connection to the server drops - `cl_reconnect_attempts`.
+//!
+//! The first attempt is immediate, then the delay starts at `cl_reconnect_delay`
+//! and doubles after each failed attempt.
+//! After connecting, the server sends a fresh `Init` and the client replies with the token
+//! from the previous one to get back its score and team, see `server::reconnect`.
+//!
+//! LATER Don't block the main thread while connecting.
+
+use std::{
+    io,
+    net::{SocketAddr, TcpStream},
+    time::Duration,
+};
+
+use crate::{
+    common::{
+        messages::{Init, ServerMessage},
+        net::{Connection, TcpConnection},
+    },
+    prelude::*,
+};
+
+/// How long each attempt can freeze the game.
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The delay doesn't grow past this many seconds.
+const MAX_DELAY: f32 = 30.0;
+
+pub(crate) enum ReconnectStatus {
+    Waiting,
+    Connected(TcpConnection, Box<Init>),
+    /// All attempts failed.
+    Failed,
+}
+
+pub(crate) struct Reconnect {
+    /// Attempts made so far.
+    pub(crate) attempt: u32,
+    /// Game time of the next attempt, also when waiting for `Init` times out.
+    next_attempt: f32,
+    /// Connected, waiting for `Init`.
+    conn: Option<TcpConnection>,
+}
+
+impl Reconnect {
+    pub(crate) fn new(game_time: f32) -> Self {
+        Self {
+            attempt: 0,
+            next_attempt: game_time,
+            conn: None,
+        }
+    }
+
+    pub(crate) fn poll(
+        &mut self,
+        cvars: &Cvars,
+        game_time: f32,
+        addr: SocketAddr,
+    ) -> ReconnectStatus {
+        if let Some(conn) = &mut self.conn {
+            let (msg, closed) = conn.receive_one_sm();
+            match msg {
+                Some(ServerMessage::Init(init)) => {
+                    return ReconnectStatus::Connected(self.conn.take().unwrap(), Box::new(init));
+                }
+                Some(_) => dbg_logf!("reconnect: first message wasn't init"),
+                None if closed => dbg_logf!("reconnect: connection closed before init"),
+                None if game_time < self.next_attempt => return ReconnectStatus::Waiting,
+                None => dbg_logf!("reconnect: timed out waiting for init"),
+            }
+            self.conn = None;
+        }
+
+        if game_time < self.next_attempt {
+            return ReconnectStatus::Waiting;
+        }
+        if self.attempt >= cvars.cl_reconnect_attempts {
+            return ReconnectStatus::Failed;
+        }
+        self.attempt += 1;
+        self.next_attempt = game_time + delay(cvars.cl_reconnect_delay, self.attempt);
+        dbg_logf!("reconnect attempt {}/{} to {}", self.attempt, cvars.cl_reconnect_attempts, addr);
+        match connect(addr) {
+            Ok(conn) => self.conn = Some(conn),
+            Err(err) => dbg_logf!("reconnect attempt failed: {}", err),
+        }
+        ReconnectStatus::Waiting
+    }
+}
+
+fn connect(addr: SocketAddr) -> io::Result<TcpConnection> {
+    let stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    stream.set_nodelay(true)?;
+    stream.set_nonblocking(true)?;
+    Ok(TcpConnection::new(stream, addr))
+}
+
+/// Seconds to wait after the given attempt (starting at 1) before the next one.
+fn delay(initial: f32, attempt: u32) -> f32 {
+    let exponent = attempt.saturating_sub(1).min(16) as i32;
+    (initial * 2.0_f32.powi(exponent)).min(MAX_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        assert_eq!(delay(1.0, 1), 1.0);
+        assert_eq!(delay(1.0, 2), 2.0);
+        assert_eq!(delay(1.0, 4), 8.0);
+        assert_eq!(delay(1.0, 10), MAX_DELAY);
+        assert_eq!(delay(1.0, u32::MAX), MAX_DELAY);
+    }
+}
@@ -0,0 +1,59 @@
+//! Objects created up front and reused oldest first - `Ring`.
+
+use std::slice;
+
+/// A fixed number of objects, usually scene nodes, reused oldest first.
+///
+/// Everything is created when the game starts so spawning effects, decals, wrecks, ...
+/// never adds nodes to the scene graph mid-game.
+/// When all are in use, the oldest one is reused.
+#[derive(Debug)]
+pub(crate) struct Ring<T> {
+    items: Vec<T>,
+    /// The next one to use - the oldest.
+    next: usize,
+}
+
+impl<T> Ring<T> {
+    /// Creates at least one item so there's always something to reuse.
+    pub(crate) fn new(size: usize, mut build: impl FnMut() -> T) -> Self {
+        let items = (0..size.max(1)).map(|_| build()).collect();
+        Self { items, next: 0 }
+    }
+
+    /// The oldest item, it becomes the newest.
+    pub(crate) fn next_mut(&mut self) -> &mut T {
+        let index = self.next;
+        self.next = (self.next + 1) % self.items.len();
+        &mut self.items[index]
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.items.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring() {
+        let mut count = 0;
+        let mut ring = Ring::new(3, || {
+            count += 1;
+            count
+        });
+        assert_eq!(*ring.next_mut(), 1);
+        assert_eq!(*ring.next_mut(), 2);
+        assert_eq!(*ring.next_mut(), 3);
+        *ring.next_mut() = 4;
+        assert_eq!(*ring.next_mut(), 2);
+        assert_eq!(*ring.next_mut(), 3);
+        assert_eq!(*ring.next_mut(), 4);
+        assert_eq!(ring.iter_mut().count(), 3);
+
+        let mut empty = Ring::new(0, || 0);
+        assert_eq!(empty.iter_mut().count(), 1);
+    }
+}
@@ -0,0 +1,159 @@
+//! Favorite servers and connection history - `favorite` and `history`.
+//!
+//! Both are kept in `cl_servers_file` so they last across sessions.
+//! The history remembers the server's name, region, the map and the last measured ping
+//! so it can be sorted by the latency we actually got instead of the region the server claims.
+//!
+//! `probe` refreshes the pings of all servers in the history without joining them.
+//!
+//! LATER Show them in a server browser once there is one.
+
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+
+/// How many servers the history remembers.
+const MAX_HISTORY: usize = 20;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct Servers {
+    /// Addresses in the order they were added.
+    pub(crate) favorites: Vec<String>,
+    /// Most recently connected first.
+    pub(crate) history: Vec<Visit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub(crate) struct Visit {
+    pub(crate) addr: String,
+    pub(crate) name: String,
+    /// `sv_region`, empty if unknown or saved by an older version.
+    #[serde(default)]
+    pub(crate) region: String,
+    pub(crate) map: String,
+    /// Round trip time in milliseconds.
+    pub(crate) ping: Option<u16>,
+}
+
+impl Servers {
+    /// A missing or invalid file means no favorites or history.
+    pub(crate) fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(text) => match ron::from_str(&text) {
+                Ok(servers) => servers,
+                Err(err) => {
+                    dbg_logf!("invalid server list in {}: {}", path, err);
+                    Self::default()
+                }
+            },
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    dbg_logf!("failed to read server list from {}: {}", path, err);
+                }
+                Self::default()
+            }
+        }
+    }
+
+    pub(crate) fn save(&self, path: &str) -> Result<(), String> {
+        let text =
+            ron::ser::to_string_pretty(self, Default::default()).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| err.to_string())
+    }
+
+    /// Returns false if it was already a favorite.
+    pub(crate) fn add_favorite(&mut self, addr: &str) -> bool {
+        if self.favorites.iter().any(|favorite| favorite == addr) {
+            return false;
+        }
+        self.favorites.push(addr.to_owned());
+        true
+    }
+
+    /// Returns false if it wasn't a favorite.
+    pub(crate) fn remove_favorite(&mut self, addr: &str) -> bool {
+        let len = self.favorites.len();
+        self.favorites.retain(|favorite| favorite != addr);
+        self.favorites.len() != len
+    }
+
+    /// Move the server to the top of the history, keeping its last ping.
+    pub(crate) fn visit(&mut self, addr: &str, name: &str, region: &str, map: &str) {
+        let i = self.history.iter().position(|visit| visit.addr == addr);
+        let ping = i.and_then(|i| self.history.remove(i).ping);
+        self.history.insert(
+            0,
+            Visit {
+                addr: addr.to_owned(),
+                name: name.to_owned(),
+                region: region.to_owned(),
+                map: map.to_owned(),
+                ping,
+            },
+        );
+        self.history.truncate(MAX_HISTORY);
+    }
+
+    /// Measured while connected or by `probe`.
+    pub(crate) fn set_ping(&mut self, addr: &str, ping: u16) {
+        if let Some(visit) = self.history.iter_mut().find(|visit| visit.addr == addr) {
+            visit.ping = Some(ping);
+        }
+    }
+
+    /// Lines for the console, most recent or lowest ping first.
+    /// Servers without a measured ping go last.
+    pub(crate) fn history_lines(&self, by_ping: bool) -> Vec<String> {
+        let mut visits: Vec<_> = self.history.iter().collect();
+        if by_ping {
+            // Stable so equal pings stay in the order they were visited.
+            visits.sort_by_key(|visit| visit.ping.unwrap_or(u16::MAX));
+        }
+        visits
+            .into_iter()
+            .map(|visit| {
+                let region = if visit.region.is_empty() {
+                    "-"
+                } else {
+                    &visit.region
+                };
+                let ping = visit.ping.map_or("-".to_owned(), |ping| format!("{} ms", ping));
+                format!("{} - {} - {} - {} - {}", visit.addr, visit.name, region, visit.map, ping)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_servers() {
+        let mut servers = Servers::default();
+        assert!(servers.add_favorite("1.2.3.4:26000"));
+        assert!(!servers.add_favorite("1.2.3.4:26000"));
+        assert!(!servers.remove_favorite("5.6.7.8:26000"));
+
+        servers.visit("1.2.3.4:26000", "one", "eu", "arena");
+        servers.set_ping("1.2.3.4:26000", 30);
+        servers.visit("5.6.7.8:26000", "two", "", "arena");
+        // Probed while connected to another server.
+        servers.set_ping("1.2.3.4:26000", 50);
+        servers.visit("1.2.3.4:26000", "one", "eu", "other");
+        assert_eq!(servers.history.len(), 2);
+        assert_eq!(servers.history[0].map, "other");
+        assert_eq!(servers.history[0].ping, Some(50));
+        assert_eq!(servers.history_lines(false)[1], "5.6.7.8:26000 - two - - - arena - -");
+
+        servers.visit("9.9.9.9:26000", "three", "us", "arena");
+        servers.set_ping("9.9.9.9:26000", 10);
+        let lines = servers.history_lines(true);
+        assert!(lines[0].starts_with("9.9.9.9:26000"));
+        assert!(lines[2].starts_with("5.6.7.8:26000"));
+
+        let text = ron::ser::to_string(&servers).unwrap();
+        assert_eq!(ron::from_str::<Servers>(&text).unwrap(), servers);
+    }
+}
@@ -0,0 +1,103 @@
+//! Blending cycles toward corrected positions instead of snapping - `cl_smooth_time`.
+//!
+//! When an update from the server moves a cycle away from where the client simulated it,
+//! the body moves right away so physics stays correct
+//! but the model (and the camera following it) keep an offset back toward the old position
+//! which decays over `cl_smooth_time`.
+//! Big corrections such as teleports still snap.
+//!
+//! LATER Rotation.
+
+use fxhash::FxHashMap;
+
+use crate::{
+    common::{entities::Cycle, GameState},
+    prelude::*,
+};
+
+/// Corrections longer than this in meters snap.
+const SNAP_DISTANCE: f32 = 5.0;
+
+/// Shorter offsets are dropped.
+const MIN_OFFSET: f32 = 0.001;
+
+#[derive(Debug, Default)]
+pub(crate) struct Smoothing {
+    /// World space offset of each corrected cycle's model from its body.
+    offsets: FxHashMap<Handle<Cycle>, Vector3<f32>>,
+}
+
+impl Smoothing {
+    /// An update moved the cycle's body from `old` to `new`.
+    pub(crate) fn correct(
+        &mut self,
+        cvars: &Cvars,
+        cycle_handle: Handle<Cycle>,
+        old: Vector3<f32>,
+        new: Vector3<f32>,
+    ) {
+        let offset = self.offset(cycle_handle) + old - new;
+        if cvars.cl_smooth_time <= 0.0 || offset.norm() > SNAP_DISTANCE {
+            self.offsets.remove(&cycle_handle);
+        } else {
+            self.offsets.insert(cycle_handle, offset);
+        }
+    }
+
+    /// Where the cycle is drawn relative to its body.
+    pub(crate) fn offset(&self, cycle_handle: Handle<Cycle>) -> Vector3<f32> {
+        self.offsets.get(&cycle_handle).copied().unwrap_or_else(Vector3::zeros)
+    }
+
+    /// Decay the offsets and move the models.
+    pub(crate) fn tick(&mut self, cvars: &Cvars, scene: &mut Scene, gs: &GameState, dt: f32) {
+        let decay = decay(dt, cvars.cl_smooth_time);
+        self.offsets.retain(|&handle, offset| {
+            *offset *= decay;
+            gs.cycles.is_valid_handle(handle) && offset.norm() >= MIN_OFFSET
+        });
+
+        for (cycle_handle, cycle) in gs.cycles.pair_iter() {
+            // The model is the body's child so the offset has to be in the body's space.
+            // The cycle model's origin is the body's so there's nothing else to keep.
+            let offset = self.offset(cycle_handle);
+            let rotation = **scene.graph[cycle.body_handle].local_transform().rotation();
+            let local_offset = rotation.inverse_transform_vector(&offset);
+            let model = &mut scene.graph[cycle.model_handle];
+            model.local_transform_mut().set_position(local_offset);
+        }
+    }
+}
+
+/// How much of an offset is left after `dt` seconds.
+fn decay(dt: f32, smooth_time: f32) -> f32 {
+    if smooth_time <= 0.0 {
+        0.0
+    } else {
+        (-dt / smooth_time).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_correct() {
+        let cvars = Cvars::default();
+        let mut smoothing = Smoothing::default();
+        let handle = Handle::new(1, 1);
+
+        smoothing.correct(&cvars, handle, v!(1 0 0), v!(0 0 0));
+        assert_eq!(smoothing.offset(handle), v!(1 0 0));
+        // Corrections add up.
+        smoothing.correct(&cvars, handle, v!(0 0 0), v!(0 0 -1));
+        assert_eq!(smoothing.offset(handle), v!(1 0 1));
+
+        smoothing.correct(&cvars, handle, v!(0 0 0), v!(100 0 0));
+        assert_eq!(smoothing.offset(handle), Vector3::zeros());
+
+        assert_eq!(decay(0.1, 0.0), 0.0);
+        assert!(decay(0.1, 0.1) < 0.5);
+    }
+}
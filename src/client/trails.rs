@@ -0,0 +1,213 @@
+//! Light walls behind cycles.
+//!
+//! Only the visuals - built on the client from cycle positions every frame
+//! as a ribbon mesh with a glowing material. Older parts shrink until they disappear.
+//!
+//! Long matches make a lot of segments so trails outside the camera's view aren't rebuilt,
+//! distant parts use fewer segments (`r_trail_lod_distance`)
+//! and each trail has at most `r_trail_max_segments`.
+//!
+//! LATER Build them from replicated segments once trails have collisions.
+
+use std::{collections::VecDeque, sync::Arc};
+
+use fxhash::FxHashMap;
+use fyrox::{
+    core::{
+        math::{aabb::AxisAlignedBoundingBox, TriangleDefinition},
+        parking_lot::Mutex,
+        sstorage::ImmutableString,
+    },
+    material::{Material, PropertyValue},
+    scene::mesh::{
+        buffer::{TriangleBuffer, VertexBuffer},
+        surface::{SurfaceBuilder, SurfaceData, SurfaceSharedData},
+        vertex::StaticVertex,
+        Mesh, MeshBuilder, RenderPath,
+    },
+};
+
+use crate::{
+    client::game::player_color,
+    common::{entities::Cycle, GameState},
+    prelude::*,
+};
+
+pub(crate) struct Trails {
+    trails: FxHashMap<Handle<Cycle>, Trail>,
+}
+
+struct Trail {
+    /// Positions of the cycle and when they were added, oldest first.
+    points: VecDeque<(Vec3, f32)>,
+    /// How many points were removed from the front,
+    /// so LOD picks the same points as the trail gets older.
+    removed: usize,
+    /// Reused by `rebuild` for the points left after LOD so it doesn't allocate every frame.
+    lod_points: Vec<(Vec3, f32)>,
+    mesh_handle: Handle<Node>,
+    material: Arc<Mutex<Material>>,
+}
+
+impl Trails {
+    pub(crate) fn new() -> Self {
+        Self {
+            trails: FxHashMap::default(),
+        }
+    }
+
+    pub(crate) fn update(
+        &mut self,
+        cvars: &Cvars,
+        scene: &mut Scene,
+        gs: &GameState,
+        camera_handle: Handle<Node>,
+    ) {
+        self.trails.retain(|&cycle_handle, trail| {
+            let keep = cvars.r_trail_quality > 0 && gs.cycles.is_valid_handle(cycle_handle);
+            if !keep {
+                scene.remove_node(trail.mesh_handle);
+            }
+            keep
+        });
+        if cvars.r_trail_quality <= 0 {
+            return;
+        }
+
+        let spacing = 1.0 / cvars.r_trail_quality as f32;
+        let camera = scene.graph[camera_handle].as_camera();
+        let camera_pos = camera.global_position();
+        let frustum = camera.frustum();
+        for (cycle_handle, cycle) in gs.cycles.pair_iter() {
+            let trail = self.trails.entry(cycle_handle).or_insert_with(|| Trail::new(scene));
+
+            let pos = scene.graph[cycle.body_handle].global_position();
+            let len = trail.points.len();
+            // The newest point always follows the cycle so the wall doesn't lag behind it.
+            if len >= 2 && (trail.points[len - 2].0 - pos).norm() < spacing {
+                trail.points[len - 1] = (pos, gs.game_time);
+            } else {
+                trail.points.push_back((pos, gs.game_time));
+            }
+            while let Some(&(_, time)) = trail.points.front() {
+                let too_many = trail.points.len() > cvars.r_trail_max_segments + 1;
+                if gs.game_time - time <= cvars.r_trail_lifetime && !too_many {
+                    break;
+                }
+                trail.points.pop_front();
+                trail.removed += 1;
+            }
+
+            let mut aabb = AxisAlignedBoundingBox::default();
+            for &(pos, _) in &trail.points {
+                aabb.add_point(pos);
+            }
+            aabb.max.y += cvars.r_trail_height;
+            let visible = !cvars.r_trail_cull || frustum.is_intersects_aabb(&aabb);
+            scene.graph[trail.mesh_handle].set_visibility(visible);
+            if !visible {
+                continue;
+            }
+
+            let color = player_color(cvars, gs, cycle.player_handle);
+            trail.rebuild(cvars, scene, gs.game_time, color, camera_pos);
+        }
+    }
+}
+
+impl Trail {
+    fn new(scene: &mut Scene) -> Self {
+        let mesh_handle = MeshBuilder::new(BaseBuilder::new())
+            .with_render_path(RenderPath::Forward)
+            .build(&mut scene.graph);
+        Self {
+            points: VecDeque::new(),
+            removed: 0,
+            lod_points: Vec::new(),
+            mesh_handle,
+            material: Arc::new(Mutex::new(Material::standard())),
+        }
+    }
+
+    fn rebuild(
+        &mut self,
+        cvars: &Cvars,
+        scene: &mut Scene,
+        game_time: f32,
+        color: Color,
+        camera_pos: Vec3,
+    ) {
+        let mesh = scene.graph[self.mesh_handle].cast_mut::<Mesh>().unwrap();
+        mesh.clear_surfaces();
+        if self.points.len() < 2 {
+            return;
+        }
+
+        let last = self.points.len() - 1;
+        let removed = self.removed;
+        let points = &mut self.lod_points;
+        points.clear();
+        points.extend(
+            self.points
+                .iter()
+                .enumerate()
+                .filter(|&(i, &(pos, _))| {
+                    if i == 0 || i == last || cvars.r_trail_lod_distance <= 0.0 {
+                        return true;
+                    }
+                    // Every 2nd point after the distance, every 4th after twice the distance, ...
+                    // Powers of two so points kept further away are also kept closer.
+                    let lod = ((pos - camera_pos).norm() / cvars.r_trail_lod_distance) as u32;
+                    let step = 1_usize << lod.min(16);
+                    (removed + i) % step == 0
+                })
+                .map(|(_, &point)| point),
+        );
+
+        let mut vertices = Vec::with_capacity(points.len() * 2);
+        for (i, &(pos, time)) in points.iter().enumerate() {
+            let fade = (1.0 - (game_time - time) / cvars.r_trail_lifetime).clamp(0.0, 1.0);
+            let top = pos + UP * cvars.r_trail_height * fade;
+
+            let prev = points[i.saturating_sub(1)].0;
+            let next = points[(i + 1).min(points.len() - 1)].0;
+            let normal = (next - prev).cross(&UP).try_normalize(f32::EPSILON).unwrap_or(LEFT);
+
+            let u = i as f32;
+            vertices.push(StaticVertex::from_pos_uv_normal(pos, Vector2::new(u, 0.0), normal));
+            vertices.push(StaticVertex::from_pos_uv_normal(top, Vector2::new(u, 1.0), normal));
+        }
+
+        let mut triangles = Vec::with_capacity((points.len() - 1) * 4);
+        for i in 1..points.len() as u32 {
+            let (bottom1, top1, bottom2, top2) = (2 * i - 2, 2 * i - 1, 2 * i, 2 * i + 1);
+            // Both sides of the wall.
+            triangles.push(TriangleDefinition([bottom1, bottom2, top1]));
+            triangles.push(TriangleDefinition([top1, bottom2, top2]));
+            triangles.push(TriangleDefinition([bottom1, top1, bottom2]));
+            triangles.push(TriangleDefinition([top1, top2, bottom2]));
+        }
+
+        let vertex_buffer =
+            VertexBuffer::new(vertices.len(), StaticVertex::layout(), vertices).unwrap();
+        let data = SurfaceData::new(vertex_buffer, TriangleBuffer::new(triangles), true);
+
+        {
+            let mut material = self.material.lock();
+            let glow = cvars.r_trail_glow;
+            let emission = Vec3::new(color.r as f32, color.g as f32, color.b as f32) / 255.0 * glow;
+            let res1 = material
+                .set_property(&ImmutableString::new("diffuseColor"), PropertyValue::Color(color));
+            let res2 = material.set_property(
+                &ImmutableString::new("emissionStrength"),
+                PropertyValue::Vector3(emission),
+            );
+            soft_assert!(res1.is_ok() && res2.is_ok(), "trail material: {:?} {:?}", res1, res2);
+        }
+
+        let surface = SurfaceBuilder::new(SurfaceSharedData::new(data))
+            .with_material(Arc::clone(&self.material))
+            .build();
+        mesh.add_surface(surface);
+    }
+}
@@ -0,0 +1,196 @@
+//! The local player's weapon in the corner of the screen - `r_viewmodel`.
+//!
+//! A mesh attached to the camera, only on the client.
+//! It's raised when the player gets a cycle, kicks back when firing
+//! and bobs with the cycle's velocity and sways when it speeds up or turns.
+//!
+//! Meant for first person (`cl_camera_3rd_person_back 0`) but works in any view.
+//!
+//! LATER It's rendered in the world so it can clip into walls when the camera is close to one.
+//! LATER A switch animation between weapons once there's more than one.
+
+use std::f32::consts::TAU;
+
+use fyrox::{
+    core::algebra::Matrix4,
+    scene::{
+        camera::Projection,
+        mesh::{
+            surface::{SurfaceBuilder, SurfaceData, SurfaceSharedData},
+            MeshBuilder,
+        },
+    },
+};
+
+use crate::prelude::*;
+
+/// How long the kick after firing lasts in seconds.
+const FIRE_DURATION: f32 = 0.15;
+/// How far back the weapon moves when firing.
+const FIRE_KICK: f32 = 0.06;
+/// Pitch up when firing, in radians.
+const FIRE_PITCH: f32 = 0.15;
+
+/// How long raising the weapon takes in seconds.
+const SWITCH_DURATION: f32 = 0.4;
+/// How far below its position the weapon starts when raised.
+const SWITCH_DROP: f32 = 0.3;
+
+/// Bob cycles per meter travelled.
+const BOB_FREQUENCY: f32 = 0.25;
+/// Bob is strongest at this speed and above, in m/s.
+const BOB_FULL_SPEED: f32 = 20.0;
+
+/// Sway can't move the weapon further than this.
+const SWAY_MAX: f32 = 0.05;
+/// How fast sway follows acceleration, higher is faster.
+const SWAY_SMOOTHING: f32 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Anim {
+    Idle,
+    /// Kicking back after a shot, game time when it started.
+    Fire(f32),
+    /// Being raised after getting a cycle, game time when it started.
+    Switch(f32),
+}
+
+pub(crate) struct ViewModel {
+    node: Handle<Node>,
+    anim: Anim,
+    visible: bool,
+    /// Where in the bob cycle the weapon is, in cycles.
+    bob_phase: f32,
+    /// Smoothed offset in camera space.
+    sway: Vec3,
+    /// Velocity in camera space last frame, sway follows its changes.
+    local_vel_prev: Vec3,
+}
+
+impl ViewModel {
+    pub(crate) fn new(scene: &mut Scene, camera_handle: Handle<Node>) -> Self {
+        // Pointing forward along local Z like the camera.
+        let body = SurfaceData::make_cube(Matrix4::new_nonuniform_scaling(&v!(0.06 0.08 0.2)));
+        let barrel = SurfaceData::make_cube(
+            Matrix4::new_translation(&v!(0 0.02 0.15))
+                * Matrix4::new_nonuniform_scaling(&v!(0.025 0.025 0.2)),
+        );
+        let node = MeshBuilder::new(BaseBuilder::new().with_visibility(false))
+            .with_surfaces(vec![
+                SurfaceBuilder::new(SurfaceSharedData::new(body)).build(),
+                SurfaceBuilder::new(SurfaceSharedData::new(barrel)).build(),
+            ])
+            .with_cast_shadows(false)
+            .build(&mut scene.graph);
+        scene.graph.link_nodes(node, camera_handle);
+        Self {
+            node,
+            anim: Anim::Idle,
+            visible: false,
+            bob_phase: 0.0,
+            sway: Vec3::zeros(),
+            local_vel_prev: Vec3::zeros(),
+        }
+    }
+
+    /// The local player fired.
+    pub(crate) fn fire(&mut self, game_time: f32) {
+        // Firing interrupts raising the weapon.
+        self.anim = Anim::Fire(game_time);
+    }
+
+    /// Call after the camera is updated.
+    ///
+    /// `velocity` is the local player's cycle's, `None` hides the weapon.
+    pub(crate) fn update(
+        &mut self,
+        cvars: &Cvars,
+        scene: &mut Scene,
+        camera_handle: Handle<Node>,
+        game_time: f32,
+        dt: f32,
+        velocity: Option<Vec3>,
+    ) {
+        let visible = cvars.r_viewmodel && velocity.is_some();
+        let appeared = visible && !self.visible;
+        if appeared {
+            self.anim = Anim::Switch(game_time);
+        }
+        self.visible = visible;
+        scene.graph[self.node].set_visibility(visible);
+        let Some(velocity) = velocity else {
+            return;
+        };
+
+        let camera = &scene.graph[camera_handle];
+        let cam_rot = **camera.local_transform().rotation();
+        let cam_fov = match camera.as_camera().projection() {
+            Projection::Perspective(perspective) => perspective.fov,
+            Projection::Orthographic(_) => cvars.cl_camera_fov.to_radians(),
+        };
+
+        // Bob
+        let speed = velocity.norm();
+        self.bob_phase = (self.bob_phase + speed * dt * BOB_FREQUENCY) % 1.0;
+        let bob_amount = cvars.r_viewmodel_bob * (speed / BOB_FULL_SPEED).min(1.0);
+        let angle = self.bob_phase * TAU;
+        let bob = v!(angle.sin() * 0.5, -angle.cos().abs(), 0) * bob_amount;
+
+        // Sway - the weapon lags behind when the cycle speeds up or turns.
+        // Turning rotates the camera so it also changes velocity in camera space.
+        let local_vel = cam_rot.inverse() * velocity;
+        if appeared {
+            self.local_vel_prev = local_vel;
+        }
+        let accel = if dt > 0.0 {
+            (local_vel - self.local_vel_prev) / dt
+        } else {
+            Vec3::zeros()
+        };
+        self.local_vel_prev = local_vel;
+        let target = (-accel * cvars.r_viewmodel_sway).cap_magnitude(SWAY_MAX);
+        self.sway += (target - self.sway) * (SWAY_SMOOTHING * dt).min(1.0);
+
+        // Animation
+        let mut offset = Vec3::zeros();
+        let mut pitch = 0.0;
+        match self.anim {
+            Anim::Idle => {}
+            Anim::Fire(start) => {
+                let progress = (game_time - start) / FIRE_DURATION;
+                if progress >= 1.0 {
+                    self.anim = Anim::Idle;
+                } else {
+                    let kick = (1.0 - progress).powi(2);
+                    offset += BACK * FIRE_KICK * kick;
+                    pitch = FIRE_PITCH * kick;
+                }
+            }
+            Anim::Switch(start) => {
+                let progress = (game_time - start) / SWITCH_DURATION;
+                if progress >= 1.0 {
+                    self.anim = Anim::Idle;
+                } else {
+                    offset += DOWN * SWITCH_DROP * (1.0 - progress).powi(2);
+                }
+            }
+        }
+
+        // Scaling X and Y in camera space makes the weapon look like
+        // it was rendered with `r_viewmodel_fov` instead of the camera's FOV.
+        let fov = cvars.r_viewmodel_fov.clamp(1.0, 179.0).to_radians();
+        let scale = (cam_fov / 2.0).tan() / (fov / 2.0).tan();
+        let pos = v!(cvars.r_viewmodel_left, cvars.r_viewmodel_up, cvars.r_viewmodel_forward)
+            + bob
+            + self.sway
+            + offset;
+        let pos = v!(pos.x * scale, pos.y * scale, pos.z);
+        // Positive pitch tilts the barrel up.
+        let rot = UnitQuaternion::from_axis_angle(&LEFT_AXIS, -pitch);
+        scene.graph[self.node]
+            .local_transform_mut()
+            .set_position(pos)
+            .set_rotation(rot)
+            .set_scale(v!(scale, scale, 1));
+    }
+}
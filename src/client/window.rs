@@ -0,0 +1,102 @@
+//! Window integration - icon, title and taskbar flashing.
+//!
+//! The title shows the server, map and score so the game can be found when alt+tabbing.
+//! When the match starts while the window is in the background,
+//! the taskbar entry flashes (`cl_window_flash`).
+//!
+//! LATER A proper icon once the logo can be loaded without decoding PNGs by hand.
+
+use fyrox::window::{Icon, UserAttentionType};
+
+use crate::{client::game::ClientGame, common::entities::MatchPhase, prelude::*};
+
+const ICON_SIZE: u32 = 32;
+
+pub(crate) struct WindowIntegration {
+    focused: bool,
+    /// The title last set so the window is only updated when it changes.
+    title: String,
+    /// The match was in warmup during the last update.
+    warmup: bool,
+}
+
+impl WindowIntegration {
+    pub(crate) fn new(engine: &Engine) -> Self {
+        match Icon::from_rgba(icon_rgba(), ICON_SIZE, ICON_SIZE) {
+            Ok(icon) => engine.get_window().set_window_icon(Some(icon)),
+            Err(err) => dbg_logf!("bad window icon: {}", err),
+        }
+
+        Self {
+            // Windows start focused, if not we get an event.
+            focused: true,
+            title: String::new(),
+            warmup: false,
+        }
+    }
+
+    pub(crate) fn focused(&mut self, focus: bool) {
+        self.focused = focus;
+    }
+
+    pub(crate) fn update(&mut self, cvars: &Cvars, engine: &Engine, cg: &ClientGame) {
+        let window = engine.get_window();
+
+        let score = match cg.gs.phase {
+            MatchPhase::Warmup { .. } => None,
+            MatchPhase::Live { .. } | MatchPhase::Overtime { .. } | MatchPhase::Paused { .. } => {
+                Some(cg.gs.players.try_borrow(cg.lp.player_handle).map_or(0, |player| player.score))
+            }
+        };
+        let title = title(&cg.server_name, &cg.map, score);
+        if title != self.title {
+            window.set_title(&title);
+            self.title = title;
+        }
+
+        let warmup = matches!(cg.gs.phase, MatchPhase::Warmup { .. });
+        if self.warmup && !warmup && !self.focused && cvars.cl_window_flash {
+            window.request_user_attention(Some(UserAttentionType::Informational));
+        }
+        self.warmup = warmup;
+    }
+}
+
+/// The score is only shown once the match is live.
+fn title(server_name: &str, map: &str, score: Option<u32>) -> String {
+    let mut title = format!("RustCycles - {} - {}", server_name, map);
+    if let Some(score) = score {
+        title.push_str(&format!(" - score {}", score));
+    }
+    title
+}
+
+/// A light cycle's trail turning a corner on a dark background.
+fn icon_rgba() -> Vec<u8> {
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let horizontal = (14..18).contains(&y) && (4..24).contains(&x);
+            let vertical = (20..24).contains(&x) && (6..18).contains(&y);
+            let pixel = if horizontal || vertical {
+                [0, 220, 255, 255]
+            } else {
+                [16, 16, 32, 255]
+            };
+            rgba.extend_from_slice(&pixel);
+        }
+    }
+    rgba
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title() {
+        assert_eq!(title("srv", "arena", None), "RustCycles - srv - arena");
+        assert_eq!(title("srv", "arena", Some(3)), "RustCycles - srv - arena - score 3");
+        assert_eq!(icon_rgba().len(), (ICON_SIZE * ICON_SIZE * 4) as usize);
+    }
+}
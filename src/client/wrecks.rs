@@ -0,0 +1,207 @@
+//! Broken pieces of destroyed cycles - `r_wreck_time`.
+//!
+//! When a kill arrives, the victim's cycle is still there (it's despawned right after)
+//! so its pieces start where it was and get thrown away from the killer.
+//! They bounce off the map and shrink away at the end of `r_wreck_time`.
+//!
+//! Pieces are plain meshes moved by the client instead of rigid bodies
+//! so they can't affect gameplay, prediction or traces.
+//! There are `r_wrecks_max` of them in a `Ring`. All pieces of a wreck share one material
+//! so reusing it for another victim only needs one color change.
+//!
+//! LATER `r_wrecks_max` is only read when connecting, the ring could grow or shrink instead.
+//! LATER Use the hit position once kills say where the victim was hit.
+
+use std::sync::Arc;
+
+use fyrox::{
+    core::{algebra::Matrix4, parking_lot::Mutex, sstorage::ImmutableString},
+    material::{Material, PropertyValue},
+    scene::mesh::{
+        surface::{SurfaceBuilder, SurfaceData, SurfaceSharedData},
+        MeshBuilder,
+    },
+};
+
+use crate::{
+    client::{game::player_color, ring::Ring},
+    common::GameState,
+    prelude::*,
+};
+
+/// Offsets from the cycle's center and half extents, together roughly the cycle's hitbox.
+const PIECES: [(Vec3, Vec3); 6] = [
+    (Vec3::new(0.06, 0.13, 0.13), Vec3::new(0.06, 0.13, 0.13)),
+    (Vec3::new(-0.06, 0.13, 0.13), Vec3::new(0.06, 0.13, 0.13)),
+    (Vec3::new(0.06, 0.13, -0.13), Vec3::new(0.06, 0.13, 0.13)),
+    (Vec3::new(-0.06, 0.13, -0.13), Vec3::new(0.06, 0.13, 0.13)),
+    (Vec3::new(0.0, -0.15, 0.15), Vec3::new(0.03, 0.12, 0.12)),
+    (Vec3::new(0.0, -0.15, -0.15), Vec3::new(0.03, 0.12, 0.12)),
+];
+
+/// Pieces shrink during the last this many seconds.
+const FADE_TIME: f32 = 1.0;
+/// Speed away from the killer in m/s.
+const BLOW_SPEED: f32 = 6.0;
+/// Speed away from the cycle's center in m/s.
+const SCATTER_SPEED: f32 = 2.0;
+/// Radians per second for each meter of offset from the center.
+const SPIN: f32 = 40.0;
+const GRAVITY: f32 = 9.81;
+/// How much speed is kept after hitting the map.
+const BOUNCE: f32 = 0.3;
+
+pub(crate) struct Wrecks {
+    wrecks: Ring<Wreck>,
+}
+
+struct Wreck {
+    pieces: Vec<Piece>,
+    material: Arc<Mutex<Material>>,
+    /// Game time when the wreck disappears, `None` if it's not in use.
+    end: Option<f32>,
+}
+
+struct Piece {
+    node: Handle<Node>,
+    pos: Vec3,
+    rot: UnitQuaternion<f32>,
+    vel: Vec3,
+    ang_vel: Vec3,
+}
+
+impl Wrecks {
+    pub(crate) fn new(cvars: &Cvars, scene: &mut Scene) -> Self {
+        let wrecks = Ring::new(cvars.r_wrecks_max, || Wreck::new(scene));
+        Self { wrecks }
+    }
+
+    /// Break the victim's cycle, nothing if it doesn't have one.
+    pub(crate) fn spawn(
+        &mut self,
+        cvars: &Cvars,
+        scene: &mut Scene,
+        gs: &GameState,
+        killer_index: u32,
+        victim_index: u32,
+    ) {
+        if cvars.r_wreck_time <= 0.0 {
+            return;
+        }
+        let cycle_body = |player_index| {
+            let player = gs.players.at(player_index)?;
+            let cycle = &gs.cycles[player.cycle_handle?];
+            Some(&scene.graph[cycle.body_handle])
+        };
+        let Some(body) = cycle_body(victim_index) else {
+            return;
+        };
+        let pos = body.global_position();
+        let rot = **body.local_transform().rotation();
+        let vel = body.as_rigid_body().lin_vel();
+
+        // Map kills and suicides throw the pieces up.
+        let blow = match cycle_body(killer_index) {
+            Some(killer) if killer_index != victim_index => {
+                (pos - killer.global_position()).try_normalize(f32::EPSILON).unwrap_or(UP)
+            }
+            _ => UP,
+        };
+        let victim_handle = gs.players.handle_from_index(victim_index);
+        let color = player_color(cvars, gs, victim_handle);
+
+        let wreck = self.wrecks.next_mut();
+        wreck.end = Some(gs.game_time + cvars.r_wreck_time);
+        {
+            let mut material = wreck.material.lock();
+            let res = material
+                .set_property(&ImmutableString::new("diffuseColor"), PropertyValue::Color(color));
+            soft_assert!(res.is_ok(), "wreck material: {:?}", res);
+        }
+        for (piece, &(offset, _)) in wreck.pieces.iter_mut().zip(&PIECES) {
+            let offset = rot * offset;
+            let out = offset.try_normalize(f32::EPSILON).unwrap_or(UP);
+            piece.pos = pos + offset;
+            piece.rot = rot;
+            piece.vel = vel + blow * BLOW_SPEED + out * SCATTER_SPEED;
+            piece.ang_vel = offset.cross(&blow) * SPIN;
+            let node = &mut scene.graph[piece.node];
+            node.set_visibility(true);
+            node.local_transform_mut()
+                .set_position(piece.pos)
+                .set_rotation(piece.rot)
+                .set_scale(v!(1 1 1));
+        }
+    }
+
+    /// Move the pieces and hide finished wrecks.
+    pub(crate) fn update(&mut self, scene: &mut Scene, game_time: f32, dt: f32) {
+        for wreck in self.wrecks.iter_mut() {
+            let Some(end) = wreck.end else {
+                continue;
+            };
+            let left = end - game_time;
+            if left <= 0.0 {
+                wreck.end = None;
+                for piece in &wreck.pieces {
+                    scene.graph[piece.node].set_visibility(false);
+                }
+                continue;
+            }
+
+            let scale = (left / FADE_TIME).min(1.0);
+            for piece in &mut wreck.pieces {
+                piece.vel += DOWN * GRAVITY * dt;
+                let step = piece.vel * dt;
+                // Cycles don't stop pieces, that would look like they can hit players.
+                let hits = trace_line(scene, piece.pos, step, TraceOptions::filter(!IG_ENTITIES));
+                if let Some(hit) = hits.first() {
+                    piece.pos = hit.position.coords;
+                    let normal = hit.normal;
+                    piece.vel = (piece.vel - 2.0 * piece.vel.dot(&normal) * normal) * BOUNCE;
+                    piece.ang_vel *= BOUNCE;
+                } else {
+                    piece.pos += step;
+                }
+                piece.rot = UnitQuaternion::from_scaled_axis(piece.ang_vel * dt) * piece.rot;
+
+                scene.graph[piece.node]
+                    .local_transform_mut()
+                    .set_position(piece.pos)
+                    .set_rotation(piece.rot)
+                    .set_scale(v!(scale, scale, scale));
+            }
+        }
+    }
+}
+
+impl Wreck {
+    fn new(scene: &mut Scene) -> Self {
+        let material = Arc::new(Mutex::new(Material::standard()));
+        let pieces = PIECES
+            .iter()
+            .map(|&(_, half_size)| {
+                let data =
+                    SurfaceData::make_cube(Matrix4::new_nonuniform_scaling(&(half_size * 2.0)));
+                let surface = SurfaceBuilder::new(SurfaceSharedData::new(data))
+                    .with_material(Arc::clone(&material))
+                    .build();
+                let node = MeshBuilder::new(BaseBuilder::new().with_visibility(false))
+                    .with_surfaces(vec![surface])
+                    .build(&mut scene.graph);
+                Piece {
+                    node,
+                    pos: Vec3::zeros(),
+                    rot: UnitQuaternion::identity(),
+                    vel: Vec3::zeros(),
+                    ang_vel: Vec3::zeros(),
+                }
+            })
+            .collect();
+        Self {
+            pieces,
+            material,
+            end: None,
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! Console commands - anything you can type into the console that's not a cvar.
+//!
+//! The console only knows about cvars so commands are intercepted here
+//! before they reach `Cvars`. From the console's point of view,
+//! `exec file.cfg` looks the same as setting a cvar called `exec`
+//! and `asserts` looks the same as printing a cvar called `asserts`.
+//!
+//! Commands which need the game (e.g. `ready`) are only collected here
+//! and run by the client process once the console is done.
+
+use std::{cell::RefCell, fs, net::SocketAddr};
+
+use cvars_console::CvarAccess;
+use strum::IntoEnumIterator;
+
+use crate::{
+    client::bindings::{Binding, Button},
+    common::{
+        entities::{Emote, TEAM_NAMES},
+        messages::Cheat,
+        net::PingStats,
+    },
+    debug,
+    prelude::*,
+};
+
+const BIND_USAGE: &str =
+    "usage: `bind <button> <action>` or `bind <button> +<axis>`, e.g. `bind w +move`";
+
+const FAVORITE_USAGE: &str =
+    "usage: `favorite add <ip:port>` or `favorite remove <ip:port>`, `favorite` to list them";
+
+/// How many scripts can be nested using `exec` inside a script.
+///
+/// This protects against scripts which (accidentally) exec each other in a cycle.
+const EXEC_MAX_DEPTH: usize = 8;
+
+/// What `net_stats` without arguments shows.
+const NET_STATS_SECONDS: f32 = 10.0;
+
+/// Commands which need access to the game, not just cvars.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum GameCommand {
+    /// `ready` or `ready 0` to cancel.
+    Ready(bool),
+    /// `team <name>`, the index into `TEAM_NAMES`.
+    Team(u8),
+    /// `bind <button> <action>` or `unbind <button>` which is `None`.
+    Bind(Button, Option<Binding>),
+    /// `noclip`, `god`, `give <weapon>` or `teleport <x> <y> <z>`, the server checks `sv_cheats`.
+    Cheat(Cheat),
+    /// `input_record <path>` or `input_record` to stop.
+    InputRecord(Option<String>),
+    /// `input_play <path>` or `input_play` to stop.
+    InputPlay(Option<String>),
+    /// `say <text>`, can also be a chat command such as `say /me waves`.
+    Say(String),
+    /// `emote <name>`.
+    Emote(Emote),
+    /// `mute <player>`, the index of a player who's connected.
+    Mute(u32),
+    /// `unmute <player>`.
+    Unmute(u32),
+    /// `mutelist`
+    MuteList,
+    /// `favorite add <addr>`.
+    FavoriteAdd(String),
+    /// `favorite remove <addr>`.
+    FavoriteRemove(String),
+    /// `favorite` to list them.
+    Favorites,
+    /// `history` or `history ping` to list the fastest servers first.
+    History { by_ping: bool },
+    /// `probe` to measure ping to servers in the history.
+    Probe,
+    /// `motd` to show the server's message of the day again.
+    Motd,
+    /// `stats`
+    Stats,
+    /// `rewind <seconds>`, only in local games.
+    Rewind(f32),
+    /// `net_stats <seconds>`, only in local games.
+    NetStats(f32),
+    /// `resume` after `rewind`.
+    Resume,
+    /// `savestate <file>`, only in local games.
+    SaveState(String),
+    /// `loadstate <file>`, only in local games.
+    LoadState(String),
+}
+
+/// Wraps `Cvars` so the console can also run commands, not just set/get cvars.
+pub(crate) struct CvarsWithCommands<'a> {
+    pub(crate) cvars: &'a mut Cvars,
+    /// A `RefCell` because commands without arguments go through `get_string` which takes `&self`.
+    game_commands: RefCell<Vec<GameCommand>>,
+    /// For `ping`, only available when connected.
+    ping: Option<&'a PingStats>,
+}
+
+impl<'a> CvarsWithCommands<'a> {
+    pub(crate) fn new(cvars: &'a mut Cvars) -> Self {
+        Self {
+            cvars,
+            game_commands: RefCell::new(Vec::new()),
+            ping: None,
+        }
+    }
+
+    pub(crate) fn with_ping(mut self, ping: &'a PingStats) -> Self {
+        self.ping = Some(ping);
+        self
+    }
+
+    /// Commands the caller should run, in the order they were entered.
+    pub(crate) fn take_game_commands(&mut self) -> Vec<GameCommand> {
+        self.game_commands.take()
+    }
+}
+
+impl CvarAccess for CvarsWithCommands<'_> {
+    fn get_string(&self, cvar_name: &str) -> Result<String, String> {
+        match cvar_name {
+            "asserts" => Ok(asserts()),
+            "ready" => {
+                self.game_commands.borrow_mut().push(GameCommand::Ready(true));
+                Ok("ready, `ready 0` to cancel".to_owned())
+            }
+            "team" => Err(format!("usage: `team <name>`, teams: {}", TEAM_NAMES.join(", "))),
+            "bind" => Err(BIND_USAGE.to_owned()),
+            "unbind" => Err("usage: `unbind <button>`".to_owned()),
+            "noclip" => {
+                self.game_commands.borrow_mut().push(GameCommand::Cheat(Cheat::Noclip));
+                Ok("toggling noclip, needs sv_cheats".to_owned())
+            }
+            "god" => {
+                self.game_commands.borrow_mut().push(GameCommand::Cheat(Cheat::God));
+                Ok("toggling god mode, needs sv_cheats".to_owned())
+            }
+            "give" => Err("usage: `give <weapon>`".to_owned()),
+            "teleport" => Err("usage: `teleport <x> <y> <z>`".to_owned()),
+            "input_record" => {
+                self.game_commands.borrow_mut().push(GameCommand::InputRecord(None));
+                Ok("stopping input recording".to_owned())
+            }
+            "input_play" => {
+                self.game_commands.borrow_mut().push(GameCommand::InputPlay(None));
+                Ok("stopping input playback".to_owned())
+            }
+            "say" => Err("usage: `say <text>`, e.g. `say /me waves`".to_owned()),
+            "emote" => Err(emote_usage()),
+            "mute" => Err("usage: `mute <player number>`, see the scoreboard".to_owned()),
+            "unmute" => Err("usage: `unmute <player number>`".to_owned()),
+            "mutelist" => {
+                self.game_commands.borrow_mut().push(GameCommand::MuteList);
+                Ok("muted players:".to_owned())
+            }
+            "favorite" => {
+                self.game_commands.borrow_mut().push(GameCommand::Favorites);
+                Ok("favorite servers, `favorite add <addr>` to add one:".to_owned())
+            }
+            "history" => {
+                let command = GameCommand::History { by_ping: false };
+                self.game_commands.borrow_mut().push(command);
+                Ok("recently joined servers, `history ping` to sort by ping:".to_owned())
+            }
+            "probe" => {
+                self.game_commands.borrow_mut().push(GameCommand::Probe);
+                Ok("measuring ping to servers in the history, see `history ping`".to_owned())
+            }
+            "motd" => {
+                self.game_commands.borrow_mut().push(GameCommand::Motd);
+                Ok("showing the message of the day".to_owned())
+            }
+            "stats" => {
+                self.game_commands.borrow_mut().push(GameCommand::Stats);
+                Ok("lifetime stats:".to_owned())
+            }
+            "rewind" => Err("usage: `rewind <seconds>`".to_owned()),
+            "savestate" => Err("usage: `savestate <file>`".to_owned()),
+            "loadstate" => Err("usage: `loadstate <file>`".to_owned()),
+            "resume" => {
+                self.game_commands.borrow_mut().push(GameCommand::Resume);
+                Ok("resuming".to_owned())
+            }
+            "net_stats" => {
+                let command = GameCommand::NetStats(NET_STATS_SECONDS);
+                self.game_commands.borrow_mut().push(command);
+                Ok(format!(
+                    "messages sent during the last {} s, only in local games:",
+                    NET_STATS_SECONDS
+                ))
+            }
+            "ping" => match self.ping {
+                Some(ping) => Ok(ping.summary()),
+                None => Err("not connected".to_owned()),
+            },
+            _ => self.cvars.get_string(cvar_name),
+        }
+    }
+
+    fn set_str(&mut self, cvar_name: &str, cvar_value: &str) -> Result<(), String> {
+        match cvar_name {
+            "asserts" if cvar_value == "clear" => {
+                debug::details::asserts_clear();
+                Ok(())
+            }
+            "asserts" => Err("usage: `asserts` to list, `asserts clear` to reset".to_owned()),
+            "exec" => exec(self.cvars, cvar_value),
+            "ready" => {
+                let ready = match cvar_value {
+                    "1" | "true" => true,
+                    "0" | "false" => false,
+                    _ => return Err("usage: `ready` or `ready 0` to cancel".to_owned()),
+                };
+                self.game_commands.borrow_mut().push(GameCommand::Ready(ready));
+                Ok(())
+            }
+            // The server only knows how many teams there are, we can at least check the name.
+            "team" => match TEAM_NAMES.iter().position(|&name| name == cvar_value) {
+                Some(team) => {
+                    self.game_commands.borrow_mut().push(GameCommand::Team(team as u8));
+                    Ok(())
+                }
+                None => {
+                    Err(format!("unknown team {}, teams: {}", cvar_value, TEAM_NAMES.join(", ")))
+                }
+            },
+            "bind" => {
+                let (button, binding) = cvar_value
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| BIND_USAGE.to_owned())?;
+                let button = button.parse()?;
+                let binding = binding.trim().parse()?;
+                let command = GameCommand::Bind(button, Some(binding));
+                self.game_commands.borrow_mut().push(command);
+                Ok(())
+            }
+            "unbind" => {
+                let button = cvar_value.parse()?;
+                self.game_commands.borrow_mut().push(GameCommand::Bind(button, None));
+                Ok(())
+            }
+            "give" => {
+                let command = GameCommand::Cheat(Cheat::Give(cvar_value.to_owned()));
+                self.game_commands.borrow_mut().push(command);
+                Ok(())
+            }
+            "teleport" => {
+                let pos = parse_pos(cvar_value).ok_or("usage: `teleport <x> <y> <z>`")?;
+                let command = GameCommand::Cheat(Cheat::Teleport(pos));
+                self.game_commands.borrow_mut().push(command);
+                Ok(())
+            }
+            "input_record" => {
+                let command = GameCommand::InputRecord(Some(cvar_value.to_owned()));
+                self.game_commands.borrow_mut().push(command);
+                Ok(())
+            }
+            "input_play" => {
+                let command = GameCommand::InputPlay(Some(cvar_value.to_owned()));
+                self.game_commands.borrow_mut().push(command);
+                Ok(())
+            }
+            "say" => {
+                let command = GameCommand::Say(cvar_value.to_owned());
+                self.game_commands.borrow_mut().push(command);
+                Ok(())
+            }
+            "emote" => {
+                let emote: Emote = cvar_value.parse().map_err(|_| emote_usage())?;
+                self.game_commands.borrow_mut().push(GameCommand::Emote(emote));
+                Ok(())
+            }
+            "mute" => {
+                let player_index =
+                    cvar_value.parse().map_err(|_| "usage: `mute <player number>`")?;
+                self.game_commands.borrow_mut().push(GameCommand::Mute(player_index));
+                Ok(())
+            }
+            "unmute" => {
+                let player_index =
+                    cvar_value.parse().map_err(|_| "usage: `unmute <player number>`")?;
+                self.game_commands.borrow_mut().push(GameCommand::Unmute(player_index));
+                Ok(())
+            }
+            "favorite" => {
+                let command = parse_favorite(cvar_value).ok_or(FAVORITE_USAGE)?;
+                self.game_commands.borrow_mut().push(command);
+                Ok(())
+            }
+            "history" if cvar_value == "ping" => {
+                let command = GameCommand::History { by_ping: true };
+                self.game_commands.borrow_mut().push(command);
+                Ok(())
+            }
+            "history" => Err("usage: `history` or `history ping`".to_owned()),
+            "rewind" => match cvar_value.parse() {
+                Ok(seconds) if seconds > 0.0 => {
+                    self.game_commands.borrow_mut().push(GameCommand::Rewind(seconds));
+                    Ok(())
+                }
+                _ => Err("usage: `rewind <seconds>`".to_owned()),
+            },
+            "savestate" => {
+                let command = GameCommand::SaveState(cvar_value.to_owned());
+                self.game_commands.borrow_mut().push(command);
+                Ok(())
+            }
+            "loadstate" => {
+                let command = GameCommand::LoadState(cvar_value.to_owned());
+                self.game_commands.borrow_mut().push(command);
+                Ok(())
+            }
+            "net_stats" => match cvar_value.parse() {
+                Ok(seconds) if seconds > 0.0 => {
+                    self.game_commands.borrow_mut().push(GameCommand::NetStats(seconds));
+                    Ok(())
+                }
+                _ => {
+                    Err("usage: `net_stats <seconds>`, at most 60, only in local games".to_owned())
+                }
+            },
+            _ => self.cvars.set_str(cvar_name, cvar_value),
+        }?;
+        debug::crash::set_cvars(self.cvars);
+        Ok(())
+    }
+}
+
+fn emote_usage() -> String {
+    let emotes: Vec<_> = Emote::iter().map(|emote| emote.to_string()).collect();
+    format!("usage: `emote <name>`, emotes: {}", emotes.join(", "))
+}
+
+/// `add <addr>` or `remove <addr>` where the address includes the port.
+fn parse_favorite(s: &str) -> Option<GameCommand> {
+    let (action, addr) = s.split_once(char::is_whitespace)?;
+    let addr = addr.trim();
+    addr.parse::<SocketAddr>().ok()?;
+    match action {
+        "add" => Some(GameCommand::FavoriteAdd(addr.to_owned())),
+        "remove" => Some(GameCommand::FavoriteRemove(addr.to_owned())),
+        _ => None,
+    }
+}
+
+/// Three numbers separated by whitespace.
+fn parse_pos(s: &str) -> Option<[f32; 3]> {
+    let mut coords = s.split_whitespace().map(|coord| coord.parse().ok());
+    let pos = [coords.next()??, coords.next()??, coords.next()??];
+    coords.next().is_none().then_some(pos)
+}
+
+/// List soft asserts which failed and how many times.
+fn asserts() -> String {
+    let lines = debug::details::asserts_text();
+    if lines.is_empty() {
+        "no soft asserts failed".to_owned()
+    } else {
+        lines.join("\n")
+    }
+}
+
+/// Run a script - a text file with one console line per line.
+///
+/// Each line is either `cvar_name value` or a command (e.g. `exec other.cfg`).
+/// Empty lines and lines starting with `//` or `#` are ignored.
+///
+/// A failing line is reported (with the file name and line number)
+/// but doesn't stop the rest of the script from running.
+pub(crate) fn exec(cvars: &mut Cvars, path: &str) -> Result<(), String> {
+    exec_nested(cvars, path, 0)
+}
+
+fn exec_nested(cvars: &mut Cvars, path: &str, depth: usize) -> Result<(), String> {
+    if depth >= EXEC_MAX_DEPTH {
+        return Err(format!("exec {}: too many nested scripts (max {})", path, EXEC_MAX_DEPTH));
+    }
+
+    let script = fs::read_to_string(path)
+        .map_err(|err| format!("exec {}: failed to read: {}", path, err))?;
+
+    let mut failed = 0;
+    for (i, line) in script.lines().enumerate() {
+        let res = match parse_line(line) {
+            Ok(None) => continue,
+            Ok(Some(("exec", nested_path))) => exec_nested(cvars, nested_path, depth + 1),
+            Ok(Some((cvar_name, cvar_value))) => cvars.set_str(cvar_name, cvar_value),
+            Err(msg) => Err(msg),
+        };
+        if let Err(msg) = res {
+            dbg_logf!("{}:{}: {}", path, i + 1, msg);
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        Err(format!("exec {}: {} line(s) failed", path, failed))
+    } else {
+        Ok(())
+    }
+}
+
+/// Split a line of a script into the name and value.
+///
+/// Returns `None` for lines which should be skipped (empty and comments).
+/// The value is the rest of the line so it can contain spaces.
+fn parse_line(line: &str) -> Result<Option<(&str, &str)>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with("//") || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    // Cvar names can optionally be prefixed by '+', same as on the command line.
+    let line = line.strip_prefix('+').unwrap_or(line);
+
+    match line.split_once(char::is_whitespace) {
+        Some((name, value)) => Ok(Some((name, value.trim()))),
+        None => Err(format!("missing value for `{}`", line)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(parse_line(""), Ok(None));
+        assert_eq!(parse_line("   "), Ok(None));
+        assert_eq!(parse_line("// comment"), Ok(None));
+        assert_eq!(parse_line("# comment"), Ok(None));
+        assert_eq!(parse_line("cl_camera_fov 100"), Ok(Some(("cl_camera_fov", "100"))));
+        assert_eq!(parse_line("  +d_draw   false  "), Ok(Some(("d_draw", "false"))));
+        assert_eq!(parse_line("exec other.cfg"), Ok(Some(("exec", "other.cfg"))));
+        assert!(parse_line("d_draw").is_err());
+    }
+
+    #[test]
+    fn test_parse_pos() {
+        assert_eq!(parse_pos("1 -2.5  3"), Some([1.0, -2.5, 3.0]));
+        assert_eq!(parse_pos("1 2"), None);
+        assert_eq!(parse_pos("1 2 3 4"), None);
+        assert_eq!(parse_pos("1 x 3"), None);
+    }
+
+    #[test]
+    fn test_exec_missing_file() {
+        let mut cvars = Cvars::default();
+        assert!(exec(&mut cvars, "this/file/does/not/exist.cfg").is_err());
+    }
+}
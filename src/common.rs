@@ -1,24 +1,59 @@
 //! Data and code shared between the client and server. Most gamelogic goes here.
 
+pub(crate) mod components;
+pub(crate) mod desync;
 pub(crate) mod entities;
+pub(crate) mod hot_reload;
+pub(crate) mod json;
+pub(crate) mod map;
 pub(crate) mod messages;
 pub(crate) mod net;
+pub(crate) mod palette;
+pub(crate) mod pickups;
+pub(crate) mod platforms;
+pub(crate) mod schedule;
+pub(crate) mod theme;
 pub(crate) mod trace;
 
-use std::fmt::{self, Debug, Display, Formatter};
+use std::{path::Path, rc::Rc};
 
-use fyrox::scene::collider::InteractionGroups;
+use fyrox::{core::futures::executor, scene::collider::InteractionGroups};
 use rand::{distributions::Uniform, Rng, SeedableRng};
 use rand_xoshiro::Xoshiro256PlusPlus;
-use serde::{Deserialize, Serialize};
 
 use crate::{
-    common::entities::{Cycle, Player, PlayerState, Projectile},
+    common::{
+        components::ComponentStore,
+        entities::{
+            Cheats, Cycle, Effect, GameEvent, Hit, Kill, MatchPhase, MatchPhaseExt, Player,
+            PlayerState, Projectile, VehicleKind, VehicleKindExt,
+        },
+        hot_reload::DataWatcher,
+        map::MapManifest,
+        schedule::{Data, Schedule, System},
+    },
     prelude::*,
 };
 
+pub(crate) use rustcycles_protocol::input::{Action, Axis, Deg, Input};
+
+const CYCLE_MODEL: &str = "data/rustcycle/rustcycle.fbx";
+
+/// Which side runs the gamelogic.
+///
+/// Both run the same movement and weapon code so clients predict what the server does
+/// but only the server decides the outcomes, e.g. what a hit does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Authority {
+    Server,
+    /// Outcomes come from the server's messages.
+    Client,
+}
+
 /// The state of the game - all data needed to run the gamelogic.
 pub(crate) struct GameState {
+    pub(crate) authority: Authority,
+
     /// This gamelogic frame's time in seconds.
     ///
     /// This does *not* have to run at the same speed as real world time.
@@ -41,32 +76,119 @@ pub(crate) struct GameState {
     pub range_uniform11: Uniform<f64>,
 
     pub(crate) scene_handle: Handle<Scene>,
+    pub(crate) map: MapManifest,
+    /// The instance of the map's model.
+    map_handle: Handle<Node>,
+    /// Bodies of the map's moving platforms, same order as `map.platforms`.
+    pub(crate) platform_handles: Vec<Handle<Node>>,
+    /// Models of the map's pickups, same order as `map.pickups`.
+    pub(crate) pickup_handles: Vec<Handle<Node>>,
+    /// Changed by the game mode on the server and replicated to clients.
+    pub(crate) phase: MatchPhase,
     cycle_model: Model,
     pub(crate) players: Pool<Player>,
     pub(crate) cycles: Pool<Cycle>,
     pub(crate) projectiles: Pool<Projectile>,
+    /// Gameplay data which doesn't need a field in `Player`, removed with the player.
+    pub(crate) player_components: ComponentStore<Player>,
+    /// Same for cycles.
+    pub(crate) cycle_components: ComponentStore<Cycle>,
+
+    /// Players who fired this frame, one entry per projectile, the server counts them for stats.
+    pub(crate) shots: Vec<Handle<Player>>,
+
+    /// Hits which happened this frame, the server uses them for assists.
+    pub(crate) hits: Vec<Hit>,
+
+    /// Kills which happened this frame, the server passes them to its hooks.
+    pub(crate) kills: Vec<Kill>,
+
+    /// Effects which happened this frame, the server sends them to clients.
+    pub(crate) effects: Vec<Effect>,
+
+    /// Changes clients need to know about, the server replicates them at the end of each tick.
+    /// Unlike the lists above, it's not cleared automatically.
+    pub(crate) events: Vec<GameEvent>,
+
+    /// See `d_hot_reload`.
+    data_watcher: Option<DataWatcher>,
+
+    /// Systems run by `tick_before_physics`, shared so they can run while `self` is borrowed.
+    schedule: Rc<Schedule<TickSystem>>,
+}
+
+type TickSystem = fn(&mut GameState, &Cvars, &mut Scene, f32);
+
+/// Everything `GameState::tick_before_physics` runs, the order comes from `after`.
+pub(crate) fn tick_systems() -> Vec<System<TickSystem>> {
+    use Data::*;
+
+    // Typed so the closures become fn pointers.
+    let systems: [System<TickSystem>; 4] = [
+        System {
+            name: "cycle cvars",
+            reads: &[Players, Cycles],
+            writes: &[Physics],
+            after: &[],
+            while_paused: true,
+            run: |gs, cvars, scene, _| gs.apply_cycle_cvars(cvars, scene),
+        },
+        System {
+            name: "move cycles",
+            reads: &[Players, Cycles],
+            writes: &[Physics],
+            after: &["cycle cvars"],
+            while_paused: false,
+            run: |gs, cvars, scene, dt| gs.move_cycles(cvars, scene, dt),
+        },
+        System {
+            name: "fire weapons",
+            reads: &[Players, Cycles, Physics],
+            writes: &[Projectiles],
+            after: &["move cycles"],
+            while_paused: false,
+            run: |gs, cvars, scene, _| gs.fire_weapons(cvars, scene),
+        },
+        System {
+            name: "fly projectiles",
+            reads: &[Cycles, Physics],
+            writes: &[Players, Projectiles, Events],
+            after: &["fire weapons"],
+            while_paused: false,
+            run: |gs, cvars, scene, dt| gs.fly_projectiles(cvars, scene, dt),
+        },
+    ];
+    systems.into()
+}
+
+/// What `GameState::hot_reload` replaced.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct HotReloaded {
+    pub(crate) map: bool,
+    pub(crate) cycle_model: bool,
 }
 
 impl GameState {
-    pub(crate) async fn new(cvars: &Cvars, engine: &mut Engine) -> Self {
+    pub(crate) async fn new(cvars: &Cvars, engine: &mut Engine, authority: Authority) -> Self {
         let mut scene = Scene::new();
 
-        engine
+        // LATER Report error if loading fails
+        let map = MapManifest::load(&cvars.g_map).unwrap();
+        let map_handle = engine
             .resource_manager
-            .request_model("data/arena/arena.rgs")
+            .request_model(&map.model)
             .await
             .unwrap()
             .instantiate(&mut scene);
+        let platform_handles = platforms::build(&mut scene, &map.platforms);
+        let pickup_handles = pickups::build(&mut scene, &map.pickups);
 
-        let cycle_model = engine
-            .resource_manager
-            .request_model("data/rustcycle/rustcycle.fbx")
-            .await
-            .unwrap();
+        let cycle_model = engine.resource_manager.request_model(CYCLE_MODEL).await.unwrap();
 
         let scene_handle = engine.scenes.add(scene);
 
         Self {
+            authority,
             game_time: 0.0,
             // We wanna avoid having to specialcase divisions by zero in the first frame.
             // It would usually be 0.0 / 0.0 anyway so now it's 0.0 / -1.0.
@@ -75,19 +197,159 @@ impl GameState {
             rng: Xoshiro256PlusPlus::seed_from_u64(cvars.d_seed),
             range_uniform11: Uniform::new_inclusive(-1.0, 1.0),
             scene_handle,
+            map,
+            map_handle,
+            platform_handles,
+            pickup_handles,
+            phase: if cvars.g_warmup {
+                MatchPhase::Warmup { deadline: None }
+            } else {
+                MatchPhase::live(cvars, 0.0)
+            },
             cycle_model,
             players: Pool::new(),
             cycles: Pool::new(),
             projectiles: Pool::new(),
+            player_components: ComponentStore::default(),
+            cycle_components: ComponentStore::default(),
+            shots: Vec::new(),
+            hits: Vec::new(),
+            kills: Vec::new(),
+            effects: Vec::new(),
+            events: Vec::new(),
+            data_watcher: None,
+            schedule: Rc::new(Schedule::new(tick_systems()).unwrap()),
         }
     }
 
+    /// Replace the map and cycle model when their files change, see `d_hot_reload`.
+    ///
+    /// A change anywhere in a model's directory counts since models use other files (e.g. textures).
+    /// LATER Reload the map's environment on the client.
+    pub(crate) fn hot_reload(&mut self, cvars: &Cvars, engine: &mut Engine) -> HotReloaded {
+        let mut reloaded = HotReloaded::default();
+        if !cvars.d_hot_reload {
+            self.data_watcher = None;
+            return reloaded;
+        }
+        let watcher = self.data_watcher.get_or_insert_with(|| DataWatcher::new("data"));
+        if self.game_time < watcher.next_scan {
+            return reloaded;
+        }
+        watcher.next_scan = self.game_time + cvars.d_hot_reload_interval;
+
+        let changed = watcher.scan();
+        let model_changed = |model: &str| {
+            let dir = Path::new(model).parent().unwrap_or_else(|| Path::new(model));
+            changed.iter().any(|path| path.starts_with(dir))
+        };
+        let manifest_path = MapManifest::path(&cvars.g_map);
+        reloaded.map = changed.iter().any(|path| path == Path::new(&manifest_path))
+            || model_changed(&self.map.model);
+        reloaded.cycle_model = model_changed(CYCLE_MODEL);
+        if !reloaded.map && !reloaded.cycle_model {
+            return reloaded;
+        }
+
+        // Fyrox caches resources so without this we'd get the old ones.
+        executor::block_on(engine.resource_manager.reload_resources());
+
+        if reloaded.map {
+            match MapManifest::load(&cvars.g_map) {
+                Ok(map) => self.map = map,
+                Err(err) => dbg_logf!("failed to reload map manifest: {}", err),
+            }
+            match executor::block_on(engine.resource_manager.request_model(&self.map.model)) {
+                Ok(model) => {
+                    let scene = &mut engine.scenes[self.scene_handle];
+                    scene.remove_node(self.map_handle);
+                    self.map_handle = model.instantiate(scene);
+                    for &handle in &self.platform_handles {
+                        scene.remove_node(handle);
+                    }
+                    self.platform_handles = platforms::build(scene, &self.map.platforms);
+                    for &handle in &self.pickup_handles {
+                        scene.remove_node(handle);
+                    }
+                    self.pickup_handles = pickups::build(scene, &self.map.pickups);
+
+                    // The map might have changed around cycles so drop them from spawn height.
+                    for cycle in &self.cycles {
+                        let body = scene.graph[cycle.body_handle].as_rigid_body_mut();
+                        let mut pos = **body.local_transform().position();
+                        pos.y = cvars.g_cycle_spawn_height;
+                        body.local_transform_mut().set_position(pos);
+                        body.set_lin_vel(Vec3::zeros());
+                    }
+                    dbg_logf!("reloaded map {}", self.map.model);
+                }
+                Err(err) => {
+                    dbg_logf!("failed to reload map {}: {:?}", self.map.model, err);
+                    reloaded.map = false;
+                }
+            }
+        }
+
+        if reloaded.cycle_model {
+            match executor::block_on(engine.resource_manager.request_model(CYCLE_MODEL)) {
+                Ok(model) => {
+                    self.cycle_model = model;
+                    let scene = &mut engine.scenes[self.scene_handle];
+                    for cycle in self.cycles.iter_mut() {
+                        scene.remove_node(cycle.model_handle);
+                        cycle.model_handle = self.cycle_model.instantiate(scene);
+                        scene.graph.link_nodes(cycle.model_handle, cycle.body_handle);
+                    }
+                    dbg_logf!("reloaded cycle model");
+                }
+                Err(err) => {
+                    dbg_logf!("failed to reload cycle model: {:?}", err);
+                    reloaded.cycle_model = false;
+                }
+            }
+        }
+
+        reloaded
+    }
+
     pub(crate) fn tick_before_physics(&mut self, cvars: &Cvars, engine: &mut Engine, dt: f32) {
         let scene = &mut engine.scenes[self.scene_handle];
 
+        self.shots.clear();
+        self.hits.clear();
+        self.kills.clear();
+        self.effects.clear();
+
         scene.graph.physics.integration_parameters.max_ccd_substeps =
             cvars.g_physics_max_ccd_substeps;
 
+        let paused = matches!(self.phase, MatchPhase::Paused { .. });
+        let schedule = Rc::clone(&self.schedule);
+        for system in schedule.systems() {
+            if paused && !system.while_paused {
+                continue;
+            }
+            let _timer = dbg_timer!(system.name);
+            (system.run)(self, cvars, scene, dt);
+        }
+        if paused {
+            self.hold_cycles(scene);
+        }
+
+        dbg_textf!("Projectiles: {}", self.projectiles.total_count());
+    }
+
+    /// Stop all cycles while the match is paused, physics still runs.
+    fn hold_cycles(&self, scene: &mut Scene) {
+        for cycle in &self.cycles {
+            let body = scene.graph[cycle.body_handle].as_rigid_body_mut();
+            body.set_lin_vel(Vec3::zeros());
+            body.set_ang_vel(Vec3::zeros());
+        }
+    }
+
+    /// Accelerate and turn cycles according to their players' input.
+    fn move_cycles(&self, cvars: &Cvars, scene: &mut Scene, dt: f32) {
         for cycle in &self.cycles {
             let player = &self.players[cycle.player_handle];
 
@@ -95,23 +357,29 @@ impl GameState {
             let input = player.input;
             let rot = UnitQuaternion::from_axis_angle(&UP_AXIS, input.yaw.to_radians());
             let body = scene.graph[cycle.body_handle].as_rigid_body_mut();
-            if playing {
-                let forward = rot * FORWARD;
+            if playing && player.cheats.noclip {
+                // Fly where the camera looks, same as observing.
+                let pitch_axis = rot * LEFT_AXIS;
+                let pitch = UnitQuaternion::from_axis_angle(&pitch_axis, input.pitch.to_radians());
+                let forward = pitch * rot * FORWARD;
                 let left = rot * LEFT;
-
-                let mut wheel_accel = Vec3::zeros();
-                if input.forward {
-                    wheel_accel += forward * dt * cvars.g_wheel_acceleration;
-                }
-                if input.backward {
-                    wheel_accel -= forward * dt * cvars.g_wheel_acceleration;
+                let mut vel = forward * input.axis(Axis::Move) - left * input.axis(Axis::Steer);
+                if input.pressed(Action::Up) {
+                    vel += UP;
                 }
-                if input.left {
-                    wheel_accel += left * dt * cvars.g_wheel_acceleration;
-                }
-                if input.right {
-                    wheel_accel -= left * dt * cvars.g_wheel_acceleration;
+                if input.pressed(Action::Down) {
+                    vel -= UP;
                 }
+                body.set_lin_vel(vel * cvars.g_noclip_speed);
+            } else if playing {
+                let forward = rot * FORWARD;
+                let left = rot * LEFT;
+
+                let wheel_accel = (forward * input.axis(Axis::Move)
+                    - left * input.axis(Axis::Steer))
+                    * dt
+                    * cvars.g_wheel_acceleration
+                    * cycle.vehicle.acceleration(cvars);
 
                 let mut lin_vel = body.lin_vel();
                 lin_vel += wheel_accel;
@@ -124,19 +392,34 @@ impl GameState {
             //  Use an impulse proportional to mouse movement instead?
             //  https://www.rapier.rs/docs/user_guides/rust/rigid_bodies/#forces-and-impulses
             body.local_transform_mut().set_rotation(rot);
+        }
+    }
 
-            if input.fire1 {
-                let _ = self.projectiles.spawn(Projectile {
-                    player_handle: cycle.player_handle,
-                    pos: **body.local_transform().position(),
-                    vel: dir * cvars.g_projectile_speed,
-                    time_fired: self.game_time,
-                });
+    fn fire_weapons(&mut self, cvars: &Cvars, scene: &Scene) {
+        for cycle in &self.cycles {
+            let input = self.players[cycle.player_handle].input;
+            if !input.pressed(Action::Fire1) {
+                continue;
             }
+            let rot = UnitQuaternion::from_axis_angle(&UP_AXIS, input.yaw.to_radians());
+            let body = &scene.graph[cycle.body_handle];
+            let _ = self.projectiles.spawn(Projectile {
+                player_handle: cycle.player_handle,
+                pos: **body.local_transform().position(),
+                vel: rot * FORWARD * cvars.g_projectile_speed,
+                time_fired: self.game_time,
+            });
+            // Counted here, the projectile might be gone before anything else sees it.
+            self.shots.push(cycle.player_handle);
         }
+    }
 
-        // LATER Split into functions
+    /// Move projectiles and find what they hit.
+    ///
+    /// Clients only predict where projectiles go, what a hit does comes from the server.
+    fn fly_projectiles(&mut self, cvars: &Cvars, scene: &Scene, dt: f32) {
         // LATER iter_handles()?
+        let _cat = dbg_category!(Projectiles);
         let mut free = None;
         'outer: for (proj_handle, proj) in self.projectiles.pair_iter_mut() {
             if proj.time_fired + cvars.g_projectile_lifetime < self.game_time {
@@ -147,14 +430,46 @@ impl GameState {
             let step = proj.vel * dt;
 
             let hits = trace_line(scene, proj.pos, step, Default::default());
+            // The shooter might have started observing since.
+            let own_collider_handle = self.players[proj.player_handle]
+                .cycle_handle
+                .map(|cycle_handle| self.cycles[cycle_handle].collider_handle);
             for hit in hits {
-                let cycle_handle = self.players[proj.player_handle].cycle_handle.unwrap();
-                let cycle_collider_handle = self.cycles[cycle_handle].collider_handle;
-                if hit.collider == cycle_collider_handle {
+                if Some(hit.collider) == own_collider_handle {
                     // LATER Let the player shoot himself - enable self collision after the projectile clears the player's hitbox.
                     continue;
                 }
 
+                let victim = self.cycles.iter().find(|c| c.collider_handle == hit.collider);
+                let god = victim.map_or(false, |v| self.players[v.player_handle].cheats.god);
+                if let Some(victim) = victim.filter(|_| !god) {
+                    if self.authority == Authority::Server {
+                        self.hits.push(Hit {
+                            attacker: proj.player_handle,
+                            victim: victim.player_handle,
+                        });
+                        // LATER Absorb a part of the damage once there's health.
+                        let armor = &mut self.players[victim.player_handle].armor;
+                        if *armor > 0 {
+                            *armor = armor.saturating_sub(cvars.g_armor_per_hit);
+                        } else {
+                            self.kills.push(Kill {
+                                killer: proj.player_handle,
+                                victim: victim.player_handle,
+                            });
+                        }
+                    }
+                    self.effects.push(Effect::Explosion {
+                        pos: hit.position.coords,
+                        victim_index: Some(victim.player_handle.index()),
+                    });
+                } else {
+                    self.effects.push(Effect::Impact {
+                        pos: hit.position.coords,
+                        normal: hit.normal,
+                    });
+                }
+
                 // Free projectile
                 dbg_cross!(hit.position.coords, 0.5);
                 free = Some(proj_handle);
@@ -169,39 +484,118 @@ impl GameState {
         if let Some(handle) = free {
             self.projectiles.free(handle);
         }
+    }
 
-        dbg_textf!("Projectiles: {}", self.projectiles.total_count());
+    /// How many playing players are ready and how many are playing.
+    pub(crate) fn ready_count(&self) -> (usize, usize) {
+        let mut ready = 0;
+        let mut playing = 0;
+        for player in &self.players {
+            if player.ps == PlayerState::Playing {
+                playing += 1;
+                if player.ready {
+                    ready += 1;
+                }
+            }
+        }
+        (ready, playing)
+    }
+
+    /// Change the player's cheats and update their cycle's physics to match.
+    pub(crate) fn set_cheats(
+        &mut self,
+        scene: &mut Scene,
+        player_handle: Handle<Player>,
+        cheats: Cheats,
+    ) {
+        let player = &mut self.players[player_handle];
+        player.cheats = cheats;
+        let Some(cycle_handle) = player.cycle_handle else {
+            return;
+        };
+        let cycle = &self.cycles[cycle_handle];
+
+        let filter = if cheats.noclip { IG_NONE } else { IG_ALL };
+        let collider = scene.graph[cycle.collider_handle].as_collider_mut();
+        collider.set_collision_groups(InteractionGroups::new(IG_ENTITIES, filter));
+        // Gravity is turned off in `apply_cycle_cvars`.
     }
 
+    /// Physics constants are cvars so they can be changed any time.
+    fn apply_cycle_cvars(&self, cvars: &Cvars, scene: &mut Scene) {
+        for cycle in &self.cycles {
+            let noclip = self.players[cycle.player_handle].cheats.noclip;
+            let body = scene.graph[cycle.body_handle].as_rigid_body_mut();
+            let gravity = cvars.g_cycle_gravity_scale;
+            body.set_gravity_scale(if noclip { 0.0 } else { gravity });
+            body.set_lin_damping(cvars.g_cycle_linear_damping);
+            body.set_mass(cvars.g_cycle_mass);
+
+            let collider = scene.graph[cycle.collider_handle].as_collider_mut();
+            collider.set_friction(cvars.g_cycle_friction);
+            collider.set_restitution(cvars.g_cycle_restitution);
+        }
+    }
+
+    /// Where new cycles appear, slightly randomized.
+    pub(crate) fn spawn_pos(&mut self, cvars: &Cvars) -> Vec3 {
+        let left = f64::from(cvars.g_cycle_spawn_spread) * self.rng.sample(self.range_uniform11);
+        v!(left, cvars.g_cycle_spawn_height, 0)
+    }
+
+    /// Move the player's cycle and stop it.
+    pub(crate) fn teleport(&mut self, scene: &mut Scene, player_handle: Handle<Player>, pos: Vec3) {
+        if let Some(cycle_handle) = self.players[player_handle].cycle_handle {
+            let body = scene.graph[self.cycles[cycle_handle].body_handle].as_rigid_body_mut();
+            body.local_transform_mut().set_position(pos);
+            body.set_lin_vel(Vec3::zeros());
+        }
+    }
+
+    /// Kill the player's cycle, e.g. for pits.
+    /// The server respawns it when processing the kill.
+    ///
+    /// Counts as a suicide - LATER Credit whoever pushed them.
+    pub(crate) fn kill_by_map(&mut self, player_handle: Handle<Player>, pos: Vec3) {
+        self.kills.push(Kill {
+            killer: player_handle,
+            victim: player_handle,
+        });
+        self.effects.push(Effect::Explosion {
+            pos,
+            victim_index: Some(player_handle.index()),
+        });
+    }
+
+    /// Clients remove the player's cycle along with the player so there's no event for it.
     pub(crate) fn free_player(&mut self, scene: &mut Scene, player_handle: Handle<Player>) {
-        let player = self.players.free(player_handle);
-        if let Some(handle) = player.cycle_handle {
-            let cycle = self.cycles.free(handle);
-            scene.remove_node(cycle.body_handle);
+        if let Some(cycle_handle) = self.players[player_handle].cycle_handle {
+            self.remove_cycle(scene, cycle_handle);
         }
+        self.players.free(player_handle);
+        self.player_components.remove_entity(player_handle);
     }
 
     pub(crate) fn spawn_cycle(
         &mut self,
+        cvars: &Cvars,
         scene: &mut Scene,
         player_handle: Handle<Player>,
-        cycle_index: Option<u32>,
+        vehicle: VehicleKind,
+        cycle_handle: Option<Handle<Cycle>>,
     ) -> Handle<Cycle> {
-        let node_handle = self.cycle_model.instantiate(scene);
+        let model_handle = self.cycle_model.instantiate(scene);
         let collider_handle = ColliderBuilder::new(BaseBuilder::new())
             // Size manually copied from the result of rusty-editor's Fit Collider
             // LATER Remove rustcycle.rgs?
             .with_shape(ColliderShape::cuboid(0.125, 0.271, 0.271))
             .with_collision_groups(InteractionGroups::new(IG_ENTITIES, IG_ALL))
             .build(&mut scene.graph);
-        // Slightly randomize spawn pos just to use the RNG
-        let left = 3.0 * self.rng.sample(self.range_uniform11);
+        let pos = self.spawn_pos(cvars);
         let body_handle = RigidBodyBuilder::new(
             BaseBuilder::new()
-                .with_local_transform(
-                    TransformBuilder::new().with_local_position(v!(left, 5, 0)).build(),
-                )
-                .with_children(&[node_handle, collider_handle]),
+                .with_local_transform(TransformBuilder::new().with_local_position(pos).build())
+                .with_children(&[model_handle, collider_handle]),
         )
         .with_ccd_enabled(true)
         .with_locked_rotations(true)
@@ -210,20 +604,48 @@ impl GameState {
 
         let cycle = Cycle {
             player_handle,
+            vehicle,
             body_handle,
             collider_handle,
+            model_handle,
         };
-        let cycle_handle = if let Some(index) = cycle_index {
-            self.cycles.spawn_at(index, cycle).unwrap()
+        // Clients use the same handle as the server.
+        let cycle_handle = if let Some(cycle_handle) = cycle_handle {
+            self.cycles.spawn_at_handle(cycle_handle, cycle).unwrap()
         } else {
             self.cycles.spawn(cycle)
         };
 
         self.players[player_handle].cycle_handle = Some(cycle_handle);
+        // Players keep cheats while observing, the new cycle needs to match.
+        let cheats = self.players[player_handle].cheats;
+        self.set_cheats(scene, player_handle, cheats);
+        if self.authority == Authority::Server {
+            let armor = &mut self.players[player_handle].armor;
+            *armor = (*armor).max(vehicle.armor(cvars));
+        }
 
+        self.events.push(GameEvent::SpawnCycle {
+            player_handle,
+            cycle_handle,
+            vehicle,
+        });
         cycle_handle
     }
 
+    /// Remove the cycle, e.g. when the player starts observing.
+    pub(crate) fn despawn_cycle(&mut self, scene: &mut Scene, cycle_handle: Handle<Cycle>) {
+        self.remove_cycle(scene, cycle_handle);
+        self.events.push(GameEvent::DespawnCycle { cycle_handle });
+    }
+
+    fn remove_cycle(&mut self, scene: &mut Scene, cycle_handle: Handle<Cycle>) {
+        let cycle = self.cycles.free(cycle_handle);
+        self.cycle_components.remove_entity(cycle_handle);
+        self.players[cycle.player_handle].cycle_handle = None;
+        scene.remove_node(cycle.body_handle);
+    }
+
     /// Draw arrows in a different orientation every frame.
     ///
     /// This helps:
@@ -250,177 +672,19 @@ impl GameState {
     }
 }
 
-// LATER Would be nice to send as little as possible since this is networked.
-#[derive(Clone, Copy, Default, Serialize, Deserialize)]
-pub(crate) struct Input {
-    /// LATER This should probably never be networked, since cl and sv have different time.
-    pub(crate) real_time: f32,
-
-    /// LATER specify whether this is the current or prev or next frame,
-    /// it might get messy depending on when input vs timekeeping is done
-    /// and when it's sent.
-    pub(crate) game_time: f32,
-
-    /// Counterclockwise: 0 is directly forward, negative is left, positive right.
-    ///
-    /// Nalgebra rotations follow the right hand rule,
-    /// thumb points in +Y (up), the curl of fingers shows direction.
-    ///
-    /// Some things like shooting need the angle at the exact time
-    /// so we send yaw and pitch with each input, not just once per frame.
-    pub(crate) yaw: Deg,
-    pub(crate) yaw_speed: Deg,
-    pub(crate) pitch: Deg,
-    pub(crate) pitch_speed: Deg,
-
-    pub(crate) fire1: bool,
-    pub(crate) fire2: bool,
-    pub(crate) marker1: bool,
-    pub(crate) marker2: bool,
-    pub(crate) zoom: bool,
-    pub(crate) forward: bool,
-    pub(crate) backward: bool,
-    pub(crate) left: bool,
-    pub(crate) right: bool,
-    pub(crate) up: bool,
-    pub(crate) down: bool,
-    pub(crate) prev_weapon: bool,
-    pub(crate) next_weapon: bool,
-    pub(crate) reload: bool,
-    pub(crate) flag: bool,
-    pub(crate) grenade: bool,
-    pub(crate) map: bool,
-    pub(crate) score: bool,
-    pub(crate) chat: bool,
-    pub(crate) pause: bool,
-    pub(crate) screenshot: bool,
-    // ^ when adding fields, also add them to other impls and functions below
-}
-
-// LATER ClientInput? - zoom, map, chat, score, pause, screenshot, console, ...
-// These don't need to be networked
-
-impl Input {
-    pub(crate) fn release_all_keys(&mut self) {
-        self.fire1 = false;
-        self.fire2 = false;
-        self.marker1 = false;
-        self.marker2 = false;
-        self.zoom = false;
-        self.forward = false;
-        self.backward = false;
-        self.left = false;
-        self.right = false;
-        self.up = false;
-        self.down = false;
-        self.prev_weapon = false;
-        self.next_weapon = false;
-        self.reload = false;
-        self.flag = false;
-        self.grenade = false;
-        self.map = false;
-        self.score = false;
-        self.chat = false;
-        self.pause = false;
-        self.screenshot = false;
-    }
-}
-
-impl Debug for Input {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        // Only output the pressed buttons so it's more readable.
-        write!(
-            f,
-            "Input {{ time {} yaw {} {}/s pitch {} {}/s ",
-            self.game_time, self.yaw, self.yaw_speed, self.pitch, self.pitch_speed,
-        )?;
-        if self.fire1 {
-            write!(f, "fire1 ")?;
-        }
-        if self.fire2 {
-            write!(f, "fire2 ")?;
-        }
-        if self.marker1 {
-            write!(f, "marker1 ")?;
-        }
-        if self.marker2 {
-            write!(f, "marker2 ")?;
-        }
-        if self.zoom {
-            write!(f, "zoom ")?;
-        }
-        if self.forward {
-            write!(f, "forward ")?;
-        }
-        if self.backward {
-            write!(f, "backward ")?;
-        }
-        if self.left {
-            write!(f, "left ")?;
-        }
-        if self.right {
-            write!(f, "right ")?;
-        }
-        if self.up {
-            write!(f, "up ")?;
-        }
-        if self.down {
-            write!(f, "down ")?;
-        }
-        if self.prev_weapon {
-            write!(f, "prev_weapon ")?;
-        }
-        if self.next_weapon {
-            write!(f, "next_weapon ")?;
-        }
-        if self.reload {
-            write!(f, "reload ")?;
-        }
-        if self.flag {
-            write!(f, "flag ")?;
-        }
-        if self.grenade {
-            write!(f, "grenade ")?;
-        }
-        if self.map {
-            write!(f, "map ")?;
-        }
-        if self.score {
-            write!(f, "score ")?;
-        }
-        if self.chat {
-            write!(f, "chat ")?;
-        }
-        if self.pause {
-            write!(f, "pause ")?;
-        }
-        if self.screenshot {
-            write!(f, "screenshot ")?;
-        }
-        write!(f, "}}")?;
-        Ok(())
-    }
-}
-
-// Why not use an existing crate like https://crates.io/crates/angle?
-// - Not worth adding a dep for such a simple thing
-// - It shows signs of lack of attention to detail
-//   (bad readme, missing doc comments, inconsistent formatting)
-//   on the surface so it probably contains deeper issues as well.
-// - It tries to be smart and implements questionable ops such as comparisons.
-// This reasoning might change if this struct gets larger but it'll probably mean
-// only taking inspiration and bits of code from the angle crate, not adding it as a dep.
-#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
-pub(crate) struct Deg(pub(crate) f32);
-
-impl Deg {
-    pub(crate) fn to_radians(self) -> f32 {
-        self.0.to_radians()
-    }
+/// Gamelogic on top of the networked `Input`.
+pub(crate) trait InputExt {
+    /// Random driving and shooting for bots.
+    fn randomize(&mut self, rng: &mut impl Rng);
 }
 
-impl Display for Deg {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}°", self.0)
+impl InputExt for Input {
+    fn randomize(&mut self, rng: &mut impl Rng) {
+        let forward = rng.gen_bool(0.8);
+        let left = rng.gen_bool(0.2);
+        let right = rng.gen_bool(0.2);
+        self.set_axis(Axis::Move, if forward { 1.0 } else { 0.0 });
+        self.set_axis(Axis::Steer, f32::from(i8::from(right) - i8::from(left)));
+        self.set_pressed(Action::Fire1, rng.gen_bool(0.1));
     }
 }
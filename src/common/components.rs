@@ -0,0 +1,167 @@
+//! Gameplay data attached to players and cycles without adding fields to them.
+//!
+//! `GameState` has one `ComponentStore` per kind of entity, each holds a `Components` map
+//! for every component type used so far. Components are removed with their entity
+//! and a reused pool slot doesn't match old components because handles have a generation.
+//!
+//! LATER Replicate components which are `Serialize` without adding a message for each.
+
+// Not every method has a user yet.
+#![allow(dead_code)]
+
+use std::any::{Any, TypeId};
+
+use fxhash::FxHashMap;
+
+use crate::prelude::*;
+
+/// One component type for one kind of entity.
+#[derive(Debug, Clone)]
+pub(crate) struct Components<E, T> {
+    items: FxHashMap<Handle<E>, T>,
+}
+
+impl<E, T> Components<E, T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            items: FxHashMap::default(),
+        }
+    }
+
+    /// Returns the previous value.
+    pub(crate) fn insert(&mut self, handle: Handle<E>, value: T) -> Option<T> {
+        self.items.insert(handle, value)
+    }
+
+    pub(crate) fn get(&self, handle: Handle<E>) -> Option<&T> {
+        self.items.get(&handle)
+    }
+
+    pub(crate) fn get_mut(&mut self, handle: Handle<E>) -> Option<&mut T> {
+        self.items.get_mut(&handle)
+    }
+
+    pub(crate) fn remove(&mut self, handle: Handle<E>) -> Option<T> {
+        self.items.remove(&handle)
+    }
+
+    /// In no particular order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Handle<E>, &T)> {
+        self.items.iter().map(|(&handle, value)| (handle, value))
+    }
+}
+
+impl<E, T> Default for Components<E, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lets `ComponentStore` remove an entity from maps without knowing their types.
+trait AnyComponents<E> {
+    fn remove_entity(&mut self, handle: Handle<E>);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<E: 'static, T: 'static> AnyComponents<E> for Components<E, T> {
+    fn remove_entity(&mut self, handle: Handle<E>) {
+        self.remove(handle);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// All component types of one kind of entity, e.g. `ComponentStore<Player>`.
+pub(crate) struct ComponentStore<E> {
+    maps: FxHashMap<TypeId, Box<dyn AnyComponents<E>>>,
+}
+
+impl<E> Default for ComponentStore<E> {
+    fn default() -> Self {
+        Self {
+            maps: FxHashMap::default(),
+        }
+    }
+}
+
+impl<E: 'static> ComponentStore<E> {
+    /// `None` if no component of this type was ever inserted.
+    pub(crate) fn of<T: 'static>(&self) -> Option<&Components<E, T>> {
+        let map = self.maps.get(&TypeId::of::<T>())?;
+        map.as_any().downcast_ref()
+    }
+
+    /// Created on first use.
+    pub(crate) fn of_mut<T: 'static>(&mut self) -> &mut Components<E, T> {
+        self.maps
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Components::<E, T>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+
+    pub(crate) fn get<T: 'static>(&self, handle: Handle<E>) -> Option<&T> {
+        self.of::<T>()?.get(handle)
+    }
+
+    pub(crate) fn get_mut<T: 'static>(&mut self, handle: Handle<E>) -> Option<&mut T> {
+        self.of_mut::<T>().get_mut(handle)
+    }
+
+    /// Returns the previous value.
+    pub(crate) fn insert<T: 'static>(&mut self, handle: Handle<E>, value: T) -> Option<T> {
+        self.of_mut().insert(handle, value)
+    }
+
+    pub(crate) fn remove<T: 'static>(&mut self, handle: Handle<E>) -> Option<T> {
+        self.of_mut().remove(handle)
+    }
+
+    /// The entity was freed, drop all its components.
+    pub(crate) fn remove_entity(&mut self, handle: Handle<E>) {
+        for map in self.maps.values_mut() {
+            map.remove_entity(handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+
+    #[derive(Debug, PartialEq)]
+    struct Ammo(u32);
+
+    #[test]
+    fn test_component_store() {
+        let mut store = ComponentStore::<()>::default();
+        let a = Handle::new(1, 1);
+        let b = Handle::new(2, 1);
+        let a_reused = Handle::new(1, 2);
+
+        assert!(store.of::<Health>().is_none());
+        assert_eq!(store.insert(a, Health(100)), None);
+        store.insert(a, Ammo(5));
+        store.insert(b, Health(50));
+        store.get_mut::<Health>(b).unwrap().0 -= 10;
+        assert_eq!(store.get(b), Some(&Health(40)));
+        assert_eq!(store.get::<Ammo>(a_reused), None);
+
+        store.remove_entity(a);
+        assert_eq!(store.get::<Health>(a), None);
+        assert_eq!(store.get::<Ammo>(a), None);
+        assert_eq!(store.of::<Health>().unwrap().iter().count(), 1);
+        assert_eq!(store.remove(b), Some(Health(40)));
+    }
+}
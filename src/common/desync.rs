@@ -0,0 +1,125 @@
+//! Detecting when the client's predicted state diverges from the server's.
+//!
+//! When `d_desync_check` is enabled, the client periodically sends a hash of its state
+//! right after applying an update, together with the server tick the update was from.
+//! The server replies with its own state at that tick and the client logs the first field
+//! that differs.
+
+use std::{
+    collections::VecDeque,
+    hash::{Hash, Hasher},
+};
+
+use fxhash::FxHasher64;
+
+use crate::{
+    common::{entities::PlayerState, GameState},
+    prelude::*,
+};
+
+pub(crate) use rustcycles_protocol::messages::StateSummary;
+
+/// How many ticks the server remembers, checks for older ones are ignored.
+const HISTORY_TICKS: usize = 120;
+
+/// The server's states during the last `HISTORY_TICKS` ticks,
+/// kept only while somebody is checking.
+#[derive(Debug, Default)]
+pub(crate) struct DesyncHistory {
+    /// The server's `frame_number` and state at the end of that tick, oldest first.
+    states: VecDeque<(u32, StateSummary)>,
+}
+
+impl DesyncHistory {
+    pub(crate) fn record(&mut self, frame_number: u32, state: StateSummary) {
+        if self.states.len() >= HISTORY_TICKS {
+            self.states.pop_front();
+        }
+        self.states.push_back((frame_number, state));
+    }
+
+    /// `None` if the tick is too old or hasn't happened yet.
+    pub(crate) fn get(&self, frame_number: u32) -> Option<&StateSummary> {
+        self.states
+            .iter()
+            .find(|(recorded, _)| *recorded == frame_number)
+            .map(|(_, state)| state)
+    }
+}
+
+/// Building and hashing `StateSummary`s from the game state.
+pub(crate) trait StateSummaryExt {
+    fn new(cvars: &Cvars, gs: &GameState, scene: &Scene) -> Self;
+
+    fn hash(&self) -> u64;
+}
+
+impl StateSummaryExt for StateSummary {
+    fn new(cvars: &Cvars, gs: &GameState, scene: &Scene) -> Self {
+        let precision = cvars.d_desync_check_precision;
+        let quantize = |v: Vec3| v.iter().map(|&x| (x / precision).round() as i64).collect();
+
+        let mut fields = Vec::new();
+        for (player_handle, player) in gs.players.pair_iter() {
+            let values = match player.ps {
+                PlayerState::Observing => vec![0],
+                PlayerState::Spectating { spectatee_handle } => {
+                    vec![1, spectatee_handle.index() as i64]
+                }
+                PlayerState::Playing => vec![2],
+            };
+            fields.push(StateField {
+                name: format!("player {} state", player_handle.index()),
+                values,
+            });
+        }
+        for (cycle_handle, cycle) in gs.cycles.pair_iter() {
+            let body = scene.graph[cycle.body_handle].as_rigid_body();
+            let rot = body.local_transform().rotation().scaled_axis();
+            let index = cycle_handle.index();
+            fields.push(StateField {
+                name: format!("cycle {} pos", index),
+                values: quantize(**body.local_transform().position()),
+            });
+            fields.push(StateField {
+                name: format!("cycle {} rot", index),
+                values: quantize(rot),
+            });
+            fields.push(StateField {
+                name: format!("cycle {} vel", index),
+                values: quantize(body.lin_vel()),
+            });
+        }
+        for (proj_handle, proj) in gs.projectiles.pair_iter() {
+            fields.push(StateField {
+                name: format!("projectile {} pos", proj_handle.index()),
+                values: quantize(proj.pos),
+            });
+        }
+
+        Self { fields }
+    }
+
+    fn hash(&self) -> u64 {
+        let mut hasher = FxHasher64::default();
+        self.fields.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let mut history = DesyncHistory::default();
+        for frame_number in 0..=HISTORY_TICKS as u32 {
+            history.record(frame_number, StateSummary { fields: Vec::new() });
+        }
+        assert!(history.get(0).is_none());
+        assert!(history.get(1).is_some());
+        assert!(history.get(HISTORY_TICKS as u32).is_some());
+        assert!(history.get(HISTORY_TICKS as u32 + 1).is_none());
+    }
+}
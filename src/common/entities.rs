@@ -8,14 +8,46 @@
 //! This is not a violation of the ECS pattern,
 //! because they don't modify game state - they're not behavior.
 
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
 use crate::{common::Input, prelude::*};
 
+pub(crate) use rustcycles_protocol::entities::{
+    Cheats, Effect, Emote, Look, MatchPhase, OvertimeRule, VehicleKind, SKINS,
+};
+
+/// Names of teams for `team`, the index is sent over the network, see `g_teams`.
+pub(crate) const TEAM_NAMES: &[&str] = &[
+    "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel",
+];
+
 /// A client connected to a server. Can be observing, spectating or playing.
 #[derive(Debug)]
 pub(crate) struct Player {
     pub(crate) input: Input,
     pub(crate) ps: PlayerState,
     pub(crate) cycle_handle: Option<Handle<Cycle>>,
+    pub(crate) look: Look,
+    /// Wants the match to start, only matters during warmup.
+    pub(crate) ready: bool,
+    /// Index into `TEAM_NAMES`, `None` in free for all.
+    pub(crate) team: Option<u8>,
+    /// Kills this round, kept by the game mode.
+    pub(crate) score: u32,
+    /// Kills this round where the player hit the victim shortly before someone else killed them.
+    pub(crate) assists: u32,
+    /// Round trip time in milliseconds as measured by the server.
+    pub(crate) ping: Option<u16>,
+    pub(crate) cheats: Cheats,
+    /// Taken instead of dying when hit, see `g_armor_per_hit`.
+    pub(crate) armor: u32,
+    /// Picked up a cloak, the server keeps track of when it runs out.
+    pub(crate) cloaked: bool,
+    /// Identifies the player across sessions, see `client::mutes`. Chosen by the client, not verified.
+    pub(crate) fingerprint: Option<u64>,
+    /// What the player's next cycle will be, see `cl_vehicle`.
+    pub(crate) vehicle: VehicleKind,
 }
 
 impl Player {
@@ -24,6 +56,17 @@ impl Player {
             input: Input::default(),
             ps: PlayerState::Observing,
             cycle_handle,
+            look: Look::default(),
+            ready: false,
+            team: None,
+            score: 0,
+            assists: 0,
+            ping: None,
+            cheats: Cheats::default(),
+            armor: 0,
+            cloaked: false,
+            fingerprint: None,
+            vehicle: VehicleKind::Standard,
         }
     }
 }
@@ -40,11 +83,132 @@ pub(crate) enum PlayerState {
     Playing,
 }
 
+/// Behavior of `MatchPhase` which depends on the rest of the game.
+pub(crate) trait MatchPhaseExt {
+    /// A new round which ends `g_time_limit` from now.
+    fn live(cvars: &Cvars, game_time: f32) -> Self;
+}
+
+impl MatchPhaseExt for MatchPhase {
+    fn live(cvars: &Cvars, game_time: f32) -> Self {
+        let end = (cvars.g_time_limit > 0.0).then_some(game_time + cvars.g_time_limit);
+        Self::Live { end }
+    }
+}
+
+/// How dead players get a new cycle, see `g_respawn`.
+///
+/// Pub because it's used in cvars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum RespawnMode {
+    /// Right after dying.
+    Instant,
+    /// `g_respawn_time` seconds after dying.
+    Timed,
+    /// Everyone who died since the previous wave spawns together,
+    /// waves come every `g_respawn_wave_time` seconds.
+    Wave,
+}
+
+/// Behavior of `VehicleKind` which depends on cvars.
+pub(crate) trait VehicleKindExt {
+    /// Multiplies `g_wheel_acceleration`.
+    fn acceleration(self, cvars: &Cvars) -> f32;
+
+    /// Armor the player gets when the vehicle spawns.
+    fn armor(self, cvars: &Cvars) -> u32;
+}
+
+impl VehicleKindExt for VehicleKind {
+    fn acceleration(self, cvars: &Cvars) -> f32 {
+        match self {
+            VehicleKind::Standard => 1.0,
+            VehicleKind::Fast => cvars.g_vehicle_fast_acceleration,
+            VehicleKind::Armored => cvars.g_vehicle_armored_acceleration,
+        }
+    }
+
+    fn armor(self, cvars: &Cvars) -> u32 {
+        match self {
+            VehicleKind::Armored => cvars.g_vehicle_armored_armor,
+            VehicleKind::Standard | VehicleKind::Fast => 0,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct Cycle {
     pub(crate) player_handle: Handle<Player>,
+    pub(crate) vehicle: VehicleKind,
     pub(crate) body_handle: Handle<Node>,
     pub(crate) collider_handle: Handle<Node>,
+    /// The instance of the cycle's model, a child of the body.
+    pub(crate) model_handle: Handle<Node>,
+}
+
+/// A player's cycle was hit by another player's projectile.
+///
+/// The server replaces the victim's cycle with a new one at a spawn point.
+///
+/// LATER There's no health yet so any hit counts as a kill (unless the victim has armor).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Kill {
+    pub(crate) killer: Handle<Player>,
+    pub(crate) victim: Handle<Player>,
+}
+
+/// A player's cycle was hit by another player's projectile, whether or not it was a kill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Hit {
+    pub(crate) attacker: Handle<Player>,
+    pub(crate) victim: Handle<Player>,
+}
+
+/// A change to the game state which clients need to be told about.
+///
+/// Gamelogic only records these, the server turns them into messages in one place
+/// so the rules don't depend on networking. Clients get the same changes
+/// from the server's messages so they drop their own events.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum GameEvent {
+    /// The player is now playing, after their cycle's `SpawnCycle` if they got one.
+    Join {
+        player_handle: Handle<Player>,
+    },
+    /// The player is now observing, after their cycle's `DespawnCycle`.
+    Observe {
+        player_handle: Handle<Player>,
+    },
+    SpawnCycle {
+        player_handle: Handle<Player>,
+        cycle_handle: Handle<Cycle>,
+        vehicle: VehicleKind,
+    },
+    DespawnCycle {
+        cycle_handle: Handle<Cycle>,
+    },
+    /// Before the victim's `DespawnCycle` so clients can still see where the cycle was.
+    Kill {
+        kill: Kill,
+        assisters: Vec<Handle<Player>>,
+    },
+    /// The player took one of the map's pickups, same order as `MapManifest::pickups`.
+    Pickup {
+        player_handle: Handle<Player>,
+        pickup_index: usize,
+    },
+    /// The player's cycle was destroyed, they get a new one in this many seconds.
+    Respawn {
+        player_handle: Handle<Player>,
+        time_left: f32,
+    },
+    /// The player left the map's bounds and has this many seconds to come back
+    /// or `None` if they came back (or were killed).
+    OutOfBounds {
+        player_handle: Handle<Player>,
+        time_left: Option<f32>,
+    },
 }
 
 #[derive(Debug)]
@@ -54,3 +218,23 @@ pub(crate) struct Projectile {
     pub(crate) vel: Vec3,
     pub(crate) time_fired: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_look() {
+        assert_eq!(Look::parse_color("ff8000"), Some([255, 128, 0]));
+        assert_eq!(Look::parse_color("#00FF10"), Some([0, 255, 16]));
+        assert_eq!(Look::parse_color("ff80"), None);
+        assert_eq!(Look::parse_color("gg8000"), None);
+        assert_eq!(Look::parse_color("ff80€"), None);
+
+        let dark = Look {
+            color: [0, 10, 20],
+            skin: 200,
+        };
+        assert_eq!(dark.validated(), Look::default());
+    }
+}
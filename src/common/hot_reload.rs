@@ -0,0 +1,89 @@
+//! Noticing changes in `data/` so maps and models can be reloaded while the game runs - `d_hot_reload`.
+//!
+//! This just compares modification times every `d_hot_reload_interval` seconds.
+//! The data directory is small so it's cheap enough and doesn't need any OS-specific APIs.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use fxhash::FxHashMap;
+
+pub(crate) struct DataWatcher {
+    dir: PathBuf,
+    mtimes: FxHashMap<PathBuf, SystemTime>,
+    /// Game time of the next scan.
+    pub(crate) next_scan: f32,
+}
+
+impl DataWatcher {
+    /// Files which already exist don't count as changed.
+    pub(crate) fn new(dir: impl Into<PathBuf>) -> Self {
+        let mut watcher = Self {
+            dir: dir.into(),
+            mtimes: FxHashMap::default(),
+            next_scan: 0.0,
+        };
+        watcher.scan();
+        watcher
+    }
+
+    /// Files which were added or modified since the last scan, sorted.
+    pub(crate) fn scan(&mut self) -> Vec<PathBuf> {
+        let mut mtimes = FxHashMap::default();
+        visit(&self.dir, &mut mtimes);
+        let mut changed: Vec<_> = mtimes
+            .iter()
+            .filter(|&(path, mtime)| self.mtimes.get(path) != Some(mtime))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.sort();
+        self.mtimes = mtimes;
+        changed
+    }
+}
+
+fn visit(dir: &Path, mtimes: &mut FxHashMap<PathBuf, SystemTime>) {
+    // Files can disappear while we're looking, e.g. editors often save to a temporary file first.
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => visit(&path, mtimes),
+            Ok(metadata) => {
+                if let Ok(mtime) = metadata.modified() {
+                    mtimes.insert(path, mtime);
+                }
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        let dir = env::temp_dir().join(format!("rustcycles-hot-reload-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("maps")).unwrap();
+        fs::write(dir.join("maps/old.ron"), "()").unwrap();
+
+        let mut watcher = DataWatcher::new(&dir);
+        assert!(watcher.scan().is_empty());
+
+        fs::write(dir.join("maps/new.ron"), "()").unwrap();
+        assert_eq!(watcher.scan(), vec![dir.join("maps/new.ron")]);
+        assert!(watcher.scan().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
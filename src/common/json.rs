@@ -0,0 +1,33 @@
+//! Writing JSON for the few places which talk to other programs.
+//!
+//! LATER Use serde_json if we ever need to parse it.
+
+use std::fmt::Write;
+
+/// Quote and escape a string for JSON.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_string() {
+        assert_eq!(json_string("arena"), r#""arena""#);
+        assert_eq!(json_string("a \"b\"\\c\n\t"), r#""a \"b\"\\c\n\u0009""#);
+    }
+}
@@ -0,0 +1,314 @@
+//! Map manifests - `data/maps/<name>.ron`.
+//!
+//! A manifest says which model to load and sets up the environment
+//! (skybox, lighting, fog) so maps can look different without code changes.
+//! It can also add triggers such as jump pads, moving platforms and pickups
+//! which don't need to be part of the model and limit where cycles can go.
+//!
+//! LATER Clients should load the map from `Init::map` instead of their own `g_map`.
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct MapManifest {
+    /// Path to the map's scene or model.
+    pub(crate) model: String,
+    #[serde(default)]
+    pub(crate) environment: Environment,
+    #[serde(default)]
+    pub(crate) triggers: Vec<Trigger>,
+    #[serde(default)]
+    pub(crate) platforms: Vec<Platform>,
+    #[serde(default)]
+    pub(crate) pickups: Vec<Pickup>,
+    /// `None` means cycles can go anywhere, e.g. for closed maps.
+    #[serde(default)]
+    pub(crate) bounds: Option<Bounds>,
+}
+
+impl MapManifest {
+    pub(crate) fn path(name: &str) -> String {
+        format!("data/maps/{}.ron", name)
+    }
+
+    pub(crate) fn load(name: &str) -> Result<Self, String> {
+        let path = Self::path(name);
+        let text = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path, err))?;
+        Self::parse(&text).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        ron::from_str(text).map_err(|err| err.to_string())
+    }
+}
+
+/// A box which does something to cycles inside it, checked by the server every tick.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct Trigger {
+    /// Opposite corners of the box, axis aligned.
+    pub(crate) min: [f32; 3],
+    pub(crate) max: [f32; 3],
+    pub(crate) kind: TriggerKind,
+}
+
+impl Trigger {
+    pub(crate) fn contains(&self, pos: Vec3) -> bool {
+        (0..3).all(|i| self.min[i] <= pos[i] && pos[i] <= self.max[i])
+    }
+
+    pub(crate) fn center(&self) -> Vec3 {
+        (Vec3::from(self.min) + Vec3::from(self.max)) / 2.0
+    }
+
+    pub(crate) fn half_extents(&self) -> Vec3 {
+        (Vec3::from(self.max) - Vec3::from(self.min)) / 2.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub(crate) enum TriggerKind {
+    /// Set the cycle's velocity, e.g. `(0.0, 15.0, 0.0)` to launch it straight up.
+    JumpPad { velocity: [f32; 3] },
+    /// Move the cycle keeping its velocity.
+    ///
+    /// For a pair, put two teleporters pointing to just outside each other
+    /// so cycles don't bounce between them.
+    Teleporter { destination: [f32; 3] },
+    /// Kills the cycle and puts it back at the spawn, e.g. for pits.
+    Kill,
+}
+
+/// A box which moves along a path and/or spins, e.g. elevators and rotating doors.
+///
+/// Moved by the server, cycles standing on top ride along.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct Platform {
+    pub(crate) half_extents: [f32; 3],
+    /// Positions of the center, the platform goes through them in a loop.
+    /// For an elevator, use two points and it'll go back and forth.
+    pub(crate) path: Vec<[f32; 3]>,
+    /// Seconds to get from one point to the next.
+    #[serde(default = "default_segment_time")]
+    pub(crate) segment_time: f32,
+    /// Degrees per second around the vertical axis, positive is counterclockwise when seen from above.
+    #[serde(default)]
+    pub(crate) spin: f32,
+}
+
+fn default_segment_time() -> f32 {
+    1.0
+}
+
+impl Platform {
+    /// Position and yaw in degrees at the given game time.
+    ///
+    /// Only depends on time so the platform is always where it should be
+    /// regardless of what happened before.
+    pub(crate) fn pose(&self, time: f32) -> (Vec3, f32) {
+        let yaw = (self.spin * time) % 360.0;
+        let pos = match self.path.len() {
+            0 => Vec3::zeros(),
+            1 => Vec3::from(self.path[0]),
+            _ if self.segment_time <= 0.0 => Vec3::from(self.path[0]),
+            len => {
+                let segments = time / self.segment_time;
+                let i = segments as usize % len;
+                let t = segments.fract();
+                let from = Vec3::from(self.path[i]);
+                let to = Vec3::from(self.path[(i + 1) % len]);
+                from.lerp(&to, t)
+            }
+        };
+        (pos, yaw)
+    }
+}
+
+/// An item cycles pick up by driving through it, taken by the server.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct Pickup {
+    pub(crate) pos: [f32; 3],
+    pub(crate) kind: PickupKind,
+    /// Seconds until it appears again after being taken.
+    #[serde(default = "default_respawn_time")]
+    pub(crate) respawn_time: f32,
+}
+
+fn default_respawn_time() -> f32 {
+    30.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub(crate) enum PickupKind {
+    /// Up to `g_armor_max`.
+    Armor { amount: u32 },
+    /// Makes the cycle almost invisible to enemies for this many seconds or until it fires.
+    /// The trail is still visible.
+    Cloak { duration: f32 },
+}
+
+/// The playable area so cycles can't fall off open maps forever.
+///
+/// Cycles outside the box get `g_out_of_bounds_time` seconds to come back,
+/// cycles further than `margin` outside are killed immediately.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub(crate) struct Bounds {
+    /// Opposite corners of the box, axis aligned.
+    pub(crate) min: [f32; 3],
+    pub(crate) max: [f32; 3],
+    pub(crate) margin: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BoundsCheck {
+    Inside,
+    /// Outside the box but within the margin.
+    Warning,
+    Outside,
+}
+
+impl Bounds {
+    pub(crate) fn check(&self, pos: Vec3) -> BoundsCheck {
+        let distance = (0..3)
+            .map(|i| (self.min[i] - pos[i]).max(pos[i] - self.max[i]))
+            .fold(0.0, f32::max);
+        if distance <= 0.0 {
+            BoundsCheck::Inside
+        } else if distance <= self.margin {
+            BoundsCheck::Warning
+        } else {
+            BoundsCheck::Outside
+        }
+    }
+}
+
+/// How the map looks, only used by the client.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct Environment {
+    pub(crate) skybox: Skybox,
+    /// RGB, `None` keeps the engine's default.
+    pub(crate) ambient_light: Option<[u8; 3]>,
+    pub(crate) sun: Option<Sun>,
+    pub(crate) fog: Option<Fog>,
+}
+
+/// Paths to textures, missing sides are left empty.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub(crate) struct Skybox {
+    pub(crate) front: Option<String>,
+    pub(crate) back: Option<String>,
+    pub(crate) left: Option<String>,
+    pub(crate) right: Option<String>,
+    pub(crate) top: Option<String>,
+    pub(crate) bottom: Option<String>,
+}
+
+/// A directional light.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct Sun {
+    /// Which way the light shines, doesn't have to be normalized.
+    pub(crate) direction: [f32; 3],
+    pub(crate) color: [u8; 3],
+    pub(crate) intensity: f32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub(crate) struct Fog {
+    pub(crate) color: [u8; 3],
+    /// Distance where fog starts.
+    pub(crate) start: f32,
+    /// Distance where fog hides everything.
+    pub(crate) end: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let arena = MapManifest::parse(include_str!("../../data/maps/arena.ron")).unwrap();
+        assert_eq!(arena.model, "data/arena/arena.rgs");
+
+        let text = r#"(
+            model: "m.rgs",
+            environment: (
+                ambient_light: Some((10, 20, 30)),
+                sun: Some((direction: (0.0, -1.0, 1.0), color: (255, 240, 200), intensity: 1.5)),
+                fog: Some((color: (50, 50, 60), start: 10.0, end: 100.0)),
+            ),
+        )"#;
+        let manifest = MapManifest::parse(text).unwrap();
+        assert_eq!(manifest.environment.ambient_light, Some([10, 20, 30]));
+        assert_eq!(manifest.environment.sun.unwrap().intensity, 1.5);
+        assert_eq!(manifest.environment.fog.unwrap().end, 100.0);
+        assert!(manifest.environment.skybox.top.is_none());
+
+        assert!(MapManifest::parse("(environment: ())").is_err());
+
+        let text = r#"(
+            model: "m.rgs",
+            triggers: [
+                (min: (-1.0, 0.0, -1.0), max: (1.0, 1.0, 1.0), kind: JumpPad(velocity: (0.0, 15.0, 0.0))),
+                (min: (0.0, -10.0, 0.0), max: (5.0, -5.0, 5.0), kind: Kill),
+            ],
+        )"#;
+        let manifest = MapManifest::parse(text).unwrap();
+        let pad = &manifest.triggers[0];
+        assert!(pad.contains(v!(0.5 1 -1)));
+        assert!(!pad.contains(v!(0.5 1.1 0)));
+        assert_eq!(pad.center(), v!(0 0.5 0));
+        assert_eq!(manifest.triggers[1].kind, TriggerKind::Kill);
+        assert!(manifest.platforms.is_empty());
+        assert!(manifest.bounds.is_none());
+        assert!(manifest.pickups.is_empty());
+
+        let text = r#"(
+            model: "m.rgs",
+            pickups: [
+                (pos: (0.0, 1.0, 0.0), kind: Armor(amount: 50)),
+                (pos: (5.0, 1.0, 0.0), kind: Armor(amount: 100), respawn_time: 60.0),
+                (pos: (0.0, 1.0, 5.0), kind: Cloak(duration: 10.0)),
+            ],
+        )"#;
+        let manifest = MapManifest::parse(text).unwrap();
+        assert_eq!(manifest.pickups[0].kind, PickupKind::Armor { amount: 50 });
+        assert_eq!(manifest.pickups[0].respawn_time, 30.0);
+        assert_eq!(manifest.pickups[1].respawn_time, 60.0);
+        assert_eq!(manifest.pickups[2].kind, PickupKind::Cloak { duration: 10.0 });
+
+        let text = r#"(
+            model: "m.rgs",
+            bounds: Some((min: (-50.0, -10.0, -50.0), max: (50.0, 100.0, 50.0), margin: 20.0)),
+        )"#;
+        let bounds = MapManifest::parse(text).unwrap().bounds.unwrap();
+        assert_eq!(bounds.check(v!(0 0 0)), BoundsCheck::Inside);
+        assert_eq!(bounds.check(v!(50 100 -50)), BoundsCheck::Inside);
+        assert_eq!(bounds.check(v!(0 -20 0)), BoundsCheck::Warning);
+        assert_eq!(bounds.check(v!(60 0 -60)), BoundsCheck::Warning);
+        assert_eq!(bounds.check(v!(0 -31 0)), BoundsCheck::Outside);
+
+        let text = r#"(
+            model: "m.rgs",
+            platforms: [
+                (half_extents: (2.0, 0.25, 2.0), path: [(0.0, 0.0, 0.0), (0.0, 10.0, 0.0)], segment_time: 4.0),
+                (half_extents: (4.0, 1.0, 0.25), path: [(5.0, 1.0, 5.0)], spin: 90.0),
+            ],
+        )"#;
+        let manifest = MapManifest::parse(text).unwrap();
+        let elevator = &manifest.platforms[0];
+        assert_eq!(elevator.pose(0.0), (v!(0 0 0), 0.0));
+        assert_eq!(elevator.pose(2.0), (v!(0 5 0), 0.0));
+        assert_eq!(elevator.pose(6.0), (v!(0 5 0), 0.0));
+        assert_eq!(elevator.pose(9.0), (v!(0 2.5 0), 0.0));
+        let door = &manifest.platforms[1];
+        assert_eq!(door.segment_time, 1.0);
+        assert_eq!(door.pose(1.5), (v!(5 1 5), 135.0));
+        assert_eq!(door.pose(5.0), (v!(5 1 5), 90.0));
+    }
+}
@@ -1,32 +1,42 @@
 use std::{
     collections::VecDeque,
     io::{self, ErrorKind, Read, Write},
-    iter, mem,
     net::{SocketAddr, TcpListener, TcpStream},
     sync::mpsc::{Receiver, Sender, TryRecvError},
 };
 
-use serde::{de::DeserializeOwned, Serialize};
+use rustcycles_protocol::parse_one;
+use serde::de::DeserializeOwned;
 
 use crate::common::messages::{ClientMessage, ServerMessage};
 
+pub(crate) use rustcycles_protocol::{
+    deserialize, serialize, serialize_into, MsgLen, NetworkMessage, FRAME_HEADER_LEN, HEADER_LEN,
+};
+
 pub(crate) trait Listener {
     fn accept_conn(&mut self) -> io::Result<Box<dyn Connection>>;
 }
 
 pub(crate) struct LocalListener {
-    conn: Option<LocalConnection>,
+    conns: Vec<LocalConnection>,
 }
 
 impl LocalListener {
     pub(crate) fn new(conn: LocalConnection) -> Self {
-        Self { conn: Some(conn) }
+        Self::with_conns(vec![conn])
+    }
+
+    /// The connections are accepted in order.
+    pub(crate) fn with_conns(mut conns: Vec<LocalConnection>) -> Self {
+        conns.reverse();
+        Self { conns }
     }
 }
 
 impl Listener for LocalListener {
     fn accept_conn(&mut self) -> io::Result<Box<dyn Connection>> {
-        let conn = self.conn.take();
+        let conn = self.conns.pop();
         match conn {
             Some(conn) => Ok(Box::new(conn)),
             None => Err(io::Error::new(ErrorKind::WouldBlock, "dummy")),
@@ -52,15 +62,6 @@ impl Listener for TcpListener {
     }
 }
 
-type MsgLen = u32;
-const HEADER_LEN: usize = mem::size_of::<MsgLen>();
-
-#[derive(Debug, Clone)]
-pub(crate) struct NetworkMessage {
-    content_len: [u8; HEADER_LEN],
-    buf: Vec<u8>,
-}
-
 /// A trait to abstract over local and remove connections.
 ///
 /// Note that ideally `receive` (and `receive_one`) would have a sigature like this:
@@ -70,7 +71,9 @@ pub(crate) struct NetworkMessage {
 ///     M: DeserializeOwned;
 /// ```
 /// but generic methods are not object safe so we wouldn't be able to use dynamic dispatch.
-pub(crate) trait Connection {
+///
+/// Connections are `Send` so the server can send to multiple clients in parallel.
+pub(crate) trait Connection: Send {
     fn send(&mut self, network_msg: &NetworkMessage) -> Result<(), io::Error>;
 
     // `#[must_use]` only does something in the trait definition,
@@ -80,6 +83,7 @@ pub(crate) trait Connection {
     /// Read all available messages and return them.
     ///
     /// Also return whether the connection has been closed (doesn't matter if cleanly or reading failed).
+    /// Receiving a malformed message is a protocol violation and also counts as closed.
     #[must_use]
     fn receive_cm(&mut self) -> (Vec<ClientMessage>, bool);
 
@@ -137,10 +141,13 @@ impl LocalConnection {
     {
         let res = self.receiver.try_recv();
         match res {
-            Ok(msg) => {
-                let msg = bincode::deserialize(&msg.buf).unwrap();
-                (Some(msg), false)
-            }
+            Ok(msg) => match deserialize(&msg.buf) {
+                Ok(msg) => (Some(msg), false),
+                Err(err) => {
+                    dbg_logf!("Protocol violation from local connection: {}", err);
+                    (None, true)
+                }
+            },
             Err(TryRecvError::Empty) => (None, false),
             Err(TryRecvError::Disconnected) => (None, true),
         }
@@ -149,8 +156,12 @@ impl LocalConnection {
 
 impl Connection for LocalConnection {
     fn send(&mut self, network_msg: &NetworkMessage) -> Result<(), io::Error> {
-        self.sender.send(network_msg.clone()).unwrap();
-        Ok(())
+        // LATER This allocates for every message. Send the buffers back through another channel
+        // so they can be reused if local games ever need it.
+        // The other side was dropped - same as a closed TCP stream.
+        self.sender
+            .send(network_msg.clone())
+            .map_err(|_| io::Error::new(ErrorKind::BrokenPipe, "local connection closed"))
     }
 
     fn receive_cm(&mut self) -> (Vec<ClientMessage>, bool) {
@@ -177,7 +188,13 @@ impl Connection for LocalConnection {
 pub(crate) struct TcpConnection {
     stream: TcpStream,
     buffer: VecDeque<u8>,
+    /// Reused for every message so the header and content can be written at once.
+    out: Vec<u8>,
     pub(crate) addr: SocketAddr,
+    /// Total including headers.
+    pub(crate) bytes_sent: usize,
+    /// Total including headers.
+    pub(crate) bytes_received: usize,
 }
 
 impl TcpConnection {
@@ -185,10 +202,29 @@ impl TcpConnection {
         Self {
             stream,
             buffer: VecDeque::new(),
+            out: Vec::new(),
             addr,
+            bytes_sent: 0,
+            bytes_received: 0,
         }
     }
 
+    /// Send bytes which already contain message headers.
+    pub(crate) fn send_raw(&mut self, bytes: &[u8]) -> Result<(), io::Error> {
+        self.stream.write_all(bytes)?;
+        self.stream.flush()?;
+        self.bytes_sent += bytes.len();
+        Ok(())
+    }
+
+    /// Read into `buffer` and count the bytes.
+    fn read(&mut self) -> bool {
+        let len_before = self.buffer.len();
+        let closed = read(&mut self.stream, &mut self.buffer);
+        self.bytes_received += self.buffer.len() - len_before;
+        closed
+    }
+
     /// Read all available bytes from `stream` into `buffer`,
     /// parse messages that are complete and return them in a vector.
     ///
@@ -197,8 +233,19 @@ impl TcpConnection {
     where
         M: DeserializeOwned,
     {
-        let closed = read(&mut self.stream, &mut self.buffer);
-        let msgs = iter::from_fn(|| parse_one(&mut self.buffer)).collect();
+        let mut closed = self.read();
+        let mut msgs = Vec::new();
+        loop {
+            match parse_one(&mut self.buffer) {
+                Ok(Some(msg)) => msgs.push(msg),
+                Ok(None) => break,
+                Err(err) => {
+                    dbg_logf!("Protocol violation from {}: {}", self.addr, err);
+                    closed = true;
+                    break;
+                }
+            }
+        }
         (msgs, closed)
     }
 
@@ -210,9 +257,14 @@ impl TcpConnection {
     where
         M: DeserializeOwned,
     {
-        let closed = read(&mut self.stream, &mut self.buffer);
-        let msg = parse_one(&mut self.buffer);
-        (msg, closed)
+        let closed = self.read();
+        match parse_one(&mut self.buffer) {
+            Ok(msg) => (msg, closed),
+            Err(err) => {
+                dbg_logf!("Protocol violation from {}: {}", self.addr, err);
+                (None, true)
+            }
+        }
     }
 }
 
@@ -223,10 +275,12 @@ impl Connection for TcpConnection {
         //       General purpose compression could help a bit,
         //       but using what we know about the data should give much better results.
 
-        // Prefix data by length so it's easy to parse on the other side.
-        self.stream.write_all(&network_msg.content_len)?;
-        self.stream.write_all(&network_msg.buf)?;
+        // Prefix data by length and checksum so it's easy to parse and verify on the other side.
+        self.out.clear();
+        network_msg.write_frame(&mut self.out);
+        self.stream.write_all(&self.out)?;
         self.stream.flush()?; // LATER No idea if necessary or how it interacts with set_nodelay
+        self.bytes_sent += self.out.len();
 
         Ok(())
     }
@@ -252,19 +306,6 @@ impl Connection for TcpConnection {
     }
 }
 
-pub(crate) fn serialize<M>(msg: M) -> NetworkMessage
-where
-    M: Serialize,
-{
-    let buf = bincode::serialize(&msg).expect("bincode failed to serialize message");
-    let content_len = MsgLen::try_from(buf.len())
-        .unwrap_or_else(|err| {
-            panic!("bincode message length ({} bytes) overflowed its type: {:?}", buf.len(), err)
-        })
-        .to_le_bytes();
-    NetworkMessage { content_len, buf }
-}
-
 /// Read all available bytes until the stream would block.
 fn read(stream: &mut TcpStream, buffer: &mut VecDeque<u8>) -> bool {
     // LATER Test networking thoroughly
@@ -297,30 +338,174 @@ fn read(stream: &mut TcpStream, buffer: &mut VecDeque<u8>) -> bool {
     }
 }
 
-/// Parse a message from `buffer` or return None if there's not enough data.
-fn parse_one<M>(buffer: &mut VecDeque<u8>) -> Option<M>
-where
-    M: DeserializeOwned,
-{
-    if buffer.len() < HEADER_LEN {
-        return None;
+/// Starts every latency probe, see `server::probe`.
+pub(crate) const PROBE_MAGIC: [u8; 4] = *b"RCpr";
+/// The magic followed by a token the client picks, replies are the same bytes.
+pub(crate) const PROBE_LEN: usize = PROBE_MAGIC.len() + 8;
+
+/// Recent round trip times of a connection, see `sv_ping_interval`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PingStats {
+    /// In milliseconds, newest last.
+    samples: VecDeque<f32>,
+}
+
+impl PingStats {
+    /// Enough for half a minute with the default `sv_ping_interval`.
+    const MAX_SAMPLES: usize = 30;
+
+    pub(crate) fn add(&mut self, ms: f32) {
+        if self.samples.len() >= Self::MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(ms);
     }
 
-    // There's no convenient way to make this generic over msg len 2 and 4,
-    // just keep one version commented out.
-    //let len_bytes = [buffer[0], buffer[1]];
-    //let content_len = usize::from(MsgLen::from_le_bytes(len_bytes));
-    let len_bytes = [buffer[0], buffer[1], buffer[2], buffer[3]];
-    let content_len = usize::try_from(MsgLen::from_le_bytes(len_bytes)).unwrap();
+    pub(crate) fn last(&self) -> Option<f32> {
+        self.samples.back().copied()
+    }
 
-    if buffer.len() < HEADER_LEN + content_len {
-        // Not enough bytes in buffer for a full message.
-        return None;
+    /// Human readable statistics for the `ping` command.
+    ///
+    /// Jitter is the average difference between consecutive samples.
+    pub(crate) fn summary(&self) -> String {
+        let Some(last) = self.last() else {
+            return "no ping measured yet".to_owned();
+        };
+        let count = self.samples.len();
+        let min = self.samples.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = self.samples.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let avg = self.samples.iter().sum::<f32>() / count as f32;
+        let diffs = self.samples.iter().zip(self.samples.iter().skip(1));
+        let jitter = diffs.map(|(a, b)| (b - a).abs()).sum::<f32>() / (count - 1).max(1) as f32;
+        format!(
+            "ping {:.0} ms, min {:.0}, avg {:.1}, max {:.0}, jitter {:.1} ({} samples)",
+            last, min, avg, max, jitter, count
+        )
     }
+}
+
+/// How many server ticks the updates which arrived during each recent tick covered,
+/// see `hud_net_warnings`. Each update covers one tick unless `sv_snapshot_rate` is lower.
+///
+/// TCP doesn't lose packets but the game stalls all the same when they arrive late,
+/// so late updates are counted as lost.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct UpdateStats {
+    /// Newest last.
+    ticks: VecDeque<u32>,
+}
+
+impl UpdateStats {
+    /// One second at 60 ticks per second.
+    ///
+    /// Updates often arrive in bunches, a shorter window would report loss
+    /// even when they all arrive.
+    const MAX_TICKS: usize = 60;
+
+    pub(crate) fn add(&mut self, server_ticks: u32) {
+        if self.ticks.len() >= Self::MAX_TICKS {
+            self.ticks.pop_front();
+        }
+        self.ticks.push_back(server_ticks);
+    }
+
+    /// Fraction of ticks in the window not covered by an update, 0 to 1.
+    pub(crate) fn loss(&self) -> f32 {
+        if self.ticks.is_empty() {
+            return 0.0;
+        }
+        let received = self.ticks.iter().sum::<u32>() as f32;
+        (1.0 - received / self.ticks.len() as f32).max(0.0)
+    }
+}
+
+/// Entry point for fuzzing - decode arbitrary bytes as a stream of messages of both types.
+///
+/// LATER Hook this up to cargo-fuzz once the messages are in the protocol crate,
+/// fuzz targets can't depend on a binary crate.
+#[allow(dead_code)]
+pub(crate) fn fuzz_decode(data: &[u8]) {
+    let mut buffer: VecDeque<u8> = data.iter().copied().collect();
+    while let Ok(Some(_)) = parse_one::<ClientMessage>(&mut buffer) {}
+
+    let mut buffer: VecDeque<u8> = data.iter().copied().collect();
+    while let Ok(Some(_)) = parse_one::<ServerMessage>(&mut buffer) {}
+}
 
-    buffer.drain(0..HEADER_LEN);
-    let bytes: Vec<_> = buffer.drain(0..content_len).collect();
-    let msg = bincode::deserialize(&bytes).unwrap();
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_xoshiro::Xoshiro256PlusPlus;
+    use rustcycles_protocol::{crc32, MsgLen};
+
+    use super::*;
+
+    #[test]
+    fn test_decode_garbage() {
+        // A poor man's fuzzer - mostly checks we don't panic or allocate too much.
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+        for _ in 0..1000 {
+            let len = rng.gen_range(0..64);
+            let mut data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            fuzz_decode(&data);
+
+            // Also try with a valid-looking header so the message body gets decoded.
+            let checksum = crc32(&data).to_le_bytes();
+            data.splice(0..0, checksum);
+            let content_len = (len as MsgLen).to_le_bytes();
+            data.splice(0..0, content_len);
+            fuzz_decode(&data);
+        }
+    }
+
+    #[test]
+    fn test_ping_stats() {
+        let mut stats = PingStats::default();
+        assert_eq!(stats.last(), None);
+        assert_eq!(stats.summary(), "no ping measured yet");
+
+        for ms in [40.0, 50.0, 30.0] {
+            stats.add(ms);
+        }
+        assert_eq!(stats.last(), Some(30.0));
+        assert_eq!(
+            stats.summary(),
+            "ping 30 ms, min 30, avg 40.0, max 50, jitter 15.0 (3 samples)"
+        );
+
+        for _ in 0..100 {
+            stats.add(1.0);
+        }
+        assert_eq!(stats.samples.len(), PingStats::MAX_SAMPLES);
+    }
+
+    #[test]
+    fn test_update_stats() {
+        let mut stats = UpdateStats::default();
+        assert_eq!(stats.loss(), 0.0);
 
-    Some(msg)
+        // Bunched but complete.
+        for updates in [1, 0, 2, 1] {
+            stats.add(updates);
+        }
+        assert_eq!(stats.loss(), 0.0);
+
+        for _ in 0..4 {
+            stats.add(0);
+        }
+        assert_eq!(stats.loss(), 0.5);
+
+        for _ in 0..UpdateStats::MAX_TICKS {
+            stats.add(1);
+        }
+        assert_eq!(stats.loss(), 0.0);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let network_msg = serialize(ClientMessage::Chat("hello".to_owned()));
+        let msg: ClientMessage = deserialize(&network_msg.buf).unwrap();
+        assert!(matches!(msg, ClientMessage::Chat(text) if text == "hello"));
+    }
 }
@@ -0,0 +1,58 @@
+//! Colors used to tell players and teams apart.
+//!
+//! The default palette relies on red vs green which many colorblind players can't distinguish,
+//! `cl_colorblind_mode` switches to palettes which stay distinguishable.
+//!
+//! LATER Use this for trails and the minimap once they exist.
+
+use strum_macros::{Display, EnumString};
+
+use crate::prelude::*;
+
+/// Pub because it's used in cvars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum ColorblindMode {
+    Off,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+const DEFAULT: [Color; 8] = [RED, GREEN, BLUE2, YELLOW, MAGENTA, CYAN, ORANGE, WHITE];
+
+/// The Okabe-Ito palette, works for both kinds of red-green colorblindness.
+const RED_GREEN: [Color; 8] = [
+    Color::opaque(230, 159, 0),
+    Color::opaque(86, 180, 233),
+    Color::opaque(0, 158, 115),
+    Color::opaque(240, 228, 66),
+    Color::opaque(0, 114, 178),
+    Color::opaque(213, 94, 0),
+    Color::opaque(204, 121, 167),
+    WHITE,
+];
+
+/// Avoids telling apart blue from green and yellow from pink.
+const BLUE_YELLOW: [Color; 8] = [
+    Color::opaque(220, 50, 50),
+    Color::opaque(0, 160, 160),
+    Color::opaque(255, 170, 200),
+    Color::opaque(0, 90, 90),
+    Color::opaque(140, 0, 0),
+    Color::opaque(170, 230, 230),
+    Color::opaque(120, 120, 120),
+    WHITE,
+];
+
+/// Color of the team, wraps around if there are more teams than colors.
+///
+/// In free for all, each player is a team of their own.
+pub(crate) fn team_color(mode: ColorblindMode, team: usize) -> Color {
+    let palette = match mode {
+        ColorblindMode::Off => &DEFAULT,
+        ColorblindMode::Deuteranopia | ColorblindMode::Protanopia => &RED_GREEN,
+        ColorblindMode::Tritanopia => &BLUE_YELLOW,
+    };
+    palette[team % palette.len()]
+}
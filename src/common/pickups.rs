@@ -0,0 +1,42 @@
+//! Models of the map's pickups, see `map::Pickup`.
+//!
+//! Both sides build them, the server decides which are available
+//! and clients hide the ones which aren't.
+//!
+//! LATER Proper models, different for each kind.
+
+use fyrox::{
+    core::algebra::Matrix4,
+    scene::mesh::{
+        surface::{SurfaceBuilder, SurfaceData, SurfaceSharedData},
+        MeshBuilder,
+    },
+};
+
+use crate::{common::map::Pickup, prelude::*};
+
+/// Half of the pickup's size.
+const HALF_SIZE: f32 = 0.3;
+
+/// Build a model for each pickup, in the same order as in the manifest.
+pub(crate) fn build(scene: &mut Scene, pickups: &[Pickup]) -> Vec<Handle<Node>> {
+    pickups
+        .iter()
+        .map(|pickup| {
+            let cube = SurfaceData::make_cube(Matrix4::new_scaling(HALF_SIZE * 2.0));
+            // Stand it on a corner so it doesn't look like part of the map.
+            let rot =
+                UnitQuaternion::from_euler_angles(45f32.to_radians(), 0.0, 45f32.to_radians());
+            MeshBuilder::new(
+                BaseBuilder::new().with_local_transform(
+                    TransformBuilder::new()
+                        .with_local_position(Vec3::from(pickup.pos))
+                        .with_local_rotation(rot)
+                        .build(),
+                ),
+            )
+            .with_surfaces(vec![SurfaceBuilder::new(SurfaceSharedData::new(cube)).build()])
+            .build(&mut scene.graph)
+        })
+        .collect()
+}
@@ -0,0 +1,89 @@
+//! Moving platforms - kinematic boxes from the map manifest, see `map::Platform`.
+//!
+//! Both sides build them but only the server moves them,
+//! clients get their poses in updates like cycles
+//! because client game time isn't the same as the server's.
+//!
+//! Physics alone wouldn't carry cycles since they have no friction by default
+//! so cycles standing on top are moved along with the platform.
+//!
+//! LATER Carry projectiles.
+
+use fyrox::{
+    core::algebra::Matrix4,
+    scene::mesh::{
+        surface::{SurfaceBuilder, SurfaceData, SurfaceSharedData},
+        MeshBuilder,
+    },
+};
+
+use crate::{
+    common::{map::Platform, GameState},
+    prelude::*,
+};
+
+/// How far above the platform's top a cycle's center can be and still ride it.
+const RIDE_HEIGHT: f32 = 0.5;
+
+/// Build a body for each platform, in the same order as in the manifest.
+pub(crate) fn build(scene: &mut Scene, platforms: &[Platform]) -> Vec<Handle<Node>> {
+    platforms
+        .iter()
+        .map(|platform| {
+            let half_extents = Vec3::from(platform.half_extents);
+            let (pos, yaw) = platform.pose(0.0);
+            let cube =
+                SurfaceData::make_cube(Matrix4::new_nonuniform_scaling(&(half_extents * 2.0)));
+            let mesh_handle = MeshBuilder::new(BaseBuilder::new())
+                .with_surfaces(vec![SurfaceBuilder::new(SurfaceSharedData::new(cube)).build()])
+                .build(&mut scene.graph);
+            let collider_handle = ColliderBuilder::new(BaseBuilder::new())
+                .with_shape(ColliderShape::cuboid(half_extents.x, half_extents.y, half_extents.z))
+                .build(&mut scene.graph);
+            RigidBodyBuilder::new(
+                BaseBuilder::new()
+                    .with_local_transform(
+                        TransformBuilder::new()
+                            .with_local_position(pos)
+                            .with_local_rotation(yaw_rotation(yaw))
+                            .build(),
+                    )
+                    .with_children(&[mesh_handle, collider_handle]),
+            )
+            .with_body_type(RigidBodyType::KinematicPositionBased)
+            .build(&mut scene.graph)
+        })
+        .collect()
+}
+
+/// Move platforms to where they should be at this game time and carry cycles on them.
+///
+/// Server only, before physics.
+pub(crate) fn tick(gs: &GameState, scene: &mut Scene) {
+    for (platform, &body_handle) in gs.map.platforms.iter().zip(&gs.platform_handles) {
+        let half_extents = Vec3::from(platform.half_extents);
+        let transform = scene.graph[body_handle].local_transform();
+        let old_pos = **transform.position();
+        let old_rot = **transform.rotation();
+        let (new_pos, yaw) = platform.pose(gs.game_time);
+        let new_rot = yaw_rotation(yaw);
+
+        for cycle in &gs.cycles {
+            let body = scene.graph[cycle.body_handle].as_rigid_body_mut();
+            let pos = **body.local_transform().position();
+            let local = old_rot.inverse() * (pos - old_pos);
+            let above = local.y >= half_extents.y && local.y <= half_extents.y + RIDE_HEIGHT;
+            if above && local.x.abs() <= half_extents.x && local.z.abs() <= half_extents.z {
+                body.local_transform_mut().set_position(new_pos + new_rot * local);
+            }
+        }
+
+        let transform = scene.graph[body_handle].local_transform_mut();
+        transform.set_position(new_pos);
+        transform.set_rotation(new_rot);
+    }
+}
+
+fn yaw_rotation(yaw: f32) -> UnitQuaternion<f32> {
+    UnitQuaternion::from_axis_angle(&UP_AXIS, yaw.to_radians())
+}
@@ -0,0 +1,175 @@
+//! Ordering gamelogic systems - see `GameState::tick_before_physics`.
+//!
+//! Each system declares which data it reads and writes and which systems it runs after.
+//! `Schedule::new` sorts them and checks that any two systems which touch the same data
+//! (and at least one of them writes it) have their order declared,
+//! so adding a system can't silently reorder or race with existing ones.
+//! Systems without constraints between them keep the order they were declared in.
+//!
+//! LATER Run systems which don't conflict in parallel.
+
+/// What a system can read or write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Data {
+    Players,
+    Cycles,
+    Projectiles,
+    /// Rigid bodies and colliders in the scene.
+    Physics,
+    /// Hits, kills and effects which happened this tick.
+    Events,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct System<F> {
+    /// Also used for `dbg_timer!`.
+    pub(crate) name: &'static str,
+    pub(crate) reads: &'static [Data],
+    pub(crate) writes: &'static [Data],
+    /// Names of systems which have to run before this one.
+    pub(crate) after: &'static [&'static str],
+    /// Most systems don't run while the match is paused.
+    pub(crate) while_paused: bool,
+    pub(crate) run: F,
+}
+
+impl<F> System<F> {
+    /// The first data both systems access where at least one of them writes it.
+    fn conflict(&self, other: &Self) -> Option<Data> {
+        let touches = |system: &Self, data: &Data| {
+            system.reads.contains(data) || system.writes.contains(data)
+        };
+        self.writes
+            .iter()
+            .find(|data| touches(other, *data))
+            .or_else(|| other.writes.iter().find(|data| touches(self, *data)))
+            .copied()
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Schedule<F> {
+    /// In the order they run.
+    systems: Vec<System<F>>,
+}
+
+impl<F> Schedule<F> {
+    pub(crate) fn new(systems: Vec<System<F>>) -> Result<Self, String> {
+        let index = |name: &str| systems.iter().position(|system| system.name == name);
+        let mut deps = Vec::new();
+        for (i, system) in systems.iter().enumerate() {
+            if index(system.name) != Some(i) {
+                return Err(format!("duplicate system {}", system.name));
+            }
+            let mut system_deps = Vec::new();
+            for &name in system.after {
+                let dep = index(name)
+                    .ok_or_else(|| format!("{} runs after unknown system {}", system.name, name))?;
+                system_deps.push(dep);
+            }
+            deps.push(system_deps);
+        }
+
+        // Always pick the first declared system whose dependencies already ran.
+        let mut order = Vec::new();
+        while order.len() < systems.len() {
+            let next = (0..systems.len())
+                .find(|&i| !order.contains(&i) && deps[i].iter().all(|dep| order.contains(dep)));
+            match next {
+                Some(i) => order.push(i),
+                None => {
+                    let names: Vec<_> = (0..systems.len())
+                        .filter(|i| !order.contains(i))
+                        .map(|i| systems[i].name)
+                        .collect();
+                    return Err(format!("cycle between systems {}", names.join(", ")));
+                }
+            }
+        }
+
+        for (pos, &a) in order.iter().enumerate() {
+            for &b in &order[pos + 1..] {
+                if let Some(data) = systems[a].conflict(&systems[b]) {
+                    if !runs_after(&deps, b, a) {
+                        return Err(format!(
+                            "{} and {} both access {:?}, declare which runs first",
+                            systems[a].name, systems[b].name, data
+                        ));
+                    }
+                }
+            }
+        }
+
+        let mut systems: Vec<_> = systems.into_iter().map(Some).collect();
+        let systems = order.into_iter().map(|i| systems[i].take().unwrap()).collect();
+        Ok(Self { systems })
+    }
+
+    pub(crate) fn systems(&self) -> &[System<F>] {
+        &self.systems
+    }
+}
+
+/// Whether `later` depends on `earlier`, directly or through other systems.
+fn runs_after(deps: &[Vec<usize>], later: usize, earlier: usize) -> bool {
+    deps[later].iter().any(|&dep| dep == earlier || runs_after(deps, dep, earlier))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system(
+        name: &'static str,
+        reads: &'static [Data],
+        writes: &'static [Data],
+        after: &'static [&'static str],
+    ) -> System<()> {
+        System {
+            name,
+            reads,
+            writes,
+            after,
+            while_paused: false,
+            run: (),
+        }
+    }
+
+    fn names(schedule: &Schedule<()>) -> Vec<&str> {
+        schedule.systems().iter().map(|system| system.name).collect()
+    }
+
+    #[test]
+    fn test_schedule() {
+        use Data::*;
+
+        let schedule = Schedule::new(vec![
+            system("fly", &[Players], &[Projectiles], &["fire"]),
+            system("move", &[Players], &[Physics], &[]),
+            system("fire", &[Physics], &[Projectiles], &["move"]),
+        ])
+        .unwrap();
+        assert_eq!(names(&schedule), ["move", "fire", "fly"]);
+
+        // Only reading the same data doesn't need an order.
+        let schedule = Schedule::new(vec![
+            system("a", &[Players], &[], &[]),
+            system("b", &[Players], &[], &[]),
+        ])
+        .unwrap();
+        assert_eq!(names(&schedule), ["a", "b"]);
+
+        assert!(Schedule::new(vec![
+            system("a", &[], &[Physics], &[]),
+            system("b", &[Physics], &[], &[]),
+        ])
+        .is_err());
+        assert!(
+            Schedule::new(vec![system("a", &[], &[], &["b"]), system("b", &[], &[], &["a"]),])
+                .is_err()
+        );
+        assert!(Schedule::new(vec![system("a", &[], &[], &["missing"])]).is_err());
+
+        assert!(Schedule::new(crate::common::tick_systems()).is_ok());
+    }
+}
@@ -0,0 +1,112 @@
+//! The server's icon and accent color - `sv_icon_file` and `sv_accent_color`.
+//!
+//! Sent in `Init` and shown in the scoreboard header.
+//! The icon has to be a small PNG, both sides check it so a server
+//! can't make clients decode something huge.
+//!
+//! LATER Show them in a server browser once there is one.
+
+use std::fs;
+
+use crate::{common::entities::Look, prelude::*};
+
+pub(crate) use rustcycles_protocol::messages::ServerTheme;
+
+/// Larger files are rejected, the icon is sent to every client in `Init`.
+pub(crate) const MAX_ICON_BYTES: usize = 16 * 1024;
+
+/// Maximum width and height in pixels.
+pub(crate) const MAX_ICON_SIZE: u32 = 64;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Loading and checking `ServerTheme`s.
+pub(crate) trait ServerThemeExt {
+    /// Anything invalid is logged and left out.
+    ///
+    /// The file is read for every new client so it can be replaced while the server runs.
+    fn load(cvars: &Cvars) -> Self;
+
+    /// Drop the icon if it's not a small PNG.
+    fn validated(self) -> Self;
+}
+
+impl ServerThemeExt for ServerTheme {
+    fn load(cvars: &Cvars) -> Self {
+        let mut theme = Self::default();
+        if !cvars.sv_icon_file.is_empty() {
+            match fs::read(&cvars.sv_icon_file) {
+                Ok(icon) => theme.icon = icon,
+                Err(err) => dbg_logf!("failed to read icon {}: {}", cvars.sv_icon_file, err),
+            }
+        }
+        if !cvars.sv_accent_color.is_empty() {
+            theme.accent = Look::parse_color(&cvars.sv_accent_color);
+            if theme.accent.is_none() {
+                dbg_logf!("invalid sv_accent_color {}", cvars.sv_accent_color);
+            }
+        }
+        theme.validated()
+    }
+
+    fn validated(mut self) -> Self {
+        if !self.icon.is_empty() {
+            if let Err(err) = validate_icon(&self.icon) {
+                dbg_logf!("invalid server icon: {}", err);
+                self.icon.clear();
+            }
+        }
+        self
+    }
+}
+
+/// Only checks the header, returns the width and height.
+pub(crate) fn validate_icon(data: &[u8]) -> Result<(u32, u32), String> {
+    if data.len() > MAX_ICON_BYTES {
+        return Err(format!("{} bytes, at most {} allowed", data.len(), MAX_ICON_BYTES));
+    }
+    // The signature is followed by the IHDR chunk: length, type, width and height.
+    if data.len() < 24 || data[..8] != PNG_SIGNATURE || &data[12..16] != b"IHDR" {
+        return Err("not a PNG".to_owned());
+    }
+    let width = u32::from_be_bytes(data[16..20].try_into().unwrap());
+    let height = u32::from_be_bytes(data[20..24].try_into().unwrap());
+    if width == 0 || height == 0 || width > MAX_ICON_SIZE || height > MAX_ICON_SIZE {
+        return Err(format!(
+            "{}x{} pixels, at most {}x{} allowed",
+            width, height, MAX_ICON_SIZE, MAX_ICON_SIZE
+        ));
+    }
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = PNG_SIGNATURE.to_vec();
+        data.extend_from_slice(&13u32.to_be_bytes());
+        data.extend_from_slice(b"IHDR");
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn test_validate_icon() {
+        assert_eq!(validate_icon(&png(32, 64)), Ok((32, 64)));
+        assert!(validate_icon(&png(65, 1)).is_err());
+        assert!(validate_icon(&png(0, 16)).is_err());
+        assert!(validate_icon(b"GIF89a").is_err());
+        let mut large = png(16, 16);
+        large.resize(MAX_ICON_BYTES + 1, 0);
+        assert!(validate_icon(&large).is_err());
+
+        let theme = ServerTheme {
+            icon: b"not an icon".to_vec(),
+            accent: Some([255, 128, 0]),
+        };
+        assert!(theme.validated().icon.is_empty());
+    }
+}
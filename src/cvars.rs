@@ -3,9 +3,19 @@
 use cvars::SetGet;
 use cvars_console::CvarAccess;
 
+use crate::{
+    common::{
+        entities::{OvertimeRule, RespawnMode, VehicleKind},
+        palette::ColorblindMode,
+    },
+    debug::details::LogLevel,
+    server::chat::FilterAction,
+};
+
 /// Console variables - configuration options for anything and everything.
 ///
 /// Prefix meanings:
+/// a11y_ is accessibility
 /// cl_ is client
 /// d_ is debug
 /// g_ is gameplay
@@ -22,6 +32,25 @@ pub struct Cvars {
     // Would be nice to keep alphabetically.
     //  |
     //  v
+    /// A TTF font for the HUD, e.g. to make the text larger, see `a11y_font_size`.
+    /// Empty means the built-in font. Only read at startup.
+    pub a11y_font: String,
+    /// Height of `a11y_font` in pixels.
+    pub a11y_font_size: f32,
+    /// Yellow HUD text and a stronger out of bounds tint.
+    pub a11y_high_contrast: bool,
+    /// No screen shake, view punch, full screen tint or decorative particles
+    /// (exhaust, emotes, teleports). Explosions and impacts still show hits.
+    pub a11y_reduced_effects: bool,
+
+    /// Password sent to the server to get admin privileges. Empty means don't log in.
+    ///
+    /// LATER This is sent in plain text.
+    pub cl_admin_password: String,
+
+    /// Turning speed in degrees per second when aiming with an axis (e.g. a gamepad stick).
+    pub cl_aim_axis_speed: f32,
+
     pub cl_camera_3rd_person_back: f32,
     pub cl_camera_3rd_person_up: f32,
     /// Vertical field of view in degrees.
@@ -32,14 +61,101 @@ pub struct Cvars {
     pub cl_camera_z_near: f32,
     pub cl_camera_z_far: f32,
 
+    /// Casting mode for observers - outlines and nameplates on players, number keys switch views.
+    pub cl_caster: bool,
+
+    /// Color of your cycle as `rrggbb`, white means the model's original colors.
+    pub cl_color: String,
+    /// Use colors distinguishable with colorblindness (off, deuteranopia, protanopia, tritanopia).
+    ///
+    /// Cycles get colors based on their team instead of `cl_color`.
+    pub cl_colorblind_mode: ColorblindMode,
+
+    /// Show what you're playing in Discord using this application ID, empty means disabled.
+    /// Only read at startup.
+    pub cl_discord_app_id: String,
+
+    /// How long in ms after the next update was due cycles keep moving on their last known input.
+    /// After that they stop until updates resume, except the local player's.
+    pub cl_extrapolate_max: f32,
+
+    /// Maximum screen shake, view punch and recoil in degrees.
+    pub cl_feedback_max_deg: f32,
+    /// Multiplies screen shake, view punch and recoil, 0 disables them.
+    pub cl_feedback_scale: f32,
+
     pub cl_fullscreen: bool,
     pub cl_headless: bool,
+    /// Where this client's fingerprint is kept, other players use it to mute us. Only read when connecting.
+    pub cl_identity_file: String,
+    /// Language of the HUD, the name of a file in `data/lang/`. Missing text is in English.
+    pub cl_language: String,
     pub cl_mouse_grab_on_focus: bool,
+    /// Where fingerprints of players muted using `mute` are kept. Only read when connecting.
+    pub cl_mute_file: String,
+    /// Start at most this many `probe`s per second, 0 means no limit.
+    pub cl_probe_rate: f32,
+    /// Where lifetime stats shown by `stats` are kept.
+    pub cl_profile_file: String,
+    /// View punch in degrees when you get hit.
+    pub cl_punch_damage: f32,
+    /// View punch in degrees per shot.
+    pub cl_punch_recoil: f32,
+    /// How many times to try reconnecting when the connection to the server drops, 0 means exit.
+    pub cl_reconnect_attempts: u32,
+    /// Seconds to wait after the first failed reconnect attempt, doubles after each one.
+    pub cl_reconnect_delay: f32,
+    /// Ask the server to forward its log (requires admin, see `cl_admin_password`).
+    pub cl_remote_log: bool,
+    /// How much an explosion right next to the camera shakes it, 0 to 1.
+    pub cl_shake_explosion: f32,
+    /// Explosions further than this from the camera don't shake it.
+    pub cl_shake_radius: f32,
+    /// Where servers added using `favorite` and the ones shown by `history` are kept.
+    pub cl_servers_file: String,
+    /// Model of your cycle, currently only `default`.
+    pub cl_skin: String,
+    /// Seconds over which cycles blend toward where the server says they are, 0 means snap.
+    pub cl_smooth_time: f32,
+    /// Ask the server for this many updates per second, fewer save bandwidth on slow connections.
+    ///
+    /// The server limits it to between `sv_snapshot_rate_min` and `sv_snapshot_rate`.
+    /// Only sent when connecting.
+    pub cl_updaterate: f32,
+    /// What you spawn as when the server allows choosing (`g_vehicles`),
+    /// changing it takes effect at your next spawn.
+    pub cl_vehicle: VehicleKind,
+    /// Flash the taskbar entry when the match starts while the window is in the background.
+    pub cl_window_flash: bool,
     pub cl_window_height: i32,
     pub cl_window_width: i32,
 
     pub cl_zoom_factor: f32,
 
+    /// Log each `dbg_timer!` which allocates more than `d_alloc_audit_threshold` times
+    /// in a frame, only when it's more than any frame before.
+    /// Allocations in other threads (e.g. `sv_send_threads`) count too.
+    /// Needs the `alloc_count` cargo feature.
+    pub d_alloc_audit: bool,
+    pub d_alloc_audit_threshold: usize,
+
+    /// How many bots `rustcycles bench` spawns.
+    pub d_bench_bots: usize,
+    /// How many server ticks `rustcycles bench` runs.
+    pub d_bench_ticks: usize,
+
+    /// How many clients `rustcycles botclient` connects.
+    pub d_botclient_count: usize,
+    /// Stop `rustcycles botclient` after this many seconds, 0 means run until killed.
+    pub d_botclient_seconds: f32,
+    /// Where `rustcycles botclient` connects.
+    pub d_botclient_server: String,
+
+    /// Open the crash report in the default program after writing it.
+    pub d_crash_report_open: bool,
+    /// Write a crash report (`crash-<endpoint>-<time>.txt`) when the game panics.
+    pub d_crash_reports: bool,
+
     /// A "temporary" cvar for quick testing. Normally unused but kept here
     /// so I don't have to add a cvar each time I want a quick toggle.
     pub d_dbg: bool,
@@ -48,25 +164,66 @@ pub struct Cvars {
     /// Same as d_dbg but for ints.
     pub d_dbgi: i32,
 
+    /// Save highlights to `<prefix>-<n>.rcdemo` when running `rustcycles demo`, empty means only list them.
+    pub d_demo_extract: String,
+    /// The demo for `rustcycles demo`.
+    pub d_demo_file: String,
+    /// Seconds of the demo to keep after each marker when extracting highlights.
+    pub d_demo_highlight_after: f32,
+    /// Seconds of the demo to keep before each marker when extracting highlights.
+    pub d_demo_highlight_before: f32,
+
+    /// Periodically compare the client's state with the server's and log differences.
+    pub d_desync_check: bool,
+    /// How often to check, in server ticks.
+    pub d_desync_check_interval: usize,
+    /// Differences smaller than this are ignored.
+    pub d_desync_check_precision: f32,
+
     // TODO A lot of these cvars need to be synced to server when playing locally.
     /// Master switch for debug output - the d_draw_* group.
     pub d_draw: bool,
     pub d_draw_arrows: bool,
+    /// Show soft asserts which failed and how many times.
+    pub d_draw_asserts: bool,
+    pub d_draw_boxes: bool,
+    pub d_draw_camera: bool,
+    pub d_draw_capsules: bool,
+    /// How many lines to use for circles in spheres and capsules.
+    pub d_draw_circle_segments: usize,
+    /// Draw shapes from the client (or the client part of a local game).
+    pub d_draw_client: bool,
     pub d_draw_crosses: bool,
     pub d_draw_crosses_half_len: f32,
     pub d_draw_crosses_line_from_origin: bool,
+    pub d_draw_cycles: bool,
     pub d_draw_frame_timings: bool,
     pub d_draw_frame_timings_steps: usize,
     pub d_draw_frame_timings_text: bool,
     pub d_draw_lines: bool,
+    /// Draw shapes without a category.
+    pub d_draw_misc: bool,
     /// This ruins perf in debug builds: https://github.com/FyroxEngine/Fyrox/issues/237
     pub d_draw_physics: bool,
+    pub d_draw_projectiles: bool,
     pub d_draw_rots: bool,
+    /// Length of the axes drawn by `dbg_rot`.
+    pub d_draw_rots_size: f32,
+    /// Draw shapes from the server.
+    pub d_draw_server: bool,
+    /// Color shapes by who drew them (client red, server green) instead of their own color.
+    pub d_draw_source_colors: bool,
+    pub d_draw_spheres: bool,
     pub d_draw_text: bool,
     pub d_draw_text_shadow: bool,
     pub d_draw_text_shadow_dilation: f32,
     pub d_draw_text_shadow_offset_x: f32,
     pub d_draw_text_shadow_offset_y: f32,
+    /// Show how long each part of the last frame took, see `dbg_timer`.
+    pub d_draw_timers: bool,
+    pub d_draw_world_texts: bool,
+    /// How many world texts can be shown at once. Only read at startup.
+    pub d_draw_world_texts_max: usize,
 
     pub d_engine_stats: bool,
 
@@ -82,6 +239,44 @@ pub struct Cvars {
     pub d_exit_after_one_frame: bool,
     pub d_exit_on_unknown_cvar: bool,
 
+    /// Detach the camera from the cycle and fly around freely, needs `sv_cheats`.
+    ///
+    /// The cycle keeps going with what it was doing before, just without any keys held.
+    pub d_fly_camera: bool,
+    pub d_fly_camera_speed: f32,
+
+    /// Show a graph of recent frame times.
+    pub d_graph_frametime: bool,
+    /// How far in front of the camera the graph is drawn, must be more than `cl_camera_z_near`.
+    pub d_graph_frametime_distance: f32,
+    /// How many frames to show.
+    pub d_graph_frametime_len: usize,
+
+    /// Reload the map and cycle model when files in `data/` change.
+    pub d_hot_reload: bool,
+    /// How often to check for changes in seconds.
+    pub d_hot_reload_interval: f32,
+
+    /// Show a panel listing all entities, click a row to highlight the entity.
+    pub d_inspector: bool,
+    pub d_inspector_highlight_radius: f32,
+    /// Entities that don't fit are not shown.
+    pub d_inspector_rows_max: usize,
+    pub d_inspector_width: f32,
+
+    /// Freeze gamelogic and physics on the client and the local server.
+    ///
+    /// The camera can still move and the inspector still works.
+    /// LATER Dedicated servers.
+    pub d_pause: bool,
+
+    /// Ask the server to send its debug texts and shapes.
+    /// They're only sent to clients which ask and are admins or when `sv_cheats` is on.
+    pub d_remote_debug: bool,
+
+    /// How many seconds of history a local server keeps for `rewind`.
+    pub d_rewind_seconds: f32,
+
     /// The seed to initialize the RNG.
     ///
     /// This is not very helpful by itself because by the time you can change cvars in the console,
@@ -91,12 +286,71 @@ pub struct Cvars {
     /// This means you can look at the cvar's value later and know what seed you need to replay the same game.
     pub d_seed: u64,
 
+    /// Run one tick while `d_pause` is on, then reset to false.
+    pub d_singlestep: bool,
+
+    /// Append timings of each frame (see `dbg_timer`) to this file as CSV. Empty means disabled.
+    ///
+    /// Columns: frame, endpoint, name, milliseconds, count.
+    pub d_timers_csv: String,
+
     /// Print UI messages or a subset of them.
     pub d_ui_msgs: bool,
     pub d_ui_msgs_direction_from: bool,
     pub d_ui_msgs_direction_to: bool,
     pub d_ui_msgs_mouse: bool,
 
+    /// Armor pickups can't give more than this.
+    pub g_armor_max: u32,
+    /// How much armor a hit takes instead of killing the player.
+    pub g_armor_per_hit: u32,
+
+    /// Players who hit the victim this many seconds before somebody else killed them get an assist.
+    pub g_assist_time: f32,
+
+    /// Check team sizes this often (in seconds) and move a player
+    /// if one team has 2 or more players more than another. 0 disables autobalance.
+    pub g_autobalance_interval: f32,
+    /// Players who changed teams less than this many seconds ago aren't moved by autobalance.
+    pub g_autobalance_protect: f32,
+
+    /// Pause the match when a playing player loses connection until they reconnect.
+    /// Team captains can unpause early using `/unpause` in chat.
+    pub g_competitive: bool,
+    /// Continue a paused match after this many seconds even if not everyone is back.
+    /// It also continues once `sv_reconnect_time` runs out for everyone it's waiting for.
+    pub g_competitive_pause_time: f32,
+
+    /// Cycle physics, applied every tick so handling can be tuned live.
+    ///
+    /// Friction and restitution are of the cycle's collider against everything.
+    pub g_cycle_friction: f32,
+    /// Multiplies the scene's gravity, noclip ignores it.
+    pub g_cycle_gravity_scale: f32,
+    /// How quickly the cycle slows down on its own.
+    pub g_cycle_linear_damping: f32,
+    /// Added to the mass computed from the collider's shape.
+    pub g_cycle_mass: f32,
+    pub g_cycle_restitution: f32,
+    /// Cycles spawn this high and fall to the ground.
+    pub g_cycle_spawn_height: f32,
+    /// Cycles spawn randomly up to this far to the left or right.
+    pub g_cycle_spawn_spread: f32,
+
+    /// Name of the map's manifest in `data/maps/`.
+    pub g_map: String,
+
+    /// Flying speed with the `noclip` cheat.
+    pub g_noclip_speed: f32,
+
+    /// Seconds a cycle can spend outside the map's bounds before it's killed.
+    pub g_out_of_bounds_time: f32,
+
+    /// What happens when players are tied for the lead at `g_time_limit`.
+    pub g_overtime: OvertimeRule,
+    /// Length of each overtime period with `g_overtime extra_rounds`.
+    pub g_overtime_time: f32,
+
     /// This is needed because the default 1 causes the wheel to randomly stutter/stop
     /// when passing between poles - they use a single trimesh collider.
     /// 2 is very noticeable, 5 is better, 10 is only noticeable at high speeds.
@@ -107,8 +361,65 @@ pub struct Cvars {
     pub g_projectile_lifetime: f32,
     pub g_projectile_speed: f32,
 
+    /// How dead players come back, game modes can choose differently.
+    pub g_respawn: RespawnMode,
+    /// Seconds until a new cycle with `g_respawn timed`.
+    pub g_respawn_time: f32,
+    /// Length of each wave with `g_respawn wave`.
+    pub g_respawn_wave_time: f32,
+
+    /// Free for all ends the round when a player reaches this many kills, 0 means no limit.
+    pub g_score_limit: u32,
+
+    /// Number of teams, new players join the smallest one. 0 means free for all.
+    ///
+    /// At most 8, there are only so many team names.
+    pub g_teams: usize,
+
+    /// Rounds end after this many seconds, 0 means no limit.
+    pub g_time_limit: f32,
+
+    /// Multiplies `g_wheel_acceleration` for armored vehicles.
+    pub g_vehicle_armored_acceleration: f32,
+    /// Armored vehicles spawn with this much armor.
+    pub g_vehicle_armored_armor: u32,
+    /// Multiplies `g_wheel_acceleration` for fast vehicles.
+    pub g_vehicle_fast_acceleration: f32,
+    /// Let players choose their vehicle with `cl_vehicle`, otherwise everyone gets the standard one.
+    pub g_vehicles: bool,
+
+    /// Start in warmup, kills only count once enough players are ready.
+    pub g_warmup: bool,
+    /// Portion of playing players which have to be `ready` to end warmup.
+    pub g_warmup_ready_ratio: f32,
+    /// End warmup this many seconds after the first player joins, 0 means wait for ready.
+    pub g_warmup_time: f32,
+
     pub g_wheel_acceleration: f32,
 
+    /// How many chat lines are shown at once.
+    pub hud_chat_lines: usize,
+    /// How long chat lines stay on screen in seconds.
+    pub hud_chat_time: f32,
+
+    /// How many kills are shown at once.
+    pub hud_kill_feed_lines: usize,
+    /// How long kills stay on screen in seconds.
+    pub hud_kill_feed_time: f32,
+
+    /// How many nameplates `cl_caster` can show at once.
+    pub hud_nameplates_max: usize,
+
+    /// Warn about a bad connection in the bottom right corner.
+    pub hud_net_warnings: bool,
+    /// Warn when this fraction of updates from the server (0 to 1) didn't arrive in time.
+    pub hud_net_warn_loss: f32,
+    /// Warn when ping is above this many milliseconds.
+    pub hud_net_warn_ping: f32,
+
+    /// How long the summary stays on screen after a round ends, in seconds.
+    pub hud_summary_time: f32,
+
     pub m_pitch_max: f32,
     pub m_pitch_min: f32,
 
@@ -119,12 +430,180 @@ pub struct Cvars {
     /// Additional coefficient for vertical sensitivity.
     pub m_sensitivity_vertical: f32,
 
+    /// How long scorches and impact marks stay in seconds, 0 disables them.
+    pub r_decal_time: f32,
+    /// How many decals can be visible at once, the oldest one disappears first.
+    pub r_decals_max: usize,
+
+    /// How many point lights projectiles, explosions and impacts can have at once, 0 disables them.
+    pub r_dynamic_lights_max: usize,
+
+    /// Show particle effects.
+    pub r_effects: bool,
+    /// How many particle systems of each kind are preallocated.
+    pub r_effects_pool_size: usize,
+
     pub r_quality: i32,
+
+    /// Don't rebuild trails outside the camera's view.
+    pub r_trail_cull: bool,
+    /// How much trails glow, multiplies the player's color.
+    pub r_trail_glow: f32,
+    pub r_trail_height: f32,
+    /// How long trails are visible in seconds, they shrink over this time.
+    pub r_trail_lifetime: f32,
+    /// Trails have half as many segments every this many meters from the camera, 0 disables it.
+    pub r_trail_lod_distance: f32,
+    /// Each trail's oldest segments are removed above this count.
+    pub r_trail_max_segments: usize,
+    /// Segments per meter, 0 disables trails.
+    pub r_trail_quality: i32,
+
+    /// Show the weapon in the corner of the screen, meant for first person.
+    pub r_viewmodel: bool,
+    /// How much the weapon bobs at full speed, in meters.
+    pub r_viewmodel_bob: f32,
+    /// Field of view the weapon is drawn with, independent of `cl_camera_fov`.
+    pub r_viewmodel_fov: f32,
+    /// Position relative to the camera in meters.
+    pub r_viewmodel_forward: f32,
+    pub r_viewmodel_left: f32,
+    pub r_viewmodel_up: f32,
+    /// How much the weapon lags behind when the cycle speeds up or turns, per m/s².
+    pub r_viewmodel_sway: f32,
+
+    /// How long pieces of destroyed cycles stay in seconds, 0 disables them.
+    pub r_wreck_time: f32,
+    /// How many wrecks can be visible at once, the oldest one disappears first.
+    pub r_wrecks_max: usize,
+
+    /// Color of the server's name in the scoreboard header as `rrggbb`, empty means the default.
+    pub sv_accent_color: String,
+
+    /// Password clients can use to log in as admin. Empty means nobody can.
+    pub sv_admin_password: String,
+
+    /// Move players who don't touch the controls for this many seconds to observers, 0 disables it.
+    pub sv_afk_timeout: f32,
+
+    /// Add bots when people leave so teams stay even, they leave again when people join.
+    pub sv_bot_backfill: bool,
+
+    /// What to do when a chat line contains a word from `sv_chat_filter_words`.
+    /// Unless it's `off`, the words are censored.
+    pub sv_chat_filter: FilterAction,
+    /// How long `sv_chat_filter mute` mutes players for in seconds.
+    pub sv_chat_filter_mute_time: f32,
+    /// Space separated words, case insensitive, only whole words match.
+    pub sv_chat_filter_words: String,
+    /// Minimum time between chat lines of one player in seconds, the rest are dropped.
+    pub sv_chat_interval: f32,
+    /// Longer chat lines are cut off, in bytes.
+    pub sv_chat_max_length: usize,
+
+    /// Allow cheats (`noclip`, `god`, `give`, `teleport`). Turning it off turns off everybody's cheats.
+    pub sv_cheats: bool,
+
+    /// Record everything sent to all clients into this file, empty means not recording.
+    ///
+    /// Changing it finishes the current demo and starts a new one.
+    pub sv_demo_record: String,
+
+    /// Minimum time between emotes of one player in seconds, the rest are dropped.
+    pub sv_emote_interval: f32,
+
+    /// Append joins, leaves, kills, chat and map changes to this file as JSON lines,
+    /// empty means disabled. Only read at startup.
+    pub sv_event_log: String,
+
+    /// PNG shown next to the server's name in the scoreboard, at most 64x64 pixels and 16 KiB.
+    /// Empty means no icon.
+    pub sv_icon_file: String,
+
+    /// Reject connections when this many players aren't playing, 0 means no limit.
+    pub sv_max_observers: usize,
+    /// Only this many players can play at once, 0 means no limit.
+    /// Observers and spectators don't count, players who want to join have to wait.
+    pub sv_max_players: usize,
+
+    /// Message of the day shown to players when they connect, `\n` starts a new line.
+    /// Lines starting with `# ` are headings, lines starting with `- ` are list items.
+    pub sv_motd: String,
+    /// Read the message of the day from this file instead of `sv_motd`, empty means disabled.
+    pub sv_motd_file: String,
+
+    /// Shown to players in the window title and the scoreboard header.
+    pub sv_name: String,
+
+    /// Log a warning when a message sent to clients is larger than this many bytes, 0 disables it.
+    /// See also the `net_stats` command.
+    pub sv_net_warn_size: usize,
+
+    /// How often (in seconds) to measure each client's round trip time
+    /// and send everyone scores and pings. 0 disables it.
+    pub sv_ping_interval: f32,
+
+    /// How long (in seconds) disconnected players can reconnect and keep their score and team.
+    pub sv_reconnect_time: f32,
+
+    /// Where the server is hosted, e.g. `eu-west`, empty means unknown.
+    /// Sent to clients for their server history and included in `/status`.
+    pub sv_region: String,
+
+    /// How many of `sv_max_players` only admins can join, see `cl_admin_password`.
+    pub sv_reserved_slots: usize,
+
+    /// Forward log lines to admins which asked for them using `cl_remote_log`.
+    pub sv_remote_log: bool,
+    /// Only forward lines of this level and above (debug, info, warning, error).
+    pub sv_remote_log_level: LogLevel,
+
+    /// Where `/restart_update` saves the game for the new server binary,
+    /// which loads and deletes it on startup.
+    pub sv_restart_state_file: String,
+
+    /// Send messages to clients from this many threads, 0 means send from the main thread.
+    ///
+    /// Only worth it with many clients. Only read at startup.
+    pub sv_send_threads: usize,
+
+    /// Send clients updates this many times per second, at most the tickrate (60).
+    ///
+    /// Lower rates save bandwidth with many players,
+    /// clients keep simulating between updates so movement stays smooth.
+    pub sv_snapshot_rate: f32,
+    /// Clients can't ask for fewer updates per second than this, see `cl_updaterate`.
+    pub sv_snapshot_rate_min: f32,
+
+    /// Let spectators connect to this address to watch, empty means disabled. Only read at startup.
+    pub sv_spectator_addr: String,
+    /// How many seconds spectators lag behind so they can't help players.
+    pub sv_spectator_delay: f32,
+
+    /// Serve HTTP `/status` (JSON) and `/metrics` (Prometheus) here, empty means disabled.
+    /// Only read at startup.
+    pub sv_status_addr: String,
+
+    /// Demos larger than this many bytes aren't uploaded, only their stats.
+    pub sv_upload_max_size: usize,
+    /// POST each round's demo and stats to this `http://` URL, empty means disabled.
+    /// Needs `sv_demo_record` for demos, each round after the first is then recorded
+    /// to its own file next to it, e.g. `match.round2.rcdemo`.
+    pub sv_upload_url: String,
 }
 
 impl Default for Cvars {
     fn default() -> Self {
         Self {
+            a11y_font: String::new(),
+            a11y_font_size: 28.0,
+            a11y_high_contrast: false,
+            a11y_reduced_effects: false,
+
+            cl_admin_password: String::new(),
+
+            cl_aim_axis_speed: 180.0,
+
             cl_camera_3rd_person_back: 2.0,
             cl_camera_3rd_person_up: 0.5,
             cl_camera_fov: 75.0,
@@ -132,34 +611,97 @@ impl Default for Cvars {
             cl_camera_z_near: 0.001,
             cl_camera_z_far: 2048.0,
 
+            cl_caster: false,
+
+            cl_color: "ffffff".to_owned(),
+            cl_colorblind_mode: ColorblindMode::Off,
+
+            cl_discord_app_id: String::new(),
+
+            cl_extrapolate_max: 250.0,
+
+            cl_feedback_max_deg: 5.0,
+            cl_feedback_scale: 1.0,
+
             cl_fullscreen: true,
             cl_headless: false,
+            cl_identity_file: "identity.txt".to_owned(),
+            cl_language: "en".to_owned(),
             cl_mouse_grab_on_focus: true,
+            cl_mute_file: "mutes.txt".to_owned(),
+            cl_probe_rate: 10.0,
+            cl_profile_file: "profile.ron".to_owned(),
+            cl_punch_damage: 3.0,
+            cl_punch_recoil: 0.3,
+            cl_reconnect_attempts: 5,
+            cl_reconnect_delay: 1.0,
+            cl_remote_log: false,
+            cl_shake_explosion: 0.6,
+            cl_shake_radius: 15.0,
+            cl_servers_file: "servers.ron".to_owned(),
+            cl_skin: "default".to_owned(),
+            cl_smooth_time: 0.1,
+            cl_updaterate: 60.0,
+            cl_vehicle: VehicleKind::Standard,
+            cl_window_flash: true,
             cl_window_height: 540,
             cl_window_width: 960,
 
             cl_zoom_factor: 4.0,
 
+            d_alloc_audit: false,
+            d_alloc_audit_threshold: 10,
+
+            d_bench_bots: 8,
+            d_bench_ticks: 1000,
+            d_botclient_count: 32,
+            d_botclient_seconds: 0.0,
+            d_botclient_server: "127.0.0.1:26000".to_owned(),
+            d_crash_report_open: false,
+            d_crash_reports: true,
             d_dbg: false,
             d_dbgf: 0.0,
             d_dbgi: 0,
+            d_demo_extract: String::new(),
+            d_demo_file: String::new(),
+            d_demo_highlight_after: 2.0,
+            d_demo_highlight_before: 5.0,
+            d_desync_check: false,
+            d_desync_check_interval: 60,
+            d_desync_check_precision: 0.01,
 
             d_draw: true,
             d_draw_arrows: true,
+            d_draw_asserts: true,
+            d_draw_boxes: true,
+            d_draw_camera: true,
+            d_draw_capsules: true,
+            d_draw_circle_segments: 16,
+            d_draw_client: true,
             d_draw_crosses: true,
             d_draw_crosses_half_len: 0.5,
             d_draw_crosses_line_from_origin: false,
+            d_draw_cycles: true,
             d_draw_frame_timings: true,
             d_draw_frame_timings_steps: 4,
             d_draw_frame_timings_text: false,
             d_draw_lines: true,
+            d_draw_misc: true,
             d_draw_physics: true,
+            d_draw_projectiles: true,
             d_draw_rots: true,
+            d_draw_rots_size: 1.0,
+            d_draw_server: true,
+            d_draw_source_colors: false,
+            d_draw_spheres: true,
             d_draw_text: true,
             d_draw_text_shadow: true,
             d_draw_text_shadow_dilation: 0.0,
             d_draw_text_shadow_offset_x: 1.0,
             d_draw_text_shadow_offset_y: 1.0,
+            d_draw_timers: false,
+            d_draw_world_texts: true,
+            d_draw_world_texts_max: 64,
 
             d_engine_stats: true,
 
@@ -174,21 +716,107 @@ impl Default for Cvars {
             d_exit_after_one_frame: false,
             d_exit_on_unknown_cvar: true,
 
+            d_fly_camera: false,
+            d_fly_camera_speed: 20.0,
+
+            d_graph_frametime: false,
+            d_graph_frametime_distance: 1.0,
+            d_graph_frametime_len: 300,
+
+            d_hot_reload: false,
+            d_hot_reload_interval: 1.0,
+
+            d_inspector: false,
+            d_inspector_highlight_radius: 1.5,
+            d_inspector_rows_max: 32,
+            d_inspector_width: 600.0,
+
+            d_pause: false,
+
+            d_remote_debug: false,
+
+            d_rewind_seconds: 5.0,
+
             d_seed: 0,
 
+            d_singlestep: false,
+
+            d_timers_csv: String::new(),
+
             d_ui_msgs: false,
             d_ui_msgs_direction_from: true,
             d_ui_msgs_direction_to: false,
             d_ui_msgs_mouse: false,
 
+            g_armor_max: 100,
+            g_armor_per_hit: 50,
+
+            g_assist_time: 5.0,
+
+            g_autobalance_interval: 0.0,
+            g_autobalance_protect: 30.0,
+
+            g_competitive: false,
+            g_competitive_pause_time: 300.0,
+
+            g_cycle_friction: 0.0,
+            g_cycle_gravity_scale: 1.0,
+            g_cycle_linear_damping: 0.0,
+            g_cycle_mass: 1.0,
+            g_cycle_restitution: 0.0,
+            g_cycle_spawn_height: 5.0,
+            g_cycle_spawn_spread: 3.0,
+
+            g_map: "arena".to_owned(),
+
+            g_noclip_speed: 20.0,
+
+            g_out_of_bounds_time: 5.0,
+
+            g_overtime: OvertimeRule::GoldenFrag,
+            g_overtime_time: 60.0,
+
             g_physics_max_ccd_substeps: 100,
             g_physics_nudge: 0.01,
 
             g_projectile_lifetime: 60.0,
             g_projectile_speed: 50.0,
 
+            g_respawn: RespawnMode::Instant,
+            g_respawn_time: 3.0,
+            g_respawn_wave_time: 10.0,
+
+            g_score_limit: 10,
+
+            g_teams: 0,
+
+            g_time_limit: 0.0,
+
+            g_vehicle_armored_acceleration: 0.75,
+            g_vehicle_armored_armor: 50,
+            g_vehicle_fast_acceleration: 1.3,
+            g_vehicles: false,
+
+            g_warmup: false,
+            g_warmup_ready_ratio: 1.0,
+            g_warmup_time: 60.0,
+
             g_wheel_acceleration: 20.0,
 
+            hud_chat_lines: 5,
+            hud_chat_time: 10.0,
+
+            hud_kill_feed_lines: 5,
+            hud_kill_feed_time: 5.0,
+
+            hud_nameplates_max: 32,
+
+            hud_net_warnings: true,
+            hud_net_warn_loss: 0.1,
+            hud_net_warn_ping: 150.0,
+
+            hud_summary_time: 10.0,
+
             m_pitch_max: 90.0,
             m_pitch_min: -90.0,
 
@@ -196,7 +824,92 @@ impl Default for Cvars {
             m_sensitivity_horizontal: 1.0,
             m_sensitivity_vertical: 1.0,
 
+            r_decal_time: 30.0,
+            r_decals_max: 64,
+
+            r_dynamic_lights_max: 8,
+
+            r_effects: true,
+            r_effects_pool_size: 32,
             r_quality: 0,
+
+            r_trail_cull: true,
+            r_trail_glow: 2.0,
+            r_trail_height: 0.5,
+            r_trail_lifetime: 3.0,
+            r_trail_lod_distance: 50.0,
+            r_trail_max_segments: 1000,
+            r_trail_quality: 2,
+
+            r_viewmodel: false,
+            r_viewmodel_bob: 0.02,
+            r_viewmodel_fov: 70.0,
+            r_viewmodel_forward: 0.5,
+            r_viewmodel_left: -0.25,
+            r_viewmodel_up: -0.2,
+            r_viewmodel_sway: 0.002,
+
+            r_wreck_time: 4.0,
+            r_wrecks_max: 8,
+
+            sv_accent_color: String::new(),
+
+            sv_admin_password: String::new(),
+
+            sv_afk_timeout: 0.0,
+            sv_bot_backfill: false,
+
+            sv_chat_filter: FilterAction::Off,
+            sv_chat_filter_mute_time: 60.0,
+            sv_chat_filter_words: String::new(),
+            sv_chat_interval: 0.5,
+            sv_chat_max_length: 200,
+
+            sv_cheats: false,
+
+            sv_demo_record: String::new(),
+
+            sv_emote_interval: 1.0,
+
+            sv_event_log: String::new(),
+
+            sv_icon_file: String::new(),
+
+            sv_max_observers: 0,
+            sv_max_players: 0,
+
+            sv_motd: String::new(),
+            sv_motd_file: String::new(),
+
+            sv_name: "RustCycles server".to_owned(),
+
+            sv_net_warn_size: 16 * 1024,
+
+            sv_ping_interval: 1.0,
+
+            sv_reconnect_time: 60.0,
+
+            sv_region: String::new(),
+
+            sv_reserved_slots: 0,
+
+            sv_remote_log: false,
+            sv_remote_log_level: LogLevel::Info,
+
+            sv_restart_state_file: "restart_state.ron".to_owned(),
+
+            sv_send_threads: 0,
+
+            sv_snapshot_rate: 60.0,
+            sv_snapshot_rate_min: 10.0,
+
+            sv_spectator_addr: String::new(),
+            sv_spectator_delay: 30.0,
+
+            sv_status_addr: String::new(),
+
+            sv_upload_max_size: 64 * 1024 * 1024,
+            sv_upload_url: String::new(),
         }
     }
 }
@@ -7,7 +7,13 @@
 //! - Prefer `soft_assert` over `assert` in gamecode.
 //! - Use `dbg_log*` instead of `dbg`.
 //! - Use `dbg_text*` to print things that happen every frame.
-//! - Use `dbg_line`, `dbg_arrow`, `dbg_cross`, `dbg_rot` to draw shapes in 3D space.
+//! - Use `dbg_*_once` and `dbg_*_every` to rate limit things that happen every frame.
+//! - Use `dbg_worldtext` to label things in 3D space.
+//! - Use `dbg_timer` to measure how long a block of code takes each frame
+//!   and how many times it allocates.
+//! - Use `dbg_category` to group shapes so they can be toggled by `d_draw_<category>`.
+//! - Use `dbg_line`, `dbg_arrow`, `dbg_cross`, `dbg_rot`,
+//!   `dbg_sphere`, `dbg_box`, `dbg_obb`, `dbg_capsule` to draw shapes in 3D space.
 //! - If you're testing something that needs to be toggled at runtime,
 //!   consider using `cvars.d_dbg*`.
 //!
@@ -35,6 +41,7 @@
 //! LATER Offer a way for servers and clients to autoreport errors.
 //!
 //! LATER How does sending logs from sv to cl interact with cl vs sv framerates?
+//!     (Forwarding log lines to admins with `sv_remote_log` has the same issue.)
 //! LATER Add usage examples
 
 // Implementation note: the macros should be usable
@@ -42,12 +49,17 @@
 // This means they shouldn't end with semicolons
 // or should be wrapped with an extra pair of curly braces.
 // They should evaluate to `()`.
+// The exceptions are `dbg_timer` and `dbg_category` which return a guard.
 
 #![allow(dead_code)]
 
+pub(crate) mod alloc;
+pub(crate) mod crash;
 pub(crate) mod details;
 
 /// Same as `assert!` but only prints a message without crashing.
+///
+/// Failures are also counted per call site, see `d_draw_asserts` and the `asserts` command.
 #[macro_export]
 macro_rules! soft_assert {
     // The matchers are the same as in stdlib's assert.
@@ -66,21 +78,30 @@ macro_rules! soft_assert {
             if !tmp {
                 // LATER Proper logging
                 // LATER client vs server
-                dbg_logf!("soft assertion failed: {}, {}:{}:{}", format!($($arg)+), file!(), line!(), column!());
+                $crate::debug::details::soft_assert_failed(
+                    file!(),
+                    line!(),
+                    column!(),
+                    format!($($arg)+),
+                );
             }
         }
     };
 }
 
 /// Print text into stdout. Uses `println!(..)`-style formatting.
+///
+/// On the server, the text can also be forwarded to admins, see `sv_remote_log`.
 #[macro_export]
 macro_rules! dbg_logf {
+    ( ) => {
+        dbg_logf!("")
+    };
     ( $( $t:tt )* ) => {
-        {
-            let name = $crate::debug::details::endpoint_name();
-            print!("{} ", name);
-            println!( $( $t )* );
-        }
+        $crate::debug::details::log_line(
+            $crate::debug::details::LogLevel::Info,
+            format!( $( $t )* ),
+        )
     };
 }
 
@@ -95,6 +116,39 @@ macro_rules! dbg_logd {
     };
 }
 
+/// Print text into stdout only the first time this line is reached.
+/// Uses `println!(..)`-style formatting.
+///
+/// The state is per call site and shared by all endpoints (e.g. both cl and sv in a local game).
+#[macro_export]
+macro_rules! dbg_logf_once {
+    ( $( $t:tt )* ) => {
+        {
+            static DONE: ::std::sync::atomic::AtomicBool = ::std::sync::atomic::AtomicBool::new(false);
+            if $crate::debug::details::first_time(&DONE) {
+                $crate::dbg_logf!( $( $t )* );
+            }
+        }
+    };
+}
+
+/// Print text into stdout every `n`-th time this line is reached, starting with the first.
+/// Uses `println!(..)`-style formatting.
+///
+/// Useful for logging something that happens every frame at a readable rate.
+/// The state is per call site and shared by all endpoints (e.g. both cl and sv in a local game).
+#[macro_export]
+macro_rules! dbg_logf_every {
+    ( $n:expr, $( $t:tt )* ) => {
+        {
+            static COUNTER: ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUsize::new(0);
+            if $crate::debug::details::nth_time(&COUNTER, $n) {
+                $crate::dbg_logf!( $( $t )* );
+            }
+        }
+    };
+}
+
 /// Print text onto the screen. Uses `println!(..)`-style formatting.
 ///
 /// Useful for printing debug info each frame.
@@ -115,6 +169,36 @@ macro_rules! dbg_textf {
     };
 }
 
+/// Print text onto the screen only the first time this line is reached.
+/// Uses `println!(..)`-style formatting.
+///
+/// Combine with a longer lasting shape or world text if you need to see it for more than a frame.
+#[macro_export]
+macro_rules! dbg_textf_once {
+    ( $( $t:tt )* ) => {
+        {
+            static DONE: ::std::sync::atomic::AtomicBool = ::std::sync::atomic::AtomicBool::new(false);
+            if $crate::debug::details::first_time(&DONE) {
+                $crate::dbg_textf!( $( $t )* );
+            }
+        }
+    };
+}
+
+/// Print text onto the screen every `n`-th time this line is reached, starting with the first.
+/// Uses `println!(..)`-style formatting.
+#[macro_export]
+macro_rules! dbg_textf_every {
+    ( $n:expr, $( $t:tt )* ) => {
+        {
+            static COUNTER: ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUsize::new(0);
+            if $crate::debug::details::nth_time(&COUNTER, $n) {
+                $crate::dbg_textf!( $( $t )* );
+            }
+        }
+    };
+}
+
 /// Print variables onto the screen formatted as `[file:line] var1: value1, var2: value2`.
 ///
 /// Useful for printing debug info each frame.
@@ -128,6 +212,66 @@ macro_rules! dbg_textd {
     };
 }
 
+/// Print text onto the screen at the given world coordinates.
+/// The text can be anything that implements `ToString`.
+/// Optionally specify
+/// - how long it lasts in seconds (default is 0.0 which means 1 frame)
+/// - color
+///
+/// Useful for labeling entities with their IDs, states, etc.
+#[macro_export]
+macro_rules! dbg_worldtext {
+    ($pos:expr, $text:expr, $time:expr, $color:expr) => {
+        $crate::debug::details::debug_world_text($pos, $text.to_string(), $time as f32, $color)
+    };
+    ($pos:expr, $text:expr, $time:expr) => {
+        $crate::dbg_worldtext!($pos, $text, $time, $crate::debug::details::endpoint_color())
+    };
+    ($pos:expr, $text:expr) => {
+        $crate::dbg_worldtext!($pos, $text, 0.0)
+    };
+}
+
+/// Measure how long it takes until the end of the current scope.
+///
+/// All measurements with the same name are summed up per frame
+/// and shown with `d_draw_timers` or saved to a file with `d_timers_csv`.
+///
+/// The name must be a `&'static str`.
+///
+/// # Usage
+///
+/// ```rust
+/// let _timer = dbg_timer!("physics");
+/// ```
+///
+/// Note that `let _ = dbg_timer!(...)` would drop the timer immediately.
+#[macro_export]
+macro_rules! dbg_timer {
+    ($name:expr) => {
+        $crate::debug::details::DebugTimer::new($name)
+    };
+}
+
+/// Set the category of all debug shapes drawn until the end of the current scope.
+///
+/// The argument is a variant of `DebugCategory`.
+/// Shapes drawn outside any category are `Misc`.
+///
+/// # Usage
+///
+/// ```rust
+/// let _cat = dbg_category!(Camera);
+/// ```
+#[macro_export]
+macro_rules! dbg_category {
+    ($category:ident) => {
+        $crate::debug::details::DebugCategoryGuard::new(
+            $crate::debug::details::DebugCategory::$category,
+        )
+    };
+}
+
 /// Draw a line from `begin` to `end` (in world coordinates).
 /// Optionally specify
 /// - how long it lasts in seconds (default is 0.0 which means 1 frame)
@@ -180,16 +324,100 @@ macro_rules! dbg_cross {
 }
 
 /// Draw RGB basis vectors at `point`, rotated by `rot`.
+///
+/// Red is left (X), green is up (Y), blue is forward (Z).
+/// `rot` can be anything that converts into a `UnitQuaternion`,
+/// e.g. a `Rotation3` if you have a basis instead of a quaternion.
+/// Optionally specify
+/// - how long it lasts in seconds (default is 0.0 which means 1 frame)
+/// - length of the axes (default is 1.0, multiplied by `d_draw_rots_size`)
 #[macro_export]
 macro_rules! dbg_rot {
+    ($point:expr, $rot:expr, $time:expr, $scale:expr) => {
+        $crate::debug::details::debug_rot($point, $rot, $time as f32, $scale as f32)
+    };
     ($point:expr, $rot:expr, $time:expr) => {
-        $crate::debug::details::debug_rot($point, $rot, $time as f32)
+        $crate::dbg_rot!($point, $rot, $time, 1.0)
     };
     ($point:expr, $rot:expr) => {
         $crate::dbg_rot!($point, $rot, 0.0)
     };
 }
 
+/// Draw a wireframe sphere.
+/// Optionally specify
+/// - how long it lasts in seconds (default is 0.0 which means 1 frame)
+/// - color
+#[macro_export]
+macro_rules! dbg_sphere {
+    ($center:expr, $radius:expr, $time:expr, $color:expr) => {
+        $crate::debug::details::debug_sphere($center, $radius as f32, $time as f32, $color)
+    };
+    ($center:expr, $radius:expr, $time:expr) => {
+        $crate::dbg_sphere!($center, $radius, $time, $crate::debug::details::endpoint_color())
+    };
+    ($center:expr, $radius:expr) => {
+        $crate::dbg_sphere!($center, $radius, 0.0)
+    };
+}
+
+/// Draw an axis-aligned box (AABB) given its center and half extents.
+/// Optionally specify
+/// - how long it lasts in seconds (default is 0.0 which means 1 frame)
+/// - color
+#[macro_export]
+macro_rules! dbg_box {
+    ($center:expr, $half_extents:expr, $time:expr, $color:expr) => {
+        $crate::debug::details::debug_box($center, $half_extents, $time as f32, $color)
+    };
+    ($center:expr, $half_extents:expr, $time:expr) => {
+        $crate::dbg_box!($center, $half_extents, $time, $crate::debug::details::endpoint_color())
+    };
+    ($center:expr, $half_extents:expr) => {
+        $crate::dbg_box!($center, $half_extents, 0.0)
+    };
+}
+
+/// Draw an oriented box (OBB) given its center, half extents and rotation.
+/// Optionally specify
+/// - how long it lasts in seconds (default is 0.0 which means 1 frame)
+/// - color
+#[macro_export]
+macro_rules! dbg_obb {
+    ($center:expr, $half_extents:expr, $rot:expr, $time:expr, $color:expr) => {
+        $crate::debug::details::debug_obb($center, $half_extents, $rot, $time as f32, $color)
+    };
+    ($center:expr, $half_extents:expr, $rot:expr, $time:expr) => {
+        $crate::dbg_obb!(
+            $center,
+            $half_extents,
+            $rot,
+            $time,
+            $crate::debug::details::endpoint_color()
+        )
+    };
+    ($center:expr, $half_extents:expr, $rot:expr) => {
+        $crate::dbg_obb!($center, $half_extents, $rot, 0.0)
+    };
+}
+
+/// Draw a capsule - `begin` and `end` are the centers of the hemispheres.
+/// Optionally specify
+/// - how long it lasts in seconds (default is 0.0 which means 1 frame)
+/// - color
+#[macro_export]
+macro_rules! dbg_capsule {
+    ($begin:expr, $end:expr, $radius:expr, $time:expr, $color:expr) => {
+        $crate::debug::details::debug_capsule($begin, $end, $radius as f32, $time as f32, $color)
+    };
+    ($begin:expr, $end:expr, $radius:expr, $time:expr) => {
+        $crate::dbg_capsule!($begin, $end, $radius, $time, $crate::debug::details::endpoint_color())
+    };
+    ($begin:expr, $end:expr, $radius:expr) => {
+        $crate::dbg_capsule!($begin, $end, $radius, 0.0)
+    };
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(clippy::unit_cmp)] // https://github.com/rust-lang/rust-clippy/issues/4661
@@ -232,6 +460,22 @@ mod tests {
         assert_eq!(execution_count, 4 + 1); // +1 because only one match arm runs
     }
 
+    #[test]
+    fn test_soft_assert_counts() {
+        crate::debug::details::asserts_clear();
+        for _ in 0..3 {
+            soft_assert!(2 + 2 == 5, "test_soft_assert_counts");
+        }
+        let text = crate::debug::details::asserts_text();
+        assert_eq!(text.len(), 1);
+        assert!(text[0].contains("3x: test_soft_assert_counts"));
+
+        let total = crate::debug::details::asserts_total();
+        crate::debug::details::asserts_clear();
+        assert!(crate::debug::details::asserts_text().is_empty());
+        assert_eq!(crate::debug::details::asserts_total(), total);
+    }
+
     #[test]
     fn test_logging_compiles() {
         #![allow(clippy::let_unit_value)] // We need to test that the macros eval to a ()
@@ -255,6 +499,20 @@ mod tests {
         dbg_textd!(x);
         dbg_textd!(x, y, 7);
 
+        dbg_worldtext!(v!(1 2 3), "abcd");
+        dbg_worldtext!(v!(1 2 3), format!("x: {}", x), 5);
+        dbg_worldtext!(v!(1 2 3), x, 5.0, BLUE);
+
+        dbg_logf_once!("abcd");
+        dbg_logf_once!("x: {}, y: {y}, 7: {}", x, 7);
+        dbg_logf_every!(10, "abcd");
+        dbg_logf_every!(10, "x: {}, y: {y}, 7: {}", x, 7);
+
+        dbg_textf_once!("abcd");
+        dbg_textf_once!("x: {}, y: {y}, 7: {}", x, 7);
+        dbg_textf_every!(10, "abcd");
+        dbg_textf_every!(10, "x: {}, y: {y}, 7: {}", x, 7);
+
         // Test the macros in expression position
         #[allow(unreachable_patterns)]
         let nothing = match 0 {
@@ -273,10 +531,81 @@ mod tests {
             _ => dbg_textd!(),
             _ => dbg_textd!(x),
             _ => dbg_textd!(x, y, 7),
+
+            _ => dbg_worldtext!(v!(1 2 3), "abcd"),
+            _ => dbg_worldtext!(v!(1 2 3), format!("x: {}", x), 5),
+            _ => dbg_worldtext!(v!(1 2 3), x, 5.0, BLUE),
+
+            _ => dbg_logf_once!("abcd"),
+            _ => dbg_logf_every!(10, "x: {}, y: {y}, 7: {}", x, 7),
+            _ => dbg_textf_once!("abcd"),
+            _ => dbg_textf_every!(10, "x: {}, y: {y}, 7: {}", x, 7),
         };
         assert_eq!(nothing, ());
     }
 
+    #[test]
+    fn test_rate_limit() {
+        use crate::debug::details::DEBUG_TEXTS;
+
+        let count = |prefix: &str| {
+            DEBUG_TEXTS.with(|texts| texts.borrow().iter().filter(|t| t.contains(prefix)).count())
+        };
+
+        for _ in 0..10 {
+            dbg_textf_once!("test_rate_limit once");
+            dbg_textf_every!(3, "test_rate_limit every");
+        }
+        // Calls 0, 3, 6, 9
+        assert_eq!(count("test_rate_limit once"), 1);
+        assert_eq!(count("test_rate_limit every"), 4);
+    }
+
+    #[test]
+    fn test_timer() {
+        {
+            let _timer = dbg_timer!("test_timer");
+            let _timer2 = dbg_timer!("test_timer");
+            let _allocated = vec![1];
+        }
+
+        let cvars = Cvars::default();
+        crate::debug::details::timers_end_frame(&cvars);
+        crate::debug::details::DEBUG_TIMINGS.with(|timings| {
+            let timings = timings.borrow();
+            let entry = timings.last.iter().find(|entry| entry.name == "test_timer").unwrap();
+            assert_eq!(entry.count, 2);
+            // Other tests can allocate at the same time.
+            if crate::debug::alloc::ENABLED {
+                assert!(entry.allocs >= 2);
+            }
+        });
+    }
+
+    #[test]
+    fn test_category() {
+        use crate::debug::details::{DebugCategory, DEBUG_SHAPES};
+
+        let last_category = || DEBUG_SHAPES.with(|shapes| shapes.borrow().last().unwrap().category);
+
+        dbg_line!(v!(1 2 3), v!(4 5 6));
+        assert_eq!(last_category(), DebugCategory::Misc);
+        {
+            let _cat = dbg_category!(Camera);
+            dbg_line!(v!(1 2 3), v!(4 5 6));
+            assert_eq!(last_category(), DebugCategory::Camera);
+            {
+                let _cat = dbg_category!(Cycles);
+                dbg_cross!(v!(1 2 3));
+                assert_eq!(last_category(), DebugCategory::Cycles);
+            }
+            dbg_cross!(v!(1 2 3));
+            assert_eq!(last_category(), DebugCategory::Camera);
+        }
+        dbg_line!(v!(1 2 3), v!(4 5 6));
+        assert_eq!(last_category(), DebugCategory::Misc);
+    }
+
     #[test]
     fn test_drawing_compiles_no_import() {
         #![allow(clippy::let_unit_value)] // We need to test that the macros eval to a ()
@@ -302,6 +631,24 @@ mod tests {
         let rot = UnitQuaternion::from_euler_angles(0.1, 0.2, 0.3);
         dbg_rot!(v!(1 2 3), rot);
         dbg_rot!(v!(1 2 3), rot, 5.0);
+        dbg_rot!(v!(1 2 3), rot, 5.0, 2);
+        dbg_rot!(v!(1 2 3), rot.to_rotation_matrix());
+
+        dbg_sphere!(v!(1 2 3), 4);
+        dbg_sphere!(v!(1 2 3), 4.0, 5);
+        dbg_sphere!(v!(1 2 3), 4.0, 5.0, BLUE);
+
+        dbg_box!(v!(1 2 3), v!(4 5 6));
+        dbg_box!(v!(1 2 3), v!(4 5 6), 5);
+        dbg_box!(v!(1 2 3), v!(4 5 6), 5.0, BLUE);
+
+        dbg_obb!(v!(1 2 3), v!(4 5 6), rot);
+        dbg_obb!(v!(1 2 3), v!(4 5 6), rot, 5);
+        dbg_obb!(v!(1 2 3), v!(4 5 6), rot, 5.0, BLUE);
+
+        dbg_capsule!(v!(1 2 3), v!(4 5 6), 1);
+        dbg_capsule!(v!(1 2 3), v!(4 5 6), 1.0, 5);
+        dbg_capsule!(v!(1 2 3), v!(4 5 6), 1.0, 5.0, BLUE);
 
         // Test the macros in expression position
         #[allow(unreachable_patterns)]
@@ -326,6 +673,24 @@ mod tests {
 
             _ => dbg_rot!(v!(1 2 3), rot),
             _ => dbg_rot!(v!(1 2 3), rot, 5.0),
+            _ => dbg_rot!(v!(1 2 3), rot, 5.0, 2),
+            _ => dbg_rot!(v!(1 2 3), rot.to_rotation_matrix()),
+
+            _ => dbg_sphere!(v!(1 2 3), 4),
+            _ => dbg_sphere!(v!(1 2 3), 4.0, 5),
+            _ => dbg_sphere!(v!(1 2 3), 4.0, 5.0, BLUE),
+
+            _ => dbg_box!(v!(1 2 3), v!(4 5 6)),
+            _ => dbg_box!(v!(1 2 3), v!(4 5 6), 5),
+            _ => dbg_box!(v!(1 2 3), v!(4 5 6), 5.0, BLUE),
+
+            _ => dbg_obb!(v!(1 2 3), v!(4 5 6), rot),
+            _ => dbg_obb!(v!(1 2 3), v!(4 5 6), rot, 5),
+            _ => dbg_obb!(v!(1 2 3), v!(4 5 6), rot, 5.0, BLUE),
+
+            _ => dbg_capsule!(v!(1 2 3), v!(4 5 6), 1),
+            _ => dbg_capsule!(v!(1 2 3), v!(4 5 6), 1.0, 5),
+            _ => dbg_capsule!(v!(1 2 3), v!(4 5 6), 1.0, 5.0, BLUE),
         };
         assert_eq!(nothing, ());
     }
@@ -0,0 +1,58 @@
+//! Counting allocations - used by `rustcycles bench`, `dbg_timer!` and `d_alloc_audit`.
+//!
+//! With the `alloc_count` feature, this wraps the system allocator for the whole process.
+//! The overhead is one relaxed atomic increment per allocation.
+//! Without it (the default, e.g. for release servers), nothing is counted.
+
+#[cfg(feature = "alloc_count")]
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Whether `allocations` returns real numbers.
+pub(crate) const ENABLED: bool = cfg!(feature = "alloc_count");
+
+#[cfg(feature = "alloc_count")]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[cfg(feature = "alloc_count")]
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "alloc_count")]
+struct CountingAllocator;
+
+#[cfg(feature = "alloc_count")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Total number of allocations (including reallocations) since the process started, in all threads.
+#[cfg(feature = "alloc_count")]
+pub(crate) fn allocations() -> usize {
+    ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+/// Always 0 without the `alloc_count` feature, check `ENABLED`.
+#[cfg(not(feature = "alloc_count"))]
+pub(crate) fn allocations() -> usize {
+    0
+}
@@ -0,0 +1,195 @@
+//! Crash reports - written by the panic hook so players can attach them to bug reports.
+//!
+//! The hook can't access the game's state so the interesting parts
+//! are copied here as they change.
+
+// `PanicInfo` is deprecated in favor of `PanicHookInfo` which needs Rust 1.81.
+#[allow(deprecated)]
+use std::panic::PanicInfo;
+use std::{
+    backtrace::Backtrace,
+    cell::RefCell,
+    fmt::Write as _,
+    fs,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    debug::details::{endpoint_name, DEBUG_LOG_HISTORY},
+    prelude::*,
+};
+
+thread_local! {
+    /// The current cvars, updated whenever they change.
+    static CVARS: RefCell<Option<Cvars>> = RefCell::new(None);
+
+    /// Endpoint name, frame number and game time of the last gamelogic frame.
+    static FRAMES: RefCell<Vec<(&'static str, usize, f32)>> = RefCell::new(Vec::new());
+}
+
+pub(crate) fn set_cvars(cvars: &Cvars) {
+    CVARS.with(|c| *c.borrow_mut() = Some(cvars.clone()));
+}
+
+/// Call at the start of each gamelogic frame.
+pub(crate) fn set_frame(frame_number: usize, game_time: f32) {
+    let endpoint = endpoint_name();
+    FRAMES.with(|frames| {
+        let mut frames = frames.borrow_mut();
+        match frames.iter_mut().find(|frame| frame.0 == endpoint) {
+            Some(frame) => *frame = (endpoint, frame_number, game_time),
+            None => frames.push((endpoint, frame_number, game_time)),
+        }
+    });
+}
+
+/// Called from the panic hook.
+///
+/// Nothing here should panic - if a RefCell is borrowed, that part is skipped.
+#[allow(deprecated)]
+pub(crate) fn write_report(panic_info: &PanicInfo) {
+    let cvars = CVARS
+        .try_with(|c| c.try_borrow().ok().and_then(|c| c.clone()))
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    if !cvars.d_crash_reports {
+        return;
+    }
+
+    let report = report_text(panic_info, &cvars);
+
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let path = format!("crash-{}-{}.txt", endpoint_name(), secs);
+    if let Err(err) = fs::write(&path, report) {
+        // Not using dbg_logf here because we're already panicking.
+        eprintln!("Failed to write crash report to {}: {}", path, err);
+        return;
+    }
+    eprintln!("Crash report written to {}", path);
+
+    if cvars.d_crash_report_open {
+        open(&path);
+    }
+}
+
+#[allow(deprecated)]
+fn report_text(panic_info: &PanicInfo, cvars: &Cvars) -> String {
+    let mut report = String::new();
+
+    // Writing into a String can't fail.
+    let _ = writeln!(report, "RustCycles {} crash report", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "endpoint: {}", endpoint_name());
+    let _ = writeln!(report);
+
+    let _ = writeln!(report, "panic: {}", panic_info);
+    let _ = writeln!(report);
+
+    let _ = writeln!(report, "frames:");
+    let _ = FRAMES.try_with(|frames| {
+        if let Ok(frames) = frames.try_borrow() {
+            for (endpoint, frame_number, game_time) in frames.iter() {
+                let _ = writeln!(
+                    report,
+                    "    {} frame {} game time {:.3}",
+                    endpoint, frame_number, game_time
+                );
+            }
+        }
+    });
+    let _ = writeln!(report);
+
+    let _ = writeln!(report, "cvars changed from defaults:");
+    for line in cvars_diff(cvars) {
+        let _ = writeln!(report, "    {}", line);
+    }
+    let _ = writeln!(report);
+
+    let _ = writeln!(report, "recent log:");
+    let _ = DEBUG_LOG_HISTORY.try_with(|history| {
+        if let Ok(history) = history.try_borrow() {
+            for line in history.iter() {
+                let _ = writeln!(report, "    {}", line);
+            }
+        }
+    });
+    let _ = writeln!(report);
+
+    let _ = writeln!(report, "backtrace:");
+    let _ = writeln!(report, "{}", Backtrace::force_capture());
+
+    report
+}
+
+/// Lines of the form `name: value` for each cvar which is different from its default.
+///
+/// Passwords are never included.
+fn cvars_diff(cvars: &Cvars) -> Vec<String> {
+    let default = Cvars::default();
+    cvar_names(&default)
+        .into_iter()
+        .filter(|name| !name.contains("password"))
+        .filter_map(|name| {
+            let value = cvars.get_string(&name).ok()?;
+            let default_value = default.get_string(&name).ok()?;
+            (value != default_value).then(|| format!("{}: {}", name, value))
+        })
+        .collect()
+}
+
+/// There's no way to iterate over cvars, so take the field names from their Debug output.
+///
+/// Fields are indented by 4 spaces, anything more is part of a multiline value.
+///
+/// LATER Use a list of names from the cvars crate once it generates one,
+/// 0.1.0 only generates getters and setters.
+fn cvar_names(cvars: &Cvars) -> Vec<String> {
+    format!("{:#?}", cvars)
+        .lines()
+        .filter_map(|line| {
+            let field = line.strip_prefix("    ")?;
+            let (name, _) = field.split_once(": ")?;
+            (!name.starts_with(' ')).then(|| name.to_owned())
+        })
+        .collect()
+}
+
+/// Open the file in the default program.
+fn open(path: &str) {
+    #[cfg(target_os = "windows")]
+    let res = Command::new("cmd").args(["/C", "start", "", path]).spawn();
+
+    #[cfg(target_os = "macos")]
+    let res = Command::new("open").arg(path).spawn();
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let res = Command::new("xdg-open").arg(path).spawn();
+
+    if let Err(err) = res {
+        eprintln!("Failed to open crash report: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cvars_diff() {
+        let mut cvars = Cvars::default();
+        assert!(cvars_diff(&cvars).is_empty());
+
+        cvars.cl_camera_fov = 100.0;
+        cvars.sv_admin_password = "hunter2".to_owned();
+        cvars.g_map = "arena2".to_owned();
+        assert_eq!(cvars_diff(&cvars), vec!["cl_camera_fov: 100", "g_map: arena2"]);
+
+        // Every name has to be a real cvar, `cvars_diff` would skip the rest silently.
+        let names = cvar_names(&cvars);
+        assert!(names.len() > 100);
+        for name in names {
+            assert!(cvars.get_string(&name).is_ok(), "{}", name);
+        }
+    }
+}
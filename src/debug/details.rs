@@ -4,13 +4,32 @@
 //! but in normal usage you should prefer the `dbg_*` macros
 //! and other items from the parent mod.
 
-use std::cell::RefCell;
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    f32::consts::PI,
+    fs::OpenOptions,
+    io::Write,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
 
 use fxhash::FxHashMap;
-use fyrox::{core::algebra::Vector3, scene::debug::Line};
-use serde::{Deserialize, Serialize};
-
-use crate::prelude::*;
+use fyrox::{
+    core::{algebra::Vector3, instant::Instant},
+    scene::debug::Line,
+};
+use rustcycles_protocol::debug::Rgba;
+
+use crate::{
+    common::palette::{team_color, ColorblindMode},
+    debug::alloc,
+    prelude::*,
+};
+
+pub(crate) use rustcycles_protocol::debug::{
+    DebugCategory, DebugShape, DebugSource, DebugWorldText, LogLevel, LogLine, Shape,
+};
 
 /// Private helper to print the name and value of each given variable.
 /// Not meant to be used directly.
@@ -33,56 +52,93 @@ macro_rules! __format_pairs {
     };
 }
 
-/// Helper struct, use one of the `dbg_*!()` macros.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub(crate) struct DebugShape {
-    pub(crate) shape: Shape,
-    /// Time left (decreases every frame)
-    pub(crate) time: f32,
-    #[serde(with = "ColorDef")]
-    pub(crate) color: Color,
-}
-
-/// Helper struct, use one of the `dbg_*!()` macros.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub(crate) enum Shape {
-    Line {
-        begin: Vec3,
-        end: Vec3,
-    },
-    Arrow {
-        begin: Vec3,
-        dir: Vec3,
-    },
-    Cross {
-        point: Vec3,
-    },
-    Rot {
-        point: Vec3,
-        rot: UnitQuaternion<f32>,
-    },
-}
-
-/// Fyrox's Color doesn't impl serde traits
-/// so we do this: https://serde.rs/remote-derive.html
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(remote = "Color")]
-pub struct ColorDef {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8,
-    pub a: u8,
-}
-
-impl DebugShape {
-    pub(crate) fn to_lines(&self, cvars: &Cvars, lines: &mut Lines) {
+/// Behavior of `DebugSource` which depends on the rest of the game.
+trait DebugSourceExt {
+    fn color(self, mode: ColorblindMode) -> Color;
+}
+
+impl DebugSourceExt for DebugSource {
+    fn color(self, mode: ColorblindMode) -> Color {
+        match self {
+            DebugSource::Client => team_color(mode, 0),
+            DebugSource::Server => team_color(mode, 1),
+            DebugSource::Other => team_color(mode, 2),
+        }
+    }
+}
+
+/// Colors are sent over the network as `Rgba` because the protocol crate doesn't use Fyrox.
+pub(crate) fn to_rgba(color: Color) -> Rgba {
+    Rgba {
+        r: color.r,
+        g: color.g,
+        b: color.b,
+        a: color.a,
+    }
+}
+
+pub(crate) fn from_rgba(rgba: Rgba) -> Color {
+    Color::from_rgba(rgba.r, rgba.g, rgba.b, rgba.a)
+}
+
+/// Helper struct, use `dbg_category!()`.
+///
+/// Restores the previous category when dropped.
+#[derive(Debug)]
+#[must_use = "the category only applies until it's dropped, assign it to a named variable like `_cat`"]
+pub(crate) struct DebugCategoryGuard {
+    prev: DebugCategory,
+}
+
+impl DebugCategoryGuard {
+    pub(crate) fn new(category: DebugCategory) -> Self {
+        let prev = DEBUG_CATEGORY.with(|cur| cur.replace(category));
+        Self { prev }
+    }
+}
+
+impl Drop for DebugCategoryGuard {
+    fn drop(&mut self) {
+        DEBUG_CATEGORY.with(|cur| cur.set(self.prev));
+    }
+}
+
+/// Drawing of `DebugShape`s, only done on the client.
+pub(crate) trait DebugShapeExt {
+    fn to_lines(&self, cvars: &Cvars, lines: &mut Lines);
+}
+
+impl DebugShapeExt for DebugShape {
+    fn to_lines(&self, cvars: &Cvars, lines: &mut Lines) {
+        let category_enabled = match self.category {
+            DebugCategory::Misc => cvars.d_draw_misc,
+            DebugCategory::Camera => cvars.d_draw_camera,
+            DebugCategory::Cycles => cvars.d_draw_cycles,
+            DebugCategory::Projectiles => cvars.d_draw_projectiles,
+        };
+        let source_enabled = match self.source {
+            DebugSource::Client => cvars.d_draw_client,
+            DebugSource::Server => cvars.d_draw_server,
+            DebugSource::Other => true,
+        };
+        if !category_enabled || !source_enabled {
+            return;
+        }
+
+        // Overriding the color helps when it's not clear whether cl or sv drew something.
+        let color = if cvars.d_draw_source_colors {
+            self.source.color(cvars.cl_colorblind_mode)
+        } else {
+            from_rgba(self.color)
+        };
+
         match self.shape {
             Shape::Line { begin, end } => {
                 if !cvars.d_draw_lines {
                     return;
                 }
 
-                lines.insert(begin, end, self.color);
+                lines.insert(begin, end, color);
             }
             Shape::Arrow { begin, dir } => {
                 if !cvars.d_draw_arrows {
@@ -90,7 +146,7 @@ impl DebugShape {
                 }
 
                 let end = begin + dir;
-                lines.insert(begin, end, self.color);
+                lines.insert(begin, end, color);
 
                 // When the arrow is horizontal, we want two of the side lines
                 // to be above and below the arrow body and the other two to the sides.
@@ -108,10 +164,10 @@ impl DebugShape {
                 let len = dir.magnitude();
                 let left = rot * LEFT * len;
                 let up = rot * UP * len;
-                lines.insert(end, end + (-dir + left) * 0.25, self.color);
-                lines.insert(end, end + (-dir - left) * 0.25, self.color);
-                lines.insert(end, end + (-dir + up) * 0.25, self.color);
-                lines.insert(end, end + (-dir - up) * 0.25, self.color);
+                lines.insert(end, end + (-dir + left) * 0.25, color);
+                lines.insert(end, end + (-dir - left) * 0.25, color);
+                lines.insert(end, end + (-dir + up) * 0.25, color);
+                lines.insert(end, end + (-dir - up) * 0.25, color);
             }
             Shape::Cross { point } => {
                 if !cvars.d_draw_crosses {
@@ -122,26 +178,100 @@ impl DebugShape {
                 let dir2 = v!(-1 1 1) * cvars.d_draw_crosses_half_len;
                 let dir3 = v!(1 1 -1) * cvars.d_draw_crosses_half_len;
                 let dir4 = v!(-1 1 -1) * cvars.d_draw_crosses_half_len;
-                lines.insert(point - dir1, point + dir1, self.color);
-                lines.insert(point - dir2, point + dir2, self.color);
-                lines.insert(point - dir3, point + dir3, self.color);
-                lines.insert(point - dir4, point + dir4, self.color);
+                lines.insert(point - dir1, point + dir1, color);
+                lines.insert(point - dir2, point + dir2, color);
+                lines.insert(point - dir3, point + dir3, color);
+                lines.insert(point - dir4, point + dir4, color);
 
                 if cvars.d_draw_crosses_line_from_origin {
                     // This is sometimes useful if we have trouble finding the cross.
-                    lines.insert(Vec3::zeros(), point, self.color);
+                    lines.insert(Vec3::zeros(), point, color);
                 }
             }
-            Shape::Rot { point, rot } => {
+            Shape::Rot { point, rot, scale } => {
                 if !cvars.d_draw_rots {
                     return;
                 }
 
                 // Oringally, this used SceneDrawingContext::draw_transform
                 // but this way we can use BLUE2 instead of the hard to see BLUE.
-                lines.insert(point, point + rot * LEFT, RED);
-                lines.insert(point, point + rot * UP, GREEN);
-                lines.insert(point, point + rot * FORWARD, BLUE2);
+                let len = scale * cvars.d_draw_rots_size;
+                lines.insert(point, point + rot * LEFT * len, RED);
+                lines.insert(point, point + rot * UP * len, GREEN);
+                lines.insert(point, point + rot * FORWARD * len, BLUE2);
+            }
+            Shape::Sphere { center, radius } => {
+                if !cvars.d_draw_spheres {
+                    return;
+                }
+
+                // Three great circles, one in each plane.
+                let segments = cvars.d_draw_circle_segments;
+                let left = LEFT * radius;
+                let up = UP * radius;
+                let forward = FORWARD * radius;
+                let full = 2.0 * PI;
+                lines.insert_arc(center, left, up, 0.0, full, segments, color);
+                lines.insert_arc(center, left, forward, 0.0, full, segments, color);
+                lines.insert_arc(center, up, forward, 0.0, full, segments, color);
+            }
+            Shape::Box {
+                center,
+                half_extents,
+                rot,
+            } => {
+                if !cvars.d_draw_boxes {
+                    return;
+                }
+
+                let corner = |x: f32, y: f32, z: f32| {
+                    let local = v!(x * half_extents.x, y * half_extents.y, z * half_extents.z);
+                    center + rot * local
+                };
+                // Each edge connects corners which differ in exactly one coordinate.
+                for a in [-1.0, 1.0] {
+                    for b in [-1.0, 1.0] {
+                        lines.insert(corner(-1.0, a, b), corner(1.0, a, b), color);
+                        lines.insert(corner(a, -1.0, b), corner(a, 1.0, b), color);
+                        lines.insert(corner(a, b, -1.0), corner(a, b, 1.0), color);
+                    }
+                }
+            }
+            Shape::Capsule { begin, end, radius } => {
+                if !cvars.d_draw_capsules {
+                    return;
+                }
+
+                let axis = end - begin;
+                let dir = axis.try_normalize(f32::EPSILON).unwrap_or(UP);
+                // Any 2 vectors perpendicular to the axis and each other.
+                let other = if dir.dot(&UP).abs() > 0.9 {
+                    FORWARD
+                } else {
+                    UP
+                };
+                let side1 = dir.cross(&other).normalize() * radius;
+                let side2 = dir.cross(&side1).normalize() * radius;
+                let dir = dir * radius;
+
+                let segments = cvars.d_draw_circle_segments;
+                let half = segments / 2;
+                let full = 2.0 * PI;
+
+                // Rings around both ends.
+                lines.insert_arc(begin, side1, side2, 0.0, full, segments, color);
+                lines.insert_arc(end, side1, side2, 0.0, full, segments, color);
+
+                // Lines along the body.
+                for side in [side1, -side1, side2, -side2] {
+                    lines.insert(begin + side, end + side, color);
+                }
+
+                // Hemispheres, each is 2 perpendicular half-circles.
+                lines.insert_arc(end, side1, dir, 0.0, PI, half, color);
+                lines.insert_arc(end, side2, dir, 0.0, PI, half, color);
+                lines.insert_arc(begin, side1, -dir, 0.0, PI, half, color);
+                lines.insert_arc(begin, side2, -dir, 0.0, PI, half, color);
             }
         }
     }
@@ -166,6 +296,32 @@ impl Lines {
             .and_modify(|line| line.color += color)
             .or_insert(Line { begin, end, color });
     }
+
+    /// Insert an arc approximated by `segments` lines.
+    ///
+    /// The points are `center + a * cos(angle) + b * sin(angle)`
+    /// so `a` and `b` should be perpendicular and have length equal to the radius.
+    #[allow(clippy::too_many_arguments)]
+    fn insert_arc(
+        &mut self,
+        center: Vec3,
+        a: Vec3,
+        b: Vec3,
+        angle_from: f32,
+        angle_to: f32,
+        segments: usize,
+        color: Color,
+    ) {
+        let segments = segments.max(1);
+        let step = (angle_to - angle_from) / segments as f32;
+        let point = |i: usize| {
+            let angle = angle_from + step * i as f32;
+            center + a * angle.cos() + b * angle.sin()
+        };
+        for i in 0..segments {
+            self.insert(point(i), point(i + 1), color);
+        }
+    }
 }
 
 /// Helper function, prefer `dbg_line!()` instead.
@@ -187,19 +343,329 @@ pub(crate) fn debug_cross(point: Vec3, time: f32, color: Color) {
 }
 
 /// Helper function, prefer `dbg_rot!()` instead.
-pub(crate) fn debug_rot(point: Vec3, rot: UnitQuaternion<f32>, time: f32) {
-    let shape = Shape::Rot { point, rot };
+pub(crate) fn debug_rot<R>(point: Vec3, rot: R, time: f32, scale: f32)
+where
+    R: Into<UnitQuaternion<f32>>,
+{
+    let rot = rot.into();
+    let shape = Shape::Rot { point, rot, scale };
     // Color is not used
     debug_shape(shape, time, Color::WHITE);
 }
 
+/// Helper function, prefer `dbg_sphere!()` instead.
+pub(crate) fn debug_sphere(center: Vec3, radius: f32, time: f32, color: Color) {
+    let shape = Shape::Sphere { center, radius };
+    debug_shape(shape, time, color);
+}
+
+/// Helper function, prefer `dbg_box!()` instead.
+pub(crate) fn debug_box(center: Vec3, half_extents: Vec3, time: f32, color: Color) {
+    debug_obb(center, half_extents, UnitQuaternion::identity(), time, color);
+}
+
+/// Helper function, prefer `dbg_obb!()` instead.
+pub(crate) fn debug_obb(
+    center: Vec3,
+    half_extents: Vec3,
+    rot: UnitQuaternion<f32>,
+    time: f32,
+    color: Color,
+) {
+    let shape = Shape::Box {
+        center,
+        half_extents,
+        rot,
+    };
+    debug_shape(shape, time, color);
+}
+
+/// Helper function, prefer `dbg_capsule!()` instead.
+pub(crate) fn debug_capsule(begin: Vec3, end: Vec3, radius: f32, time: f32, color: Color) {
+    let shape = Shape::Capsule { begin, end, radius };
+    debug_shape(shape, time, color);
+}
+
+/// Helper function, prefer `dbg_worldtext!()` instead.
+pub(crate) fn debug_world_text(pos: Vec3, text: String, time: f32, color: Color) {
+    DEBUG_WORLD_TEXTS.with(|texts| {
+        let text = DebugWorldText {
+            pos,
+            text,
+            time,
+            color: to_rgba(color),
+        };
+        texts.borrow_mut().push(text);
+    });
+}
+
 fn debug_shape(shape: Shape, time: f32, color: Color) {
+    let category = DEBUG_CATEGORY.with(|cur| cur.get());
+    let source = DebugSource::from_endpoint(endpoint_name());
     DEBUG_SHAPES.with(|shapes| {
-        let shape = DebugShape { shape, time, color };
+        let shape = DebugShape {
+            shape,
+            time,
+            color: to_rgba(color),
+            category,
+            source,
+        };
         shapes.borrow_mut().push(shape);
     });
 }
 
+/// Helper for `dbg_*_once`, returns true only the first time it's called with `done`.
+pub(crate) fn first_time(done: &AtomicBool) -> bool {
+    !done.swap(true, Ordering::Relaxed)
+}
+
+/// Helper for `dbg_*_every`, returns true on every `n`-th call with `counter`, starting with the first.
+pub(crate) fn nth_time(counter: &AtomicUsize, n: usize) -> bool {
+    let count = counter.fetch_add(1, Ordering::Relaxed);
+    // n == 0 would panic, treat it the same as 1.
+    count % n.max(1) == 0
+}
+
+/// How many log lines to keep for crash reports.
+const LOG_HISTORY_LEN: usize = 100;
+
+/// Helper function, prefer `dbg_logf!()` instead.
+pub(crate) fn log_line(level: LogLevel, msg: String) {
+    let text = format!("{} {}", endpoint_name(), msg);
+    println!("{}", text);
+    DEBUG_LOG_HISTORY.with(|history| {
+        let mut history = history.borrow_mut();
+        if history.len() >= LOG_HISTORY_LEN {
+            history.pop_front();
+        }
+        history.push_back(text.clone());
+    });
+    DEBUG_LOGS.with(|logs| {
+        logs.borrow_mut().push(LogLine { level, text });
+    });
+}
+
+/// Helper struct, use `dbg_timer!()`.
+///
+/// Measures time and allocations until it's dropped.
+#[derive(Debug)]
+#[must_use = "the timer measures until it's dropped, assign it to a named variable like `_timer`"]
+pub(crate) struct DebugTimer {
+    endpoint: &'static str,
+    name: &'static str,
+    start: Instant,
+    allocs_start: usize,
+}
+
+impl DebugTimer {
+    pub(crate) fn new(name: &'static str) -> Self {
+        Self {
+            endpoint: endpoint_name(),
+            name,
+            start: Instant::now(),
+            allocs_start: alloc::allocations(),
+        }
+    }
+}
+
+impl Drop for DebugTimer {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let allocs = alloc::allocations() - self.allocs_start;
+        DEBUG_TIMINGS.with(|timings| {
+            let mut timings = timings.borrow_mut();
+            let timing = timings.current.entry((self.endpoint, self.name)).or_default();
+            timing.total += elapsed;
+            timing.count += 1;
+            timing.allocs += allocs;
+        });
+    }
+}
+
+/// Durations measured by `dbg_timer!()`, aggregated per frame.
+#[derive(Debug, Default)]
+pub(crate) struct Timings {
+    /// The frame being measured right now.
+    current: FxHashMap<(&'static str, &'static str), Timing>,
+    /// The last complete frame, sorted from slowest.
+    pub(crate) last: Vec<TimingEntry>,
+    /// How many frames have been completed.
+    pub(crate) frames: u64,
+    /// The most allocations `d_alloc_audit` reported for each timer.
+    alloc_worst: FxHashMap<(&'static str, &'static str), usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Timing {
+    total: Duration,
+    count: u32,
+    allocs: usize,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TimingEntry {
+    pub(crate) endpoint: &'static str,
+    pub(crate) name: &'static str,
+    /// Sum of all measurements with this name during the frame.
+    pub(crate) total: Duration,
+    /// How many times the timer ran during the frame.
+    pub(crate) count: u32,
+    /// Allocations (including reallocations) in all threads while the timer ran.
+    pub(crate) allocs: usize,
+}
+
+/// Finish measuring the current frame and start the next one.
+///
+/// Should be called once per frame by whoever owns the main loop.
+/// If `d_timers_csv` is set, the finished frame is appended to that file.
+pub(crate) fn timers_end_frame(cvars: &Cvars) {
+    DEBUG_TIMINGS.with(|timings| {
+        let mut timings = timings.borrow_mut();
+        let timings = &mut *timings;
+
+        timings.last.clear();
+        for ((endpoint, name), timing) in timings.current.drain() {
+            timings.last.push(TimingEntry {
+                endpoint,
+                name,
+                total: timing.total,
+                count: timing.count,
+                allocs: timing.allocs,
+            });
+        }
+        timings.last.sort_by(|a, b| b.total.cmp(&a.total));
+        timings.frames += 1;
+
+        if cvars.d_alloc_audit && alloc::ENABLED {
+            for entry in &timings.last {
+                if entry.allocs <= cvars.d_alloc_audit_threshold {
+                    continue;
+                }
+                let worst = timings.alloc_worst.entry((entry.endpoint, entry.name)).or_default();
+                if entry.allocs > *worst {
+                    *worst = entry.allocs;
+                    log_line(
+                        LogLevel::Warning,
+                        format!(
+                            "{} allocated {} times in frame {} ({}x)",
+                            entry.name, entry.allocs, timings.frames, entry.count
+                        ),
+                    );
+                }
+            }
+        }
+
+        if !cvars.d_timers_csv.is_empty() {
+            // LATER(perf) Keep the file open.
+            if let Err(err) = append_csv(&cvars.d_timers_csv, timings.frames, &timings.last) {
+                dbg_logf!("Failed to write timings to {}: {}", cvars.d_timers_csv, err);
+            }
+        }
+    });
+}
+
+fn append_csv(path: &str, frame: u64, entries: &[TimingEntry]) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for entry in entries {
+        writeln!(
+            file,
+            "{},{},{},{:.3},{}",
+            frame,
+            entry.endpoint,
+            entry.name,
+            entry.total.as_secs_f64() * 1000.0,
+            entry.count,
+        )?;
+    }
+    Ok(())
+}
+
+/// Human readable breakdown of the last complete frame.
+pub(crate) fn timers_text() -> Vec<String> {
+    DEBUG_TIMINGS.with(|timings| {
+        let timings = timings.borrow();
+        timings
+            .last
+            .iter()
+            .map(|entry| {
+                let mut line = format!(
+                    "{} {:>12} {:>7.3} ms ({}x)",
+                    entry.endpoint,
+                    entry.name,
+                    entry.total.as_secs_f64() * 1000.0,
+                    entry.count,
+                );
+                if alloc::ENABLED {
+                    line.push_str(&format!(" {:>5} allocs", entry.allocs));
+                }
+                line
+            })
+            .collect()
+    })
+}
+
+/// Endpoint, file, line, column.
+type AssertLocation = (&'static str, &'static str, u32, u32);
+
+/// Helper struct, see `soft_assert!()`.
+#[derive(Debug, Clone)]
+pub(crate) struct AssertFailures {
+    pub(crate) count: u64,
+    /// The message from the most recent failure.
+    pub(crate) last_msg: String,
+}
+
+/// Helper function, prefer `soft_assert!()` instead.
+pub(crate) fn soft_assert_failed(file: &'static str, line: u32, column: u32, msg: String) {
+    log_line(
+        LogLevel::Warning,
+        format!("soft assertion failed: {}, {}:{}:{}", msg, file, line, column),
+    );
+
+    DEBUG_ASSERTS.with(|asserts| {
+        let mut asserts = asserts.borrow_mut();
+        let failures =
+            asserts.entry((endpoint_name(), file, line, column)).or_insert(AssertFailures {
+                count: 0,
+                last_msg: String::new(),
+            });
+        failures.count += 1;
+        failures.last_msg = msg;
+    });
+    DEBUG_ASSERTS_TOTAL
+        .with(|totals| *totals.borrow_mut().entry(endpoint_name()).or_insert(0) += 1);
+}
+
+/// One line per failed soft assert, most frequent first.
+pub(crate) fn asserts_text() -> Vec<String> {
+    DEBUG_ASSERTS.with(|asserts| {
+        let asserts = asserts.borrow();
+        let mut entries: Vec<_> = asserts.iter().collect();
+        entries.sort_by(|a, b| b.1.count.cmp(&a.1.count));
+        entries
+            .into_iter()
+            .map(|((endpoint, file, line, column), failures)| {
+                format!(
+                    "{} {}:{}:{} {}x: {}",
+                    endpoint, file, line, column, failures.count, failures.last_msg
+                )
+            })
+            .collect()
+    })
+}
+
+/// Failed soft asserts on the current endpoint since it started.
+///
+/// Unlike `asserts_text`, not reset by `asserts_clear` so it only ever grows.
+pub(crate) fn asserts_total() -> u64 {
+    DEBUG_ASSERTS_TOTAL.with(|totals| totals.borrow().get(endpoint_name()).copied().unwrap_or(0))
+}
+
+/// Forget failed soft asserts shown by `asserts_text`, doesn't change `asserts_total`.
+pub(crate) fn asserts_clear() {
+    DEBUG_ASSERTS.with(|asserts| asserts.borrow_mut().clear());
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct DebugEndpoint {
     pub(crate) name: &'static str,
@@ -217,8 +683,18 @@ thread_local! {
         default_color: Color::WHITE,
     });
 
+    pub(crate) static DEBUG_LOGS: RefCell<Vec<LogLine>> = RefCell::new(Vec::new());
+    /// Unlike `DEBUG_LOGS`, this is never cleared, only the oldest lines are removed.
+    pub(crate) static DEBUG_LOG_HISTORY: RefCell<VecDeque<String>> = RefCell::new(VecDeque::new());
     pub(crate) static DEBUG_TEXTS: RefCell<Vec<String>> = RefCell::new(Vec::new());
     pub(crate) static DEBUG_SHAPES: RefCell<Vec<DebugShape>> = RefCell::new(Vec::new());
+    pub(crate) static DEBUG_WORLD_TEXTS: RefCell<Vec<DebugWorldText>> = RefCell::new(Vec::new());
+    pub(crate) static DEBUG_TIMINGS: RefCell<Timings> = RefCell::new(Timings::default());
+    static DEBUG_CATEGORY: Cell<DebugCategory> = Cell::new(DebugCategory::Misc);
+    static DEBUG_ASSERTS: RefCell<FxHashMap<AssertLocation, AssertFailures>> =
+        RefCell::new(FxHashMap::default());
+    static DEBUG_ASSERTS_TOTAL: RefCell<FxHashMap<&'static str, u64>> =
+        RefCell::new(FxHashMap::default());
 }
 
 pub(crate) fn set_endpoint(name: &'static str) {
@@ -247,6 +723,8 @@ pub(crate) fn endpoint_color() -> Color {
 }
 
 pub(crate) fn clear_expired() {
+    DEBUG_LOGS.with(|logs| logs.borrow_mut().clear());
     DEBUG_TEXTS.with(|texts| texts.borrow_mut().clear());
     DEBUG_SHAPES.with(|shapes| shapes.borrow_mut().retain(|shape| shape.time > 0.0));
+    DEBUG_WORLD_TEXTS.with(|texts| texts.borrow_mut().retain(|text| text.time > 0.0));
 }
@@ -6,10 +6,13 @@
 pub(crate) mod debug;
 
 mod client;
+mod commands;
 mod common;
 mod cvars;
 mod prelude;
 mod server;
+#[cfg(test)]
+mod test_support;
 
 use std::{env, error::Error, panic, process::Command, sync::Arc};
 
@@ -61,6 +64,9 @@ use crate::{client::process::ClientProcess, prelude::*, server::process::ServerP
 // v1.0:
 //  - [ ] Include version number in binaries, report between cl and sv during handshake
 //      - Must not increase incremental build time - worst case do it only for releases
+//  - [ ] Server browser - name, map, players, ping, mode; sort, filter, double-click to connect
+//      - Needs LAN discovery and a master server first, neither exists yet
+//      - The client also needs a state without a game to show it in
 // All the LATERs
 //  - They mean something can be done better but marking it as a todo would be just noise when grepping.
 //    They're things I'd do if I had infinite time and wanted to make the project perfect.
@@ -91,6 +97,12 @@ enum Endpoint {
     Client,
     /// Run only the game server
     Server,
+    /// Run the headless benchmark
+    Bench,
+    /// Run many headless clients controlled by bots
+    BotClient,
+    /// List markers in a demo and extract highlights
+    Demo,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -120,20 +132,63 @@ fn main() -> Result<(), Box<dyn Error>> {
             opts.endpoint = Some(Endpoint::Server);
             args.next();
         }
+        Some("bench") => {
+            opts.endpoint = Some(Endpoint::Bench);
+            args.next();
+        }
+        Some("botclient") => {
+            opts.endpoint = Some(Endpoint::BotClient);
+            args.next();
+            // Shorthands for the most common cvars, they have to come before other cvars.
+            loop {
+                let cvar_name = match args.peek().map(String::as_str) {
+                    Some("--count") => "d_botclient_count",
+                    Some("--server") => "d_botclient_server",
+                    _ => break,
+                };
+                args.next();
+                let value =
+                    args.next().unwrap_or_else(|| panic!("missing value for {}", cvar_name));
+                opts.cvar_args.push(cvar_name.to_owned());
+                opts.cvar_args.push(value);
+            }
+        }
+        Some("demo") => {
+            opts.endpoint = Some(Endpoint::Demo);
+            args.next();
+            let file = args.next().unwrap_or_else(|| panic!("missing demo file"));
+            opts.cvar_args.push("d_demo_file".to_owned());
+            opts.cvar_args.push(file);
+            if args.peek().map(String::as_str) == Some("--extract") {
+                args.next();
+                let prefix = args.next().unwrap_or_else(|| panic!("missing value for --extract"));
+                opts.cvar_args.push("d_demo_extract".to_owned());
+                opts.cvar_args.push(prefix);
+            }
+        }
         #[rustfmt::skip]
         Some("--help") => {
-            println!("Usage: rustcycles [launcher|local|client|server] [cvar1 value1 cvar2 value2 ...]");
+            println!("Usage: rustcycles [launcher|local|client|server|bench|botclient|demo] [cvar1 value1 cvar2 value2 ...]");
             println!();
             println!("Commands (optional):");
             println!("    launcher   Run a local game with separate client and server processes (default)");
             println!("    local      Run a local game with client and server in one process (experimental)");
             println!("    client     Run only the game client");
             println!("    server     Run only the dedicated game server");
+            println!("    bench      Run the server with bots as fast as possible and print timings");
+            println!("               (d_bench_bots and d_bench_ticks)");
+            println!("    botclient  Connect many headless bot clients to a server and print bandwidth");
+            println!("               (--count <n> --server <addr> or d_botclient_* cvars)");
+            println!("    demo       List kills in a demo recorded using sv_demo_record");
+            println!("               (demo <file> [--extract <prefix>] to save highlights around them)");
             println!();
             println!("Cvars (optional):");
             println!("    You can specify cvars in key value pairs separated by space.");
             println!("    Example: rustcycles cl_camera_fov 100 m_sensitivity 0.8");
             println!();
+            println!("    Use `exec <file>` to run a script with one cvar and value per line.");
+            println!("    Example: rustcycles server exec server.cfg");
+            println!();
             println!("    Cvars can be changed at runtime using the console but some of them");
             println!("    are only read at startup so the value needs to be specified");
             println!("    on the command line to take effect");
@@ -159,7 +214,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Some games require cvars to be prefixed by `+` which allows more specific error messages
     // because they know it's meant to be a cvar and not a malformed command line option.
     // We might wanna require that too but this is slightly less typing for now.
-    opts.cvar_args = args.collect();
+    opts.cvar_args.extend(args);
 
     match opts.endpoint {
         // LATER None should launch client and offer choice in menu
@@ -182,6 +237,21 @@ fn main() -> Result<(), Box<dyn Error>> {
             let cvars = args_to_cvars(&opts.cvar_args)?;
             server_main(cvars);
         }
+        Some(Endpoint::Bench) => {
+            init_global_state("sv");
+            let cvars = args_to_cvars(&opts.cvar_args)?;
+            bench_main(cvars);
+        }
+        Some(Endpoint::BotClient) => {
+            init_global_state("bots");
+            let cvars = args_to_cvars(&opts.cvar_args)?;
+            client::botclient::run(&cvars);
+        }
+        Some(Endpoint::Demo) => {
+            init_global_state("demo");
+            let cvars = args_to_cvars(&opts.cvar_args)?;
+            server::demo::run(&cvars);
+        }
     }
 
     Ok(())
@@ -191,6 +261,7 @@ fn init_global_state(endpoint_name: &'static str) {
     let prev_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
         dbg_logf!("panicking");
+        debug::crash::write_report(panic_info);
         prev_hook(panic_info);
     }));
 
@@ -216,6 +287,18 @@ fn args_to_cvars(cvar_args: &[String]) -> Result<Cvars, String> {
         let str_value = cvars_iter.next().ok_or_else(|| {
             format!("missing value for cvar `{}` or incorrect command line option", cvar_name)
         })?;
+        if cvar_name == "exec" {
+            // Errors on individual lines are already reported by exec.
+            // Treat them the same as unknown cvars.
+            if let Err(msg) = commands::exec(&mut cvars, str_value) {
+                if cvars.d_exit_on_unknown_cvar {
+                    return Err(msg);
+                } else {
+                    dbg_logf!("{}", msg);
+                }
+            }
+            continue;
+        }
         let res = cvars.set_str(cvar_name, str_value);
         match res.as_ref() {
             Ok(_) => {
@@ -236,6 +319,8 @@ fn args_to_cvars(cvar_args: &[String]) -> Result<Cvars, String> {
         }
     }
 
+    debug::crash::set_cvars(&cvars);
+
     Ok(cvars)
 }
 
@@ -346,6 +431,7 @@ fn client_main(cvars: Cvars, local_server: bool) {
                 client.update();
             }
             Event::RedrawRequested(_) => {
+                let _timer = dbg_timer!("render");
                 client.engine.render().unwrap(); // LATER only crash if failed multiple times
             }
             Event::RedrawEventsCleared => {
@@ -400,6 +486,14 @@ fn server_main(cvars: Cvars) {
     });
 }
 
+fn bench_main(cvars: Cvars) {
+    // The event loop is never run, the engine just needs it to be created.
+    let event_loop = EventLoop::new();
+    let mut engine = init_engine_server(&event_loop);
+
+    executor::block_on(server::bench::run(&cvars, &mut engine));
+}
+
 fn init_engine_client(event_loop: &EventLoop<()>, cvars: &Cvars) -> Engine {
     let mut window_builder = WindowBuilder::new().with_title("RustCycles");
     if cvars.cl_fullscreen {
@@ -212,6 +212,7 @@ pub(crate) const CYAN: Color = Color::opaque(0, 255, 255);
 // raycast while ignoring the player by setting `filter` to !IG_ENTITIES.
 pub(crate) const IG_ENTITIES: BitMask = BitMask(1 << 0);
 pub(crate) const IG_ALL: BitMask = BitMask(u32::MAX);
+pub(crate) const IG_NONE: BitMask = BitMask(0);
 
 #[cfg(test)]
 mod tests {
@@ -1,4 +1,30 @@
 //! The authoritative server in a client-server multiplayer game architecture.
 
+pub(crate) mod assists;
+pub(crate) mod backfill;
+pub(crate) mod bench;
+pub(crate) mod bounds;
+pub(crate) mod chat;
+pub(crate) mod demo;
+pub(crate) mod events;
 pub(crate) mod game;
+pub(crate) mod hooks;
+pub(crate) mod metrics;
+pub(crate) mod modes;
+pub(crate) mod motd;
+pub(crate) mod net_stats;
+pub(crate) mod pause;
+pub(crate) mod pickups;
+pub(crate) mod probe;
 pub(crate) mod process;
+pub(crate) mod reconnect;
+pub(crate) mod relay;
+pub(crate) mod respawns;
+pub(crate) mod restart;
+pub(crate) mod rewind;
+pub(crate) mod savestate;
+pub(crate) mod stats;
+pub(crate) mod status;
+pub(crate) mod teams;
+pub(crate) mod triggers;
+pub(crate) mod upload;
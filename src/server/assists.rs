@@ -0,0 +1,125 @@
+//! Assists - players who hit the victim shortly before someone else killed them.
+//!
+//! Hits are remembered for `g_assist_time` seconds per victim.
+//! Dying (or disconnecting) forgets the hits on the victim.
+
+use fxhash::FxHashMap;
+
+use crate::{
+    common::entities::{Hit, Kill, Player},
+    prelude::*,
+};
+
+#[derive(Debug, Default)]
+pub(crate) struct Assists {
+    /// Who hit each victim and when, oldest first.
+    hits: FxHashMap<Handle<Player>, Vec<(Handle<Player>, f32)>>,
+}
+
+impl Assists {
+    pub(crate) fn record(&mut self, game_time: f32, hits: &[Hit]) {
+        for hit in hits {
+            let attackers = self.hits.entry(hit.victim).or_default();
+            // Only the latest hit by each attacker matters.
+            attackers.retain(|&(attacker, _)| attacker != hit.attacker);
+            attackers.push((hit.attacker, game_time));
+        }
+    }
+
+    /// Players who get an assist for the kill, neither the killer nor the victim.
+    pub(crate) fn credit(
+        &mut self,
+        cvars: &Cvars,
+        game_time: f32,
+        kill: Kill,
+    ) -> Vec<Handle<Player>> {
+        let Some(attackers) = self.hits.remove(&kill.victim) else {
+            return Vec::new();
+        };
+        attackers
+            .into_iter()
+            .filter(|&(_, time)| game_time - time <= cvars.g_assist_time)
+            .map(|(attacker, _)| attacker)
+            .filter(|&attacker| attacker != kill.killer && attacker != kill.victim)
+            .collect()
+    }
+
+    /// Forget hits by and on players who left.
+    pub(crate) fn remove_player(&mut self, player_handle: Handle<Player>) {
+        self.hits.remove(&player_handle);
+        for attackers in self.hits.values_mut() {
+            attackers.retain(|&(attacker, _)| attacker != player_handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assists() {
+        let cvars = Cvars {
+            g_assist_time: 5.0,
+            ..Cvars::default()
+        };
+        let a = Handle::new(1, 1);
+        let b = Handle::new(2, 1);
+        let c = Handle::new(3, 1);
+        let victim = Handle::new(4, 1);
+
+        let mut assists = Assists::default();
+        assists.record(
+            1.0,
+            &[Hit {
+                attacker: a,
+                victim,
+            }],
+        );
+        assists.record(
+            2.0,
+            &[Hit {
+                attacker: b,
+                victim,
+            }],
+        );
+        assists.record(
+            3.0,
+            &[Hit {
+                attacker: c,
+                victim,
+            }],
+        );
+        assists.record(
+            4.0,
+            &[Hit {
+                attacker: a,
+                victim,
+            }],
+        );
+        let kill = Kill { killer: c, victim };
+        assert_eq!(assists.credit(&cvars, 7.5, kill), vec![a]);
+        // The victim's hits are forgotten after dying.
+        assert_eq!(assists.credit(&cvars, 7.5, kill), vec![]);
+
+        assists.record(
+            10.0,
+            &[
+                Hit {
+                    attacker: a,
+                    victim,
+                },
+                Hit {
+                    attacker: b,
+                    victim,
+                },
+            ],
+        );
+        assists.remove_player(a);
+        let kill = Kill {
+            killer: victim,
+            victim,
+        };
+        assert_eq!(assists.credit(&cvars, 11.0, kill), vec![b]);
+    }
+}
@@ -0,0 +1,94 @@
+//! Bots filling in for players who left - `sv_bot_backfill`.
+//!
+//! Bots are players without a client, they're added and removed
+//! with the same messages as people so clients see no difference.
+//! Each team gets bots until it has as many players as the team with the most people.
+//! Observers don't count, bots don't replace people who are only watching.
+//! When someone joins, a bot on a team which is now too large leaves.
+//! Only one bot is added or removed per tick.
+//!
+//! LATER Bots in free for all.
+
+use crate::{
+    common::{
+        entities::{Player, PlayerState},
+        GameState,
+    },
+    prelude::*,
+    server::teams,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Backfill {
+    /// Add a bot to the smallest team.
+    Add,
+    Remove(Handle<Player>),
+}
+
+/// What to do to make teams even, `bots` are in the order they were added.
+pub(crate) fn check(cvars: &Cvars, gs: &GameState, bots: &[Handle<Player>]) -> Option<Backfill> {
+    let team_count = teams::team_sizes(cvars, gs).len();
+    if !cvars.sv_bot_backfill || team_count == 0 {
+        return bots.last().map(|&bot| Backfill::Remove(bot));
+    }
+
+    // Bots always count, even if they couldn't join because the server is full.
+    let mut sizes = vec![0; team_count];
+    let mut people = vec![0; team_count];
+    for (player_handle, player) in gs.players.pair_iter() {
+        let bot = bots.contains(&player_handle);
+        if !bot && player.ps != PlayerState::Playing {
+            continue;
+        }
+        let Some(team) = player.team.map(usize::from).filter(|&team| team < team_count) else {
+            continue;
+        };
+        sizes[team] += 1;
+        if !bot {
+            people[team] += 1;
+        }
+    }
+    let bot_teams: Vec<_> = bots.iter().map(|&bot| gs.players[bot].team).collect();
+    match plan(&sizes, &people, &bot_teams)? {
+        Plan::Add => Some(Backfill::Add),
+        Plan::Remove(i) => Some(Backfill::Remove(bots[i])),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Plan {
+    Add,
+    /// Index into bots.
+    Remove(usize),
+}
+
+/// Removing goes first so a person joining replaces a bot instead of getting one more opponent.
+/// The newest bots are removed first.
+fn plan(sizes: &[usize], people: &[usize], bot_teams: &[Option<u8>]) -> Option<Plan> {
+    let target = people.iter().copied().max().unwrap_or(0);
+    for (i, team) in bot_teams.iter().enumerate().rev() {
+        // Bots on a team which no longer exists are removed too.
+        let size = team.and_then(|team| sizes.get(usize::from(team)));
+        if size.map_or(true, |&size| size > target) {
+            return Some(Plan::Remove(i));
+        }
+    }
+    sizes.iter().any(|&size| size < target).then_some(Plan::Add)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan() {
+        assert_eq!(plan(&[0, 0], &[0, 0], &[]), None);
+        assert_eq!(plan(&[2, 1], &[2, 1], &[]), Some(Plan::Add));
+        assert_eq!(plan(&[2, 2], &[2, 1], &[Some(1)]), None);
+        // Someone joined the team with the bot.
+        assert_eq!(plan(&[2, 3], &[2, 2], &[Some(1)]), Some(Plan::Remove(0)));
+        // Everyone left.
+        assert_eq!(plan(&[1, 1], &[0, 0], &[Some(0), Some(1)]), Some(Plan::Remove(1)));
+        assert_eq!(plan(&[1, 1], &[1, 1], &[None]), Some(Plan::Remove(0)));
+    }
+}
@@ -0,0 +1,104 @@
+//! Headless benchmark - `rustcycles bench`.
+//!
+//! Runs the server simulation with bots as fast as possible
+//! and prints how long the ticks took and how many allocations they made.
+//! Rendering is not included so it doesn't need a GPU.
+
+use std::{sync::mpsc, time::Instant};
+
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+use crate::{
+    common::{
+        messages::ClientMessage,
+        net::{self, Connection, LocalConnection, LocalListener},
+        Input, InputExt,
+    },
+    debug::alloc,
+    prelude::*,
+    server::game::ServerGame,
+};
+
+/// Run `d_bench_ticks` server ticks with `d_bench_bots` bots and print the results.
+pub(crate) async fn run(cvars: &Cvars, engine: &mut Engine) {
+    // Bots are connected the same way as the client in a local game
+    // so the server also does all its networking work.
+    let mut bot_conns = Vec::new();
+    let mut server_conns = Vec::new();
+    for _ in 0..cvars.d_bench_bots {
+        let (tx1, rx1) = mpsc::channel();
+        let (tx2, rx2) = mpsc::channel();
+        server_conns.push(LocalConnection::new(tx1, rx2));
+        bot_conns.push(LocalConnection::new(tx2, rx1));
+    }
+    let listener = LocalListener::with_conns(server_conns);
+    let mut sg = ServerGame::new(cvars, engine, Box::new(listener)).await;
+    sg.accept_new_connections(cvars, engine);
+
+    for conn in &mut bot_conns {
+        conn.send(&net::serialize(ClientMessage::Join)).unwrap();
+    }
+
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(cvars.d_seed);
+
+    // Same as in ServerGame::update.
+    let dt = 1.0 / 60.0;
+    let mut tick_times = Vec::with_capacity(cvars.d_bench_ticks);
+    let mut tick_allocs = Vec::with_capacity(cvars.d_bench_ticks);
+    for _ in 0..cvars.d_bench_ticks {
+        for conn in &mut bot_conns {
+            let mut input = Input::default();
+            input.randomize(&mut rng);
+            conn.send(&net::serialize(ClientMessage::Input(input))).unwrap();
+        }
+
+        let allocs_before = alloc::allocations();
+        let start = Instant::now();
+        // Run exactly one tick.
+        let target = sg.gs.game_time + dt * 1.5;
+        sg.update(cvars, engine, target);
+        tick_times.push(start.elapsed().as_secs_f64() * 1000.0);
+        tick_allocs.push(alloc::allocations() - allocs_before);
+
+        // Don't let the messages pile up.
+        for conn in &mut bot_conns {
+            let _ = conn.receive_sm();
+        }
+    }
+
+    print_results(cvars, &mut tick_times, &tick_allocs);
+}
+
+fn print_results(cvars: &Cvars, tick_times: &mut [f64], tick_allocs: &[usize]) {
+    if tick_times.is_empty() {
+        println!("No ticks to report");
+        return;
+    }
+
+    tick_times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let percentile = |p: f64| {
+        let index = ((tick_times.len() - 1) as f64 * p).round() as usize;
+        tick_times[index]
+    };
+    let mean = tick_times.iter().sum::<f64>() / tick_times.len() as f64;
+
+    let total_allocs: usize = tick_allocs.iter().sum();
+    let max_allocs = tick_allocs.iter().max().unwrap();
+
+    println!("Bots: {}, ticks: {}", cvars.d_bench_bots, tick_times.len());
+    println!("Tick time (ms):");
+    println!("    mean {:>8.3}", mean);
+    println!("    p50  {:>8.3}", percentile(0.5));
+    println!("    p90  {:>8.3}", percentile(0.9));
+    println!("    p99  {:>8.3}", percentile(0.99));
+    println!("    max  {:>8.3}", percentile(1.0));
+    if !alloc::ENABLED {
+        println!("Allocations: not counted, build with `--features alloc_count`");
+        return;
+    }
+    println!("Allocations:");
+    println!("    total    {}", total_allocs);
+    println!("    per tick {:.1}", total_allocs as f64 / tick_allocs.len() as f64);
+    println!("    max      {}", max_allocs);
+}
@@ -0,0 +1,80 @@
+//! Keeping cycles inside the map's bounds, see `map::Bounds`.
+//!
+//! Clients are told when a player's countdown starts and stops (`GameEvent::OutOfBounds`)
+//! so they can warn them.
+
+use fxhash::FxHashMap;
+
+use crate::{
+    common::{
+        entities::{Cycle, GameEvent},
+        map::BoundsCheck,
+        GameState,
+    },
+    prelude::*,
+};
+
+#[derive(Debug, Default)]
+pub(crate) struct OutOfBounds {
+    /// Game time when each cycle outside the bounds gets killed.
+    deadlines: FxHashMap<Handle<Cycle>, f32>,
+}
+
+impl OutOfBounds {
+    /// Kill cycles which went too far or stayed outside too long.
+    pub(crate) fn tick(&mut self, cvars: &Cvars, gs: &mut GameState, scene: &mut Scene) {
+        let Some(bounds) = gs.map.bounds else {
+            return;
+        };
+
+        self.deadlines.retain(|&handle, _| gs.cycles.is_valid_handle(handle));
+        let cycle_handles: Vec<_> = gs.cycles.pair_iter().map(|(handle, _)| handle).collect();
+        for cycle_handle in cycle_handles {
+            let player_handle = gs.cycles[cycle_handle].player_handle;
+            let body_handle = gs.cycles[cycle_handle].body_handle;
+            let pos = **scene.graph[body_handle].local_transform().position();
+
+            let kill = match bounds.check(pos) {
+                BoundsCheck::Inside => {
+                    if self.deadlines.remove(&cycle_handle).is_some() {
+                        gs.events.push(GameEvent::OutOfBounds {
+                            player_handle,
+                            time_left: None,
+                        });
+                    }
+                    false
+                }
+                BoundsCheck::Warning => match self.deadlines.get(&cycle_handle) {
+                    Some(&deadline) => gs.game_time >= deadline,
+                    None => {
+                        let deadline = gs.game_time + cvars.g_out_of_bounds_time;
+                        self.deadlines.insert(cycle_handle, deadline);
+                        gs.events.push(GameEvent::OutOfBounds {
+                            player_handle,
+                            time_left: Some(cvars.g_out_of_bounds_time),
+                        });
+                        false
+                    }
+                },
+                BoundsCheck::Outside => true,
+            };
+            if !kill {
+                continue;
+            }
+
+            if self.deadlines.remove(&cycle_handle).is_some() {
+                gs.events.push(GameEvent::OutOfBounds {
+                    player_handle,
+                    time_left: None,
+                });
+            }
+            if gs.players[player_handle].cheats.god {
+                // Not killed but still shouldn't fall forever.
+                let spawn_pos = gs.spawn_pos(cvars);
+                gs.teleport(scene, player_handle, spawn_pos);
+            } else {
+                gs.kill_by_map(player_handle, pos);
+            }
+        }
+    }
+}
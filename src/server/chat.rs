@@ -0,0 +1,194 @@
+//! Chat commands - lines starting with `/` are run by the server instead of being shown.
+//!
+//! - `/me <action>` - shown as `* player 1 <action>`
+//! - `/votekick <player>` - more than half of the other players and at least 2 have to vote
+//! - `/switchteam` - join the next team, same checks as the `team` console command
+//! - `/unpause` - team captains can continue a paused match, see `g_competitive`
+//! - `/restart_update` - admins can restart the server with a new binary, see `server::restart`
+//!
+//! Players are identified by their index since there are no names yet.
+//!
+//! Lines which are shown to others can be censored, see `sv_chat_filter`.
+//!
+//! LATER Votes should expire.
+
+use fxhash::FxHashMap;
+use strum_macros::{Display, EnumString};
+
+use crate::{
+    common::entities::Player,
+    prelude::*,
+    server::hooks::{ChatAction, HookCtx, ServerHooks},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ChatCommand<'a> {
+    /// A normal line, not a command.
+    Say(&'a str),
+    Me(&'a str),
+    VoteKick(u32),
+    SwitchTeam,
+    Unpause,
+    RestartUpdate,
+}
+
+pub(crate) fn parse(line: &str) -> Result<ChatCommand<'_>, String> {
+    let line = line.trim();
+    let Some(command) = line.strip_prefix('/') else {
+        return Ok(ChatCommand::Say(line));
+    };
+    let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+    let args = args.trim();
+    match name {
+        "me" if !args.is_empty() => Ok(ChatCommand::Me(args)),
+        "me" => Err("usage: /me <action>".to_owned()),
+        "votekick" => match args.parse() {
+            Ok(player_index) => Ok(ChatCommand::VoteKick(player_index)),
+            Err(_) => Err("usage: /votekick <player number>".to_owned()),
+        },
+        "switchteam" => Ok(ChatCommand::SwitchTeam),
+        "unpause" => Ok(ChatCommand::Unpause),
+        "restart_update" => Ok(ChatCommand::RestartUpdate),
+        _ => Err(format!("unknown command /{}", name)),
+    }
+}
+
+/// Who voted to kick whom.
+#[derive(Debug, Default)]
+pub(crate) struct VoteKicks {
+    votes: FxHashMap<Handle<Player>, Vec<Handle<Player>>>,
+}
+
+impl VoteKicks {
+    /// Returns the number of votes against the target and how many are needed.
+    pub(crate) fn vote(
+        &mut self,
+        voter: Handle<Player>,
+        target: Handle<Player>,
+        player_count: usize,
+    ) -> (usize, usize) {
+        let voters = self.votes.entry(target).or_default();
+        if !voters.contains(&voter) {
+            voters.push(voter);
+        }
+        // With 2 players, one shouldn't be able to kick the other alone.
+        let others = player_count.saturating_sub(1);
+        (voters.len(), (others / 2 + 1).max(2))
+    }
+
+    /// Forget votes by and against players who left.
+    pub(crate) fn remove_player(&mut self, player_handle: Handle<Player>) {
+        self.votes.remove(&player_handle);
+        for voters in self.votes.values_mut() {
+            voters.retain(|&voter| voter != player_handle);
+        }
+    }
+}
+
+/// What to do when somebody uses a word from `sv_chat_filter_words`.
+///
+/// Pub because it's used in cvars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum FilterAction {
+    /// Don't filter.
+    Off,
+    /// Replace the words with asterisks.
+    Censor,
+    /// Censor and tell the player to stop.
+    Warn,
+    /// Don't send the line and don't let the player chat for `sv_chat_filter_mute_time` seconds.
+    Mute,
+    /// Don't send the line and kick the player.
+    Kick,
+}
+
+/// The built-in chat filter, implemented using hooks so plugins can add their own policies.
+#[derive(Debug, Default)]
+pub(crate) struct ChatFilter;
+
+impl ServerHooks for ChatFilter {
+    fn on_chat(
+        &mut self,
+        ctx: &mut HookCtx,
+        player_handle: Handle<Player>,
+        text: &mut String,
+    ) -> ChatAction {
+        let action = ctx.cvars.sv_chat_filter;
+        if action == FilterAction::Off {
+            return ChatAction::Allow;
+        }
+        let Some(censored) = censor(text, &ctx.cvars.sv_chat_filter_words) else {
+            return ChatAction::Allow;
+        };
+        dbg_logf!("chat filter: player {} said: {}", player_handle.index(), text);
+        *text = censored;
+        match action {
+            FilterAction::Off | FilterAction::Censor => ChatAction::Allow,
+            FilterAction::Warn => ChatAction::Warn("watch your language".to_owned()),
+            FilterAction::Mute => ChatAction::Mute(ctx.cvars.sv_chat_filter_mute_time),
+            FilterAction::Kick => ChatAction::Kick,
+        }
+    }
+}
+
+/// Replace whole words from the space separated list with asterisks, ignoring case.
+///
+/// Returns `None` if there's nothing to censor.
+fn censor(text: &str, words: &str) -> Option<String> {
+    let words: Vec<_> = words.split_whitespace().map(str::to_lowercase).collect();
+    let mut censored = String::with_capacity(text.len());
+    let mut found = false;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let end = rest.find(|c: char| !c.is_alphanumeric()).unwrap_or(rest.len());
+        let (word, after) = rest.split_at(end);
+        if !word.is_empty() && words.contains(&word.to_lowercase()) {
+            found = true;
+            censored.extend(word.chars().map(|_| '*'));
+        } else {
+            censored.push_str(word);
+        }
+        // The separator, if any.
+        let mut chars = after.chars();
+        if let Some(c) = chars.next() {
+            censored.push(c);
+        }
+        rest = chars.as_str();
+    }
+    found.then_some(censored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat() {
+        assert_eq!(parse(" hello "), Ok(ChatCommand::Say("hello")));
+        assert_eq!(parse("/me waves"), Ok(ChatCommand::Me("waves")));
+        assert!(parse("/me").is_err());
+        assert_eq!(parse("/votekick 3"), Ok(ChatCommand::VoteKick(3)));
+        assert!(parse("/votekick bob").is_err());
+        assert_eq!(parse("/switchteam"), Ok(ChatCommand::SwitchTeam));
+        assert_eq!(parse("/unpause"), Ok(ChatCommand::Unpause));
+        assert_eq!(parse("/restart_update"), Ok(ChatCommand::RestartUpdate));
+        assert!(parse("/nope").is_err());
+
+        let mut votes = VoteKicks::default();
+        let a = Handle::new(1, 1);
+        let b = Handle::new(2, 1);
+        let target = Handle::new(3, 1);
+        assert_eq!(votes.vote(a, target, 5), (1, 3));
+        assert_eq!(votes.vote(a, target, 5), (1, 3));
+        assert_eq!(votes.vote(b, target, 5), (2, 3));
+        votes.remove_player(b);
+        assert_eq!(votes.vote(a, target, 4), (1, 2));
+        assert_eq!(votes.vote(a, b, 2), (1, 2));
+
+        assert_eq!(censor("hello world", "darn heck"), None);
+        assert_eq!(censor("Darn it, HECK!", "darn heck"), Some("**** it, ****!".to_owned()));
+        assert_eq!(censor("darned", "darn"), None);
+        assert_eq!(censor("anything", ""), None);
+    }
+}
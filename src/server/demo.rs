@@ -0,0 +1,442 @@
+//! Demos - recordings of everything the server sends to all clients.
+//!
+//! Recorded when `sv_demo_record` is set. The file starts with `MAGIC`,
+//! followed by records, each a `MsgLen` header and a bincode-serialized `Record`:
+//! one `Record::Tick` per server tick and a `Record::Index` at the end.
+//! The last 8 bytes are the offset of the index so tools can seek without reading every tick.
+//! If the server didn't shut down cleanly, there's no index and it's rebuilt by scanning.
+//!
+//! Ticks contain event markers (kills) so `rustcycles demo` can list them
+//! and extract highlight clips around them as smaller demos.
+//!
+//! LATER Playback in the client. Demos don't contain `Init` since it's only sent to one client.
+//! LATER Capture markers once there are flags.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    mem,
+    ops::RangeInclusive,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{
+        entities::Kill,
+        messages::ServerMessage,
+        net::{self, MsgLen, NetworkMessage, HEADER_LEN},
+    },
+    prelude::*,
+};
+
+const MAGIC: &[u8] = b"RCDEMO01";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+enum Record {
+    Tick(DemoTick),
+    Index(DemoIndex),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub(crate) struct DemoTick {
+    pub(crate) frame_number: u64,
+    pub(crate) game_time: f32,
+    /// Network messages sent during this tick, each with its length header.
+    pub(crate) msgs: Vec<u8>,
+    pub(crate) markers: Vec<MarkerKind>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub(crate) enum MarkerKind {
+    Kill {
+        killer_index: u32,
+        victim_index: u32,
+    },
+}
+
+impl From<Kill> for MarkerKind {
+    fn from(kill: Kill) -> Self {
+        Self::Kill {
+            killer_index: kill.killer.index(),
+            victim_index: kill.victim.index(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub(crate) struct Marker {
+    /// Index into `DemoIndex::ticks`.
+    pub(crate) tick: usize,
+    pub(crate) game_time: f32,
+    pub(crate) kind: MarkerKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub(crate) struct TickEntry {
+    /// Where the tick's record starts in the file.
+    pub(crate) offset: u64,
+    pub(crate) game_time: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub(crate) struct DemoIndex {
+    pub(crate) ticks: Vec<TickEntry>,
+    pub(crate) markers: Vec<Marker>,
+}
+
+/// Writes a demo tick by tick.
+pub(crate) struct DemoWriter<W: Write> {
+    out: W,
+    /// Bytes written so far.
+    offset: u64,
+    index: DemoIndex,
+    /// Messages of the tick being recorded.
+    msgs: Vec<u8>,
+    /// Markers of the tick being recorded.
+    markers: Vec<MarkerKind>,
+    /// Reused to serialize records.
+    buf: Vec<u8>,
+}
+
+/// Where round `round` of the demo `path` is recorded with `sv_upload_url`,
+/// the first round (0) uses `path` itself.
+pub(crate) fn round_path(path: &str, round: u32) -> String {
+    if round == 0 {
+        return path.to_owned();
+    }
+    let path = Path::new(path);
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(format!(".round{}", round + 1));
+    if let Some(ext) = path.extension() {
+        name.push(".");
+        name.push(ext);
+    }
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+impl DemoWriter<BufWriter<File>> {
+    pub(crate) fn create(path: &str) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> DemoWriter<W> {
+    pub(crate) fn new(mut out: W) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+        Ok(Self {
+            out,
+            offset: MAGIC.len() as u64,
+            index: DemoIndex::default(),
+            msgs: Vec::new(),
+            markers: Vec::new(),
+            buf: Vec::new(),
+        })
+    }
+
+    /// Add a message sent during the current tick.
+    pub(crate) fn record(&mut self, network_msg: &NetworkMessage) {
+        self.msgs.extend_from_slice(&network_msg.content_len);
+        self.msgs.extend_from_slice(&network_msg.buf);
+    }
+
+    /// Add a message which is already serialized with its header.
+    fn record_raw(&mut self, bytes: &[u8]) {
+        self.msgs.extend_from_slice(bytes);
+    }
+
+    pub(crate) fn marker(&mut self, kind: MarkerKind) {
+        self.markers.push(kind);
+    }
+
+    /// Write everything recorded since the last call as one tick.
+    pub(crate) fn end_tick(&mut self, frame_number: usize, game_time: f32) -> io::Result<()> {
+        let tick = self.index.ticks.len();
+        for &kind in &self.markers {
+            self.index.markers.push(Marker {
+                tick,
+                game_time,
+                kind,
+            });
+        }
+        self.index.ticks.push(TickEntry {
+            offset: self.offset,
+            game_time,
+        });
+
+        let record = Record::Tick(DemoTick {
+            frame_number: frame_number as u64,
+            game_time,
+            msgs: mem::take(&mut self.msgs),
+            markers: mem::take(&mut self.markers),
+        });
+        self.write_record(&record)?;
+
+        // Keep the allocation for the next tick.
+        if let Record::Tick(tick) = record {
+            self.msgs = tick.msgs;
+            self.msgs.clear();
+        }
+        Ok(())
+    }
+
+    /// Write the index. Messages recorded after the last `end_tick` are dropped.
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        let index_offset = self.offset;
+        let record = Record::Index(mem::take(&mut self.index));
+        self.write_record(&record)?;
+        self.out.write_all(&index_offset.to_le_bytes())?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write_record(&mut self, record: &Record) -> io::Result<()> {
+        self.buf.clear();
+        bincode::serialize_into(&mut self.buf, record).expect("bincode failed to serialize record");
+        let len = MsgLen::try_from(self.buf.len()).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, "demo record too long".to_owned())
+        })?;
+        self.out.write_all(&len.to_le_bytes())?;
+        self.out.write_all(&self.buf)?;
+        self.offset += (HEADER_LEN + self.buf.len()) as u64;
+        Ok(())
+    }
+}
+
+/// A demo loaded into memory.
+pub(crate) struct Demo {
+    bytes: Vec<u8>,
+    pub(crate) index: DemoIndex,
+}
+
+impl Demo {
+    pub(crate) fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|err| format!("{}: {}", path, err))?;
+        Self::parse(bytes).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    pub(crate) fn parse(bytes: Vec<u8>) -> Result<Self, String> {
+        if !bytes.starts_with(MAGIC) {
+            return Err("not a demo file".to_owned());
+        }
+
+        // Use the index if the demo has one.
+        if bytes.len() >= MAGIC.len() + 8 {
+            let trailer: [u8; 8] = bytes[bytes.len() - 8..].try_into().unwrap();
+            let index_offset = u64::from_le_bytes(trailer);
+            if let Ok(index_offset) = usize::try_from(index_offset) {
+                if let Some((Record::Index(index), _)) = read_record(&bytes, index_offset) {
+                    return Ok(Self { bytes, index });
+                }
+            }
+        }
+
+        dbg_logf!("demo has no index, scanning all ticks");
+        let mut index = DemoIndex::default();
+        let mut offset = MAGIC.len();
+        // A partially written record at the end means the server crashed, ignore it.
+        while let Some((Record::Tick(tick), len)) = read_record(&bytes, offset) {
+            let tick_index = index.ticks.len();
+            for kind in tick.markers {
+                index.markers.push(Marker {
+                    tick: tick_index,
+                    game_time: tick.game_time,
+                    kind,
+                });
+            }
+            index.ticks.push(TickEntry {
+                offset: offset as u64,
+                game_time: tick.game_time,
+            });
+            offset += len;
+        }
+        Ok(Self { bytes, index })
+    }
+
+    pub(crate) fn tick(&self, tick: usize) -> Result<DemoTick, String> {
+        let entry = self.index.ticks.get(tick).ok_or_else(|| format!("no tick {}", tick))?;
+        let offset = usize::try_from(entry.offset).map_err(|err| err.to_string())?;
+        match read_record(&self.bytes, offset) {
+            Some((Record::Tick(tick), _)) => Ok(tick),
+            _ => Err(format!("corrupted tick {} at offset {}", tick, offset)),
+        }
+    }
+
+    /// The first tick at or after `game_time`, clamped to the last tick.
+    pub(crate) fn seek(&self, game_time: f32) -> usize {
+        let tick = self.index.ticks.partition_point(|entry| entry.game_time < game_time);
+        tick.min(self.index.ticks.len().saturating_sub(1))
+    }
+
+    /// Write the ticks in `range` as a new demo.
+    ///
+    /// Messages other than updates from before the range (players joining, cycles spawning, ...)
+    /// are included in the first tick so the clip is self-contained.
+    pub(crate) fn extract<W: Write>(
+        &self,
+        range: RangeInclusive<usize>,
+        out: W,
+    ) -> Result<W, String> {
+        let mut writer = DemoWriter::new(out).map_err(|err| err.to_string())?;
+        for i in 0..*range.start() {
+            let tick = self.tick(i)?;
+            for (bytes, msg) in split_msgs(&tick.msgs)? {
+                if !matches!(msg, ServerMessage::Update(_)) {
+                    writer.record_raw(bytes);
+                }
+            }
+        }
+        for i in range {
+            let tick = self.tick(i)?;
+            writer.record_raw(&tick.msgs);
+            for kind in tick.markers {
+                writer.marker(kind);
+            }
+            let frame_number = tick.frame_number as usize;
+            writer.end_tick(frame_number, tick.game_time).map_err(|err| err.to_string())?;
+        }
+        writer.finish().map_err(|err| err.to_string())
+    }
+}
+
+/// Read the record at `offset`, returns it and its length including the header.
+fn read_record(bytes: &[u8], offset: usize) -> Option<(Record, usize)> {
+    let header = bytes.get(offset..offset.checked_add(HEADER_LEN)?)?;
+    let len = usize::try_from(MsgLen::from_le_bytes(header.try_into().unwrap())).ok()?;
+    let start = offset + HEADER_LEN;
+    let content = bytes.get(start..start.checked_add(len)?)?;
+    let record = net::deserialize(content).ok()?;
+    Some((record, HEADER_LEN + len))
+}
+
+/// Split a tick's messages, returns each one's bytes including the header and the message.
+fn split_msgs(mut msgs: &[u8]) -> Result<Vec<(&[u8], ServerMessage)>, String> {
+    let mut ret = Vec::new();
+    while !msgs.is_empty() {
+        let header = msgs.get(..HEADER_LEN).ok_or("truncated message header")?;
+        let len = MsgLen::from_le_bytes(header.try_into().unwrap()) as usize;
+        let bytes = msgs.get(..HEADER_LEN + len).ok_or("truncated message")?;
+        let msg = net::deserialize(&bytes[HEADER_LEN..]).map_err(|err| err.to_string())?;
+        ret.push((bytes, msg));
+        msgs = &msgs[bytes.len()..];
+    }
+    Ok(ret)
+}
+
+/// `rustcycles demo` - list markers and optionally extract highlights around them.
+pub(crate) fn run(cvars: &Cvars) {
+    let demo = match Demo::load(&cvars.d_demo_file) {
+        Ok(demo) => demo,
+        Err(err) => {
+            println!("Failed to load demo: {}", err);
+            return;
+        }
+    };
+
+    let duration = match (demo.index.ticks.first(), demo.index.ticks.last()) {
+        (Some(first), Some(last)) => last.game_time - first.game_time,
+        _ => 0.0,
+    };
+    println!(
+        "{} ticks, {:.1} s, {} markers",
+        demo.index.ticks.len(),
+        duration,
+        demo.index.markers.len()
+    );
+
+    for (i, marker) in demo.index.markers.iter().enumerate() {
+        println!(
+            "#{:<4} tick {:>7} time {:>8.2}  {:?}",
+            i, marker.tick, marker.game_time, marker.kind
+        );
+
+        if cvars.d_demo_extract.is_empty() {
+            continue;
+        }
+        let first = demo.seek(marker.game_time - cvars.d_demo_highlight_before);
+        let last = demo.seek(marker.game_time + cvars.d_demo_highlight_after);
+        let path = format!("{}-{}.rcdemo", cvars.d_demo_extract, i);
+        let res = File::create(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|file| demo.extract(first..=last, BufWriter::new(file)));
+        match res {
+            Ok(_) => println!("      extracted ticks {}..={} to {}", first, last, path),
+            Err(err) => println!("      failed to extract to {}: {}", path, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::messages::{NetHandle, Update};
+
+    use super::*;
+
+    fn record_demo() -> Vec<u8> {
+        let mut writer = DemoWriter::new(Vec::new()).unwrap();
+        for frame in 1..=10 {
+            if frame == 2 {
+                let msg = ServerMessage::Join {
+                    player_handle: NetHandle {
+                        index: 0,
+                        generation: 1,
+                    },
+                };
+                writer.record(&net::serialize(msg));
+            }
+            writer.record(&net::serialize(ServerMessage::Update(Update::default())));
+            if frame == 6 {
+                writer.marker(MarkerKind::Kill {
+                    killer_index: 0,
+                    victim_index: 1,
+                });
+            }
+            writer.end_tick(frame, frame as f32 * 0.5).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn test_index() {
+        let bytes = record_demo();
+        let demo = Demo::parse(bytes.clone()).unwrap();
+        assert_eq!(demo.index.ticks.len(), 10);
+        assert_eq!(demo.index.markers.len(), 1);
+        assert_eq!(demo.index.markers[0].tick, 5);
+        assert_eq!(demo.seek(3.0), 5);
+        assert_eq!(demo.seek(100.0), 9);
+        assert_eq!(demo.tick(5).unwrap().frame_number, 6);
+
+        // Without the index, scanning has to find the same ticks.
+        let truncated = bytes[..demo.index.ticks[9].offset as usize + 3].to_vec();
+        let scanned = Demo::parse(truncated).unwrap();
+        assert_eq!(scanned.index.ticks, demo.index.ticks[..9]);
+        assert_eq!(scanned.index.markers, demo.index.markers);
+    }
+
+    #[test]
+    fn test_extract() {
+        let demo = Demo::parse(record_demo()).unwrap();
+        let clip = demo.extract(4..=6, Vec::new()).unwrap();
+        let clip = Demo::parse(clip).unwrap();
+        assert_eq!(clip.index.ticks.len(), 3);
+        assert_eq!(clip.index.markers[0].tick, 1);
+
+        // The join from before the clip is kept, older updates aren't.
+        let first = clip.tick(0).unwrap();
+        let msgs = split_msgs(&first.msgs).unwrap();
+        assert_eq!(msgs.len(), 2);
+        assert!(
+            matches!(msgs[0].1, ServerMessage::Join { player_handle } if player_handle.index == 0)
+        );
+        assert!(matches!(msgs[1].1, ServerMessage::Update(_)));
+    }
+
+    #[test]
+    fn test_round_path() {
+        assert_eq!(round_path("demos/match.rcdemo", 0), "demos/match.rcdemo");
+        assert_eq!(round_path("demos/match.rcdemo", 1), "demos/match.round2.rcdemo");
+        assert_eq!(round_path("match", 2), "match.round3");
+    }
+}
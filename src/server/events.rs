@@ -0,0 +1,133 @@
+//! Event log for external tools - `sv_event_log`.
+//!
+//! One JSON object per line with the wall clock time (seconds since the Unix epoch),
+//! the game time and the event's fields, e.g.
+//! `{"time":1700000000.123,"game_time":12.5,"event":"leave","player":3}`.
+//! Every line is written immediately so the file can be followed while the server runs.
+
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::common::json::json_string;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Event<'a> {
+    Join {
+        player_index: u32,
+        addr: &'a str,
+    },
+    Leave {
+        player_index: u32,
+    },
+    Kill {
+        killer_index: u32,
+        victim_index: u32,
+        assist_indices: &'a [u32],
+    },
+    /// What the player wrote, before hooks changed it, and what the hooks decided.
+    Chat {
+        player_index: u32,
+        text: &'a str,
+        action: &'a str,
+    },
+    Map {
+        name: &'a str,
+    },
+}
+
+pub(crate) struct EventLog<W: Write> {
+    out: W,
+}
+
+impl<W: Write> EventLog<W> {
+    pub(crate) fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    pub(crate) fn write(&mut self, game_time: f32, event: Event) -> io::Result<()> {
+        // Before the epoch only if the clock is very wrong, the game time is still there.
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |duration| duration.as_secs_f64());
+        self.out.write_all(line(time, game_time, event).as_bytes())?;
+        self.out.flush()
+    }
+}
+
+fn line(time: f64, game_time: f32, event: Event) -> String {
+    let mut line = format!(r#"{{"time":{:.3},"game_time":{:.3},"#, time, game_time);
+    match event {
+        Event::Join { player_index, addr } => {
+            write!(line, r#""event":"join","player":{},"addr":{}"#, player_index, json_string(addr))
+        }
+        Event::Leave { player_index } => {
+            write!(line, r#""event":"leave","player":{}"#, player_index)
+        }
+        Event::Kill {
+            killer_index,
+            victim_index,
+            assist_indices,
+        } => {
+            let assists: Vec<_> = assist_indices.iter().map(u32::to_string).collect();
+            write!(
+                line,
+                r#""event":"kill","killer":{},"victim":{},"assists":[{}]"#,
+                killer_index,
+                victim_index,
+                assists.join(",")
+            )
+        }
+        Event::Chat {
+            player_index,
+            text,
+            action,
+        } => write!(
+            line,
+            r#""event":"chat","player":{},"text":{},"action":"{}""#,
+            player_index,
+            json_string(text),
+            action
+        ),
+        Event::Map { name } => write!(line, r#""event":"map","name":{}"#, json_string(name)),
+    }
+    .unwrap();
+    line.push_str("}\n");
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        let kill = Event::Kill {
+            killer_index: 1,
+            victim_index: 2,
+            assist_indices: &[3, 4],
+        };
+        assert_eq!(
+            line(1.5, 2.0, kill),
+            "{\"time\":1.500,\"game_time\":2.000,\"event\":\"kill\",\
+            \"killer\":1,\"victim\":2,\"assists\":[3,4]}\n"
+        );
+
+        let chat = Event::Chat {
+            player_index: 1,
+            text: "\"hi\"",
+            action: "allow",
+        };
+        assert!(line(0.0, 0.0, chat)
+            .trim_end()
+            .ends_with(r#""text":"\"hi\"","action":"allow"}"#));
+
+        let mut log = EventLog::new(Vec::new());
+        log.write(0.0, Event::Leave { player_index: 7 }).unwrap();
+        log.write(0.0, Event::Map { name: "arena" }).unwrap();
+        let text = String::from_utf8(log.out).unwrap();
+        assert_eq!(text.lines().count(), 2);
+    }
+}
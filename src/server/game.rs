@@ -1,21 +1,75 @@
 //! Server-side gamelogic.
 
-use std::{io::ErrorKind, mem};
+use std::{
+    cell::RefCell,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, ErrorKind},
+    mem,
+    rc::Rc,
+    sync::Mutex,
+};
+
+use fyrox::core::instant::Instant;
+
+use rayon::{
+    iter::{ParallelBridge, ParallelIterator},
+    ThreadPool, ThreadPoolBuilder,
+};
 
 use crate::{
     common::{
-        entities::{Player, PlayerState},
+        desync::{DesyncHistory, StateSummary, StateSummaryExt},
+        entities::{
+            Cheats, Effect, GameEvent, MatchPhase, Player, PlayerState, VehicleKind, TEAM_NAMES,
+        },
         messages::{
-            AddPlayer, ClientMessage, CyclePhysics, Init, PlayerCycle, PlayerInput, ServerMessage,
-            Update,
+            AddPlayer, ChatLine, Cheat, ClientMessage, CyclePhysics, DebugItems, HandleExt, Init,
+            MatchSummary, PlatformPose, PlayerCheats, PlayerCycle, PlayerFingerprint, PlayerInput,
+            PlayerKill, PlayerLook, PlayerPowerups, PlayerScore, PlayerTeam, ServerMessage, Update,
         },
-        net::{self, Connection, Listener},
-        GameState,
+        net::{self, Connection, Listener, NetworkMessage, PingStats},
+        platforms,
+        theme::{ServerTheme, ServerThemeExt},
+        Authority, GameState, InputExt,
+    },
+    debug::{
+        self,
+        details::{DEBUG_LOGS, DEBUG_SHAPES, DEBUG_TEXTS, DEBUG_WORLD_TEXTS},
     },
-    debug::details::{DEBUG_SHAPES, DEBUG_TEXTS},
     prelude::*,
+    server::{
+        assists::Assists,
+        backfill::{self, Backfill},
+        bounds::OutOfBounds,
+        chat::{self, ChatCommand, ChatFilter, VoteKicks},
+        demo::{self, DemoWriter},
+        events::{Event, EventLog},
+        hooks::{ChatAction, HookEvent, Hooks},
+        metrics::Metrics,
+        modes::FreeForAll,
+        motd,
+        net_stats::NetStats,
+        pause::MatchPause,
+        pickups::Pickups,
+        reconnect::Reconnects,
+        relay::SpectatorRelay,
+        respawns::Respawns,
+        restart,
+        rewind::RewindBuffer,
+        savestate::{SaveState, SavedPlayerState},
+        stats::MatchStats,
+        status::StatusServer,
+        teams::{self, TeamTime},
+        triggers::Triggers,
+        upload::Uploads,
+    },
 };
 
+/// How many effects are kept for a client skipping updates, the oldest are dropped first.
+///
+/// A low enough `cl_updaterate` could otherwise make the server keep effects for minutes.
+const SKIPPED_EFFECTS_MAX: usize = 256;
+
 /// A game server. Could be dedicated or a listen server.
 ///
 /// Lets clients connect to play. Contains the authoritative copy of the game state.
@@ -23,6 +77,75 @@ pub(crate) struct ServerGame {
     pub(crate) gs: GameState,
     listener: Box<dyn Listener>,
     clients: Pool<RemoteClient>,
+    /// Players without a client in the order they were added, see `sv_bot_backfill`.
+    bots: Vec<Handle<Player>>,
+    /// Reused by `network_send` so messages don't allocate once the buffer is large enough.
+    send_buf: NetworkMessage,
+    /// The previous `Update`, its buffers are reused to build the next one.
+    ///
+    /// Effects are collected here between updates, see `sv_snapshot_rate`.
+    ///
+    /// LATER Keep a ring of past updates for delta compression.
+    update_buf: Update,
+    /// `frame_number` of the last update, sent in `Init`.
+    update_frame: u32,
+    /// States at the last updates while clients use `d_desync_check`.
+    desync_history: DesyncHistory,
+    /// Reused by `sys_send_debug` so it doesn't allocate once the buffers are large enough.
+    debug_buf: DebugItems,
+    /// See `sv_send_threads`.
+    send_pool: Option<ThreadPool>,
+    /// Clients `send_pool` failed to send to, reused by `network_send_ref`.
+    send_errors: Vec<(Handle<RemoteClient>, io::Error)>,
+    pub(crate) hooks: Hooks,
+    /// Events which happened outside gamelogic, dispatched to hooks during the next tick.
+    hook_events: Vec<HookEvent>,
+    /// See `sv_demo_record`.
+    demo: Option<DemoWriter<BufWriter<File>>>,
+    /// The value of `sv_demo_record` when recording started or stopped.
+    demo_path: String,
+    /// With `sv_upload_url`, each round after the first is recorded to its own file.
+    demo_round: u32,
+    /// See `sv_upload_url`.
+    uploads: Uploads,
+    /// See `sv_event_log`.
+    event_log: Option<EventLog<File>>,
+    /// See `sv_spectator_addr`.
+    relay: Option<SpectatorRelay>,
+    /// See `sv_status_addr`.
+    status: Option<StatusServer>,
+    metrics: Metrics,
+    /// See `net_stats`.
+    pub(crate) net_stats: NetStats,
+    /// Only in local games, see `rewind`.
+    pub(crate) rewind_buffer: Option<RewindBuffer>,
+    vote_kicks: VoteKicks,
+    assists: Assists,
+    /// See `sv_reconnect_time`.
+    reconnects: Reconnects,
+    /// Filled by `MatchStats` when rounds end.
+    summaries: Rc<RefCell<Vec<MatchSummary>>>,
+    triggers: Triggers,
+    out_of_bounds: OutOfBounds,
+    pickups: Pickups,
+    respawns: Respawns,
+    /// See `g_competitive`.
+    pause: Option<MatchPause>,
+    /// The last phase sent to clients, hooks change `gs.phase` and we replicate it.
+    phase_sent: MatchPhase,
+    /// The last `sv_cheats` sent to clients.
+    cheats_allowed_sent: bool,
+    /// Game time of the next `g_autobalance_interval` check.
+    autobalance_next: f32,
+    /// Game time of the next `sv_ping_interval` round.
+    ping_next: f32,
+    /// Number of the last `ServerMessage::Ping` so late replies can be told apart.
+    ping_id: u32,
+    /// Set by `/restart_update`, the server process restarts after this update.
+    pub(crate) restart_requested: bool,
+    /// What the previous server saved for `/restart_update`,
+    /// kept until its players reconnect or `sv_reconnect_time` runs out.
+    restart_state: Option<SaveState>,
 }
 
 impl ServerGame {
@@ -31,34 +154,191 @@ impl ServerGame {
         engine: &mut Engine,
         listener: Box<dyn Listener>,
     ) -> Self {
-        let gs = GameState::new(cvars, engine).await;
+        let gs = GameState::new(cvars, engine, Authority::Server).await;
 
-        Self {
+        let mut hooks = Hooks::new();
+        // The built-in mode, chat filter and stats are hooks like plugins so they can be replaced.
+        hooks.add(Box::new(FreeForAll::default()));
+        hooks.add(Box::new(ChatFilter));
+        let summaries = Rc::new(RefCell::new(Vec::new()));
+        hooks.add(Box::new(MatchStats::new(Rc::clone(&summaries))));
+
+        let send_pool = if cvars.sv_send_threads == 0 {
+            None
+        } else {
+            let res = ThreadPoolBuilder::new()
+                .num_threads(cvars.sv_send_threads)
+                .thread_name(|i| format!("sv-send-{}", i))
+                .build();
+            match res {
+                Ok(pool) => Some(pool),
+                Err(err) => {
+                    dbg_logf!(
+                        "failed to start send threads, sending from the main thread: {}",
+                        err
+                    );
+                    None
+                }
+            }
+        };
+
+        let relay = if cvars.sv_spectator_addr.is_empty() {
+            None
+        } else {
+            match SpectatorRelay::bind(&cvars.sv_spectator_addr) {
+                Ok(relay) => {
+                    dbg_logf!("spectators can connect to {}", cvars.sv_spectator_addr);
+                    Some(relay)
+                }
+                Err(err) => {
+                    dbg_logf!(
+                        "failed to listen for spectators on {}: {}",
+                        cvars.sv_spectator_addr,
+                        err
+                    );
+                    None
+                }
+            }
+        };
+
+        let status = if cvars.sv_status_addr.is_empty() {
+            None
+        } else {
+            match StatusServer::bind(&cvars.sv_status_addr) {
+                Ok(status) => {
+                    dbg_logf!("serving status on http://{}/status", cvars.sv_status_addr);
+                    Some(status)
+                }
+                Err(err) => {
+                    dbg_logf!("failed to serve status on {}: {}", cvars.sv_status_addr, err);
+                    None
+                }
+            }
+        };
+
+        let event_log = if cvars.sv_event_log.is_empty() {
+            None
+        } else {
+            match OpenOptions::new().create(true).append(true).open(&cvars.sv_event_log) {
+                Ok(file) => Some(EventLog::new(file)),
+                Err(err) => {
+                    dbg_logf!("failed to open event log {}: {}", cvars.sv_event_log, err);
+                    None
+                }
+            }
+        };
+
+        let mut sg = Self {
+            phase_sent: gs.phase,
+            cheats_allowed_sent: cvars.sv_cheats,
             gs,
             listener,
             clients: Pool::new(),
+            bots: Vec::new(),
+            send_buf: NetworkMessage::default(),
+            update_buf: Update::default(),
+            update_frame: 0,
+            desync_history: DesyncHistory::default(),
+            debug_buf: DebugItems::default(),
+            send_pool,
+            send_errors: Vec::new(),
+            hooks,
+            hook_events: Vec::new(),
+            demo: None,
+            demo_path: String::new(),
+            demo_round: 0,
+            uploads: Uploads::new(),
+            event_log,
+            relay,
+            status,
+            metrics: Metrics::default(),
+            net_stats: NetStats::default(),
+            rewind_buffer: None,
+            vote_kicks: VoteKicks::default(),
+            assists: Assists::default(),
+            reconnects: Reconnects::new(),
+            summaries,
+            triggers: Triggers::default(),
+            out_of_bounds: OutOfBounds::default(),
+            pickups: Pickups::default(),
+            respawns: Respawns::default(),
+            pause: None,
+            autobalance_next: 0.0,
+            ping_next: 0.0,
+            ping_id: 0,
+            restart_requested: false,
+            restart_state: None,
+        };
+        sg.log_event(Event::Map { name: &cvars.g_map });
+        if let Some(state) = restart::load(cvars) {
+            sg.restore_match(&state);
+            sg.restart_state = Some(state);
         }
+        sg
     }
 
     pub(crate) fn update(&mut self, cvars: &Cvars, engine: &mut Engine, game_time_target: f32) {
         // This is similar to Client::update,
         // see that for more information.
+        //
+        // Gamelogic and physics run on this thread so the simulation stays deterministic.
+        // Only sending to clients can run in parallel (`sv_send_threads`), the rest isn't worth it:
+        // - Updates are built once per tick and the same bytes are sent to everyone.
+        // - Bots draw from `gs.rng` so they have to run in order to stay deterministic.
+        // - Stats only add a few numbers per event.
 
         let dt = 1.0 / 60.0;
         while self.gs.game_time + dt < game_time_target {
+            let tick_start = Instant::now();
             self.gs.game_time_prev = self.gs.game_time;
             self.gs.game_time += dt;
             self.gs.frame_number += 1;
+            debug::crash::set_frame(self.gs.frame_number, self.gs.game_time);
+
+            self.demo_begin_tick(cvars);
+
+            {
+                let _timer = dbg_timer!("net recv");
+                self.tick_begin_frame(cvars, engine);
+            }
+
+            if let Some(rewind_buffer) = &mut self.rewind_buffer {
+                let scene = &engine.scenes[self.gs.scene_handle];
+                rewind_buffer.begin_tick(cvars, &mut self.gs, scene);
+            }
 
-            self.tick_begin_frame(engine);
+            {
+                let _timer = dbg_timer!("gamelogic");
+                self.gs.tick_before_physics(cvars, engine, dt);
+                // Nothing moves while paused so nothing else needs to run.
+                if self.pause.is_none() {
+                    let scene = &mut engine.scenes[self.gs.scene_handle];
+                    self.triggers.tick(cvars, &mut self.gs, scene);
+                    platforms::tick(&self.gs, scene);
+                    self.pickups.tick(cvars, &mut self.gs, scene);
+                    self.out_of_bounds.tick(cvars, &mut self.gs, scene);
+                }
+            }
 
-            self.gs.tick_before_physics(cvars, engine, dt);
+            // Before hooks so assists count towards the round the kill happened in.
+            self.sys_kills(cvars, engine);
+            self.sys_respawn(cvars, engine);
+            {
+                let _timer = dbg_timer!("hooks");
+                self.tick_hooks(cvars);
+            }
+            self.sys_send_summaries(cvars, engine);
+            self.sys_send_phase(engine);
+            self.sys_replicate(engine);
 
             // There's currently no need to split this into pre_ and post_update like on the client.
             // Dummy control flow and lag since we don't use fyrox plugins.
             let mut cf = fyrox::event_loop::ControlFlow::Poll;
             let mut lag = 0.0;
-            engine.update(dt, &mut cf, &mut lag);
+            {
+                let _timer = dbg_timer!("physics");
+                engine.update(dt, &mut cf, &mut lag);
+            }
             // Sanity check - if the engine starts doing something with these, we'll know.
             assert_eq!(cf, fyrox::event_loop::ControlFlow::Poll);
             assert_eq!(lag, 0.0);
@@ -66,51 +346,837 @@ impl ServerGame {
             // `sys_send_update` sends debug shapes and text to client.
             // Any debug calls after it will show up next frame.
             self.gs.debug_engine_updates(cvars, v!(-5 5 3));
-            self.sys_send_update(engine);
+            {
+                let _timer = dbg_timer!("net send");
+                self.sys_send_update(cvars, engine);
+                self.sys_send_log(cvars, engine);
+            }
+            self.demo_end_tick();
+            self.sys_upload(cvars);
+            if let Some(relay) = &mut self.relay {
+                relay.end_tick(cvars, self.gs.game_time);
+            }
+            self.metrics.tick(tick_start.elapsed());
+            self.net_stats.warn(cvars);
+            if let Some(status) = &mut self.status {
+                let players = self.clients.alive_count();
+                status.end_tick(cvars, &self.gs, &self.metrics, players);
+            }
             self.gs.debug_engine_updates(cvars, v!(-6 5 3));
         }
     }
 
-    fn tick_begin_frame(&mut self, engine: &mut Engine) {
-        self.accept_new_connections(engine);
-        self.sys_receive(engine);
+    /// Restore the state from `seconds` ago, returns how far back it actually went.
+    pub(crate) fn rewind(&mut self, engine: &mut Engine, seconds: f32) -> Result<f32, String> {
+        let rewind_buffer = self.rewind_buffer.as_mut().ok_or("rewind is disabled")?;
+        let scene = &mut engine.scenes[self.gs.scene_handle];
+        rewind_buffer.rewind(&mut self.gs, scene, seconds)
+    }
+
+    pub(crate) fn savestate(
+        &self,
+        cvars: &Cvars,
+        engine: &Engine,
+        path: &str,
+    ) -> Result<(), String> {
+        let scene = &engine.scenes[self.gs.scene_handle];
+        let mut state = SaveState::new(cvars, &self.gs, scene);
+        for saved in &mut state.players {
+            saved.reconnect_token = self
+                .client_handle(saved.handle())
+                .map(|client_handle| self.clients[client_handle].reconnect_token);
+        }
+        state.save(path)
+    }
+
+    /// Restore the state saved in `path`, returns how many players were restored.
+    pub(crate) fn loadstate(
+        &mut self,
+        cvars: &Cvars,
+        engine: &mut Engine,
+        path: &str,
+    ) -> Result<usize, String> {
+        let state = SaveState::load(path)?;
+        if state.map != cvars.g_map {
+            return Err(format!("saved on map {}, this is {}", state.map, cvars.g_map));
+        }
+
+        let delay = self.restore_match(&state);
+        let mut restored = 0;
+        for saved in &state.players {
+            let player_handle = saved.handle();
+            if !self.gs.players.is_valid_handle(player_handle) {
+                dbg_logf!("player {} from the save isn't connected, skipping", saved.index);
+                continue;
+            }
+            self.restore_player(cvars, engine, player_handle, saved, delay);
+            restored += 1;
+        }
+        Ok(restored)
+    }
+
+    /// Restore what doesn't belong to any player, returns how much later the game time is now.
+    fn restore_match(&mut self, state: &SaveState) -> f32 {
+        let delay = self.gs.game_time - state.game_time;
+        self.gs.rng = state.rng.clone();
+        // Replicated by `sys_send_phase`.
+        self.gs.phase = state.phase.delayed(delay);
+        delay
+    }
+
+    /// `delay` is how much later the game time is now than when it was saved.
+    fn restore_player(
+        &mut self,
+        cvars: &Cvars,
+        engine: &mut Engine,
+        player_handle: Handle<Player>,
+        saved: &SavedPlayerState,
+        delay: f32,
+    ) {
+        if let Some(team) = saved.team {
+            self.set_team(engine, player_handle, team);
+            let time = TeamTime(saved.team_time.unwrap_or(0.0) + delay);
+            self.gs.player_components.insert(player_handle, time);
+        } else if self.gs.players[player_handle].team.is_some() {
+            self.leave_team(engine, player_handle);
+        }
+        if self.gs.players[player_handle].cheats != saved.cheats {
+            self.set_cheats(engine, player_handle, saved.cheats);
+        }
+
+        if !saved.playing {
+            if self.gs.players[player_handle].ps == PlayerState::Playing {
+                self.observe(engine, player_handle);
+            }
+        } else {
+            // Respawn timers aren't saved, players waiting for one get their cycle right away.
+            let vehicle = saved.cycle.as_ref().map_or(saved.vehicle, |cycle| cycle.vehicle);
+            let scene = &mut engine.scenes[self.gs.scene_handle];
+            let cycle_handle = match self.gs.players[player_handle].cycle_handle {
+                Some(cycle_handle) if self.gs.cycles[cycle_handle].vehicle == vehicle => {
+                    cycle_handle
+                }
+                Some(cycle_handle) => {
+                    self.gs.despawn_cycle(scene, cycle_handle);
+                    self.gs.spawn_cycle(cvars, scene, player_handle, vehicle, None)
+                }
+                None => self.gs.spawn_cycle(cvars, scene, player_handle, vehicle, None),
+            };
+            if let Some(saved_cycle) = &saved.cycle {
+                saved_cycle.restore(&self.gs.cycles[cycle_handle], scene);
+            }
+            self.respawns.remove_player(player_handle);
+
+            if self.gs.players[player_handle].ps != PlayerState::Playing {
+                self.gs.players[player_handle].ps = PlayerState::Playing;
+                self.gs.events.push(GameEvent::Join { player_handle });
+            }
+        }
+
+        // After spawning, a new cycle gives the vehicle's armor.
+        // Scores are sent to everyone with pings.
+        let player = &mut self.gs.players[player_handle];
+        player.score = saved.score;
+        player.assists = saved.assists;
+        player.armor = saved.armor;
+        player.vehicle = saved.vehicle;
+    }
+
+    fn tick_begin_frame(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        self.accept_new_connections(cvars, engine);
+        self.sys_receive(cvars, engine);
+        self.sys_pause(engine);
+        self.sys_backfill(cvars, engine);
+        self.sys_bot_inputs();
+        self.sys_autobalance(cvars, engine);
+        self.sys_afk(cvars, engine);
+        self.sys_cheats_off(cvars, engine);
+        self.sys_ping(cvars, engine);
+        if self.gs.hot_reload(cvars, engine).map {
+            self.log_event(Event::Map { name: &cvars.g_map });
+        }
+    }
+
+    /// Pass everything that happened this frame to hooks, then let them run their per-tick logic.
+    fn tick_hooks(&mut self, cvars: &Cvars) {
+        let mut events = mem::take(&mut self.hook_events);
+        events.extend(self.gs.kills.iter().map(|&kill| HookEvent::Kill(kill)));
+        events.push(HookEvent::Tick);
+        for event in events {
+            self.hooks.dispatch(cvars, &mut self.gs, event);
+        }
+    }
+
+    /// Move a player from the largest team if it's too large, see `g_autobalance_interval`.
+    fn sys_autobalance(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        if cvars.g_autobalance_interval <= 0.0 || self.gs.game_time < self.autobalance_next {
+            return;
+        }
+        self.autobalance_next = self.gs.game_time + cvars.g_autobalance_interval;
+
+        if let Some((player_handle, team)) = teams::autobalance(cvars, &self.gs) {
+            dbg_logf!("autobalance: moving player {}", player_handle.index());
+            self.set_team(engine, player_handle, team);
+        }
+    }
+
+    /// Add or remove a bot if teams are uneven, see `sv_bot_backfill`.
+    fn sys_backfill(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        match backfill::check(cvars, &self.gs, &self.bots) {
+            Some(Backfill::Add) => {
+                let player_handle = self.add_player(cvars, engine);
+                self.bots.push(player_handle);
+                dbg_logf!("bot {} added", player_handle.index());
+                self.log_event(Event::Join {
+                    player_index: player_handle.index(),
+                    addr: "bot",
+                });
+                // Bots don't hold up warmup.
+                self.gs.players[player_handle].ready = true;
+                let msg = ServerMessage::Ready {
+                    player_handle: player_handle.net(),
+                    ready: true,
+                };
+                self.network_send(engine, msg, SendDest::All);
+                self.join(cvars, engine, player_handle);
+            }
+            Some(Backfill::Remove(player_handle)) => {
+                self.bots.retain(|&bot| bot != player_handle);
+                dbg_logf!("bot {} removed", player_handle.index());
+                self.remove_player(engine, player_handle);
+            }
+            None => {}
+        }
+    }
+
+    /// Bots drive and shoot randomly like `rustcycles botclient`.
+    fn sys_bot_inputs(&mut self) {
+        for &player_handle in &self.bots {
+            self.gs.players[player_handle].input.randomize(&mut self.gs.rng);
+        }
+    }
+
+    /// Move players who haven't touched the controls for `sv_afk_timeout` to observers.
+    ///
+    /// Observers don't count towards warmup's ready ratio so they don't hold up the match.
+    fn sys_afk(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        if cvars.sv_afk_timeout <= 0.0 {
+            return;
+        }
+        let afk: Vec<_> = self
+            .clients
+            .iter()
+            .filter(|client| self.gs.players[client.player_handle].ps == PlayerState::Playing)
+            .filter(|client| self.gs.game_time - client.last_active > cvars.sv_afk_timeout)
+            .map(|client| client.player_handle)
+            .collect();
+        for player_handle in afk {
+            dbg_logf!("player {} is AFK, now observing", player_handle.index());
+            self.observe(engine, player_handle);
+            let text = "you were moved to observers for being AFK, shoot to join again".to_owned();
+            self.reply(engine, player_handle, text);
+        }
+    }
+
+    /// Send everyone the latest scores and pings, then start measuring again.
+    ///
+    /// Replies are only handled once per tick so pings include up to a tick on each side.
+    fn sys_ping(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        if cvars.sv_ping_interval <= 0.0 || self.gs.game_time < self.ping_next {
+            return;
+        }
+        self.ping_next = self.gs.game_time + cvars.sv_ping_interval;
+
+        for client in &self.clients {
+            let ping = client.ping.last().map(|ms| ms.round().min(f32::from(u16::MAX)) as u16);
+            self.gs.players[client.player_handle].ping = ping;
+        }
+        let scores = self
+            .gs
+            .players
+            .pair_iter()
+            .map(|(player_handle, player)| PlayerScore {
+                player_handle: player_handle.net(),
+                score: player.score,
+                assists: player.assists,
+                ping: player.ping,
+            })
+            .collect();
+        self.network_send(engine, ServerMessage::Scores(scores), SendDest::All);
+
+        // Pings which weren't answered by now are lost or too late.
+        self.ping_id = self.ping_id.wrapping_add(1);
+        let handles: Vec<_> = self.clients.pair_iter().map(|(handle, _)| handle).collect();
+        for client_handle in handles {
+            if !self.clients.is_valid_handle(client_handle) {
+                continue; // Disconnected while sending to an earlier client.
+            }
+            self.clients[client_handle].ping_sent = Some((self.ping_id, Instant::now()));
+            let msg = ServerMessage::Ping(self.ping_id);
+            self.network_send(engine, msg, SendDest::One(client_handle));
+        }
+    }
+
+    fn set_team(&mut self, engine: &mut Engine, player_handle: Handle<Player>, team: u8) {
+        self.gs.players[player_handle].team = Some(team);
+        let time = TeamTime(self.gs.game_time);
+        self.gs.player_components.insert(player_handle, time);
+
+        let player_index = player_handle.index();
+        dbg_logf!("player {} is now on team {}", player_index, TEAM_NAMES[usize::from(team)]);
+        let msg = ServerMessage::PlayerTeam(PlayerTeam {
+            player_handle: player_handle.net(),
+            team: Some(team),
+        });
+        self.network_send(engine, msg, SendDest::All);
+    }
+
+    fn leave_team(&mut self, engine: &mut Engine, player_handle: Handle<Player>) {
+        self.gs.players[player_handle].team = None;
+        self.gs.player_components.remove::<TeamTime>(player_handle);
+
+        dbg_logf!("player {} is no longer on a team", player_handle.index());
+        let msg = ServerMessage::PlayerTeam(PlayerTeam {
+            player_handle: player_handle.net(),
+            team: None,
+        });
+        self.network_send(engine, msg, SendDest::All);
+    }
+
+    /// Show a chat line to everyone or run the chat command, errors are sent back to the player.
+    fn chat(
+        &mut self,
+        cvars: &Cvars,
+        engine: &mut Engine,
+        player_handle: Handle<Player>,
+        line: &str,
+    ) {
+        let res = match chat::parse(line) {
+            Ok(ChatCommand::Say(text)) => {
+                self.player_say(cvars, engine, player_handle, text, false)
+            }
+            Ok(ChatCommand::Me(text)) => self.player_say(cvars, engine, player_handle, text, true),
+            Ok(ChatCommand::VoteKick(target_index)) => {
+                self.votekick(engine, player_handle, target_index)
+            }
+            Ok(ChatCommand::SwitchTeam) => self.switch_team(cvars, engine, player_handle),
+            Ok(ChatCommand::Unpause) => self.captain_unpause(engine, player_handle),
+            Ok(ChatCommand::RestartUpdate) => self.request_restart(player_handle),
+            Err(err) => Err(err),
+        };
+        if let Err(err) = res {
+            self.reply(engine, player_handle, err);
+        }
+    }
+
+    /// Let hooks moderate the line, then send it to everyone.
+    fn player_say(
+        &mut self,
+        cvars: &Cvars,
+        engine: &mut Engine,
+        player_handle: Handle<Player>,
+        text: &str,
+        me: bool,
+    ) -> Result<(), String> {
+        let client_handle = self.client_handle(player_handle).ok_or("not connected")?;
+        let muted_left = self.clients[client_handle].chat_muted_until - self.gs.game_time;
+        if muted_left > 0.0 {
+            return Err(format!("you're muted for {:.0} more seconds", muted_left.ceil()));
+        }
+
+        let original = text;
+        let mut text = text.to_owned();
+        let action = self.hooks.chat(cvars, &mut self.gs, player_handle, &mut text);
+        let player_index = player_handle.index();
+        let action_name = match action {
+            ChatAction::Allow => "allow",
+            ChatAction::Warn(_) => "warn",
+            ChatAction::Mute(_) => "mute",
+            ChatAction::Kick => "kick",
+        };
+        self.log_event(Event::Chat {
+            player_index,
+            text: original,
+            action: action_name,
+        });
+        match action {
+            ChatAction::Allow => {}
+            ChatAction::Warn(warning) => self.reply(engine, player_handle, warning),
+            ChatAction::Mute(seconds) => {
+                dbg_logf!("muting player {} for {} s", player_index, seconds);
+                self.clients[client_handle].chat_muted_until = self.gs.game_time + seconds;
+                return Err(format!("you're muted for {:.0} seconds", seconds));
+            }
+            ChatAction::Kick => {
+                let text = format!("player {} was kicked for what they said", player_index);
+                self.say(engine, None, &text, false);
+                self.disconnect(engine, client_handle);
+                return Ok(());
+            }
+        }
+        self.say(engine, Some(player_index), &text, me);
+        Ok(())
+    }
+
+    /// Send a chat line to everyone, `player_index` is `None` for lines from the server.
+    fn say(&mut self, engine: &mut Engine, player_index: Option<u32>, text: &str, me: bool) {
+        let line = ChatLine {
+            player_index,
+            text: text.to_owned(),
+            me,
+        };
+        dbg_logf!("chat: {}", line);
+        self.network_send(engine, ServerMessage::Chat(line), SendDest::All);
+    }
+
+    /// Send a chat line from the server only to this player.
+    fn reply(&mut self, engine: &mut Engine, player_handle: Handle<Player>, text: String) {
+        if let Some(client_handle) = self.client_handle(player_handle) {
+            let line = ChatLine {
+                player_index: None,
+                text,
+                me: false,
+            };
+            self.network_send(engine, ServerMessage::Chat(line), SendDest::One(client_handle));
+        }
+    }
+
+    /// `/votekick` - kick the target once more than half of the other players
+    /// and at least 2 voted.
+    fn votekick(
+        &mut self,
+        engine: &mut Engine,
+        voter: Handle<Player>,
+        target_index: u32,
+    ) -> Result<(), String> {
+        let target = self.gs.players.handle_from_index(target_index);
+        if !self.gs.players.is_valid_handle(target) {
+            return Err(format!("no player {}", target_index));
+        }
+        if target == voter {
+            return Err("you can't vote to kick yourself".to_owned());
+        }
+
+        let player_count = self.gs.players.alive_count() as usize;
+        let (votes, needed) = self.vote_kicks.vote(voter, target, player_count);
+        let text = format!("vote to kick player {}: {}/{}", target_index, votes, needed);
+        self.say(engine, None, &text, false);
+        if votes >= needed {
+            if let Some(client_handle) = self.client_handle(target) {
+                self.say(engine, None, &format!("player {} was kicked", target_index), false);
+                self.disconnect(engine, client_handle);
+            }
+        }
+        Ok(())
+    }
+
+    /// `/switchteam` - move to the next team if it doesn't make teams uneven.
+    fn switch_team(
+        &mut self,
+        cvars: &Cvars,
+        engine: &mut Engine,
+        player_handle: Handle<Player>,
+    ) -> Result<(), String> {
+        let team_count = teams::team_sizes(cvars, &self.gs).len();
+        if team_count == 0 {
+            return Err("there are no teams".to_owned());
+        }
+        let current = self.gs.players[player_handle].team;
+        let team = current.map_or(0, |team| (usize::from(team) + 1) % team_count) as u8;
+        teams::check_switch(cvars, &self.gs, player_handle, team)?;
+        self.set_team(engine, player_handle, team);
+        Ok(())
+    }
+
+    /// `/unpause` - continue a paused match without waiting for the rest.
+    ///
+    /// Only team captains can do it, without teams anyone playing can.
+    fn captain_unpause(
+        &mut self,
+        engine: &mut Engine,
+        player_handle: Handle<Player>,
+    ) -> Result<(), String> {
+        if self.pause.is_none() {
+            return Err("the match isn't paused".to_owned());
+        }
+        let player = &self.gs.players[player_handle];
+        let allowed = match player.team {
+            Some(team) => teams::captain(&self.gs, team, &self.bots) == Some(player_handle),
+            None => player.ps == PlayerState::Playing,
+        };
+        if !allowed {
+            return Err("only team captains can unpause".to_owned());
+        }
+        let text = format!("player {} unpaused the match", player_handle.index());
+        self.unpause(engine, &text);
+        Ok(())
+    }
+
+    /// `/restart_update` - admins can restart the server with a new binary.
+    fn request_restart(&mut self, player_handle: Handle<Player>) -> Result<(), String> {
+        let client_handle = self.client_handle(player_handle).ok_or("not connected")?;
+        if !self.clients[client_handle].admin {
+            return Err("only admins can restart the server".to_owned());
+        }
+        dbg_logf!("player {} requested a restart", player_handle.index());
+        self.restart_requested = true;
+        Ok(())
+    }
+
+    /// Save the state for the new server and tell players, see `server::restart`.
+    pub(crate) fn prepare_restart(
+        &mut self,
+        cvars: &Cvars,
+        engine: &mut Engine,
+    ) -> Result<(), String> {
+        let path = &cvars.sv_restart_state_file;
+        if path.is_empty() {
+            return Err("sv_restart_state_file is empty".to_owned());
+        }
+        self.savestate(cvars, engine, path)?;
+        let text = "the server is restarting for an update, you'll be reconnected";
+        self.say(engine, None, text, false);
+        // The process is replaced without running destructors.
+        if let Some(demo) = self.demo.take() {
+            if let Err(err) = demo.finish() {
+                dbg_logf!("failed to finish demo {}: {}", self.demo_path, err);
+            }
+        }
+        Ok(())
+    }
+
+    fn client_handle(&self, player_handle: Handle<Player>) -> Option<Handle<RemoteClient>> {
+        self.clients
+            .pair_iter()
+            .find(|(_, client)| client.player_handle == player_handle)
+            .map(|(client_handle, _)| client_handle)
+    }
+
+    /// Run a cheat command if `sv_cheats` allows it. Either way, it's logged.
+    fn cheat(
+        &mut self,
+        cvars: &Cvars,
+        engine: &mut Engine,
+        player_handle: Handle<Player>,
+        cheat: Cheat,
+    ) {
+        let player_index = player_handle.index();
+        if !cvars.sv_cheats {
+            // LATER Tell the player why.
+            dbg_logf!("player {} tried to cheat without sv_cheats: {:?}", player_index, cheat);
+            return;
+        }
+        dbg_logf!("player {} cheated: {:?}", player_index, cheat);
+
+        let mut cheats = self.gs.players[player_handle].cheats;
+        match cheat {
+            Cheat::Noclip => cheats.noclip = !cheats.noclip,
+            Cheat::God => cheats.god = !cheats.god,
+            Cheat::Give(weapon) => {
+                // LATER Weapons.
+                dbg_logf!("player {} can't get {}: there are no weapons yet", player_index, weapon);
+                return;
+            }
+            Cheat::Teleport(pos) => {
+                if pos.iter().all(|coord| coord.is_finite()) {
+                    let scene = &mut engine.scenes[self.gs.scene_handle];
+                    self.gs.teleport(scene, player_handle, Vec3::from(pos));
+                }
+                return;
+            }
+        }
+        self.set_cheats(engine, player_handle, cheats);
+    }
+
+    /// Tell clients when `sv_cheats` changes, turning it off turns off everybody's cheats.
+    fn sys_cheats_off(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        if cvars.sv_cheats != self.cheats_allowed_sent {
+            self.cheats_allowed_sent = cvars.sv_cheats;
+            let msg = ServerMessage::CheatsAllowed(cvars.sv_cheats);
+            self.network_send(engine, msg, SendDest::All);
+        }
+        if cvars.sv_cheats {
+            return;
+        }
+        let cheaters: Vec<_> = self
+            .gs
+            .players
+            .pair_iter()
+            .filter(|(_, player)| player.cheats != Cheats::default())
+            .map(|(player_handle, _)| player_handle)
+            .collect();
+        for player_handle in cheaters {
+            dbg_logf!("sv_cheats is off, turning off cheats of player {}", player_handle.index());
+            self.set_cheats(engine, player_handle, Cheats::default());
+        }
+    }
+
+    fn set_cheats(&mut self, engine: &mut Engine, player_handle: Handle<Player>, cheats: Cheats) {
+        let scene = &mut engine.scenes[self.gs.scene_handle];
+        self.gs.set_cheats(scene, player_handle, cheats);
+
+        let msg = ServerMessage::PlayerCheats(PlayerCheats {
+            player_handle: player_handle.net(),
+            cheats,
+        });
+        self.network_send(engine, msg, SendDest::All);
+    }
+
+    /// Credit assists and tell clients who killed whom.
+    fn sys_kills(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        self.assists.record(self.gs.game_time, &self.gs.hits);
+        let kills = self.gs.kills.clone();
+        let mut victims = Vec::new();
+        for kill in kills {
+            if !victims.contains(&kill.victim) {
+                victims.push(kill.victim);
+            }
+            let assisters = self.assists.credit(cvars, self.gs.game_time, kill);
+            // Same as score.
+            if !matches!(self.gs.phase, MatchPhase::Warmup { .. }) {
+                for &assister in &assisters {
+                    self.gs.players[assister].assists += 1;
+                }
+            }
+            let assist_indices: Vec<_> = assisters.iter().map(|handle| handle.index()).collect();
+            self.log_event(Event::Kill {
+                killer_index: kill.killer.index(),
+                victim_index: kill.victim.index(),
+                assist_indices: &assist_indices,
+            });
+            self.gs.events.push(GameEvent::Kill { kill, assisters });
+        }
+
+        // Dead cycles are removed, the players get a new one in `sys_respawn`.
+        let scene = &mut engine.scenes[self.gs.scene_handle];
+        let mode = self.hooks.respawn_mode(cvars);
+        for victim in victims {
+            let player = &self.gs.players[victim];
+            if player.ps != PlayerState::Playing {
+                continue;
+            }
+            let Some(cycle_handle) = player.cycle_handle else {
+                continue;
+            };
+            self.gs.despawn_cycle(scene, cycle_handle);
+            let time_left = self.respawns.schedule(cvars, mode, self.gs.game_time, victim);
+            if time_left > 0.0 {
+                self.gs.events.push(GameEvent::Respawn {
+                    player_handle: victim,
+                    time_left,
+                });
+            }
+        }
+    }
+
+    /// Give players whose respawn time has come a new cycle at a spawn point.
+    fn sys_respawn(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        // The timers are moved when the match continues.
+        if self.pause.is_some() {
+            return;
+        }
+        let scene = &mut engine.scenes[self.gs.scene_handle];
+        for player_handle in self.respawns.due(self.gs.game_time) {
+            let player = &self.gs.players[player_handle];
+            if player.ps != PlayerState::Playing || player.cycle_handle.is_some() {
+                continue;
+            }
+            // The player can switch vehicles by getting a new cycle.
+            let vehicle = spawn_vehicle(cvars, player);
+            self.gs.spawn_cycle(cvars, scene, player_handle, vehicle, None);
+        }
+    }
+
+    /// Send clients the changes gamelogic recorded as `GameEvent`s.
+    fn sys_replicate(&mut self, engine: &mut Engine) {
+        for event in mem::take(&mut self.gs.events) {
+            let msg = match event {
+                GameEvent::Join { player_handle } => ServerMessage::Join {
+                    player_handle: player_handle.net(),
+                },
+                GameEvent::Observe { player_handle } => ServerMessage::Observe {
+                    player_handle: player_handle.net(),
+                },
+                GameEvent::SpawnCycle {
+                    player_handle,
+                    cycle_handle,
+                    vehicle,
+                } => ServerMessage::SpawnCycle(PlayerCycle {
+                    player_handle: player_handle.net(),
+                    cycle_handle: cycle_handle.net(),
+                    vehicle,
+                }),
+                GameEvent::DespawnCycle { cycle_handle } => ServerMessage::DespawnCycle {
+                    cycle_handle: cycle_handle.net(),
+                },
+                GameEvent::Kill { kill, assisters } => ServerMessage::Kill(PlayerKill {
+                    killer_index: kill.killer.index(),
+                    victim_index: kill.victim.index(),
+                    assist_indices: assisters.iter().map(|handle| handle.index()).collect(),
+                }),
+                GameEvent::Pickup {
+                    player_handle,
+                    pickup_index,
+                } => ServerMessage::Pickup {
+                    player_handle: player_handle.net(),
+                    pickup_index: pickup_index as u32,
+                },
+                GameEvent::Respawn {
+                    player_handle,
+                    time_left,
+                } => ServerMessage::Respawn {
+                    player_index: player_handle.index(),
+                    time_left,
+                },
+                GameEvent::OutOfBounds {
+                    player_handle,
+                    time_left,
+                } => ServerMessage::OutOfBounds {
+                    player_index: player_handle.index(),
+                    time_left,
+                },
+            };
+            self.network_send(engine, msg, SendDest::All);
+        }
+    }
+
+    fn sys_send_summaries(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        let summaries = mem::take(&mut *self.summaries.borrow_mut());
+        for summary in summaries {
+            self.uploads.queue(cvars, &summary);
+            self.network_send(engine, ServerMessage::MatchSummary(summary), SendDest::All);
+        }
+    }
+
+    /// Tell clients when hooks changed the match phase.
+    fn sys_send_phase(&mut self, engine: &mut Engine) {
+        if self.gs.phase != self.phase_sent {
+            self.phase_sent = self.gs.phase;
+            let msg = ServerMessage::MatchPhase(self.gs.phase);
+            self.network_send(engine, msg, SendDest::All);
+        }
+    }
+
+    /// Start, stop or switch recording when `sv_demo_record` changes.
+    fn demo_begin_tick(&mut self, cvars: &Cvars) {
+        if cvars.sv_demo_record == self.demo_path {
+            return;
+        }
+
+        if let Some(demo) = self.demo.take() {
+            match demo.finish() {
+                Ok(_) => dbg_logf!("finished recording demo {}", self.demo_path),
+                Err(err) => dbg_logf!("failed to finish demo {}: {}", self.demo_path, err),
+            }
+        }
+
+        self.demo_path = cvars.sv_demo_record.clone();
+        self.demo_round = 0;
+        if self.demo_path.is_empty() {
+            return;
+        }
+        match DemoWriter::create(&self.demo_path) {
+            Ok(demo) => {
+                dbg_logf!("recording demo {}", self.demo_path);
+                self.demo = Some(demo);
+            }
+            Err(err) => dbg_logf!("failed to record demo {}: {}", self.demo_path, err),
+        }
+    }
+
+    /// Write everything sent to all clients this tick and mark kills.
+    fn demo_end_tick(&mut self) {
+        if let Some(demo) = &mut self.demo {
+            for &kill in &self.gs.kills {
+                demo.marker(kill.into());
+            }
+            if let Err(err) = demo.end_tick(self.gs.frame_number, self.gs.game_time) {
+                dbg_logf!("failed to write demo {}, stopping: {}", self.demo_path, err);
+                self.demo = None;
+            }
+        }
+    }
+
+    /// Upload rounds which ended this tick, now that the demo contains the whole round.
+    fn sys_upload(&mut self, cvars: &Cvars) {
+        self.uploads.poll();
+        let pending = self.uploads.take_pending();
+        if pending.is_empty() {
+            return;
+        }
+        let mut demo = self.demo_restart();
+        for stats in pending {
+            self.uploads.start(cvars, stats, demo.take());
+        }
     }
 
-    pub(crate) fn accept_new_connections(&mut self, engine: &mut Engine) {
+    /// Finish the demo and record the next round to a new file, returns the finished file.
+    fn demo_restart(&mut self) -> Option<String> {
+        let demo = self.demo.take()?;
+        let finished_path = demo::round_path(&self.demo_path, self.demo_round);
+        let finished = match demo.finish() {
+            Ok(_) => Some(finished_path),
+            Err(err) => {
+                dbg_logf!("failed to finish demo {}: {}", finished_path, err);
+                None
+            }
+        };
+        self.demo_round += 1;
+        let path = demo::round_path(&self.demo_path, self.demo_round);
+        match DemoWriter::create(&path) {
+            Ok(demo) => {
+                dbg_logf!("recording demo {}", path);
+                self.demo = Some(demo);
+            }
+            Err(err) => dbg_logf!("failed to record demo {}: {}", path, err),
+        }
+        finished
+    }
+
+    pub(crate) fn accept_new_connections(&mut self, cvars: &Cvars, engine: &mut Engine) {
         loop {
             match self.listener.accept_conn() {
                 Ok(conn) => {
+                    // Everyone starts observing, `sv_max_players` is checked when joining.
+                    let observers = self
+                        .gs
+                        .players
+                        .iter()
+                        .filter(|player| player.ps != PlayerState::Playing)
+                        .count();
+                    if cvars.sv_max_observers > 0 && observers >= cvars.sv_max_observers {
+                        // LATER Tell the client why.
+                        dbg_logf!("reject {}: too many observers", conn.addr());
+                        continue;
+                    }
                     dbg_logf!("accept {}", conn.addr());
 
-                    // Add player
                     // This is sent to all clients except the new one.
-                    let player = Player::new(None);
-                    let player_handle = self.gs.players.spawn(player);
-                    let add_player = AddPlayer {
-                        name: "Player".to_owned(), // LATER from client
-                        player_index: player_handle.index(),
-                    };
-                    let msg = ServerMessage::AddPlayer(add_player);
-                    self.network_send(engine, msg, SendDest::All);
+                    let player_handle = self.add_player(cvars, engine);
 
                     // Create client
                     // This is after adding the player so that we can send the new client
-                    // its own player index.
-                    let client = RemoteClient::new(conn, player_handle);
+                    // its own player handle.
+                    let addr = conn.addr();
+                    let reconnect_token = self.reconnects.token();
+                    // `Init` already contains the result of pending events,
+                    // the new client must not get them again.
+                    self.sys_replicate(engine);
+                    let client = RemoteClient::new(conn, player_handle, reconnect_token);
                     let client_handle = self.clients.spawn(client);
-                    self.send_init(engine, client_handle);
+                    self.send_init(cvars, engine, client_handle);
+                    if let Some(text) = motd::load(cvars) {
+                        let msg = ServerMessage::Motd(text);
+                        self.network_send(engine, msg, SendDest::One(client_handle));
+                    }
 
-                    // Spawn cycle
-                    let scene = &mut engine.scenes[self.gs.scene_handle];
-                    let cycle_handle = self.gs.spawn_cycle(scene, player_handle, None);
+                    // The cycle is spawned when the player joins.
 
-                    // Tell all players
-                    let player_cycle = PlayerCycle {
-                        player_index: player_handle.index(),
-                        cycle_index: cycle_handle.index(),
-                    };
-                    let msg = ServerMessage::SpawnCycle(player_cycle);
-                    self.network_send(engine, msg, SendDest::All);
+                    let player_index = player_handle.index();
+                    self.log_event(Event::Join {
+                        player_index,
+                        addr: &addr,
+                    });
                 }
                 Err(err) => match err.kind() {
                     ErrorKind::WouldBlock => {
@@ -122,36 +1188,175 @@ impl ServerGame {
         }
     }
 
-    fn sys_receive(&mut self, engine: &mut Engine) {
+    /// Add an observing player and tell clients, both for people and bots.
+    ///
+    /// The team is assigned here so a new client gets it in `Init`.
+    fn add_player(&mut self, cvars: &Cvars, engine: &mut Engine) -> Handle<Player> {
+        let player = Player::new(None);
+        let player_handle = self.gs.players.spawn(player);
+        let add_player = AddPlayer {
+            name: "Player".to_owned(), // LATER from client
+            player_handle: player_handle.net(),
+        };
+        let msg = ServerMessage::AddPlayer(add_player);
+        self.network_send(engine, msg, SendDest::All);
+
+        if let Some(team) = teams::smallest(&teams::team_sizes(cvars, &self.gs)) {
+            self.set_team(engine, player_handle, team);
+        }
+        self.hook_events.push(HookEvent::PlayerJoin(player_handle));
+        player_handle
+    }
+
+    fn sys_receive(&mut self, cvars: &Cvars, engine: &mut Engine) {
         let mut disconnected = Vec::new();
         let mut msgs_to_all = Vec::new();
+        let mut msgs_to_one = Vec::new();
+        let mut team_switches = Vec::new();
+        let mut cheats = Vec::new();
+        let mut chats = Vec::new();
+        let mut reconnect_tokens = Vec::new();
+        let mut joins = Vec::new();
+        let mut observes = Vec::new();
+        let mut fingerprints = Vec::new();
+        let mut admin_logins = Vec::new();
         for (client_handle, client) in self.clients.pair_iter_mut() {
             let (msgs, closed) = client.conn.receive_cm();
+            self.metrics.received(msgs.len());
             // We might have received valid messages before the stream was closed - handle them
             // even though for some, such as player input, it doesn't affect anything.
             for msg in msgs {
                 match msg {
                     ClientMessage::Input(input) => {
                         // LATER (server reconciliation) handle more inputs arriving in one frame
-                        self.gs.players[client.player_handle].input = input;
+                        let player = &mut self.gs.players[client.player_handle];
+                        if !input.same_controls(&player.input) {
+                            client.last_active = self.gs.game_time;
+                        }
+                        player.input = input;
                     }
-                    ClientMessage::Chat(chat) => {
-                        // LATER Show chat in-game
-                        dbg_logd!(chat);
+                    ClientMessage::Chat(line) => {
+                        // Dropped silently like emotes.
+                        if self.gs.game_time >= client.chat_next {
+                            client.chat_next = self.gs.game_time + cvars.sv_chat_interval;
+                            let line = motd::truncate(&line, cvars.sv_chat_max_length);
+                            chats.push((client.player_handle, line.to_owned()));
+                        }
+                    }
+                    ClientMessage::Emote(emote) => {
+                        // Dropped silently, the client doesn't need to know.
+                        if self.gs.game_time >= client.emote_next {
+                            client.emote_next = self.gs.game_time + cvars.sv_emote_interval;
+                            let player_index = client.player_handle.index();
+                            dbg_logf!("player {} emote: {}", player_index, emote);
+                            let msg = ServerMessage::Emote {
+                                player_handle: client.player_handle.net(),
+                                emote,
+                            };
+                            msgs_to_all.push(msg);
+                        }
                     }
                     ClientMessage::Join => {
-                        self.gs.players[client.player_handle].ps = PlayerState::Playing;
-                        let player_index = client.player_handle.index();
-                        dbg_logf!("player {} is now playing", player_index);
-                        let msg = ServerMessage::Join { player_index };
+                        client.last_active = self.gs.game_time;
+                        joins.push(client.player_handle);
+                    }
+                    ClientMessage::Look(look) => {
+                        let look = look.validated();
+                        self.gs.players[client.player_handle].look = look;
+                        let player_handle = client.player_handle.net();
+                        let msg = ServerMessage::PlayerLook(PlayerLook {
+                            player_handle,
+                            look,
+                        });
                         msgs_to_all.push(msg);
                     }
+                    ClientMessage::Fingerprint(fingerprint) => {
+                        self.gs.players[client.player_handle].fingerprint = Some(fingerprint);
+                        fingerprints.push(PlayerFingerprint {
+                            player_handle: client.player_handle.net(),
+                            fingerprint,
+                        });
+                    }
+                    ClientMessage::Ready(ready) => {
+                        let player = &mut self.gs.players[client.player_handle];
+                        if player.ready != ready {
+                            player.ready = ready;
+                            let player_index = client.player_handle.index();
+                            dbg_logf!("player {} ready: {}", player_index, ready);
+                            let msg = ServerMessage::Ready {
+                                player_handle: client.player_handle.net(),
+                                ready,
+                            };
+                            msgs_to_all.push(msg);
+                        }
+                    }
+                    ClientMessage::Team(team) => {
+                        team_switches.push((client.player_handle, team));
+                    }
+                    ClientMessage::Cheat(cheat) => {
+                        cheats.push((client.player_handle, cheat));
+                    }
+                    ClientMessage::Pong(id) => match client.ping_sent {
+                        Some((sent_id, sent_time)) if sent_id == id => {
+                            let rtt = sent_time.elapsed().as_secs_f32() * 1000.0;
+                            client.ping.add(rtt);
+                            client.ping_sent = None;
+                        }
+                        _ => dbg_logf!("player {} late pong", client.player_handle.index()),
+                    },
                     ClientMessage::Observe => {
-                        self.gs.players[client.player_handle].ps = PlayerState::Observing;
+                        dbg_logf!("player {} is now observing", client.player_handle.index());
+                        observes.push(client.player_handle);
+                    }
+                    ClientMessage::AdminLogin {
+                        password,
+                        remote_log,
+                    } => {
                         let player_index = client.player_handle.index();
-                        dbg_logf!("player {} is now observing", player_index);
-                        let msg = ServerMessage::Observe { player_index };
-                        msgs_to_all.push(msg);
+                        // Empty password means admin login is disabled.
+                        if !cvars.sv_admin_password.is_empty()
+                            && passwords_match(&password, &cvars.sv_admin_password)
+                        {
+                            client.admin = true;
+                            client.remote_log = remote_log;
+                            admin_logins.push(client_handle);
+                            dbg_logf!(
+                                "player {} logged in as admin (remote log: {})",
+                                player_index,
+                                remote_log
+                            );
+                        } else {
+                            dbg_logf!("player {} failed to log in as admin", player_index);
+                        }
+                    }
+                    ClientMessage::DesyncCheck { frame_number, hash } => {
+                        client.desync_check = true;
+                        // The client does the logging, we just reply with our state at that tick.
+                        // Checks which took too long are ignored, the client gives up on them.
+                        let Some(state) = self.desync_history.get(frame_number) else {
+                            continue;
+                        };
+                        let server_hash = state.hash();
+                        let msg = ServerMessage::DesyncCheck {
+                            frame_number,
+                            hash: server_hash,
+                            state: (server_hash != hash).then(|| state.clone()),
+                        };
+                        msgs_to_one.push((client_handle, msg));
+                    }
+                    ClientMessage::UpdateRate(rate) => {
+                        client.update_rate = rate;
+                        client.updates_since_sent = 0;
+                    }
+                    ClientMessage::Reconnect(token) => {
+                        reconnect_tokens.push((client.player_handle, token));
+                    }
+                    ClientMessage::RemoteDebug(remote_debug) => {
+                        client.remote_debug = remote_debug;
+                    }
+                    ClientMessage::Vehicle(vehicle) => {
+                        // Used at the next spawn, see `spawn_vehicle`.
+                        self.gs.players[client.player_handle].vehicle = vehicle;
                     }
                 }
             }
@@ -160,66 +1365,412 @@ impl ServerGame {
             }
         }
         for client_handle in disconnected {
+            let player_index = self.clients[client_handle].player_handle.index();
+            let paused = self.pause_for_reconnect(cvars, client_handle);
             self.disconnect(engine, client_handle);
+            if paused {
+                let text =
+                    format!("match paused, waiting for player {} to reconnect", player_index);
+                self.say(engine, None, &text, false);
+            }
         }
         for msg in msgs_to_all {
             self.network_send(engine, msg, SendDest::All);
         }
+        // Anyone who knows a fingerprint can pretend to be that player (to get around mutes)
+        // so only admins get them, see `send_init`.
+        for client_handle in admin_logins {
+            for fingerprint in self.player_fingerprints() {
+                if self.clients.is_valid_handle(client_handle) {
+                    let msg = ServerMessage::PlayerFingerprint(fingerprint);
+                    self.network_send(engine, msg, SendDest::One(client_handle));
+                }
+            }
+        }
+        if !fingerprints.is_empty() {
+            let admins: Vec<_> = self
+                .clients
+                .pair_iter()
+                .filter(|(_, client)| client.admin)
+                .map(|(client_handle, _)| client_handle)
+                .collect();
+            for fingerprint in fingerprints {
+                let msg = ServerMessage::PlayerFingerprint(fingerprint);
+                for &client_handle in &admins {
+                    if self.clients.is_valid_handle(client_handle) {
+                        self.network_send_ref(engine, &msg, SendDest::One(client_handle));
+                    }
+                }
+            }
+        }
+        for player_handle in joins {
+            if self.gs.players.is_valid_handle(player_handle) {
+                self.join(cvars, engine, player_handle);
+            }
+        }
+        for player_handle in observes {
+            if self.gs.players.is_valid_handle(player_handle) {
+                self.observe(engine, player_handle);
+            }
+        }
+        for (player_handle, team) in team_switches {
+            if !self.gs.players.is_valid_handle(player_handle) {
+                continue;
+            }
+            // Checked one at a time, earlier switches change team sizes.
+            match teams::check_switch(cvars, &self.gs, player_handle, team) {
+                Ok(()) => self.set_team(engine, player_handle, team),
+                // LATER Tell the player why.
+                Err(reason) => {
+                    dbg_logf!("player {} can't switch teams: {}", player_handle.index(), reason)
+                }
+            }
+        }
+        for (player_handle, cheat) in cheats {
+            if self.gs.players.is_valid_handle(player_handle) {
+                self.cheat(cvars, engine, player_handle, cheat);
+            }
+        }
+        self.reconnects.expire(self.gs.game_time, cvars.sv_reconnect_time);
+        // The new server's game time started at 0.
+        if self.restart_state.is_some() && self.gs.game_time > cvars.sv_reconnect_time {
+            self.restart_state = None;
+        }
+        for (player_handle, token) in reconnect_tokens {
+            if self.gs.players.is_valid_handle(player_handle) {
+                self.reconnect(cvars, engine, player_handle, token);
+            }
+        }
+        for (player_handle, line) in chats {
+            // The player might have been kicked by an earlier line.
+            if self.gs.players.is_valid_handle(player_handle) {
+                self.chat(cvars, engine, player_handle, &line);
+            }
+        }
+        for (client_handle, msg) in msgs_to_one {
+            if self.clients.is_valid_handle(client_handle) {
+                self.network_send(engine, msg, SendDest::One(client_handle));
+            }
+        }
+    }
+
+    /// Give a new player the state they had before losing connection.
+    fn reconnect(
+        &mut self,
+        cvars: &Cvars,
+        engine: &mut Engine,
+        player_handle: Handle<Player>,
+        token: u64,
+    ) {
+        let player_index = player_handle.index();
+        let restored = self.restart_state.as_mut().and_then(|state| {
+            let i = state.players.iter().position(|p| p.reconnect_token == Some(token))?;
+            // Same delay as in `restore_match`, the new server's game time started at 0.
+            Some((state.players.remove(i), -state.game_time))
+        });
+        if let Some((saved, delay)) = restored {
+            dbg_logf!("player {} is back after the restart", player_index);
+            if let Some(client_handle) = self.client_handle(player_handle) {
+                self.clients[client_handle].last_active = self.gs.game_time;
+            }
+            self.restore_player(cvars, engine, player_handle, &saved, delay);
+            return;
+        }
+        let Some(saved) = self.reconnects.take(token) else {
+            dbg_logf!("player {} sent an unknown or expired reconnect token", player_index);
+            return;
+        };
+        dbg_logf!("player {} reconnected with score {}", player_index, saved.score);
+        if self.pause.as_mut().map_or(false, |pause| pause.reconnected(token)) {
+            self.unpause(engine, "everyone is back");
+        }
+
+        let player = &mut self.gs.players[player_handle];
+        player.score = saved.score;
+        player.assists = saved.assists;
+        // Scores are sent to everyone with pings.
+        if let Some(team) = saved.team {
+            self.set_team(engine, player_handle, team);
+        }
+        if saved.playing {
+            if let Some(client_handle) = self.client_handle(player_handle) {
+                self.clients[client_handle].last_active = self.gs.game_time;
+            }
+            self.join(cvars, engine, player_handle);
+        }
+    }
+
+    /// Pause a live competitive match when a playing player loses connection,
+    /// see `g_competitive`. Kicked players don't pause it.
+    ///
+    /// Returns whether the match is now waiting for the player.
+    fn pause_for_reconnect(&mut self, cvars: &Cvars, client_handle: Handle<RemoteClient>) -> bool {
+        let client = &self.clients[client_handle];
+        let token = client.reconnect_token;
+        let playing = self.gs.players[client.player_handle].ps == PlayerState::Playing;
+        if !cvars.g_competitive || !playing {
+            return false;
+        }
+        if let Some(pause) = &mut self.pause {
+            pause.awaiting.push(token);
+        } else if let MatchPhase::Live { .. } | MatchPhase::Overtime { .. } = self.gs.phase {
+            self.pause = Some(MatchPause::new(self.gs.phase, self.gs.game_time, token));
+            let deadline = self.gs.game_time + cvars.g_competitive_pause_time;
+            self.gs.phase = MatchPhase::Paused { deadline };
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Continue a paused match once it's waited long enough
+    /// or nobody it's waiting for can reconnect anymore.
+    fn sys_pause(&mut self, engine: &mut Engine) {
+        let Some(pause) = &mut self.pause else {
+            return;
+        };
+        let MatchPhase::Paused { deadline } = self.gs.phase else {
+            // A hook changed the phase, e.g. ended the round.
+            self.pause = None;
+            return;
+        };
+        pause.awaiting.retain(|&token| self.reconnects.is_saved(token));
+        if pause.awaiting.is_empty() {
+            self.unpause(engine, "nobody else can reconnect");
+        } else if self.gs.game_time >= deadline {
+            self.unpause(engine, "the pause is over");
+        }
+    }
+
+    fn unpause(&mut self, engine: &mut Engine, reason: &str) {
+        if let Some(pause) = self.pause.take() {
+            self.gs.phase = pause.resume(self.gs.game_time);
+            let duration = pause.duration(self.gs.game_time);
+            self.pickups.delay(duration);
+            // Waiting players' HUDs need the new countdown.
+            for (player_handle, time_left) in self.respawns.delay(duration, self.gs.game_time) {
+                self.gs.events.push(GameEvent::Respawn {
+                    player_handle,
+                    time_left,
+                });
+            }
+            let text = format!("match continues: {}", reason);
+            self.say(engine, None, &text, false);
+        }
+    }
+
+    /// Spawn the player's cycle and start playing unless `sv_max_players` are already playing.
+    fn join(&mut self, cvars: &Cvars, engine: &mut Engine, player_handle: Handle<Player>) {
+        if self.gs.players[player_handle].ps == PlayerState::Playing {
+            return;
+        }
+        let player_index = player_handle.index();
+        let playing = self
+            .gs
+            .players
+            .iter()
+            .filter(|player| player.ps == PlayerState::Playing)
+            .count();
+        let client_handle = self.client_handle(player_handle);
+        let admin = client_handle.map_or(false, |client_handle| self.clients[client_handle].admin);
+        if cvars.sv_max_players > 0 && playing >= player_slots(cvars, admin) {
+            dbg_logf!("player {} can't join: server is full", player_index);
+            let text = "the server is full, you can keep watching".to_owned();
+            self.reply(engine, player_handle, text);
+            return;
+        }
+
+        if self.gs.players[player_handle].cycle_handle.is_none() {
+            let scene = &mut engine.scenes[self.gs.scene_handle];
+            let vehicle = spawn_vehicle(cvars, &self.gs.players[player_handle]);
+            self.gs.spawn_cycle(cvars, scene, player_handle, vehicle, None);
+        }
+
+        self.gs.players[player_handle].ps = PlayerState::Playing;
+        dbg_logf!("player {} is now playing", player_index);
+        // After `SpawnCycle` so clients never see a playing player without a cycle.
+        self.gs.events.push(GameEvent::Join { player_handle });
+    }
+
+    /// Observers don't have a cycle so they don't take up space on the map.
+    fn observe(&mut self, engine: &mut Engine, player_handle: Handle<Player>) {
+        self.gs.players[player_handle].ps = PlayerState::Observing;
+        self.respawns.remove_player(player_handle);
+        if let Some(cycle_handle) = self.gs.players[player_handle].cycle_handle {
+            let scene = &mut engine.scenes[self.gs.scene_handle];
+            self.gs.despawn_cycle(scene, cycle_handle);
+        }
+        self.gs.events.push(GameEvent::Observe { player_handle });
     }
 
     fn disconnect(&mut self, engine: &mut Engine, client_handle: Handle<RemoteClient>) {
-        let scene = &mut engine.scenes[self.gs.scene_handle];
         let client = self.clients.free(client_handle);
-        self.gs.free_player(scene, client.player_handle);
+        let player = &self.gs.players[client.player_handle];
+        self.reconnects.save(client.reconnect_token, self.gs.game_time, player);
+        self.remove_player(engine, client.player_handle);
+    }
+
+    /// Free the player and tell clients, both for people and bots.
+    fn remove_player(&mut self, engine: &mut Engine, player_handle: Handle<Player>) {
+        // Events about the player, e.g. a bot's `Join` from this tick, have to arrive first.
+        self.sys_replicate(engine);
+        let scene = &mut engine.scenes[self.gs.scene_handle];
+        self.gs.free_player(scene, player_handle);
+        self.vote_kicks.remove_player(player_handle);
+        self.assists.remove_player(player_handle);
+        self.respawns.remove_player(player_handle);
+        self.hook_events.push(HookEvent::PlayerLeave(player_handle));
+        let player_index = player_handle.index();
+        self.log_event(Event::Leave { player_index });
         let msg = ServerMessage::RemovePlayer {
-            player_index: client.player_handle.index(),
+            player_handle: player_handle.net(),
         };
         self.network_send(engine, msg, SendDest::All);
     }
 
-    fn send_init(&mut self, engine: &mut Engine, client_handle: Handle<RemoteClient>) {
-        let mut player_indices = Vec::new();
+    fn player_fingerprints(&self) -> Vec<PlayerFingerprint> {
+        self.gs
+            .players
+            .pair_iter()
+            .filter_map(|(player_handle, player)| {
+                player.fingerprint.map(|fingerprint| PlayerFingerprint {
+                    player_handle: player_handle.net(),
+                    fingerprint,
+                })
+            })
+            .collect()
+    }
+
+    fn send_init(
+        &mut self,
+        cvars: &Cvars,
+        engine: &mut Engine,
+        client_handle: Handle<RemoteClient>,
+    ) {
+        let mut player_handles = Vec::new();
         for (player_handle, _) in self.gs.players.pair_iter() {
-            player_indices.push(player_handle.index());
+            player_handles.push(player_handle.net());
         }
-        let local_player_index = self.clients[client_handle].player_handle.index();
+        let local_player_handle = self.clients[client_handle].player_handle.net();
 
         let mut player_cycles = Vec::new();
         for (cycle_handle, cycle) in self.gs.cycles.pair_iter() {
             let init_player = PlayerCycle {
-                player_index: cycle.player_handle.index(),
-                cycle_index: cycle_handle.index(),
+                player_handle: cycle.player_handle.net(),
+                cycle_handle: cycle_handle.net(),
+                vehicle: cycle.vehicle,
             };
             player_cycles.push(init_player);
         }
 
+        let mut player_looks = Vec::new();
+        for (player_handle, player) in self.gs.players.pair_iter() {
+            player_looks.push(PlayerLook {
+                player_handle: player_handle.net(),
+                look: player.look,
+            });
+        }
+
+        // Only admins, see `sys_receive`.
+        let player_fingerprints = if self.clients[client_handle].admin {
+            self.player_fingerprints()
+        } else {
+            Vec::new()
+        };
+
+        let ready_handles = self
+            .gs
+            .players
+            .pair_iter()
+            .filter(|(_, player)| player.ready)
+            .map(|(player_handle, _)| player_handle.net())
+            .collect();
+
+        let player_teams = self
+            .gs
+            .players
+            .pair_iter()
+            .filter_map(|(player_handle, player)| {
+                player.team.map(|team| PlayerTeam {
+                    player_handle: player_handle.net(),
+                    team: Some(team),
+                })
+            })
+            .collect();
+
+        let player_cheats = self
+            .gs
+            .players
+            .pair_iter()
+            .filter(|(_, player)| player.cheats != Cheats::default())
+            .map(|(player_handle, player)| PlayerCheats {
+                player_handle: player_handle.net(),
+                cheats: player.cheats,
+            })
+            .collect();
+
         let init = Init {
-            player_indices,
-            local_player_index,
+            player_handles,
+            local_player_handle,
             player_cycles,
             player_projectiles: Vec::new(), // LATER
+            player_looks,
+            player_fingerprints,
+            ready_handles,
+            player_teams,
+            player_cheats,
+            cheats_allowed: self.cheats_allowed_sent,
+            phase: self.gs.phase,
+            server_name: cvars.sv_name.clone(),
+            region: cvars.sv_region.clone(),
+            map: cvars.g_map.clone(),
+            theme: ServerTheme::load(cvars),
+            reconnect_token: self.clients[client_handle].reconnect_token,
+            // The next update's `ticks` also count the ticks since the last one.
+            frame_number: self.update_frame,
         };
         let msg = ServerMessage::Init(init);
         self.network_send(engine, msg, SendDest::One(client_handle));
     }
 
-    fn sys_send_update(&mut self, engine: &mut Engine) {
+    fn sys_send_update(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        // Effects only exist for one tick so they have to be collected until the next update.
+        // Debug items stay in their buffers until sent.
+        self.update_buf.ticks += 1;
+        self.update_buf.effects.extend_from_slice(&self.gs.effects);
+        if self.update_buf.ticks < snapshot_interval(cvars.sv_snapshot_rate) {
+            return;
+        }
+
         let scene = &engine.scenes[self.gs.scene_handle];
 
-        let mut player_inputs = Vec::new();
+        self.update_frame = self.gs.frame_number as u32;
+        if self.clients.iter().any(|client| client.desync_check) {
+            let state = StateSummary::new(cvars, &self.gs, scene);
+            self.desync_history.record(self.update_frame, state);
+        }
+
+        // Reuse last frame's buffers so this doesn't allocate once they're large enough.
+        let mut update = mem::take(&mut self.update_buf);
+
+        let player_inputs = &mut update.player_inputs;
+        player_inputs.clear();
+        player_inputs.reserve(self.gs.players.alive_count() as usize);
         for (player_handle, player) in self.gs.players.pair_iter() {
             let pi = PlayerInput {
-                player_index: player_handle.index(),
+                player_handle: player_handle.net(),
                 input: player.input,
             };
             player_inputs.push(pi);
         }
 
-        let mut cycle_physics = Vec::new();
+        let cycle_physics = &mut update.cycle_physics;
+        cycle_physics.clear();
+        cycle_physics.reserve(self.gs.cycles.alive_count() as usize);
         for (cycle_handle, cycle) in self.gs.cycles.pair_iter() {
             let body = scene.graph[cycle.body_handle].as_rigid_body();
             let cp = CyclePhysics {
-                cycle_index: cycle_handle.index(),
+                cycle_handle: cycle_handle.net(),
                 translation: **body.local_transform().position(),
                 rotation: **body.local_transform().rotation(),
                 velocity: body.lin_vel(),
@@ -227,48 +1778,243 @@ impl ServerGame {
             cycle_physics.push(cp);
         }
 
-        // Send debug items, then clear everything on the server
-        // so it doesn't get sent again next frame.
+        update.platform_poses.clear();
+        for &body_handle in &self.gs.platform_handles {
+            let transform = scene.graph[body_handle].local_transform();
+            update.platform_poses.push(PlatformPose {
+                translation: **transform.position(),
+                rotation: **transform.rotation(),
+            });
+        }
+
+        update.pickups.clear();
+        update.pickups.extend(self.pickups.available());
+
+        update.player_powerups.clear();
+        for (player_handle, player) in self.gs.players.pair_iter() {
+            update.player_powerups.push(PlayerPowerups {
+                player_handle: player_handle.net(),
+                armor: player.armor,
+                cloaked: player.cloaked,
+            });
+        }
+
+        // Clients with a lower `cl_updaterate` skip some updates
+        // and get what they missed right before the next one.
+        let mut skipping = Vec::new();
+        let mut catch_ups = Vec::new();
+        for (client_handle, client) in self.clients.pair_iter_mut() {
+            client.updates_since_sent += 1;
+            if client.updates_since_sent < updates_per_send(cvars, client.update_rate) {
+                client.skipped_ticks += update.ticks;
+                client.skipped_effects.extend_from_slice(&update.effects);
+                let excess = client.skipped_effects.len().saturating_sub(SKIPPED_EFFECTS_MAX);
+                client.skipped_effects.drain(..excess);
+                skipping.push(client_handle);
+            } else {
+                client.updates_since_sent = 0;
+                if client.skipped_ticks > 0 {
+                    let msg = ServerMessage::Skipped {
+                        ticks: mem::take(&mut client.skipped_ticks),
+                        effects: mem::take(&mut client.skipped_effects),
+                    };
+                    catch_ups.push((client_handle, msg));
+                }
+            }
+        }
+        for (client_handle, msg) in catch_ups {
+            self.network_send(engine, msg, SendDest::One(client_handle));
+        }
+
+        let msg = ServerMessage::Update(update);
+        let dest = if skipping.is_empty() {
+            SendDest::All
+        } else {
+            SendDest::AllExcept(skipping)
+        };
+        self.network_send_ref(engine, &msg, dest);
+        if let ServerMessage::Update(mut update) = msg {
+            update.ticks = 0;
+            update.effects.clear();
+            self.update_buf = update;
+        }
+
+        self.sys_send_debug(cvars, engine);
+    }
+
+    /// Send debug items to clients which asked for them (`d_remote_debug`)
+    /// if they're admins or `sv_cheats` is on,
+    /// then clear everything on the server so it doesn't get sent again next update.
+    ///
+    /// They used to be part of `Update` but most players don't want them
+    /// and they can easily be larger than the rest of the update.
+    fn sys_send_debug(&mut self, cvars: &Cvars, engine: &mut Engine) {
         // Calling debug::details::cleanup() would only clear expired.
-        let debug_texts = DEBUG_TEXTS.with(|texts| {
-            let mut texts = texts.borrow_mut();
-            mem::take(&mut *texts)
+        // Swapping with the cleared buffers from last time means neither side has to allocate.
+        let mut debug = mem::take(&mut self.debug_buf);
+        debug.texts.clear();
+        DEBUG_TEXTS.with(|texts| {
+            mem::swap(&mut *texts.borrow_mut(), &mut debug.texts);
         });
-        let debug_shapes = DEBUG_SHAPES.with(|shapes| {
-            let mut shapes = shapes.borrow_mut();
-            mem::take(&mut *shapes)
+        debug.shapes.clear();
+        DEBUG_SHAPES.with(|shapes| {
+            mem::swap(&mut *shapes.borrow_mut(), &mut debug.shapes);
         });
+        debug.world_texts.clear();
+        DEBUG_WORLD_TEXTS.with(|texts| {
+            mem::swap(&mut *texts.borrow_mut(), &mut debug.world_texts);
+        });
+
+        // They show where everyone is and what bots are thinking so they're a cheat.
+        let wants =
+            |client: &RemoteClient| client.remote_debug && (client.admin || cvars.sv_cheats);
+        if !self.clients.iter().any(wants) {
+            self.debug_buf = debug;
+            return;
+        }
 
-        let msg = ServerMessage::Update(Update {
-            player_inputs,
-            cycle_physics,
-            debug_texts,
-            debug_shapes,
+        // Serialize once, then send the same bytes to everyone who wants them.
+        let msg = ServerMessage::Debug(debug);
+        net::serialize_into(&mut self.send_buf, &msg);
+        let network_msg = &self.send_buf;
+        self.net_stats.record(self.gs.game_time, &msg, network_msg.buf.len());
+        let mut count = 0;
+        let mut disconnected = Vec::new();
+        for (handle, client) in self.clients.pair_iter_mut() {
+            if !wants(client) {
+                continue;
+            }
+            count += 1;
+            if let Err(e) = client.conn.send(network_msg) {
+                dbg_logf!("Error in sys_send_debug - index {}: {:?}", handle.index(), e);
+                disconnected.push(handle);
+            }
+        }
+        self.metrics.sent(network_msg, count);
+        if let ServerMessage::Debug(debug) = msg {
+            self.debug_buf = debug;
+        }
+        for client_handle in disconnected {
+            self.disconnect(engine, client_handle);
+        }
+    }
+
+    /// Forward log lines to admins who asked for them.
+    ///
+    /// The log is cleared even if nobody wants it so it doesn't grow forever.
+    fn sys_send_log(&mut self, cvars: &Cvars, engine: &mut Engine) {
+        let logs = DEBUG_LOGS.with(|logs| {
+            let mut logs = logs.borrow_mut();
+            mem::take(&mut *logs)
         });
-        self.network_send(engine, msg, SendDest::All);
+        if !cvars.sv_remote_log {
+            return;
+        }
+
+        let lines: Vec<_> = logs
+            .into_iter()
+            .filter(|line| line.level >= cvars.sv_remote_log_level)
+            .collect();
+        if lines.is_empty() {
+            return;
+        }
+
+        let recipients: Vec<_> = self
+            .clients
+            .pair_iter()
+            .filter(|(_, client)| client.admin && client.remote_log)
+            .map(|(handle, _)| handle)
+            .collect();
+        for client_handle in recipients {
+            let msg = ServerMessage::Log(lines.clone());
+            self.network_send(engine, msg, SendDest::One(client_handle));
+        }
+    }
+
+    /// See `sv_event_log`, stops logging after an error so it doesn't spam.
+    fn log_event(&mut self, event: Event) {
+        if let Some(event_log) = &mut self.event_log {
+            if let Err(err) = event_log.write(self.gs.game_time, event) {
+                dbg_logf!("failed to write event log, disabling it: {}", err);
+                self.event_log = None;
+            }
+        }
     }
 
     // LATER This only needs Engine for self.disconnect,
     // but forces all callers to also take Engine.
     fn network_send(&mut self, engine: &mut Engine, msg: ServerMessage, dest: SendDest) {
-        // LATER This is incredibly ugly, plus creating the Vec is inafficient.
-        //          - Save all streams in a Vec?
-        //          - Inline this fn and remove SendDest?
+        self.network_send_ref(engine, &msg, dest);
+    }
+
+    /// Same as `network_send` but lets the caller reuse the message.
+    fn network_send_ref(&mut self, engine: &mut Engine, msg: &ServerMessage, dest: SendDest) {
+        // Serialize once, then send the same bytes to everyone.
+        net::serialize_into(&mut self.send_buf, msg);
+        let network_msg = &self.send_buf;
+        self.net_stats.record(self.gs.game_time, msg, network_msg.buf.len());
+
+        // Doesn't allocate unless somebody disconnected.
         let mut disconnected = Vec::new();
-        let network_msg = net::serialize(msg);
-        match dest {
-            SendDest::One(handle) => {
-                if let Err(e) = self.clients[handle].conn.send(&network_msg) {
+        let skip = match &dest {
+            SendDest::AllExcept(skip) => skip.as_slice(),
+            _ => &[],
+        };
+        match &dest {
+            &SendDest::One(handle) => {
+                self.metrics.sent(network_msg, 1);
+                if let Err(e) = self.clients[handle].conn.send(network_msg) {
                     dbg_logf!("Error in network_send One - index {}: {:?}", handle.index(), e);
                     disconnected.push(handle);
                 }
             }
-            SendDest::All => {
-                for (handle, client) in self.clients.pair_iter_mut() {
-                    if let Err(e) = client.conn.send(&network_msg) {
+            SendDest::All | SendDest::AllExcept(_) => {
+                // Spectators and demos always get everything.
+                let count = self.clients.alive_count() - skip.len() as u32;
+                self.metrics.sent(network_msg, count);
+                if let Some(demo) = &mut self.demo {
+                    demo.record(network_msg);
+                }
+                if let Some(relay) = &mut self.relay {
+                    relay.record(msg, network_msg);
+                }
+
+                if let Some(pool) = &self.send_pool {
+                    // Debug macros use thread locals which would be lost on worker threads
+                    // so keep the errors and log them here.
+                    let errors = Mutex::new(mem::take(&mut self.send_errors));
+                    let clients = &mut self.clients;
+                    pool.install(|| {
+                        clients
+                            .pair_iter_mut()
+                            .filter(|(handle, _)| !skip.contains(handle))
+                            .par_bridge()
+                            .for_each(|(handle, client)| {
+                                if let Err(e) = client.conn.send(network_msg) {
+                                    errors.lock().unwrap().push((handle, e));
+                                }
+                            });
+                    });
+                    let mut errors = errors.into_inner().unwrap();
+                    for (handle, e) in errors.drain(..) {
                         dbg_logf!("Error in network_send All - index {}: {:?}", handle.index(), e);
                         disconnected.push(handle);
                     }
+                    self.send_errors = errors;
+                } else {
+                    for (handle, client) in self.clients.pair_iter_mut() {
+                        if skip.contains(&handle) {
+                            continue;
+                        }
+                        if let Err(e) = client.conn.send(network_msg) {
+                            dbg_logf!(
+                                "Error in network_send All - index {}: {:?}",
+                                handle.index(),
+                                e
+                            );
+                            disconnected.push(handle);
+                        }
+                    }
                 }
             }
         };
@@ -278,21 +2024,117 @@ impl ServerGame {
     }
 }
 
+/// The player's choice if `g_vehicles` allows it.
+fn spawn_vehicle(cvars: &Cvars, player: &Player) -> VehicleKind {
+    if cvars.g_vehicles {
+        player.vehicle
+    } else {
+        VehicleKind::Standard
+    }
+}
+
+/// How many players can play before the server is full for this one.
+///
+/// The last `sv_reserved_slots` are only for admins.
+fn player_slots(cvars: &Cvars, admin: bool) -> usize {
+    if admin {
+        cvars.sv_max_players
+    } else {
+        cvars.sv_max_players.saturating_sub(cvars.sv_reserved_slots)
+    }
+}
+
+/// Takes the same time no matter where the passwords differ
+/// so it can't be guessed one character at a time by measuring how long the server takes.
+/// The length still leaks.
+fn passwords_match(given: &str, expected: &str) -> bool {
+    let (given, expected) = (given.as_bytes(), expected.as_bytes());
+    let diff = expected
+        .iter()
+        .enumerate()
+        .fold(0, |diff, (i, &byte)| diff | (byte ^ given.get(i).copied().unwrap_or(!byte)));
+    diff == 0 && given.len() == expected.len()
+}
+
+/// Ticks between updates for `sv_snapshot_rate`.
+fn snapshot_interval(rate: f32) -> u32 {
+    if rate <= 0.0 {
+        return 1;
+    }
+    (60.0 / rate).round().max(1.0) as u32
+}
+
+/// How many updates a client with this `cl_updaterate` gets one of.
+fn updates_per_send(cvars: &Cvars, requested: f32) -> u32 {
+    let server_rate = 60.0 / snapshot_interval(cvars.sv_snapshot_rate) as f32;
+    // Not `clamp` which panics if the server misconfigures min above max.
+    let rate = requested.max(cvars.sv_snapshot_rate_min).min(server_rate);
+    if rate <= 0.0 {
+        return 1;
+    }
+    (server_rate / rate).round().max(1.0) as u32
+}
+
 enum SendDest {
     One(Handle<RemoteClient>),
     All,
+    /// All except clients which are skipping this message.
+    AllExcept(Vec<Handle<RemoteClient>>),
 }
 
 struct RemoteClient {
     conn: Box<dyn Connection>,
     player_handle: Handle<Player>,
+    /// Logged in using `sv_admin_password`.
+    admin: bool,
+    /// Wants to receive the server's log, only honored for admins.
+    remote_log: bool,
+    /// Wants to receive debug items, see `d_remote_debug`.
+    remote_debug: bool,
+    /// The last `ServerMessage::Ping` which wasn't answered yet.
+    ping_sent: Option<(u32, Instant)>,
+    ping: PingStats,
+    /// Game time when the player can emote again, see `sv_emote_interval`.
+    emote_next: f32,
+    /// Game time when the player can chat again, see `sv_chat_filter`.
+    chat_muted_until: f32,
+    /// Game time when the player can send another chat line, see `sv_chat_interval`.
+    chat_next: f32,
+    /// Game time when the player last touched the controls, see `sv_afk_timeout`.
+    last_active: f32,
+    /// Sent in `Init`, see `sv_reconnect_time`.
+    reconnect_token: u64,
+    /// Requested by the client, see `cl_updaterate`.
+    update_rate: f32,
+    /// Updates since the last one this client got.
+    updates_since_sent: u32,
+    /// Ticks and effects from skipped updates, sent as `ServerMessage::Skipped`.
+    skipped_ticks: u32,
+    skipped_effects: Vec<Effect>,
+    /// Sent a `ClientMessage::DesyncCheck` so the server keeps `desync_history`.
+    desync_check: bool,
 }
 
 impl RemoteClient {
-    fn new(conn: Box<dyn Connection>, player_handle: Handle<Player>) -> Self {
+    fn new(conn: Box<dyn Connection>, player_handle: Handle<Player>, reconnect_token: u64) -> Self {
         Self {
             conn,
             player_handle,
+            admin: false,
+            remote_log: false,
+            remote_debug: false,
+            ping_sent: None,
+            ping: PingStats::default(),
+            emote_next: 0.0,
+            chat_muted_until: 0.0,
+            chat_next: 0.0,
+            last_active: 0.0,
+            reconnect_token,
+            update_rate: 60.0,
+            updates_since_sent: 0,
+            skipped_ticks: 0,
+            skipped_effects: Vec::new(),
+            desync_check: false,
         }
     }
 }
@@ -0,0 +1,169 @@
+//! Match events which game modes and plugins can react to.
+//!
+//! Implement `ServerHooks` and register it with `Hooks::add`.
+//! Events are dispatched once per tick after gamelogic, in the order they happened.
+//!
+//! LATER Expose this to a scripting language.
+
+use std::collections::VecDeque;
+
+use crate::{
+    common::{
+        entities::{Kill, MatchPhase, MatchPhaseExt, Player, RespawnMode},
+        GameState,
+    },
+    prelude::*,
+};
+
+/// Callbacks for match events, all of them do nothing by default.
+pub(crate) trait ServerHooks {
+    /// A player connected. They're observing, not playing yet.
+    fn on_player_join(&mut self, _ctx: &mut HookCtx, _player_handle: Handle<Player>) {}
+
+    /// A player disconnected. The handle is no longer valid in `GameState`.
+    fn on_player_leave(&mut self, _ctx: &mut HookCtx, _player_handle: Handle<Player>) {}
+
+    fn on_kill(&mut self, _ctx: &mut HookCtx, _kill: Kill) {}
+
+    /// Called after `HookCtx::start_match`, `gs.phase` is already `Live`.
+    fn on_match_start(&mut self, _ctx: &mut HookCtx) {}
+
+    /// Called after `HookCtx::end_round`.
+    fn on_round_end(&mut self, _ctx: &mut HookCtx, _winner: Option<Handle<Player>>) {}
+
+    /// Called every tick after all other events.
+    fn on_tick(&mut self, _ctx: &mut HookCtx) {}
+
+    /// How dead players get a new cycle, the first hook which returns `Some` decides.
+    fn respawn_mode(&self, _cvars: &Cvars) -> Option<RespawnMode> {
+        None
+    }
+
+    /// A player is saying something, called immediately, not once per tick.
+    ///
+    /// Hooks can change the text. If one doesn't allow it, the rest aren't asked.
+    /// Chat commands such as `/votekick` aren't moderated, only what gets shown to others.
+    fn on_chat(
+        &mut self,
+        _ctx: &mut HookCtx,
+        _player_handle: Handle<Player>,
+        _text: &mut String,
+    ) -> ChatAction {
+        ChatAction::Allow
+    }
+}
+
+/// What the server should do with a chat line, see `ServerHooks::on_chat`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ChatAction {
+    Allow,
+    /// Send the line and tell the player this.
+    Warn(String),
+    /// Drop the line and don't let the player chat for this many seconds.
+    Mute(f32),
+    /// Drop the line and kick the player.
+    Kick,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HookEvent {
+    PlayerJoin(Handle<Player>),
+    PlayerLeave(Handle<Player>),
+    Kill(Kill),
+    MatchStart,
+    RoundEnd(Option<Handle<Player>>),
+    Tick,
+}
+
+/// What hooks can access and do.
+pub(crate) struct HookCtx<'a> {
+    pub(crate) cvars: &'a Cvars,
+    pub(crate) gs: &'a mut GameState,
+    /// Events caused by hooks, dispatched after the current one.
+    queue: &'a mut VecDeque<HookEvent>,
+}
+
+impl HookCtx<'_> {
+    /// End warmup.
+    pub(crate) fn start_match(&mut self) {
+        self.gs.phase = MatchPhase::live(self.cvars, self.gs.game_time);
+        self.queue.push_back(HookEvent::MatchStart);
+    }
+
+    pub(crate) fn end_round(&mut self, winner: Option<Handle<Player>>) {
+        self.queue.push_back(HookEvent::RoundEnd(winner));
+    }
+}
+
+/// All registered hooks in the order they were added.
+pub(crate) struct Hooks {
+    hooks: Vec<Box<dyn ServerHooks>>,
+}
+
+impl Hooks {
+    pub(crate) fn new() -> Self {
+        Self { hooks: Vec::new() }
+    }
+
+    pub(crate) fn add(&mut self, hooks: Box<dyn ServerHooks>) {
+        self.hooks.push(hooks);
+    }
+
+    /// Instant if no hook cares.
+    pub(crate) fn respawn_mode(&self, cvars: &Cvars) -> RespawnMode {
+        self.hooks
+            .iter()
+            .find_map(|hooks| hooks.respawn_mode(cvars))
+            .unwrap_or(RespawnMode::Instant)
+    }
+
+    /// Let hooks moderate a chat line, then dispatch any events they caused.
+    pub(crate) fn chat(
+        &mut self,
+        cvars: &Cvars,
+        gs: &mut GameState,
+        player_handle: Handle<Player>,
+        text: &mut String,
+    ) -> ChatAction {
+        let mut queue = VecDeque::new();
+        let mut action = ChatAction::Allow;
+        for hooks in &mut self.hooks {
+            let mut ctx = HookCtx {
+                cvars,
+                gs,
+                queue: &mut queue,
+            };
+            action = hooks.on_chat(&mut ctx, player_handle, text);
+            if action != ChatAction::Allow {
+                break;
+            }
+        }
+        for event in queue {
+            self.dispatch(cvars, gs, event);
+        }
+        action
+    }
+
+    /// Run `event` through all hooks, followed by any events they caused.
+    pub(crate) fn dispatch(&mut self, cvars: &Cvars, gs: &mut GameState, event: HookEvent) {
+        let mut queue = VecDeque::new();
+        queue.push_back(event);
+        while let Some(event) = queue.pop_front() {
+            for hooks in &mut self.hooks {
+                let mut ctx = HookCtx {
+                    cvars,
+                    gs,
+                    queue: &mut queue,
+                };
+                match event {
+                    HookEvent::PlayerJoin(handle) => hooks.on_player_join(&mut ctx, handle),
+                    HookEvent::PlayerLeave(handle) => hooks.on_player_leave(&mut ctx, handle),
+                    HookEvent::Kill(kill) => hooks.on_kill(&mut ctx, kill),
+                    HookEvent::MatchStart => hooks.on_match_start(&mut ctx),
+                    HookEvent::RoundEnd(winner) => hooks.on_round_end(&mut ctx, winner),
+                    HookEvent::Tick => hooks.on_tick(&mut ctx),
+                }
+            }
+        }
+    }
+}
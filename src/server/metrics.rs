@@ -0,0 +1,118 @@
+//! Server health metrics, served in Prometheus' text format on `sv_status_addr` at `/metrics`.
+//!
+//! Counters only ever go up, Prometheus computes rates such as bytes per second from them.
+//! They're always collected, it's just a few additions per tick.
+
+use std::{fmt::Write, time::Duration};
+
+use crate::{
+    common::net::{NetworkMessage, FRAME_HEADER_LEN},
+    debug,
+};
+
+#[derive(Debug, Default)]
+pub(crate) struct Metrics {
+    ticks: u64,
+    tick_seconds_sum: f64,
+    /// How long the most recent tick took.
+    tick_seconds_last: f64,
+    sent_messages: u64,
+    /// Including headers, counted once for each client.
+    sent_bytes: u64,
+    received_messages: u64,
+}
+
+impl Metrics {
+    pub(crate) fn tick(&mut self, duration: Duration) {
+        self.ticks += 1;
+        self.tick_seconds_sum += duration.as_secs_f64();
+        self.tick_seconds_last = duration.as_secs_f64();
+    }
+
+    /// A message was sent to this many clients.
+    pub(crate) fn sent(&mut self, network_msg: &NetworkMessage, clients: u32) {
+        let len = FRAME_HEADER_LEN + network_msg.buf.len();
+        self.sent_messages += clients as u64;
+        self.sent_bytes += len as u64 * clients as u64;
+    }
+
+    pub(crate) fn received(&mut self, count: usize) {
+        self.received_messages += count as u64;
+    }
+
+    /// Everything in Prometheus' text exposition format.
+    pub(crate) fn text(&self, players: u32) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: &dyn std::fmt::Display| {
+            writeln!(text, "# HELP rustcycles_{} {}", name, help).unwrap();
+            writeln!(text, "# TYPE rustcycles_{} {}", name, kind).unwrap();
+            writeln!(text, "rustcycles_{} {}", name, value).unwrap();
+        };
+        metric("ticks_total", "counter", "Server ticks.", &self.ticks);
+        metric(
+            "tick_seconds_total",
+            "counter",
+            "Time spent in server ticks.",
+            &self.tick_seconds_sum,
+        );
+        metric(
+            "tick_seconds_last",
+            "gauge",
+            "Duration of the most recent tick.",
+            &self.tick_seconds_last,
+        );
+        metric("players", "gauge", "Players connected.", &players);
+        metric(
+            "sent_messages_total",
+            "counter",
+            "Messages sent, once per client.",
+            &self.sent_messages,
+        );
+        metric(
+            "sent_bytes_total",
+            "counter",
+            "Bytes sent including headers, once per client.",
+            &self.sent_bytes,
+        );
+        metric(
+            "received_messages_total",
+            "counter",
+            "Messages received from clients.",
+            &self.received_messages,
+        );
+        metric(
+            "soft_asserts_total",
+            "counter",
+            "Failed soft asserts on the server.",
+            &debug::details::asserts_total(),
+        );
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text() {
+        let mut metrics = Metrics::default();
+        metrics.tick(Duration::from_millis(2));
+        metrics.tick(Duration::from_millis(4));
+        let network_msg = NetworkMessage {
+            content_len: Default::default(),
+            checksum: Default::default(),
+            buf: vec![0; 10],
+        };
+        metrics.sent(&network_msg, 3);
+
+        let text = metrics.text(3);
+        assert!(
+            text.contains("\n# TYPE rustcycles_ticks_total counter\nrustcycles_ticks_total 2\n")
+        );
+        assert!(text.contains("\nrustcycles_tick_seconds_last 0.004\n"));
+        assert!(text.contains("\nrustcycles_players 3\n"));
+        let bytes = 3 * (FRAME_HEADER_LEN + 10);
+        assert!(text.contains(&format!("\nrustcycles_sent_bytes_total {}\n", bytes)));
+    }
+}
@@ -0,0 +1,197 @@
+//! Built-in game modes, implemented using the same hooks as plugins.
+
+use crate::{
+    common::{
+        entities::{Kill, MatchPhase, MatchPhaseExt, OvertimeRule, Player, RespawnMode},
+        GameState,
+    },
+    prelude::*,
+    server::hooks::{HookCtx, ServerHooks},
+};
+
+/// Everyone against everyone, the first player to reach `g_score_limit` kills wins the round.
+///
+/// Otherwise whoever leads at `g_time_limit` wins, ties go to overtime (`g_overtime`).
+/// Scores are kept in `Player::score` so they get replicated.
+#[derive(Debug, Default)]
+pub(crate) struct FreeForAll {
+    /// Leaders who can still win `GoldenFrag` or `SuddenDeath` overtime.
+    contenders: Vec<Handle<Player>>,
+}
+
+impl FreeForAll {
+    /// The time limit or an overtime period ran out.
+    fn time_up(&mut self, ctx: &mut HookCtx) {
+        let leaders = leaders(ctx.gs);
+        if leaders.len() <= 1 {
+            ctx.end_round(leaders.first().copied());
+            return;
+        }
+
+        let rule = ctx.cvars.g_overtime;
+        dbg_logf!("{} players tied for the lead, overtime: {}", leaders.len(), rule);
+        let end = match rule {
+            OvertimeRule::Off => {
+                ctx.end_round(None);
+                return;
+            }
+            OvertimeRule::ExtraRounds => Some(ctx.gs.game_time + ctx.cvars.g_overtime_time),
+            OvertimeRule::GoldenFrag | OvertimeRule::SuddenDeath => {
+                self.contenders = leaders;
+                None
+            }
+        };
+        ctx.gs.phase = MatchPhase::Overtime { rule, end };
+    }
+
+    /// Remove a leader from overtime, the last one left wins.
+    fn drop_contender(&mut self, ctx: &mut HookCtx, player_handle: Handle<Player>) {
+        let len = self.contenders.len();
+        self.contenders.retain(|&handle| handle != player_handle);
+        if len > 1 && self.contenders.len() == 1 {
+            ctx.end_round(Some(self.contenders[0]));
+        }
+    }
+}
+
+impl ServerHooks for FreeForAll {
+    fn on_player_leave(&mut self, ctx: &mut HookCtx, player_handle: Handle<Player>) {
+        self.drop_contender(ctx, player_handle);
+    }
+
+    fn on_kill(&mut self, ctx: &mut HookCtx, kill: Kill) {
+        dbg_logf!("player {} killed player {}", kill.killer.index(), kill.victim.index());
+        let rule = match ctx.gs.phase {
+            MatchPhase::Warmup { .. } | MatchPhase::Paused { .. } => return,
+            MatchPhase::Live { .. } => None,
+            MatchPhase::Overtime { rule, .. } => Some(rule),
+        };
+
+        // E.g. falling into a kill volume.
+        if kill.killer == kill.victim {
+            return;
+        }
+        let Some(killer) = ctx.gs.players.try_borrow_mut(kill.killer) else {
+            return;
+        };
+        killer.score += 1;
+
+        let limit = ctx.cvars.g_score_limit;
+        if limit > 0 && killer.score == limit {
+            ctx.end_round(Some(kill.killer));
+            return;
+        }
+
+        match rule {
+            Some(OvertimeRule::GoldenFrag) if self.contenders.contains(&kill.killer) => {
+                ctx.end_round(Some(kill.killer));
+            }
+            Some(OvertimeRule::SuddenDeath) => self.drop_contender(ctx, kill.victim),
+            _ => {}
+        }
+    }
+
+    fn on_match_start(&mut self, ctx: &mut HookCtx) {
+        dbg_logf!("match started at {:.1} s", ctx.gs.game_time);
+        reset_scores(ctx.gs);
+    }
+
+    fn on_round_end(&mut self, ctx: &mut HookCtx, winner: Option<Handle<Player>>) {
+        let time = ctx.gs.game_time;
+        match winner {
+            Some(handle) => {
+                dbg_logf!("round over at {:.1} s, player {} wins", time, handle.index())
+            }
+            None => dbg_logf!("round over at {:.1} s, nobody wins", time),
+        }
+        // LATER Respawn everyone
+        reset_scores(ctx.gs);
+        self.contenders.clear();
+        ctx.gs.phase = MatchPhase::live(ctx.cvars, time);
+    }
+
+    fn on_tick(&mut self, ctx: &mut HookCtx) {
+        match ctx.gs.phase {
+            MatchPhase::Warmup { deadline } => warmup_tick(ctx, deadline),
+            MatchPhase::Live { end: Some(end) } | MatchPhase::Overtime { end: Some(end), .. }
+                if ctx.gs.game_time >= end =>
+            {
+                self.time_up(ctx)
+            }
+            _ => {}
+        }
+    }
+
+    fn respawn_mode(&self, cvars: &Cvars) -> Option<RespawnMode> {
+        Some(cvars.g_respawn)
+    }
+}
+
+/// Players with the highest score.
+fn leaders(gs: &GameState) -> Vec<Handle<Player>> {
+    let Some(best) = gs.players.iter().map(|player| player.score).max() else {
+        return Vec::new();
+    };
+    gs.players
+        .pair_iter()
+        .filter(|(_, player)| player.score == best)
+        .map(|(handle, _)| handle)
+        .collect()
+}
+
+fn reset_scores(gs: &mut GameState) {
+    for player in gs.players.iter_mut() {
+        player.score = 0;
+        player.assists = 0;
+    }
+}
+
+/// Start the match once enough playing players are ready or the warmup time runs out.
+///
+/// The timer starts when the first player joins so a lone player doesn't wait forever.
+fn warmup_tick(ctx: &mut HookCtx, deadline: Option<f32>) {
+    let (ready, playing) = ctx.gs.ready_count();
+    if playing == 0 {
+        return;
+    }
+
+    if enough_ready(ready, playing, ctx.cvars.g_warmup_ready_ratio) {
+        dbg_logf!("{}/{} players ready", ready, playing);
+        ctx.start_match();
+        return;
+    }
+
+    match deadline {
+        Some(deadline) if ctx.gs.game_time >= deadline => {
+            dbg_logf!("warmup time is up, {}/{} players ready", ready, playing);
+            ctx.start_match();
+        }
+        None if ctx.cvars.g_warmup_time > 0.0 => {
+            let deadline = ctx.gs.game_time + ctx.cvars.g_warmup_time;
+            ctx.gs.phase = MatchPhase::Warmup {
+                deadline: Some(deadline),
+            };
+        }
+        _ => {}
+    }
+}
+
+/// At least one player and at least `ratio` of all players have to be ready.
+fn enough_ready(ready: usize, playing: usize, ratio: f32) -> bool {
+    ready > 0 && ready as f32 >= ratio * playing as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enough_ready() {
+        assert!(!enough_ready(0, 1, 0.0));
+        assert!(enough_ready(1, 1, 1.0));
+        assert!(!enough_ready(2, 3, 1.0));
+        assert!(enough_ready(3, 3, 1.0));
+        assert!(enough_ready(2, 3, 0.5));
+        assert!(!enough_ready(1, 4, 0.5));
+    }
+}
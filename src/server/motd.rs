@@ -0,0 +1,58 @@
+//! Message of the day - `sv_motd` and `sv_motd_file`.
+//!
+//! Sent to each client right after `Init`, the client shows it until the player closes it.
+//! Lines starting with `# ` are headings and lines starting with `- ` are list items,
+//! the client decides how they look.
+
+use std::fs;
+
+use crate::prelude::*;
+
+/// Longer messages are cut off so a large file can't flood clients.
+const MAX_LEN: usize = 4096;
+
+/// The message with real newlines, `None` if there's nothing to show.
+///
+/// The file is read for every new client so it can be edited while the server runs.
+pub(crate) fn load(cvars: &Cvars) -> Option<String> {
+    let text = if cvars.sv_motd_file.is_empty() {
+        // Cvars can't contain newlines.
+        cvars.sv_motd.replace("\\n", "\n")
+    } else {
+        match fs::read_to_string(&cvars.sv_motd_file) {
+            Ok(text) => text,
+            Err(err) => {
+                dbg_logf!("failed to read motd from {}: {}", cvars.sv_motd_file, err);
+                return None;
+            }
+        }
+    };
+    let text = truncate(text.trim(), MAX_LEN);
+    (!text.is_empty()).then(|| text.to_owned())
+}
+
+/// At most `max_len` bytes, cut at a char boundary.
+pub(crate) fn truncate(text: &str, max_len: usize) -> &str {
+    let mut end = text.len().min(max_len);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let mut cvars = Cvars::default();
+        assert_eq!(load(&cvars), None);
+
+        cvars.sv_motd = "# Rules\\n- be nice ".to_owned();
+        assert_eq!(load(&cvars).unwrap(), "# Rules\n- be nice");
+
+        let long = "ž".repeat(MAX_LEN);
+        assert_eq!(truncate(&long, MAX_LEN).len(), MAX_LEN);
+    }
+}
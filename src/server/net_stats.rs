@@ -0,0 +1,132 @@
+//! Sizes of messages sent to clients by type - the `net_stats` command and `sv_net_warn_size`.
+//!
+//! Each message is counted once when it's serialized, no matter how many clients get it.
+//! Sizes don't include the frame header.
+//! Stats are kept per second of game time for the last `MAX_SECONDS`.
+
+use std::collections::VecDeque;
+
+use fxhash::FxHashMap;
+
+use crate::{common::messages::ServerMessage, prelude::*};
+
+/// How far back `net_stats` can look.
+const MAX_SECONDS: usize = 60;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct VariantStats {
+    count: u32,
+    bytes: u64,
+    max: usize,
+    /// Already logged as too large this second.
+    warned: bool,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct NetStats {
+    /// Whole seconds of game time and what was sent during them, newest last.
+    seconds: VecDeque<(u32, FxHashMap<&'static str, VariantStats>)>,
+}
+
+impl NetStats {
+    pub(crate) fn record(&mut self, game_time: f32, msg: &ServerMessage, len: usize) {
+        let second = game_time as u32;
+        if self.seconds.back().map_or(true, |&(last, _)| last != second) {
+            if self.seconds.len() >= MAX_SECONDS {
+                self.seconds.pop_front();
+            }
+            self.seconds.push_back((second, FxHashMap::default()));
+        }
+
+        let variant: &'static str = msg.into();
+        let stats = self.seconds.back_mut().unwrap().1.entry(variant).or_default();
+        stats.count += 1;
+        stats.bytes += len as u64;
+        stats.max = stats.max.max(len);
+    }
+
+    /// Log message types which were larger than `sv_net_warn_size`,
+    /// each at most once per second so the log stays readable.
+    pub(crate) fn warn(&mut self, cvars: &Cvars) {
+        let limit = cvars.sv_net_warn_size;
+        let Some((_, variants)) = self.seconds.back_mut() else {
+            return;
+        };
+        for (variant, stats) in variants {
+            if limit > 0 && stats.max > limit && !stats.warned {
+                stats.warned = true;
+                dbg_logf!(
+                    "{} message was {} bytes, sv_net_warn_size is {}",
+                    variant,
+                    stats.max,
+                    limit
+                );
+            }
+        }
+    }
+
+    /// Counts and sizes by type during the last `seconds`, largest total first.
+    ///
+    /// At most `MAX_SECONDS`, older stats are gone.
+    pub(crate) fn lines(&self, game_time: f32, seconds: f32) -> Vec<String> {
+        let seconds = seconds.min(MAX_SECONDS as f32);
+        let mut totals: FxHashMap<&str, VariantStats> = FxHashMap::default();
+        // The current second is only partially over, count it as a whole one.
+        let since = game_time - seconds;
+        for (second, variants) in &self.seconds {
+            if (*second + 1) as f32 <= since {
+                continue;
+            }
+            for (&variant, stats) in variants {
+                let total = totals.entry(variant).or_default();
+                total.count += stats.count;
+                total.bytes += stats.bytes;
+                total.max = total.max.max(stats.max);
+            }
+        }
+
+        let mut totals: Vec<_> = totals.into_iter().collect();
+        totals.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then(a.0.cmp(b.0)));
+        if totals.is_empty() {
+            return vec!["nothing sent".to_owned()];
+        }
+        totals
+            .into_iter()
+            .map(|(variant, stats)| {
+                let avg = stats.bytes / u64::from(stats.count);
+                format!(
+                    "{}: {} messages, {} bytes, avg {}, max {}",
+                    variant, stats.count, stats.bytes, avg, stats.max
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        let mut stats = NetStats::default();
+        assert_eq!(stats.lines(0.0, 10.0), vec!["nothing sent"]);
+
+        stats.record(0.5, &ServerMessage::Ping(1), 10);
+        stats.record(5.5, &ServerMessage::Ping(2), 20);
+        stats.record(5.6, &ServerMessage::CheatsAllowed(true), 100);
+        assert_eq!(
+            stats.lines(6.0, 10.0),
+            vec![
+                "CheatsAllowed: 1 messages, 100 bytes, avg 100, max 100",
+                "Ping: 2 messages, 30 bytes, avg 15, max 20",
+            ]
+        );
+        assert_eq!(stats.lines(6.0, 2.0)[1], "Ping: 1 messages, 20 bytes, avg 20, max 20");
+
+        for second in 0..100 {
+            stats.record(second as f32, &ServerMessage::Ping(0), 1);
+        }
+        assert_eq!(stats.seconds.len(), MAX_SECONDS);
+    }
+}
@@ -0,0 +1,64 @@
+//! Pausing competitive matches when somebody loses connection - `g_competitive`.
+//!
+//! When a playing player disconnects without being kicked during a live match,
+//! the phase becomes `MatchPhase::Paused` until everyone who dropped reconnects
+//! (see `server::reconnect`), `g_competitive_pause_time` runs out
+//! or a team captain types `/unpause`.
+//! Afterwards the match continues with its deadlines, respawn and pickup timers
+//! moved by how long the pause took.
+
+use crate::common::entities::MatchPhase;
+
+#[derive(Debug, Clone)]
+pub(crate) struct MatchPause {
+    /// Where the match was before pausing.
+    phase: MatchPhase,
+    /// Game time when the match was paused.
+    start: f32,
+    /// Reconnect tokens of players who haven't come back yet.
+    pub(crate) awaiting: Vec<u64>,
+}
+
+impl MatchPause {
+    pub(crate) fn new(phase: MatchPhase, start: f32, token: u64) -> Self {
+        Self {
+            phase,
+            start,
+            awaiting: vec![token],
+        }
+    }
+
+    /// The player came back, returns whether that was the last one.
+    pub(crate) fn reconnected(&mut self, token: u64) -> bool {
+        self.awaiting.retain(|&awaiting| awaiting != token);
+        self.awaiting.is_empty()
+    }
+
+    /// How long the match has been paused.
+    pub(crate) fn duration(&self, game_time: f32) -> f32 {
+        game_time - self.start
+    }
+
+    /// The phase to continue with.
+    pub(crate) fn resume(&self, game_time: f32) -> MatchPhase {
+        self.phase.delayed(self.duration(game_time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause() {
+        let phase = MatchPhase::Live { end: Some(100.0) };
+        let mut pause = MatchPause::new(phase, 50.0, 1);
+        pause.awaiting.push(2);
+        assert!(!pause.reconnected(1));
+        assert!(!pause.reconnected(3));
+        assert!(pause.reconnected(2));
+        assert_eq!(pause.resume(80.0), MatchPhase::Live { end: Some(130.0) });
+        let pause = MatchPause::new(MatchPhase::Live { end: None }, 50.0, 1);
+        assert_eq!(pause.resume(80.0), MatchPhase::Live { end: None });
+    }
+}
@@ -0,0 +1,106 @@
+//! Taking and respawning the map's pickups, see `map::Pickup`.
+//!
+//! Also runs timed powerups such as the cloak.
+
+use fxhash::FxHashMap;
+
+use crate::{
+    common::{
+        entities::{GameEvent, Player, PlayerState},
+        map::PickupKind,
+        Action, GameState,
+    },
+    prelude::*,
+};
+
+/// Pickups are taken when a cycle's center gets this close.
+const PICKUP_RADIUS: f32 = 1.0;
+
+#[derive(Debug, Default)]
+pub(crate) struct Pickups {
+    /// Game time when each taken pickup appears again, `None` if it's available.
+    /// Same order as `MapManifest::pickups`.
+    respawn_at: Vec<Option<f32>>,
+    /// Game time when each cloaked player becomes visible again.
+    cloak_ends: FxHashMap<Handle<Player>, f32>,
+}
+
+impl Pickups {
+    /// Respawn pickups whose time has come and let cycles take the available ones.
+    ///
+    /// Cloaks run out or break when the player fires.
+    pub(crate) fn tick(&mut self, cvars: &Cvars, gs: &mut GameState, scene: &Scene) {
+        self.cloak_ends.retain(|&player_handle, &mut end| {
+            let Some(player) = gs.players.try_borrow_mut(player_handle) else {
+                return false;
+            };
+            if gs.game_time < end && !player.input.pressed(Action::Fire1) {
+                return true;
+            }
+            player.cloaked = false;
+            false
+        });
+
+        // The map can change with hot reloading.
+        self.respawn_at.resize(gs.map.pickups.len(), None);
+
+        for (index, pickup) in gs.map.pickups.iter().enumerate() {
+            if let Some(time) = self.respawn_at[index] {
+                if gs.game_time < time {
+                    continue;
+                }
+                self.respawn_at[index] = None;
+            }
+
+            let pos = Vec3::from(pickup.pos);
+            for cycle in &gs.cycles {
+                let player = &mut gs.players[cycle.player_handle];
+                let cycle_pos = **scene.graph[cycle.body_handle].local_transform().position();
+                if player.ps != PlayerState::Playing || (cycle_pos - pos).norm() > PICKUP_RADIUS {
+                    continue;
+                }
+                let taken = match pickup.kind {
+                    PickupKind::Armor { amount } => take_armor(cvars, player, amount),
+                    PickupKind::Cloak { duration } => {
+                        // Another cloak just restarts the timer.
+                        player.cloaked = true;
+                        self.cloak_ends.insert(cycle.player_handle, gs.game_time + duration);
+                        true
+                    }
+                };
+                if taken {
+                    self.respawn_at[index] = Some(gs.game_time + pickup.respawn_time);
+                    gs.events.push(GameEvent::Pickup {
+                        player_handle: cycle.player_handle,
+                        pickup_index: index,
+                    });
+                    break;
+                }
+            }
+        }
+    }
+
+    /// The match was paused, move respawns and cloak ends by how long it took.
+    pub(crate) fn delay(&mut self, by: f32) {
+        for time in self.respawn_at.iter_mut().flatten() {
+            *time += by;
+        }
+        for end in self.cloak_ends.values_mut() {
+            *end += by;
+        }
+    }
+
+    /// Which pickups can be taken, same order as `MapManifest::pickups`.
+    pub(crate) fn available(&self) -> impl Iterator<Item = bool> + '_ {
+        self.respawn_at.iter().map(Option::is_none)
+    }
+}
+
+/// Returns false if the player doesn't need it so it's left for others.
+fn take_armor(cvars: &Cvars, player: &mut Player, amount: u32) -> bool {
+    if player.armor >= cvars.g_armor_max {
+        return false;
+    }
+    player.armor = (player.armor + amount).min(cvars.g_armor_max);
+    true
+}
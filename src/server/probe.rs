@@ -0,0 +1,52 @@
+//! Answering latency probes so clients can measure ping without joining - see `client::probe`.
+//!
+//! A UDP socket on the same address as the game's TCP listener sends valid probes back unchanged.
+//! Replies are never larger than requests so it can't be used to amplify attacks.
+
+use std::{
+    io::{self, ErrorKind},
+    net::UdpSocket,
+};
+
+use crate::{
+    common::net::{PROBE_LEN, PROBE_MAGIC},
+    prelude::*,
+};
+
+/// The most probes answered per tick so a flood can't stall the server.
+const MAX_PER_TICK: usize = 64;
+
+pub(crate) struct ProbeResponder {
+    socket: UdpSocket,
+}
+
+impl ProbeResponder {
+    pub(crate) fn bind(addr: &str) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket })
+    }
+
+    /// Answer probes which arrived so far, anything else is ignored.
+    pub(crate) fn respond(&mut self) {
+        // One byte longer so longer packets don't look valid after being cut off.
+        let mut buf = [0; PROBE_LEN + 1];
+        for _ in 0..MAX_PER_TICK {
+            match self.socket.recv_from(&mut buf) {
+                Ok((len, addr)) => {
+                    if len != PROBE_LEN || !buf.starts_with(&PROBE_MAGIC) {
+                        continue;
+                    }
+                    if let Err(err) = self.socket.send_to(&buf[..len], addr) {
+                        dbg_logf!("failed to answer probe from {}: {}", addr, err);
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    // E.g. on Windows when a previous reply was rejected, the next one can be fine.
+                    dbg_logf!("failed to receive probe: {}", err);
+                }
+            }
+        }
+    }
+}
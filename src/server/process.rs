@@ -1,10 +1,14 @@
 //! The process that runs a dedicated server.
 
-use std::net::TcpListener;
+use std::{fs, net::TcpListener};
 
 use fyrox::core::instant::Instant;
 
-use crate::{prelude::*, server::game::ServerGame};
+use crate::{
+    debug,
+    prelude::*,
+    server::{game::ServerGame, probe::ProbeResponder, restart},
+};
 
 /// The process that runs a dedicated server.
 pub(crate) struct ServerProcess {
@@ -12,12 +16,23 @@ pub(crate) struct ServerProcess {
     pub(crate) clock: Instant,
     pub(crate) engine: Engine,
     sg: ServerGame,
+    /// See `server::probe`.
+    probes: Option<ProbeResponder>,
 }
 
 impl ServerProcess {
     pub(crate) async fn new(cvars: Cvars, mut engine: Engine) -> Self {
-        let listener = TcpListener::bind("127.0.0.1:26000").unwrap();
+        let addr = "127.0.0.1:26000";
+        let listener = TcpListener::bind(addr).unwrap();
         listener.set_nonblocking(true).unwrap();
+        // Same port so clients can probe the address they'd connect to.
+        let probes = match ProbeResponder::bind(addr) {
+            Ok(probes) => Some(probes),
+            Err(err) => {
+                dbg_logf!("failed to listen for probes on {}: {}", addr, err);
+                None
+            }
+        };
 
         let sg = ServerGame::new(&cvars, &mut engine, Box::new(listener)).await;
 
@@ -26,12 +41,48 @@ impl ServerProcess {
             clock: Instant::now(),
             engine,
             sg,
+            probes,
         }
     }
 
     pub(crate) fn update(&mut self) {
+        debug::details::timers_end_frame(&self.cvars);
+        if self.cvars.d_draw_timers {
+            // There's no screen on the dedicated server, let the clients draw it.
+            for line in debug::details::timers_text() {
+                dbg_textf!("{}", line);
+            }
+        }
+
+        if self.cvars.d_draw_asserts {
+            for line in debug::details::asserts_text() {
+                dbg_textf!("soft assert {}", line);
+            }
+        }
+
         let target = self.real_time();
         self.sg.update(&self.cvars, &mut self.engine, target);
+        if let Some(probes) = &mut self.probes {
+            probes.respond();
+        }
+
+        if self.sg.restart_requested {
+            self.restart();
+        }
+    }
+
+    /// Replace the process with the new binary, see `server::restart`.
+    fn restart(&mut self) {
+        self.sg.restart_requested = false;
+        if let Err(err) = self.sg.prepare_restart(&self.cvars, &mut self.engine) {
+            dbg_logf!("failed to prepare restart: {}", err);
+            return;
+        }
+        dbg_logf!("restarting");
+        let err = restart::exec();
+        dbg_logf!("failed to restart: {}", err);
+        // The old server keeps running, don't let a later start load outdated state.
+        let _ = fs::remove_file(&self.cvars.sv_restart_state_file);
     }
 
     pub(crate) fn real_time(&self) -> f32 {
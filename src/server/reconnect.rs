@@ -0,0 +1,92 @@
+//! Letting players who lost their connection continue where they left off - `sv_reconnect_time`.
+//!
+//! Every client gets a random token in `Init`. Tokens come from the OS's secure RNG
+//! so a client can't predict other players' tokens from its own.
+//! When a client disconnects, its score, team and whether it was playing are kept
+//! under its token for a while. When it connects again, it's a new player
+//! but it sends the old token and gets its state back.
+//!
+//! LATER Keep the player (and cycle) around instead so others don't see a leave and a join.
+
+use rand::{rngs::OsRng, RngCore};
+
+use crate::common::entities::{Player, PlayerState};
+
+/// What a disconnected player gets back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SavedPlayer {
+    pub(crate) score: u32,
+    pub(crate) assists: u32,
+    pub(crate) team: Option<u8>,
+    pub(crate) playing: bool,
+}
+
+pub(crate) struct Reconnects {
+    /// Token, game time of the disconnect and the player's state, oldest first.
+    saved: Vec<(u64, f32, SavedPlayer)>,
+}
+
+impl Reconnects {
+    pub(crate) fn new() -> Self {
+        Self { saved: Vec::new() }
+    }
+
+    /// Not from the gamelogic RNG, that one is predictable and depends on `d_seed`.
+    ///
+    /// Panics if the OS can't provide randomness, nothing sensible to do then.
+    pub(crate) fn token(&self) -> u64 {
+        OsRng.next_u64()
+    }
+
+    pub(crate) fn save(&mut self, token: u64, game_time: f32, player: &Player) {
+        let saved = SavedPlayer {
+            score: player.score,
+            assists: player.assists,
+            team: player.team,
+            playing: player.ps == PlayerState::Playing,
+        };
+        self.saved.push((token, game_time, saved));
+    }
+
+    /// Forget players who've been gone for more than `max_time` seconds.
+    pub(crate) fn expire(&mut self, game_time: f32, max_time: f32) {
+        self.saved.retain(|&(_, time, _)| game_time - time <= max_time);
+    }
+
+    pub(crate) fn is_saved(&self, token: u64) -> bool {
+        self.saved.iter().any(|&(saved_token, _, _)| saved_token == token)
+    }
+
+    /// Each token can only be used once.
+    pub(crate) fn take(&mut self, token: u64) -> Option<SavedPlayer> {
+        let i = self.saved.iter().position(|&(saved_token, _, _)| saved_token == token)?;
+        Some(self.saved.remove(i).2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnects() {
+        let mut reconnects = Reconnects::new();
+        let token1 = reconnects.token();
+        let token2 = reconnects.token();
+        assert_ne!(token1, token2);
+
+        let mut player = Player::new(None);
+        player.score = 5;
+        player.team = Some(1);
+        reconnects.save(token1, 10.0, &player);
+        reconnects.save(token2, 20.0, &player);
+
+        reconnects.expire(45.0, 30.0);
+        assert_eq!(reconnects.take(token1), None);
+        let saved = reconnects.take(token2).unwrap();
+        assert_eq!(saved.score, 5);
+        assert_eq!(saved.team, Some(1));
+        assert!(!saved.playing);
+        assert_eq!(reconnects.take(token2), None);
+    }
+}
@@ -0,0 +1,277 @@
+//! Live spectating - `sv_spectator_addr`.
+//!
+//! Everything the server sends to all clients is forwarded to read-only spectator connections,
+//! delayed by `sv_spectator_delay` so spectators can't tell players where their opponents are.
+//! Spectators don't count against `sv_max_players` and anything they send is ignored.
+//!
+//! Spectators that connect later first get the released messages which still describe the game
+//! (players and their looks and teams, live cycles, the match phase, ...) so they know who's in it.
+//! Messages which were replaced or are about players who left are dropped
+//! so this doesn't grow during a long match.
+//!
+//! LATER A client mode which starts from these messages instead of `Init`.
+//! LATER Relay to other relays to scale to many spectators.
+
+use std::{
+    collections::VecDeque,
+    io::{self, ErrorKind},
+    mem,
+    net::TcpListener,
+};
+
+use crate::{
+    common::{
+        messages::{
+            AddPlayer, NetHandle, PlayerCheats, PlayerCycle, PlayerFingerprint, PlayerLook,
+            PlayerTeam, ServerMessage,
+        },
+        net::{Connection, NetworkMessage, TcpConnection},
+    },
+    prelude::*,
+};
+
+pub(crate) struct SpectatorRelay {
+    listener: TcpListener,
+    spectators: Vec<TcpConnection>,
+    /// Ticks waiting for the delay to pass, oldest first.
+    delayed: VecDeque<DelayedTick>,
+    /// The tick being recorded.
+    current: DelayedTick,
+    /// Released messages for spectators who connect later.
+    setup: Setup,
+}
+
+#[derive(Default)]
+struct DelayedTick {
+    game_time: f32,
+    /// All messages with their headers.
+    msgs: Vec<u8>,
+    /// Messages which change `Setup`, each with its header.
+    setup: Vec<(SetupChange, Vec<u8>)>,
+}
+
+/// What a late spectator needs to know, in the order it was sent.
+///
+/// Only the latest message with each key is kept.
+#[derive(Debug, Default)]
+struct Setup {
+    msgs: Vec<(SetupKey, Vec<u8>)>,
+}
+
+/// How a message changes `Setup`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SetupChange {
+    /// Keep the message, replacing the previous one with the same key.
+    Keep(SetupKey),
+    /// Forget everything about the player, including their cycles.
+    RemovePlayer(NetHandle),
+    DespawnCycle(NetHandle),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SetupKey {
+    /// About the whole match, e.g. its phase.
+    Match(&'static str),
+    /// About one player, e.g. their look.
+    Player(NetHandle, &'static str),
+    Cycle {
+        player_handle: NetHandle,
+        cycle_handle: NetHandle,
+    },
+}
+
+impl SpectatorRelay {
+    pub(crate) fn bind(addr: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            spectators: Vec::new(),
+            delayed: VecDeque::new(),
+            current: DelayedTick::default(),
+            setup: Setup::default(),
+        })
+    }
+
+    /// Add a message sent to all clients during the current tick.
+    pub(crate) fn record(&mut self, msg: &ServerMessage, network_msg: &NetworkMessage) {
+        network_msg.write_frame(&mut self.current.msgs);
+        if let Some(change) = setup_change(msg) {
+            let mut frame = Vec::new();
+            network_msg.write_frame(&mut frame);
+            self.current.setup.push((change, frame));
+        }
+    }
+
+    /// Accept new spectators and send them ticks which are old enough.
+    pub(crate) fn end_tick(&mut self, cvars: &Cvars, game_time: f32) {
+        let mut tick = mem::take(&mut self.current);
+        tick.game_time = game_time;
+        self.delayed.push_back(tick);
+
+        self.accept();
+
+        // Spectators can't send anything useful but we need to notice when they leave.
+        self.spectators.retain_mut(|conn| {
+            let (_, closed) = conn.receive_cm();
+            if closed {
+                dbg_logf!("spectator {} disconnected", conn.addr);
+            }
+            !closed
+        });
+
+        while let Some(tick) = self.delayed.front() {
+            if tick.game_time > game_time - cvars.sv_spectator_delay {
+                break;
+            }
+            let tick = self.delayed.pop_front().unwrap();
+            for (change, frame) in tick.setup {
+                self.setup.apply(change, frame);
+            }
+            if tick.msgs.is_empty() {
+                continue;
+            }
+            self.spectators.retain_mut(|conn| match conn.send_raw(&tick.msgs) {
+                Ok(()) => true,
+                Err(err) => {
+                    dbg_logf!("spectator {} send error: {}", conn.addr, err);
+                    false
+                }
+            });
+        }
+    }
+
+    fn accept(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    if let Err(err) = stream.set_nodelay(true).and(stream.set_nonblocking(true)) {
+                        dbg_logf!("spectator {} setup error: {}", addr, err);
+                        continue;
+                    }
+                    let mut conn = TcpConnection::new(stream, addr);
+                    if let Err(err) = conn.send_raw(&self.setup.bytes()) {
+                        dbg_logf!("spectator {} send error: {}", addr, err);
+                        continue;
+                    }
+                    dbg_logf!("spectator {} connected", addr);
+                    self.spectators.push(conn);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    dbg_logf!("spectator accept error: {}", err);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Setup {
+    fn apply(&mut self, change: SetupChange, frame: Vec<u8>) {
+        match change {
+            SetupChange::Keep(key) => {
+                self.msgs.retain(|(k, _)| *k != key);
+                self.msgs.push((key, frame));
+            }
+            SetupChange::RemovePlayer(handle) => self.msgs.retain(|(key, _)| match *key {
+                SetupKey::Player(player_handle, _) | SetupKey::Cycle { player_handle, .. } => {
+                    player_handle != handle
+                }
+                SetupKey::Match(_) => true,
+            }),
+            SetupChange::DespawnCycle(handle) => self.msgs.retain(|(key, _)| {
+                !matches!(*key, SetupKey::Cycle { cycle_handle, .. } if cycle_handle == handle)
+            }),
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        self.msgs.iter().flat_map(|(_, frame)| frame).copied().collect()
+    }
+}
+
+/// `None` if late spectators don't need the message, e.g. updates, chat or kills.
+fn setup_change(msg: &ServerMessage) -> Option<SetupChange> {
+    let variant: &'static str = msg.into();
+    let change = match msg {
+        ServerMessage::AddPlayer(AddPlayer { player_handle, .. })
+        | ServerMessage::PlayerLook(PlayerLook { player_handle, .. })
+        | ServerMessage::PlayerFingerprint(PlayerFingerprint { player_handle, .. })
+        | ServerMessage::Ready { player_handle, .. }
+        | ServerMessage::PlayerTeam(PlayerTeam { player_handle, .. })
+        | ServerMessage::PlayerCheats(PlayerCheats { player_handle, .. }) => {
+            SetupChange::Keep(SetupKey::Player(*player_handle, variant))
+        }
+        // Only the latest of these is true.
+        ServerMessage::Observe { player_handle }
+        | ServerMessage::Spectate { player_handle, .. }
+        | ServerMessage::Join { player_handle } => {
+            SetupChange::Keep(SetupKey::Player(*player_handle, "state"))
+        }
+        ServerMessage::RemovePlayer { player_handle } => SetupChange::RemovePlayer(*player_handle),
+        ServerMessage::SpawnCycle(PlayerCycle {
+            player_handle,
+            cycle_handle,
+            ..
+        }) => SetupChange::Keep(SetupKey::Cycle {
+            player_handle: *player_handle,
+            cycle_handle: *cycle_handle,
+        }),
+        ServerMessage::DespawnCycle { cycle_handle } => SetupChange::DespawnCycle(*cycle_handle),
+        ServerMessage::MatchPhase(_)
+        | ServerMessage::CheatsAllowed(_)
+        | ServerMessage::Motd(_)
+        | ServerMessage::Scores(_) => SetupChange::Keep(SetupKey::Match(variant)),
+        ServerMessage::Init(_)
+        | ServerMessage::Chat(_)
+        | ServerMessage::Emote { .. }
+        | ServerMessage::OutOfBounds { .. }
+        | ServerMessage::Respawn { .. }
+        | ServerMessage::Ping(_)
+        | ServerMessage::Kill(_)
+        | ServerMessage::Pickup { .. }
+        | ServerMessage::MatchSummary(_)
+        | ServerMessage::Update(_)
+        | ServerMessage::Skipped { .. }
+        | ServerMessage::Debug(_)
+        | ServerMessage::Log(_)
+        | ServerMessage::DesyncCheck { .. } => return None,
+    };
+    Some(change)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle(index: u32) -> NetHandle {
+        NetHandle {
+            index,
+            generation: 1,
+        }
+    }
+
+    #[test]
+    fn test_setup() {
+        let mut setup = Setup::default();
+        let player = |index, kind| SetupChange::Keep(SetupKey::Player(handle(index), kind));
+        let cycle = |player_index, cycle_index| {
+            SetupChange::Keep(SetupKey::Cycle {
+                player_handle: handle(player_index),
+                cycle_handle: handle(cycle_index),
+            })
+        };
+        setup.apply(SetupChange::Keep(SetupKey::Match("MatchPhase")), vec![1]);
+        setup.apply(player(0, "AddPlayer"), vec![2]);
+        setup.apply(player(0, "PlayerLook"), vec![3]);
+        setup.apply(player(1, "AddPlayer"), vec![4]);
+        setup.apply(cycle(0, 0), vec![5]);
+        setup.apply(cycle(1, 1), vec![6]);
+        setup.apply(player(0, "PlayerLook"), vec![7]);
+        setup.apply(SetupChange::DespawnCycle(handle(0)), vec![]);
+        setup.apply(SetupChange::RemovePlayer(handle(1)), vec![]);
+        setup.apply(SetupChange::Keep(SetupKey::Match("MatchPhase")), vec![8]);
+        assert_eq!(setup.bytes(), vec![2, 7, 8]);
+    }
+}
@@ -0,0 +1,132 @@
+//! When dead players get a new cycle - `g_respawn`.
+//!
+//! Killed cycles are removed right away, the players keep playing without one
+//! until their respawn time. The game mode chooses how that time is picked,
+//! see `ServerHooks::respawn_mode`.
+//!
+//! Waves spawn every `g_respawn_wave_time` seconds, each one brings back
+//! everyone who died since the previous one.
+//! Pausing the match moves all respawn times by the pause's length, see `Respawns::delay`.
+
+use crate::{
+    common::entities::{Player, RespawnMode},
+    prelude::*,
+};
+
+#[derive(Debug, Default)]
+pub(crate) struct Respawns {
+    /// Players without a cycle, in the order they died.
+    waiting: Vec<Waiting>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Waiting {
+    player_handle: Handle<Player>,
+    /// Game time when the player gets a new cycle.
+    respawn: f32,
+}
+
+impl Respawns {
+    /// Returns how many seconds the player has to wait.
+    pub(crate) fn schedule(
+        &mut self,
+        cvars: &Cvars,
+        mode: RespawnMode,
+        game_time: f32,
+        player_handle: Handle<Player>,
+    ) -> f32 {
+        self.remove_player(player_handle);
+        let respawn = match mode {
+            RespawnMode::Instant => game_time,
+            RespawnMode::Timed => game_time + cvars.g_respawn_time,
+            RespawnMode::Wave => next_wave(cvars, game_time),
+        };
+        self.waiting.push(Waiting {
+            player_handle,
+            respawn,
+        });
+        respawn - game_time
+    }
+
+    /// Players whose time has come, they're no longer waiting.
+    pub(crate) fn due(&mut self, game_time: f32) -> Vec<Handle<Player>> {
+        let mut due = Vec::new();
+        self.waiting.retain(|waiting| {
+            if waiting.respawn <= game_time {
+                due.push(waiting.player_handle);
+                false
+            } else {
+                true
+            }
+        });
+        due
+    }
+
+    /// The player left or started observing.
+    pub(crate) fn remove_player(&mut self, player_handle: Handle<Player>) {
+        self.waiting.retain(|waiting| waiting.player_handle != player_handle);
+    }
+
+    /// The match was paused, nobody should lose or gain time because of it.
+    ///
+    /// Returns the players still waiting and how many seconds they have left.
+    pub(crate) fn delay(&mut self, by: f32, game_time: f32) -> Vec<(Handle<Player>, f32)> {
+        self.waiting
+            .iter_mut()
+            .map(|waiting| {
+                waiting.respawn += by;
+                (waiting.player_handle, waiting.respawn - game_time)
+            })
+            .collect()
+    }
+}
+
+/// The first wave after the death, it brings back everyone who died in the `g_respawn_wave_time`
+/// seconds before it.
+///
+/// A wave exactly at the time of death doesn't count, the player would have no time
+/// to see what killed them.
+fn next_wave(cvars: &Cvars, died: f32) -> f32 {
+    if cvars.g_respawn_wave_time <= 0.0 {
+        return died;
+    }
+    ((died / cvars.g_respawn_wave_time).floor() + 1.0) * cvars.g_respawn_wave_time
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule() {
+        let cvars = Cvars {
+            g_respawn_time: 3.0,
+            g_respawn_wave_time: 10.0,
+            ..Cvars::default()
+        };
+        let mut respawns = Respawns::default();
+        let a = Handle::new(1, 1);
+        let b = Handle::new(2, 1);
+        let c = Handle::new(3, 1);
+
+        assert_eq!(respawns.schedule(&cvars, RespawnMode::Instant, 5.0, a), 0.0);
+        assert_eq!(respawns.due(5.0), vec![a]);
+
+        assert_eq!(respawns.schedule(&cvars, RespawnMode::Timed, 5.0, a), 3.0);
+        assert!(respawns.due(7.0).is_empty());
+        assert_eq!(respawns.due(8.0), vec![a]);
+
+        // Everyone who died in the last 10 seconds spawns with the wave.
+        assert_eq!(respawns.schedule(&cvars, RespawnMode::Wave, 20.0, a), 10.0);
+        assert_eq!(respawns.schedule(&cvars, RespawnMode::Wave, 26.0, b), 4.0);
+        assert_eq!(respawns.schedule(&cvars, RespawnMode::Wave, 29.5, c), 0.5);
+        respawns.remove_player(c);
+        assert_eq!(respawns.due(30.0), vec![a, b]);
+        assert_eq!(respawns.schedule(&cvars, RespawnMode::Wave, 31.0, c), 9.0);
+
+        // Pausing moves the wave.
+        assert_eq!(respawns.delay(5.0, 45.0), vec![(c, 0.0)]);
+        assert!(respawns.due(44.0).is_empty());
+        assert_eq!(respawns.due(45.0), vec![c]);
+    }
+}
@@ -0,0 +1,69 @@
+//! Swapping in a new server binary without dropping players for long - `/restart_update`.
+//!
+//! The admin replaces the binary on disk and sends `/restart_update`.
+//! The server saves its state to `sv_restart_state_file` (same as `savestate`)
+//! and replaces its process with the new binary using the same command line.
+//! Connections drop, clients reconnect (`cl_reconnect_attempts`) and send the token
+//! from the old server's `Init` which gets them their state back, like `sv_reconnect_time`.
+//! The new server loads the file on startup and keeps it for `sv_reconnect_time`.
+//!
+//! LATER Hand over the listening socket and connections so clients don't notice at all.
+//! LATER The new server overwrites the demo being recorded (`sv_demo_record`).
+
+use std::{env, fs, io, process::Command};
+
+use crate::{prelude::*, server::savestate::SaveState};
+
+/// The state saved by the previous server, `None` if this is a normal start.
+///
+/// The file is deleted so it's only used once.
+pub(crate) fn load(cvars: &Cvars) -> Option<SaveState> {
+    let path = &cvars.sv_restart_state_file;
+    if path.is_empty() || fs::metadata(path).is_err() {
+        return None;
+    }
+    let res = SaveState::load(path);
+    if let Err(err) = fs::remove_file(path) {
+        dbg_logf!("failed to remove restart state {}: {}", path, err);
+    }
+    match res {
+        Ok(state) if state.map == cvars.g_map => {
+            dbg_logf!("restarted with {} players to restore", state.players.len());
+            Some(state)
+        }
+        Ok(state) => {
+            dbg_logf!("restart state is for map {}, ignoring it", state.map);
+            None
+        }
+        Err(err) => {
+            dbg_logf!("invalid restart state {}: {}", path, err);
+            None
+        }
+    }
+}
+
+/// Replace this process with the binary at the same path, only returns on failure.
+pub(crate) fn exec() -> io::Error {
+    // Not `current_exe` - on Linux it points to the old file after the binary is replaced.
+    let mut args = env::args_os();
+    let Some(program) = args.next() else {
+        return io::Error::new(io::ErrorKind::NotFound, "path to self is unknown");
+    };
+    let mut command = Command::new(program);
+    command.args(args);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Sockets are closed on exec so the new server can bind the same addresses.
+        command.exec()
+    }
+    #[cfg(not(unix))]
+    {
+        // LATER The old process might still hold the ports for a moment.
+        match command.spawn() {
+            Ok(_) => std::process::exit(0),
+            Err(err) => err,
+        }
+    }
+}
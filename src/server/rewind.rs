@@ -0,0 +1,160 @@
+//! Rewinding a local game to replay physics glitches - `rewind` and `resume`.
+//!
+//! The local server keeps `d_rewind_seconds` of snapshots of cycle physics and player inputs.
+//! `rewind <seconds>` restores a snapshot and pauses (`d_pause`)
+//! so the glitch can be watched again with `d_singlestep` or `resume`.
+//! Until the rewound period is over, the recorded inputs replace what players are doing
+//! so the same thing should happen again.
+//!
+//! Game time keeps going forward, only the state is restored, otherwise clients would get confused.
+//!
+//! LATER Projectiles, cycles which were spawned or despawned since, match phase, scores, RNG.
+
+use std::collections::VecDeque;
+
+use crate::{
+    common::{
+        entities::{Cycle, Player},
+        GameState, Input,
+    },
+    prelude::*,
+};
+
+#[derive(Debug, Default)]
+pub(crate) struct RewindBuffer {
+    /// Oldest first.
+    history: VecDeque<Snapshot>,
+    /// What happened after the snapshot we rewound to, its inputs are replayed. Oldest first.
+    replay: VecDeque<Snapshot>,
+}
+
+impl RewindBuffer {
+    /// Called each tick after receiving inputs, before gamelogic.
+    pub(crate) fn begin_tick(&mut self, cvars: &Cvars, gs: &mut GameState, scene: &Scene) {
+        if let Some(snapshot) = self.replay.pop_front() {
+            for &(player_handle, input) in &snapshot.inputs {
+                if let Some(player) = gs.players.try_borrow_mut(player_handle) {
+                    player.input = input;
+                }
+            }
+        }
+        self.push(cvars.d_rewind_seconds, Snapshot::new(gs, scene));
+    }
+
+    fn push(&mut self, seconds: f32, snapshot: Snapshot) {
+        let oldest = snapshot.game_time - seconds;
+        self.history.push_back(snapshot);
+        while self.history.front().map_or(false, |s| s.game_time < oldest) {
+            self.history.pop_front();
+        }
+    }
+
+    /// Remove and return the latest snapshot taken at or before `game_time`,
+    /// or the oldest one if there's none that old.
+    ///
+    /// Everything after it is queued for replay.
+    fn take_at(&mut self, game_time: f32) -> Option<Snapshot> {
+        let newer = self.history.partition_point(|s| s.game_time <= game_time);
+        let index = newer.saturating_sub(1);
+        self.replay = self.history.split_off(index);
+        self.replay.front().cloned()
+    }
+
+    /// Restore the state from `seconds` before `game_time`, returns how far back it actually went.
+    pub(crate) fn rewind(
+        &mut self,
+        gs: &mut GameState,
+        scene: &mut Scene,
+        seconds: f32,
+    ) -> Result<f32, String> {
+        let snapshot = self.take_at(gs.game_time - seconds).ok_or("nothing recorded yet")?;
+        snapshot.restore(gs, scene);
+        Ok(gs.game_time - snapshot.game_time)
+    }
+}
+
+/// State at the start of a tick and the inputs used during it.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    game_time: f32,
+    cycles: Vec<CycleSnapshot>,
+    inputs: Vec<(Handle<Player>, Input)>,
+}
+
+#[derive(Debug, Clone)]
+struct CycleSnapshot {
+    cycle_handle: Handle<Cycle>,
+    pos: Vec3,
+    rot: UnitQuaternion<f32>,
+    lin_vel: Vec3,
+    ang_vel: Vec3,
+}
+
+impl Snapshot {
+    fn new(gs: &GameState, scene: &Scene) -> Self {
+        let cycles = gs
+            .cycles
+            .pair_iter()
+            .map(|(cycle_handle, cycle)| {
+                let body = scene.graph[cycle.body_handle].as_rigid_body();
+                CycleSnapshot {
+                    cycle_handle,
+                    pos: **body.local_transform().position(),
+                    rot: **body.local_transform().rotation(),
+                    lin_vel: body.lin_vel(),
+                    ang_vel: body.ang_vel(),
+                }
+            })
+            .collect();
+        let inputs = gs
+            .players
+            .pair_iter()
+            .map(|(player_handle, player)| (player_handle, player.input))
+            .collect();
+        Self {
+            game_time: gs.game_time,
+            cycles,
+            inputs,
+        }
+    }
+
+    fn restore(&self, gs: &mut GameState, scene: &mut Scene) {
+        for cs in &self.cycles {
+            let Some(cycle) = gs.cycles.try_borrow(cs.cycle_handle) else {
+                continue;
+            };
+            let body = scene.graph[cycle.body_handle].as_rigid_body_mut();
+            body.local_transform_mut().set_position(cs.pos).set_rotation(cs.rot);
+            body.set_lin_vel(cs.lin_vel);
+            body.set_ang_vel(cs.ang_vel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_at() {
+        let mut buffer = RewindBuffer::default();
+        for i in 0..10 {
+            let snapshot = Snapshot {
+                game_time: i as f32,
+                cycles: Vec::new(),
+                inputs: Vec::new(),
+            };
+            buffer.push(5.0, snapshot);
+        }
+        assert_eq!(buffer.history.len(), 6);
+
+        assert_eq!(buffer.take_at(7.5).unwrap().game_time, 7.0);
+        assert_eq!(buffer.history.len(), 3);
+        assert_eq!(buffer.replay.len(), 3);
+
+        // Too far back
+        assert_eq!(buffer.take_at(0.0).unwrap().game_time, 4.0);
+        assert!(buffer.history.is_empty());
+        assert!(buffer.take_at(0.0).is_none());
+    }
+}
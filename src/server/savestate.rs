@@ -0,0 +1,179 @@
+//! Saving the server's game state to a file and loading it back - `savestate` and `loadstate`.
+//!
+//! Useful to get back to the moment before a rare bug without playing until it happens again.
+//! Loading matches saved players to connected ones by handle,
+//! players who aren't connected anymore are skipped.
+//!
+//! Like `rewind`, game time keeps going forward, deadlines are moved by the difference.
+//! Trails are drawn by clients from cycle positions so there's nothing to save.
+//!
+//! `/restart_update` uses the same format, see `server::restart`.
+//!
+//! LATER Projectiles, pickups and powerups, platforms, respawn timers, state kept by hooks.
+
+use std::fs;
+
+use rand_xoshiro::Xoshiro256PlusPlus;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{
+        entities::{Cheats, Cycle, MatchPhase, Player, PlayerState, VehicleKind},
+        GameState,
+    },
+    prelude::*,
+    server::teams::TeamTime,
+};
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub(crate) struct SaveState {
+    /// Loading on a different map makes no sense.
+    pub(crate) map: String,
+    pub(crate) game_time: f32,
+    pub(crate) phase: MatchPhase,
+    /// Loading continues exactly like the game would have without saving.
+    pub(crate) rng: Xoshiro256PlusPlus,
+    pub(crate) players: Vec<SavedPlayerState>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub(crate) struct SavedPlayerState {
+    pub(crate) index: u32,
+    pub(crate) generation: u32,
+    pub(crate) playing: bool,
+    /// `None` takes the player off their team when loading.
+    pub(crate) team: Option<u8>,
+    /// Game time when the player got on the team, see `TeamTime`.
+    pub(crate) team_time: Option<f32>,
+    pub(crate) score: u32,
+    pub(crate) assists: u32,
+    pub(crate) armor: u32,
+    pub(crate) cheats: Cheats,
+    pub(crate) vehicle: VehicleKind,
+    pub(crate) cycle: Option<SavedCycle>,
+    /// From the player's `Init`, lets them get their state back after `/restart_update`.
+    /// `None` for bots.
+    pub(crate) reconnect_token: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub(crate) struct SavedCycle {
+    pub(crate) vehicle: VehicleKind,
+    pub(crate) pos: Vec3,
+    pub(crate) rot: UnitQuaternion<f32>,
+    pub(crate) lin_vel: Vec3,
+    pub(crate) ang_vel: Vec3,
+}
+
+impl SaveState {
+    pub(crate) fn new(cvars: &Cvars, gs: &GameState, scene: &Scene) -> Self {
+        let players = gs
+            .players
+            .pair_iter()
+            .map(|(player_handle, player)| SavedPlayerState {
+                index: player_handle.index(),
+                generation: player_handle.generation(),
+                playing: player.ps == PlayerState::Playing,
+                team: player.team,
+                team_time: gs
+                    .player_components
+                    .get::<TeamTime>(player_handle)
+                    .map(|&TeamTime(time)| time),
+                score: player.score,
+                assists: player.assists,
+                armor: player.armor,
+                cheats: player.cheats,
+                vehicle: player.vehicle,
+                cycle: player
+                    .cycle_handle
+                    .map(|cycle_handle| SavedCycle::new(&gs.cycles[cycle_handle], scene)),
+                // Only the server knows them, see `ServerGame::savestate`.
+                reconnect_token: None,
+            })
+            .collect();
+        Self {
+            map: cvars.g_map.clone(),
+            game_time: gs.game_time,
+            phase: gs.phase,
+            rng: gs.rng.clone(),
+            players,
+        }
+    }
+
+    pub(crate) fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        ron::from_str(&text).map_err(|err| err.to_string())
+    }
+
+    pub(crate) fn save(&self, path: &str) -> Result<(), String> {
+        let text =
+            ron::ser::to_string_pretty(self, Default::default()).map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| err.to_string())
+    }
+}
+
+impl SavedPlayerState {
+    pub(crate) fn handle(&self) -> Handle<Player> {
+        Handle::new(self.index, self.generation)
+    }
+}
+
+impl SavedCycle {
+    fn new(cycle: &Cycle, scene: &Scene) -> Self {
+        let body = scene.graph[cycle.body_handle].as_rigid_body();
+        Self {
+            vehicle: cycle.vehicle,
+            pos: **body.local_transform().position(),
+            rot: **body.local_transform().rotation(),
+            lin_vel: body.lin_vel(),
+            ang_vel: body.ang_vel(),
+        }
+    }
+
+    pub(crate) fn restore(&self, cycle: &Cycle, scene: &mut Scene) {
+        let body = scene.graph[cycle.body_handle].as_rigid_body_mut();
+        body.local_transform_mut().set_position(self.pos).set_rotation(self.rot);
+        body.set_lin_vel(self.lin_vel);
+        body.set_ang_vel(self.ang_vel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_save_state_ron() {
+        let state = SaveState {
+            map: "arena".to_owned(),
+            game_time: 12.5,
+            phase: MatchPhase::Live { end: Some(300.0) },
+            rng: Xoshiro256PlusPlus::seed_from_u64(42),
+            players: vec![SavedPlayerState {
+                index: 1,
+                generation: 3,
+                playing: true,
+                team: Some(0),
+                team_time: Some(2.0),
+                score: 7,
+                assists: 2,
+                armor: 1,
+                cheats: Cheats::default(),
+                vehicle: VehicleKind::Fast,
+                cycle: Some(SavedCycle {
+                    vehicle: VehicleKind::Fast,
+                    pos: v!(1 2 3),
+                    rot: UnitQuaternion::identity(),
+                    lin_vel: v!(0 0 10),
+                    ang_vel: v!(0 0 0),
+                }),
+                reconnect_token: Some(123),
+            }],
+        };
+        let text = ron::ser::to_string(&state).unwrap();
+        assert_eq!(ron::from_str::<SaveState>(&text).unwrap(), state);
+        assert_eq!(state.players[0].handle(), Handle::new(1, 3));
+    }
+}
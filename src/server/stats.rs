@@ -0,0 +1,175 @@
+//! Per-round statistics and awards, sent to clients as a summary when the round ends.
+//!
+//! Collected by a hook so it sees the same events as the game mode.
+//! Finished summaries are left in a shared queue for the server to send.
+//!
+//! LATER Keep the summary on screen until the next map loads once there's map rotation.
+
+use std::{cell::RefCell, rc::Rc};
+
+use fxhash::FxHashMap;
+
+use crate::{
+    common::{
+        entities::{Kill, Player},
+        messages::{Award, AwardKind, MatchSummary, PlayerSummary},
+    },
+    prelude::*,
+    server::hooks::{HookCtx, ServerHooks},
+};
+
+/// Fewer shots than this don't count for the accuracy award.
+const ACCURACY_MIN_SHOTS: u32 = 10;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PlayerStats {
+    frags: u32,
+    deaths: u32,
+    shots: u32,
+    hits: u32,
+    /// Frags since the last death.
+    streak: u32,
+    best_streak: u32,
+}
+
+pub(crate) struct MatchStats {
+    players: FxHashMap<Handle<Player>, PlayerStats>,
+    /// Summaries of rounds which ended, oldest first.
+    finished: Rc<RefCell<Vec<MatchSummary>>>,
+}
+
+impl MatchStats {
+    pub(crate) fn new(finished: Rc<RefCell<Vec<MatchSummary>>>) -> Self {
+        Self {
+            players: FxHashMap::default(),
+            finished,
+        }
+    }
+}
+
+impl ServerHooks for MatchStats {
+    fn on_player_leave(&mut self, _ctx: &mut HookCtx, player_handle: Handle<Player>) {
+        self.players.remove(&player_handle);
+    }
+
+    fn on_kill(&mut self, _ctx: &mut HookCtx, kill: Kill) {
+        let victim = self.players.entry(kill.victim).or_default();
+        victim.deaths += 1;
+        victim.streak = 0;
+        if kill.killer != kill.victim {
+            let killer = self.players.entry(kill.killer).or_default();
+            killer.frags += 1;
+            killer.streak += 1;
+            killer.best_streak = killer.best_streak.max(killer.streak);
+        }
+    }
+
+    fn on_match_start(&mut self, _ctx: &mut HookCtx) {
+        // Forget warmup.
+        self.players.clear();
+    }
+
+    fn on_round_end(&mut self, ctx: &mut HookCtx, winner: Option<Handle<Player>>) {
+        let players: Vec<_> = ctx
+            .gs
+            .players
+            .pair_iter()
+            .map(|(handle, _)| {
+                let stats = self.players.get(&handle).copied().unwrap_or_default();
+                PlayerSummary {
+                    player_index: handle.index(),
+                    frags: stats.frags,
+                    deaths: stats.deaths,
+                    shots: stats.shots,
+                    hits: stats.hits,
+                    best_streak: stats.best_streak,
+                }
+            })
+            .collect();
+        let summary = MatchSummary {
+            winner_index: winner.map(|handle| handle.index()),
+            awards: awards(&players),
+            players,
+        };
+        self.finished.borrow_mut().push(summary);
+        self.players.clear();
+    }
+
+    fn on_tick(&mut self, ctx: &mut HookCtx) {
+        for &player_handle in &ctx.gs.shots {
+            self.players.entry(player_handle).or_default().shots += 1;
+        }
+        for hit in &ctx.gs.hits {
+            self.players.entry(hit.attacker).or_default().hits += 1;
+        }
+    }
+}
+
+/// The best player in each category, if anybody qualifies. Ties go to the lowest index.
+fn awards(players: &[PlayerSummary]) -> Vec<Award> {
+    let mut awards = Vec::new();
+    let mut award = |kind, value: fn(&PlayerSummary) -> Option<f32>| {
+        let mut best: Option<(f32, u32)> = None;
+        for player in players {
+            if let Some(value) = value(player).filter(|&value| value > 0.0) {
+                if best.map_or(true, |(best, _)| value > best) {
+                    best = Some((value, player.player_index));
+                }
+            }
+        }
+        if let Some((_, player_index)) = best {
+            awards.push(Award { kind, player_index });
+        }
+    };
+    award(AwardKind::MostFrags, |p| Some(p.frags as f32));
+    award(AwardKind::BestAccuracy, |p| {
+        if p.shots >= ACCURACY_MIN_SHOTS {
+            Some(p.hits as f32 / p.shots as f32)
+        } else {
+            None
+        }
+    });
+    award(AwardKind::LongestStreak, |p| Some(p.best_streak as f32));
+    awards
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_awards() {
+        let player = |player_index, frags, shots, hits, best_streak| PlayerSummary {
+            player_index,
+            frags,
+            deaths: 0,
+            shots,
+            hits,
+            best_streak,
+        };
+        let players = [
+            player(0, 5, 100, 10, 2),
+            player(1, 5, 20, 10, 3),
+            player(2, 1, 5, 5, 1),
+        ];
+        assert_eq!(
+            awards(&players),
+            vec![
+                Award {
+                    kind: AwardKind::MostFrags,
+                    player_index: 0
+                },
+                Award {
+                    kind: AwardKind::BestAccuracy,
+                    player_index: 1
+                },
+                Award {
+                    kind: AwardKind::LongestStreak,
+                    player_index: 1
+                },
+            ]
+        );
+
+        assert!(awards(&[player(0, 0, 0, 0, 0)]).is_empty());
+    }
+}
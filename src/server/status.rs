@@ -0,0 +1,262 @@
+//! Server status over HTTP - `sv_status_addr`.
+//!
+//! `GET /status` returns JSON with the map, region, players, scores, uptime and tickrate
+//! so hosting panels and bots can monitor servers without speaking the game protocol.
+//! `GET /metrics` returns `server::metrics` for Prometheus.
+//! Requests are handled on the main thread between ticks, the server never blocks on them.
+//!
+//! LATER Player names once there are any.
+
+use std::{
+    fmt::Write,
+    io::{self, ErrorKind, Read, Write as _},
+    mem,
+    net::{SocketAddr, TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    common::{
+        entities::{MatchPhase, PlayerState, TEAM_NAMES},
+        json::json_string,
+        GameState,
+    },
+    prelude::*,
+    server::metrics::Metrics,
+};
+
+/// Same as in `ServerGame::update`.
+const TICKRATE: f32 = 60.0;
+
+/// Connections which don't send a whole request and take the response in time are dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Longer requests are dropped, we only need the first line anyway.
+const REQUEST_MAX_LEN: usize = 4096;
+
+/// Connections above this are closed right away so nobody can use up the server's sockets.
+/// Also the most connections accepted per tick.
+const PENDING_MAX: usize = 16;
+
+pub(crate) struct StatusServer {
+    listener: TcpListener,
+    /// Connections which haven't sent the whole request yet.
+    pending: Vec<PendingRequest>,
+    started: Instant,
+    /// Start of the second during which ticks are being counted.
+    window_start: Instant,
+    window_ticks: u32,
+    /// Ticks during the last whole second.
+    tickrate: f32,
+}
+
+struct PendingRequest {
+    stream: TcpStream,
+    addr: SocketAddr,
+    accepted: Instant,
+    buf: Vec<u8>,
+    /// What's left of the response once the whole request arrived.
+    out: Option<Vec<u8>>,
+}
+
+impl StatusServer {
+    pub(crate) fn bind(addr: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let now = Instant::now();
+        Ok(Self {
+            listener,
+            pending: Vec::new(),
+            started: now,
+            window_start: now,
+            window_ticks: 0,
+            tickrate: TICKRATE,
+        })
+    }
+
+    /// Measure the tickrate and answer requests which arrived so far.
+    pub(crate) fn end_tick(
+        &mut self,
+        cvars: &Cvars,
+        gs: &GameState,
+        metrics: &Metrics,
+        players: u32,
+    ) {
+        let now = Instant::now();
+        self.window_ticks += 1;
+        let elapsed = now - self.window_start;
+        if elapsed >= Duration::from_secs(1) {
+            self.tickrate = self.window_ticks as f32 / elapsed.as_secs_f32();
+            self.window_start = now;
+            self.window_ticks = 0;
+        }
+
+        self.accept(now);
+
+        let mut pending = mem::take(&mut self.pending);
+        pending.retain_mut(|req| {
+            if req.out.is_none() {
+                let mut chunk = [0; 1024];
+                loop {
+                    match req.stream.read(&mut chunk) {
+                        Ok(0) => return false,
+                        Ok(len) => req.buf.extend_from_slice(&chunk[..len]),
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                        Err(err) => {
+                            dbg_logf!("status request from {} read error: {}", req.addr, err);
+                            return false;
+                        }
+                    }
+                }
+                if req.buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let response = self.response(cvars, gs, metrics, players, &req.buf);
+                    req.out = Some(response.into_bytes());
+                }
+            }
+            if let Some(out) = &mut req.out {
+                // The socket is nonblocking so it might only take part of the response,
+                // the rest is written next tick.
+                while !out.is_empty() {
+                    match req.stream.write(out) {
+                        Ok(0) => return false,
+                        Ok(len) => {
+                            out.drain(..len);
+                        }
+                        Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                        Err(err) => {
+                            dbg_logf!("status request from {} write error: {}", req.addr, err);
+                            return false;
+                        }
+                    }
+                }
+                if out.is_empty() {
+                    return false;
+                }
+            }
+            req.buf.len() <= REQUEST_MAX_LEN && now - req.accepted < REQUEST_TIMEOUT
+        });
+        self.pending = pending;
+    }
+
+    fn accept(&mut self, now: Instant) {
+        for _ in 0..PENDING_MAX {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    if self.pending.len() >= PENDING_MAX {
+                        dbg_logf!("status request from {} dropped, too many pending", addr);
+                        continue;
+                    }
+                    if let Err(err) = stream.set_nonblocking(true) {
+                        dbg_logf!("status request from {} setup error: {}", addr, err);
+                        continue;
+                    }
+                    self.pending.push(PendingRequest {
+                        stream,
+                        addr,
+                        accepted: now,
+                        buf: Vec::new(),
+                        out: None,
+                    });
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    dbg_logf!("status accept error: {}", err);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn response(
+        &self,
+        cvars: &Cvars,
+        gs: &GameState,
+        metrics: &Metrics,
+        players: u32,
+        request: &[u8],
+    ) -> String {
+        let request = String::from_utf8_lossy(request);
+        let mut parts = request.split_whitespace();
+        let (status, content_type, body) = match (parts.next(), parts.next()) {
+            (Some("GET"), Some("/status")) => {
+                ("200 OK", "application/json", self.status_json(cvars, gs))
+            }
+            (Some("GET"), Some("/metrics")) => {
+                ("200 OK", "text/plain; version=0.0.4", metrics.text(players))
+            }
+            (Some("GET"), _) => ("404 Not Found", "text/plain", "not found\n".to_owned()),
+            _ => ("405 Method Not Allowed", "text/plain", "only GET\n".to_owned()),
+        };
+        format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        )
+    }
+
+    fn status_json(&self, cvars: &Cvars, gs: &GameState) -> String {
+        let phase = match gs.phase {
+            MatchPhase::Warmup { .. } => "warmup",
+            MatchPhase::Live { .. } => "live",
+            MatchPhase::Overtime { .. } => "overtime",
+            MatchPhase::Paused { .. } => "paused",
+        };
+
+        let mut json = String::new();
+        write!(
+            json,
+            r#"{{"map":{},"region":{},"phase":"{}","max_players":{},"#,
+            json_string(&cvars.g_map),
+            json_string(&cvars.sv_region),
+            phase,
+            cvars.sv_max_players,
+        )
+        .unwrap();
+        write!(
+            json,
+            r#""uptime":{:.0},"game_time":{:.1},"#,
+            self.started.elapsed().as_secs_f32(),
+            gs.game_time,
+        )
+        .unwrap();
+        write!(
+            json,
+            r#""tickrate":{:.1},"tickrate_target":{:.1},"players":["#,
+            self.tickrate, TICKRATE,
+        )
+        .unwrap();
+        for (i, (player_handle, player)) in gs.players.pair_iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let state = match player.ps {
+                PlayerState::Observing => "observing",
+                PlayerState::Spectating { .. } => "spectating",
+                PlayerState::Playing => "playing",
+            };
+            let team = match player.team.and_then(|team| TEAM_NAMES.get(usize::from(team))) {
+                Some(name) => json_string(name),
+                None => "null".to_owned(),
+            };
+            let ping = match player.ping {
+                Some(ping) => ping.to_string(),
+                None => "null".to_owned(),
+            };
+            write!(
+                json,
+                r#"{{"index":{},"state":"{}","team":{},"score":{},"assists":{},"ping":{}}}"#,
+                player_handle.index(),
+                state,
+                team,
+                player.score,
+                player.assists,
+                ping,
+            )
+            .unwrap();
+        }
+        json.push_str("]}");
+        json
+    }
+}
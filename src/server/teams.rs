@@ -0,0 +1,142 @@
+//! Team assignment and balancing - `g_teams`.
+//!
+//! New players join the smallest team. Players can switch using the `team` console command
+//! unless it would leave their new team 2 or more players larger than another.
+//! With `g_autobalance_interval`, players are also moved automatically when teams get uneven.
+//! Whoever has been on a team the longest is its captain, bots can't be captains.
+//!
+//! LATER Team modes, for now teams only decide colors.
+
+use crate::{
+    common::{
+        entities::{Player, TEAM_NAMES},
+        GameState,
+    },
+    prelude::*,
+};
+
+/// `g_teams` limited to the number of team names.
+fn team_count(cvars: &Cvars) -> usize {
+    cvars.g_teams.min(TEAM_NAMES.len())
+}
+
+/// Number of players on each team.
+///
+/// Players on a team which no longer exists because `g_teams` changed aren't counted.
+pub(crate) fn team_sizes(cvars: &Cvars, gs: &GameState) -> Vec<usize> {
+    let mut sizes = vec![0; team_count(cvars)];
+    for player in &gs.players {
+        if let Some(size) = player.team.and_then(|team| sizes.get_mut(usize::from(team))) {
+            *size += 1;
+        }
+    }
+    sizes
+}
+
+/// The team with the fewest players, the first one if there's a tie. `None` without teams.
+pub(crate) fn smallest(sizes: &[usize]) -> Option<u8> {
+    sizes
+        .iter()
+        .enumerate()
+        .min_by_key(|&(_, &size)| size)
+        .map(|(team, _)| team as u8)
+}
+
+/// Game time when the player got on their current team, see `g_autobalance_protect`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TeamTime(pub(crate) f32);
+
+/// 0 if the player was never moved to a team.
+fn team_time(gs: &GameState, player_handle: Handle<Player>) -> f32 {
+    gs.player_components
+        .get::<TeamTime>(player_handle)
+        .map_or(0.0, |&TeamTime(time)| time)
+}
+
+/// The player who has been on the team the longest, bots are skipped
+/// because nobody could `/unpause` for them.
+pub(crate) fn captain(gs: &GameState, team: u8, bots: &[Handle<Player>]) -> Option<Handle<Player>> {
+    gs.players
+        .pair_iter()
+        .filter(|(player_handle, player)| {
+            player.team == Some(team) && !bots.contains(player_handle)
+        })
+        .map(|(player_handle, _)| player_handle)
+        .min_by(|&a, &b| team_time(gs, a).total_cmp(&team_time(gs, b)))
+}
+
+/// Check whether the player can switch to `team`, the error says why not.
+pub(crate) fn check_switch(
+    cvars: &Cvars,
+    gs: &GameState,
+    player_handle: Handle<Player>,
+    team: u8,
+) -> Result<(), &'static str> {
+    let sizes = team_sizes(cvars, gs);
+    let from = gs.players[player_handle].team;
+    if usize::from(team) >= sizes.len() {
+        Err("no such team")
+    } else if from == Some(team) {
+        Err("already on that team")
+    } else if !switch_allowed(&sizes, from, team) {
+        Err("that team has too many players")
+    } else {
+        Ok(())
+    }
+}
+
+/// After the switch, the new team can have at most one player more than the smallest one.
+fn switch_allowed(sizes: &[usize], from: Option<u8>, to: u8) -> bool {
+    let mut sizes = sizes.to_vec();
+    if let Some(size) = from.and_then(|from| sizes.get_mut(usize::from(from))) {
+        *size -= 1;
+    }
+    sizes[usize::from(to)] += 1;
+    let min = sizes.iter().copied().min().unwrap_or(0);
+    sizes[usize::from(to)] <= min + 1
+}
+
+/// Pick a player to move to the smallest team if the largest one has 2 or more players more.
+///
+/// Players who changed teams in the last `g_autobalance_protect` seconds are left alone.
+/// Of the rest, whoever joined the team last is moved.
+pub(crate) fn autobalance(cvars: &Cvars, gs: &GameState) -> Option<(Handle<Player>, u8)> {
+    let (from, to) = imbalance(&team_sizes(cvars, gs))?;
+    gs.players
+        .pair_iter()
+        .filter(|(_, player)| player.team == Some(from))
+        .map(|(player_handle, _)| player_handle)
+        .filter(|&player_handle| {
+            gs.game_time - team_time(gs, player_handle) >= cvars.g_autobalance_protect
+        })
+        .max_by(|&a, &b| team_time(gs, a).total_cmp(&team_time(gs, b)))
+        .map(|player_handle| (player_handle, to))
+}
+
+/// The largest and the smallest team if they differ by 2 or more players.
+fn imbalance(sizes: &[usize]) -> Option<(u8, u8)> {
+    let (from, &max) = sizes.iter().enumerate().max_by_key(|&(_, &size)| size)?;
+    let (to, &min) = sizes.iter().enumerate().min_by_key(|&(_, &size)| size)?;
+    (max >= min + 2).then_some((from as u8, to as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balance() {
+        assert_eq!(smallest(&[]), None);
+        assert_eq!(smallest(&[2, 1, 1]), Some(1));
+
+        assert!(switch_allowed(&[1, 1], None, 0));
+        assert!(!switch_allowed(&[2, 1], None, 0));
+        assert!(switch_allowed(&[3, 1], Some(0), 1));
+        assert!(!switch_allowed(&[3, 1], Some(1), 0));
+        assert!(!switch_allowed(&[2, 2], Some(0), 1));
+
+        assert_eq!(imbalance(&[]), None);
+        assert_eq!(imbalance(&[2, 1]), None);
+        assert_eq!(imbalance(&[1, 3, 2]), Some((1, 0)));
+    }
+}
@@ -0,0 +1,82 @@
+//! Map triggers - jump pads, teleporters and kill volumes, see `map::Trigger`.
+//!
+//! Only the server checks them, clients get the results through normal updates
+//! and show effects the server sends them.
+//! A trigger only fires when a cycle enters it, not every tick while it's inside.
+//!
+//! LATER Trigger shapes other than boxes, triggers for projectiles.
+
+use fxhash::FxHashMap;
+
+use crate::{
+    common::{
+        entities::{Cycle, Effect},
+        map::{Trigger, TriggerKind},
+        GameState,
+    },
+    prelude::*,
+};
+
+#[derive(Debug, Default)]
+pub(crate) struct Triggers {
+    /// Which trigger each cycle was in last tick, index into `MapManifest::triggers`.
+    inside: FxHashMap<Handle<Cycle>, usize>,
+}
+
+impl Triggers {
+    /// Fire triggers cycles entered, after gamelogic and before physics.
+    pub(crate) fn tick(&mut self, cvars: &Cvars, gs: &mut GameState, scene: &mut Scene) {
+        for trigger in &gs.map.triggers {
+            dbg_box!(trigger.center(), trigger.half_extents());
+        }
+
+        self.inside.retain(|&handle, _| gs.cycles.is_valid_handle(handle));
+        let cycle_handles: Vec<_> = gs.cycles.pair_iter().map(|(handle, _)| handle).collect();
+        for cycle_handle in cycle_handles {
+            let cycle = &gs.cycles[cycle_handle];
+            let player_handle = cycle.player_handle;
+            let body_handle = cycle.body_handle;
+            let pos = **scene.graph[body_handle].local_transform().position();
+
+            // Only the first one so overlapping triggers don't fight over the cycle.
+            let Some(index) = find(&gs.map.triggers, pos) else {
+                self.inside.remove(&cycle_handle);
+                continue;
+            };
+            if self.inside.insert(cycle_handle, index) == Some(index) {
+                continue;
+            }
+
+            match gs.map.triggers[index].kind {
+                TriggerKind::JumpPad { velocity } => {
+                    let body = scene.graph[body_handle].as_rigid_body_mut();
+                    body.set_lin_vel(Vec3::from(velocity));
+                    gs.effects.push(Effect::JumpPad { pos });
+                }
+                TriggerKind::Teleporter { destination } => {
+                    let to = Vec3::from(destination);
+                    let body = scene.graph[body_handle].as_rigid_body_mut();
+                    body.local_transform_mut().set_position(to);
+                    gs.effects.push(Effect::Teleport { from: pos, to });
+                    // Arriving inside another trigger doesn't count as entering it
+                    // so badly placed teleporter pairs don't bounce cycles back and forth.
+                    match find(&gs.map.triggers, to) {
+                        Some(index) => self.inside.insert(cycle_handle, index),
+                        None => self.inside.remove(&cycle_handle),
+                    };
+                }
+                TriggerKind::Kill => {
+                    if gs.players[player_handle].cheats.god {
+                        continue;
+                    }
+                    gs.kill_by_map(player_handle, pos);
+                    self.inside.remove(&cycle_handle);
+                }
+            }
+        }
+    }
+}
+
+fn find(triggers: &[Trigger], pos: Vec3) -> Option<usize> {
+    triggers.iter().position(|trigger| trigger.contains(pos))
+}
@@ -0,0 +1,285 @@
+//! Uploading demos and stats after each round - `sv_upload_url`.
+//!
+//! When a round ends, the demo being recorded (`sv_demo_record`) is finished
+//! and the next round is recorded to a new file (see `demo::round_path`)
+//! so each upload contains one round and no recording is overwritten.
+//! The demo and the round's summary as JSON are sent in one `multipart/form-data` POST
+//! from a background thread which also reads the demo so large files don't stall ticks.
+//! Failed uploads are retried with increasing delays. Results are logged on the main thread.
+//!
+//! LATER HTTPS, needs a TLS dependency.
+
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{Read, Write},
+    mem,
+    net::{TcpStream, ToSocketAddrs},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    common::{json::json_string, messages::MatchSummary},
+    prelude::*,
+};
+
+/// Uploads which fail this many times in a row are dropped.
+const MAX_ATTEMPTS: u32 = 4;
+
+/// Doubled after each failed attempt.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Applies to connecting to each address, sending and waiting for the response separately.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// New rounds aren't uploaded while this many uploads are still running.
+const MAX_RUNNING: usize = 4;
+
+const BOUNDARY: &str = "rustcycles-upload-8d3f0c5a";
+
+pub(crate) struct Uploads {
+    /// Summaries of rounds which ended this tick, uploaded once the tick is in the demo.
+    pending: Vec<String>,
+    running: usize,
+    sender: Sender<Result<String, String>>,
+    receiver: Receiver<Result<String, String>>,
+}
+
+impl Uploads {
+    pub(crate) fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            pending: Vec::new(),
+            running: 0,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Remember a round's stats until its demo is finished.
+    pub(crate) fn queue(&mut self, cvars: &Cvars, summary: &MatchSummary) {
+        if !cvars.sv_upload_url.is_empty() {
+            self.pending.push(summary_json(cvars, summary));
+        }
+    }
+
+    pub(crate) fn take_pending(&mut self) -> Vec<String> {
+        mem::take(&mut self.pending)
+    }
+
+    /// Upload in the background, `demo_path` is `None` if there's no demo.
+    pub(crate) fn start(&mut self, cvars: &Cvars, stats: String, demo_path: Option<String>) {
+        if self.running >= MAX_RUNNING {
+            dbg_logf!("skipping upload, {} uploads still running", self.running);
+            return;
+        }
+        let (host, url_path) = match parse_url(&cvars.sv_upload_url) {
+            Ok(parts) => parts,
+            Err(err) => {
+                dbg_logf!("invalid sv_upload_url {}: {}", cvars.sv_upload_url, err);
+                return;
+            }
+        };
+        let max_size = cvars.sv_upload_max_size;
+        let sender = self.sender.clone();
+        self.running += 1;
+        thread::spawn(move || {
+            let (demo, note) = match demo_path.map(|path| read_demo(&path, max_size)) {
+                Some(Ok(demo)) => (Some(demo), String::new()),
+                Some(Err(err)) => (None, format!(" (only stats, {})", err)),
+                None => (None, String::new()),
+            };
+            let request = request(&host, &url_path, &stats, demo.as_deref());
+            drop(demo);
+
+            let mut delay = RETRY_DELAY;
+            let mut attempt = 1;
+            let res = loop {
+                match send(&host, &request) {
+                    Ok(status) => {
+                        break Ok(format!("uploaded round to {}{}: {}", host, note, status));
+                    }
+                    Err(err) if attempt >= MAX_ATTEMPTS => {
+                        break Err(format!("failed to upload round to {}: {}", host, err));
+                    }
+                    Err(_) => {
+                        thread::sleep(delay);
+                        delay *= 2;
+                        attempt += 1;
+                    }
+                }
+            };
+            // The server might be shutting down, nobody to tell.
+            let _ = sender.send(res);
+        });
+    }
+
+    /// Log results of uploads which finished since the last call.
+    pub(crate) fn poll(&mut self) {
+        while let Ok(res) = self.receiver.try_recv() {
+            self.running -= 1;
+            match res {
+                Ok(msg) | Err(msg) => dbg_logf!("{}", msg),
+            }
+        }
+    }
+}
+
+/// The finished demo, an error if it can't be read or it's larger than `sv_upload_max_size`.
+fn read_demo(path: &str, max_size: usize) -> Result<Vec<u8>, String> {
+    let metadata =
+        fs::metadata(path).map_err(|err| format!("failed to read demo {}: {}", path, err))?;
+    if metadata.len() > max_size as u64 {
+        return Err(format!("demo {} is over sv_upload_max_size", path));
+    }
+    fs::read(path).map_err(|err| format!("failed to read demo {}: {}", path, err))
+}
+
+fn summary_json(cvars: &Cvars, summary: &MatchSummary) -> String {
+    let winner = summary.winner_index.map_or("null".to_owned(), |index| index.to_string());
+    let mut json = String::new();
+    write!(
+        json,
+        r#"{{"server":{},"map":{},"winner":{},"players":["#,
+        json_string(&cvars.sv_name),
+        json_string(&cvars.g_map),
+        winner,
+    )
+    .unwrap();
+    for (i, player) in summary.players.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        write!(
+            json,
+            r#"{{"index":{},"frags":{},"deaths":{},"shots":{},"hits":{},"best_streak":{}}}"#,
+            player.player_index,
+            player.frags,
+            player.deaths,
+            player.shots,
+            player.hits,
+            player.best_streak,
+        )
+        .unwrap();
+    }
+    json.push_str(r#"],"awards":["#);
+    for (i, award) in summary.awards.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        let kind = award.kind.key().trim_start_matches("award.");
+        write!(json, r#"{{"kind":"{}","player":{}}}"#, kind, award.player_index).unwrap();
+    }
+    json.push_str("]}");
+    json
+}
+
+/// Split `http://host[:port]/path` into the address to connect to and the path.
+fn parse_url(url: &str) -> Result<(String, String), String> {
+    let rest = url.strip_prefix("http://").ok_or("only http:// is supported")?;
+    let (host, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+    if host.is_empty() {
+        return Err("missing host".to_owned());
+    }
+    let host = if host.contains(':') {
+        host.to_owned()
+    } else {
+        format!("{}:80", host)
+    };
+    Ok((host, path.to_owned()))
+}
+
+fn request(host: &str, path: &str, stats: &str, demo: Option<&[u8]>) -> Vec<u8> {
+    let mut body = Vec::new();
+    write!(
+        body,
+        "--{}\r\nContent-Disposition: form-data; name=\"stats\"; filename=\"stats.json\"\r\n\
+        Content-Type: application/json\r\n\r\n{}\r\n",
+        BOUNDARY, stats
+    )
+    .unwrap();
+    if let Some(demo) = demo {
+        write!(
+            body,
+            "--{}\r\nContent-Disposition: form-data; name=\"demo\"; filename=\"round.rcdemo\"\r\n\
+            Content-Type: application/octet-stream\r\n\r\n",
+            BOUNDARY
+        )
+        .unwrap();
+        body.extend_from_slice(demo);
+        body.extend_from_slice(b"\r\n");
+    }
+    write!(body, "--{}--\r\n", BOUNDARY).unwrap();
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: multipart/form-data; boundary={}\r\n\
+        Content-Length: {}\r\nConnection: close\r\n\r\n",
+        path,
+        host,
+        BOUNDARY,
+        body.len()
+    )
+    .into_bytes();
+    request.append(&mut body);
+    request
+}
+
+/// Returns the status line if it's a success.
+fn send(host: &str, request: &[u8]) -> Result<String, String> {
+    let mut stream = connect(host)?;
+    stream.set_write_timeout(Some(TIMEOUT)).map_err(|err| err.to_string())?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(|err| err.to_string())?;
+    stream.write_all(request).map_err(|err| err.to_string())?;
+
+    // Only the status line matters.
+    let mut response = [0; 256];
+    let len = stream.read(&mut response).map_err(|err| err.to_string())?;
+    let response = String::from_utf8_lossy(&response[..len]);
+    let status = response.lines().next().unwrap_or_default().to_owned();
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(status),
+        _ => Err(format!("unexpected response: {}", status)),
+    }
+}
+
+/// `TcpStream::connect` without a timeout could keep the thread around forever.
+fn connect(host: &str) -> Result<TcpStream, String> {
+    let mut last_err = format!("no address for {}", host);
+    for addr in host.to_socket_addrs().map_err(|err| err.to_string())? {
+        match TcpStream::connect_timeout(&addr, TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = err.to_string(),
+        }
+    }
+    Err(last_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            parse_url("http://example.com/replays/upload"),
+            Ok(("example.com:80".to_owned(), "/replays/upload".to_owned()))
+        );
+        assert_eq!(
+            parse_url("http://127.0.0.1:8080"),
+            Ok(("127.0.0.1:8080".to_owned(), "/".to_owned()))
+        );
+        assert!(parse_url("https://example.com/").is_err());
+        assert!(parse_url("http:///upload").is_err());
+
+        let request = request("example.com:80", "/", "{}", Some(b"RCDEMO01"));
+        let request = String::from_utf8(request).unwrap();
+        assert!(request.starts_with("POST / HTTP/1.1\r\nHost: example.com:80\r\n"));
+        assert!(request.contains("filename=\"round.rcdemo\""));
+        assert!(request.ends_with(&format!("RCDEMO01\r\n--{}--\r\n", BOUNDARY)));
+    }
+}
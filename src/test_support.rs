@@ -0,0 +1,376 @@
+//! Running a server and clients in one process for tests.
+//!
+//! Everything is connected using in-memory connections and advanced one tick at a time
+//! so tests can check the state of every endpoint after each tick.
+//!
+//! Fyrox currently has no headless mode so this creates a window.
+//! That's why the tests using it are ignored by default -
+//! run them with `cargo test -- --ignored` (on CI using `xvfb-run`).
+//! Winit only allows one event loop per process so all tests share one test function.
+//!
+//! Inputs of every tick are recorded so a scenario can be replayed in a new game
+//! and checked to end up in the same state. This catches gameplay regressions
+//! when physics or tuning changes as well as nondeterminism.
+
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{mpsc, Arc},
+};
+
+use fyrox::{
+    core::futures::executor,
+    dpi::LogicalSize,
+    engine::{resource_manager::ResourceManager, EngineInitParams, SerializationContext},
+    event_loop::{EventLoop, EventLoopBuilder},
+    gui::{text::TextBuilder, widget::WidgetBuilder},
+    window::WindowBuilder,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::{caster::Caster, game::ClientGame, hud::Hud, inspector::Inspector},
+    common::{
+        desync::{StateSummary, StateSummaryExt},
+        entities::Kill,
+        net::{LocalConnection, LocalListener},
+        Input,
+    },
+    debug,
+    prelude::*,
+    server::{
+        game::ServerGame,
+        hooks::{HookCtx, ServerHooks},
+    },
+};
+
+/// Same as in `ClientGame::update` and `ServerGame::update`.
+const DT: f32 = 1.0 / 60.0;
+
+/// A server and its clients sharing one engine.
+pub(crate) struct TestGame {
+    pub(crate) cvars: Cvars,
+    pub(crate) engine: Engine,
+    pub(crate) sg: ServerGame,
+    pub(crate) cgs: Vec<ClientGame>,
+    ticks: u32,
+    /// Inputs of all clients so far.
+    pub(crate) trace: InputTrace,
+}
+
+/// Inputs of each client for each tick.
+///
+/// LATER Save traces recorded in real games so scenarios don't have to be scripted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct InputTrace {
+    pub(crate) ticks: Vec<Vec<Input>>,
+}
+
+impl TestGame {
+    pub(crate) fn new(event_loop: &EventLoop<()>, cvars: Cvars, clients: usize) -> Self {
+        let mut engine = init_engine(event_loop);
+
+        let mut server_conns = Vec::new();
+        let mut client_conns = Vec::new();
+        for _ in 0..clients {
+            let (tx1, rx1) = mpsc::channel();
+            let (tx2, rx2) = mpsc::channel();
+            server_conns.push(LocalConnection::new(tx1, rx2));
+            client_conns.push(LocalConnection::new(tx2, rx1));
+        }
+
+        debug::details::set_endpoint("losv");
+        let listener = LocalListener::with_conns(server_conns);
+        let mut sg = executor::block_on(ServerGame::new(&cvars, &mut engine, Box::new(listener)));
+        // Accept all so each client finds its init data during creation.
+        sg.accept_new_connections(&cvars, &mut engine);
+
+        debug::details::set_endpoint("locl");
+        let mut cgs = Vec::new();
+        for conn in client_conns {
+            let debug_text = TextBuilder::new(WidgetBuilder::new())
+                .build(&mut engine.user_interface.build_ctx());
+            let inspector = Inspector::new(&cvars, &mut engine.user_interface);
+            let caster = Caster::new(&cvars, &mut engine.user_interface);
+            let hud = Hud::new(&cvars, &mut engine.user_interface);
+            let cg = executor::block_on(ClientGame::new(
+                &cvars,
+                &mut engine,
+                debug_text,
+                Vec::new(),
+                inspector,
+                caster,
+                hud,
+                Box::new(conn),
+            ));
+            cgs.push(cg);
+        }
+
+        Self {
+            cvars,
+            engine,
+            sg,
+            cgs,
+            ticks: 0,
+            trace: InputTrace::default(),
+        }
+    }
+
+    /// Run one tick of each client and then one tick of the server.
+    ///
+    /// Same order as `ClientProcess::update` in a local game.
+    pub(crate) fn tick(&mut self) {
+        self.ticks += 1;
+        // Aim for the middle of the tick so float errors can't make us run 0 or 2 ticks.
+        let target = (self.ticks as f32 + 0.5) * DT;
+
+        let inputs = self.cgs.iter().map(|cg| cg.lp.input).collect();
+        self.trace.ticks.push(inputs);
+
+        debug::details::set_endpoint("locl");
+        for i in 0..self.cgs.len() {
+            let scene_handle = self.cgs[i].gs.scene_handle;
+            self.enable_only(scene_handle);
+            self.cgs[i].update(&self.cvars, &mut self.engine, target);
+        }
+
+        debug::details::set_endpoint("losv");
+        self.enable_only(self.sg.gs.scene_handle);
+        self.sg.update(&self.cvars, &mut self.engine, target);
+    }
+
+    pub(crate) fn ticks(&mut self, count: u32) {
+        for _ in 0..count {
+            self.tick();
+        }
+    }
+
+    /// Run the game using recorded inputs instead of whatever the clients have now.
+    pub(crate) fn replay(&mut self, trace: &InputTrace) {
+        for inputs in &trace.ticks {
+            for (cg, input) in self.cgs.iter_mut().zip(inputs) {
+                cg.lp.input = *input;
+            }
+            self.tick();
+        }
+    }
+
+    /// Quantized state of the server for comparing games.
+    pub(crate) fn server_state(&self) -> StateSummary {
+        let scene = &self.engine.scenes[self.sg.gs.scene_handle];
+        StateSummary::new(&self.cvars, &self.sg.gs, scene)
+    }
+
+    /// Start collecting all kills on the server.
+    pub(crate) fn record_kills(&mut self) -> Rc<RefCell<Vec<Kill>>> {
+        let kills = Rc::new(RefCell::new(Vec::new()));
+        self.sg.hooks.add(Box::new(KillLog(Rc::clone(&kills))));
+        kills
+    }
+
+    /// Position of the player's cycle on the server.
+    pub(crate) fn cycle_pos(&self, player_index: u32) -> Vec3 {
+        let player = self.sg.gs.players.at(player_index).unwrap();
+        let cycle = &self.sg.gs.cycles[player.cycle_handle.unwrap()];
+        let scene = &self.engine.scenes[self.sg.gs.scene_handle];
+        **scene.graph[cycle.body_handle].local_transform().position()
+    }
+
+    /// Drop the client which closes its connection.
+    pub(crate) fn disconnect(&mut self, index: usize) {
+        let cg = self.cgs.remove(index);
+        self.engine.scenes.remove(cg.gs.scene_handle);
+    }
+
+    /// Both client and server run physics so only one scene can be enabled at a time.
+    fn enable_only(&mut self, scene_handle: Handle<Scene>) {
+        for (handle, scene) in self.engine.scenes.pair_iter_mut() {
+            scene.enabled = handle == scene_handle;
+        }
+    }
+}
+
+struct KillLog(Rc<RefCell<Vec<Kill>>>);
+
+impl ServerHooks for KillLog {
+    fn on_kill(&mut self, _ctx: &mut HookCtx, kill: Kill) {
+        self.0.borrow_mut().push(kill);
+    }
+}
+
+/// Create the event loop for all tests.
+///
+/// Tests don't run on the main thread which winit normally doesn't allow.
+pub(crate) fn event_loop() -> EventLoop<()> {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        use fyrox::platform::unix::EventLoopBuilderExtUnix;
+        EventLoopBuilder::new().with_any_thread(true).build()
+    }
+
+    #[cfg(windows)]
+    {
+        use fyrox::platform::windows::EventLoopBuilderExtWindows;
+        EventLoopBuilder::new().with_any_thread(true).build()
+    }
+
+    // LATER macOS only allows the event loop on the main thread.
+    #[cfg(target_os = "macos")]
+    {
+        EventLoopBuilder::new().build()
+    }
+}
+
+fn init_engine(event_loop: &EventLoop<()>) -> Engine {
+    let window_builder = WindowBuilder::new()
+        .with_title("RustCycles test")
+        .with_inner_size(LogicalSize::new(400, 300));
+    let serialization_context = Arc::new(SerializationContext::new());
+    let resource_manager = ResourceManager::new(serialization_context.clone());
+
+    Engine::new(EngineInitParams {
+        window_builder,
+        serialization_context,
+        resource_manager,
+        events_loop: event_loop,
+        vsync: false,
+        headless: true,
+    })
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{entities::PlayerState, Action, Axis, Deg};
+
+    use super::*;
+
+    #[test]
+    #[ignore = "creates a window, run with --ignored"]
+    fn test_game() {
+        let event_loop = event_loop();
+
+        join(&event_loop);
+        input_flow(&event_loop);
+        disconnect(&event_loop);
+        drive_replay(&event_loop);
+        kill_replay(&event_loop);
+    }
+
+    /// Join all clients and let the cycles land.
+    fn start_playing(game: &mut TestGame) {
+        game.ticks(2);
+        for cg in &mut game.cgs {
+            cg.lp.input.set_pressed(Action::Fire1, true);
+        }
+        game.ticks(3);
+        for cg in &mut game.cgs {
+            cg.lp.input.set_pressed(Action::Fire1, false);
+        }
+        game.ticks(60);
+    }
+
+    fn join(event_loop: &EventLoop<()>) {
+        let mut game = TestGame::new(event_loop, Cvars::default(), 1);
+        game.ticks(2);
+
+        // Handles are only the same on all endpoints when the generation matches, compare indices.
+        let index = game.cgs[0].lp.player_handle.index();
+        assert_eq!(game.sg.gs.players.at(index).unwrap().ps, PlayerState::Observing);
+        assert_eq!(game.sg.gs.cycles.alive_count(), 0);
+
+        // Clicking while observing joins.
+        game.cgs[0].lp.input.set_pressed(Action::Fire1, true);
+        game.ticks(3);
+        assert_eq!(game.sg.gs.players.at(index).unwrap().ps, PlayerState::Playing);
+        assert_eq!(game.cgs[0].gs.players.at(index).unwrap().ps, PlayerState::Playing);
+        assert!(game.cgs[0].gs.players.at(index).unwrap().cycle_handle.is_some());
+
+        // Observers don't keep their cycle.
+        game.cgs[0].lp.input.set_pressed(Action::Fire1, false);
+        game.cgs[0].lp.input.set_pressed(Action::Fire2, true);
+        game.ticks(3);
+        assert_eq!(game.sg.gs.players.at(index).unwrap().ps, PlayerState::Observing);
+        assert_eq!(game.sg.gs.cycles.alive_count(), 0);
+        assert_eq!(game.cgs[0].gs.cycles.alive_count(), 0);
+    }
+
+    fn input_flow(event_loop: &EventLoop<()>) {
+        let mut game = TestGame::new(event_loop, Cvars::default(), 2);
+        game.ticks(2);
+
+        let index = game.cgs[0].lp.player_handle.index();
+        game.cgs[0].lp.input.set_axis(Axis::Move, 1.0);
+        game.ticks(3);
+
+        // The server got it and forwarded it to the other client.
+        assert_eq!(game.sg.gs.players.at(index).unwrap().input.axis(Axis::Move), 1.0);
+        assert_eq!(game.cgs[1].gs.players.at(index).unwrap().input.axis(Axis::Move), 1.0);
+    }
+
+    fn disconnect(event_loop: &EventLoop<()>) {
+        let mut game = TestGame::new(event_loop, Cvars::default(), 2);
+        game.ticks(2);
+        assert_eq!(game.sg.gs.players.alive_count(), 2);
+        assert_eq!(game.cgs[1].gs.players.alive_count(), 2);
+
+        let index = game.cgs[0].lp.player_handle.index();
+        game.disconnect(0);
+        game.ticks(3);
+
+        assert_eq!(game.sg.gs.players.alive_count(), 1);
+        assert!(game.sg.gs.players.at(index).is_none());
+        assert_eq!(game.cgs[0].gs.players.alive_count(), 1);
+    }
+
+    fn drive_replay(event_loop: &EventLoop<()>) {
+        let mut game = TestGame::new(event_loop, Cvars::default(), 1);
+        start_playing(&mut game);
+
+        let index = game.cgs[0].lp.player_handle.index();
+        let start = game.cycle_pos(index);
+        game.cgs[0].lp.input.set_axis(Axis::Move, 1.0);
+        game.ticks(60);
+        game.cgs[0].lp.input.set_axis(Axis::Move, 0.0);
+        game.ticks(10);
+
+        // Yaw 0 is forward.
+        let end = game.cycle_pos(index);
+        assert!(end.z - start.z > 1.0, "start {:?} end {:?}", start, end);
+
+        let mut replayed = TestGame::new(event_loop, Cvars::default(), 1);
+        replayed.replay(&game.trace);
+        assert_eq!(replayed.server_state().first_difference(&game.server_state()), None);
+    }
+
+    fn kill_replay(event_loop: &EventLoop<()>) {
+        let mut game = TestGame::new(event_loop, Cvars::default(), 2);
+        let kills = game.record_kills();
+        start_playing(&mut game);
+
+        let killer = game.cgs[0].lp.player_handle.index();
+        let victim = game.cgs[1].lp.player_handle.index();
+        let dir = game.cycle_pos(victim) - game.cycle_pos(killer);
+        // Yaw rotates FORWARD (+Z) towards LEFT (+X).
+        let yaw = dir.x.atan2(dir.z).to_degrees();
+
+        game.cgs[0].lp.input.yaw = Deg(yaw);
+        game.cgs[0].lp.input.set_pressed(Action::Fire1, true);
+        game.tick();
+        game.cgs[0].lp.input.set_pressed(Action::Fire1, false);
+        game.ticks(30);
+
+        let killed = kills
+            .borrow()
+            .iter()
+            .any(|kill| kill.killer.index() == killer && kill.victim.index() == victim);
+        assert!(killed, "kills: {:?}", kills.borrow());
+
+        let mut replayed = TestGame::new(event_loop, Cvars::default(), 2);
+        let replayed_kills = replayed.record_kills();
+        replayed.replay(&game.trace);
+        assert_eq!(*replayed_kills.borrow(), *kills.borrow());
+        assert_eq!(replayed.server_state().first_difference(&game.server_state()), None);
+    }
+}